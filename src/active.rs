@@ -0,0 +1,53 @@
+//
+// Bookkeeping for `Engine::Active`. A cell can only change if something in
+// its 3x3 neighborhood changed the generation before, so only those cells
+// get the rule applied. Separately, live cells (which age) and warm dead
+// cells (which cool) still need their per-generation upkeep; they're kept
+// in a list of their own so a settled grid costs next to nothing.
+//
+// Edits made through `Grid`'s methods mark what they touch. Anything that
+// swaps cells in wholesale (or changes the rule) calls for a full rescan.
+//
+
+use crate::rule::Rule;
+
+// Per-cell flags.
+pub(crate) const CANDIDATE: u8 = 1;
+pub(crate) const TRACKED: u8 = 2;
+pub(crate) const FLIPS: u8 = 4;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ActiveSet {
+    // Everything has to be looked at next update.
+    pub rescan: bool,
+    // The rule the set was computed for.
+    pub rule: Rule,
+    // Cells edited since the last update.
+    pub touched: Vec<usize>,
+    // Live and warm cells, i.e. every cell that isn't dead and cold.
+    pub tracked: Vec<usize>,
+    // This update's cells to apply the rule to, and those that flip.
+    pub candidates: Vec<usize>,
+    pub flips: Vec<usize>,
+    pub flags: Vec<u8>,
+}
+
+impl ActiveSet {
+    pub fn new() -> Self {
+        Self {
+            rescan: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn touch(&mut self, i: usize) {
+        if !self.rescan {
+            self.touched.push(i);
+        }
+    }
+
+    pub fn touch_all(&mut self) {
+        self.rescan = true;
+        self.touched.clear();
+    }
+}
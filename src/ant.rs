@@ -0,0 +1,150 @@
+//
+// Langton's Ant: an ant stands on a cell facing one of the four ways. On a
+// dead cell it turns right, on a live one left; then it flips the cell and
+// steps forward, wrapping around the grid edges. A lone ant on an empty grid
+// wanders in a mess for about 10,000 steps before it starts laying down its
+// highway. Dead cells keep the usual heat trails, so the paths show.
+// Crossing a flipped edge (see `Topology`) turns an ant around to match,
+// and one that walks off a plane is gone.
+//
+// Ants happen in place of the rule, `Grid::ant_steps` moves apiece for each
+// generation, so the speed controls and pause and step work on them as they
+// do on Life.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::topology::Topology;
+
+/// How many moves each ant makes a generation, unless --ant-steps says.
+pub const DEFAULT_ANT_STEPS: u32 = 20;
+
+/// What an ant is drawn as, over the cell it's on.
+pub const ANT_COLOR: [u8; 4] = [0xff, 40, 40, 0xff];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Self; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    pub fn right(self) -> Self {
+        Self::ALL[(self as usize + 1) % 4]
+    }
+
+    pub fn left(self) -> Self {
+        Self::ALL[(self as usize + 3) % 4]
+    }
+
+    // The step a move in this direction takes, y growing downwards.
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Self::North => (0, -1),
+            Self::East => (1, 0),
+            Self::South => (0, 1),
+            Self::West => (-1, 0),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::North => "north",
+            Self::East => "east",
+            Self::South => "south",
+            Self::West => "west",
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown direction {:?} (expected north, east, south or west)",
+                    s
+                )
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ant {
+    pub x: usize,
+    pub y: usize,
+    pub heading: Direction,
+}
+
+impl Ant {
+    /// Turns the way a cell that's `alive` says, then steps forward on a
+    /// grid of `size` glued as `topology` has it. Returns false if that
+    /// took it off the grid.
+    pub fn step(&mut self, alive: bool, size: (usize, usize), topology: Topology) -> bool {
+        self.heading = if alive {
+            self.heading.left()
+        } else {
+            self.heading.right()
+        };
+        let (dx, dy) = self.heading.offset();
+        let (x, y) = (self.x as isize + dx, self.y as isize + dy);
+        let Some((x, y, mirror_x, mirror_y)) = topology.wrap_mirrored(x, y, size) else {
+            return false;
+        };
+        (self.x, self.y) = (x, y);
+        self.heading = match self.heading {
+            Direction::East | Direction::West if mirror_x => self.heading.right().right(),
+            Direction::North | Direction::South if mirror_y => self.heading.right().right(),
+            heading => heading,
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ant(x: usize, y: usize, heading: Direction) -> Ant {
+        Ant { x, y, heading }
+    }
+
+    #[test]
+    fn ants_turn_by_the_cell_and_wrap_around() {
+        let mut a = ant(0, 0, Direction::North);
+        assert!(a.step(true, (3, 2), Topology::Torus));
+        assert_eq!(a, ant(2, 0, Direction::West));
+        assert!(a.step(false, (3, 2), Topology::Torus));
+        assert_eq!(a, ant(2, 1, Direction::North));
+        assert!(a.step(false, (3, 2), Topology::Torus));
+        assert_eq!(a, ant(0, 1, Direction::East));
+    }
+
+    #[test]
+    fn ants_cross_flipped_edges_mirrored_and_fall_off_planes() {
+        // Over the top of a Klein bottle glued top to bottom, the ant
+        // comes up from the bottom on the other side.
+        let mut a = ant(0, 0, Direction::West);
+        assert!(a.step(false, (3, 2), Topology::KleinY));
+        assert_eq!(a, ant(2, 1, Direction::North));
+
+        let mut a = ant(0, 0, Direction::North);
+        assert!(!a.step(true, (3, 2), Topology::Plane));
+    }
+}
@@ -0,0 +1,141 @@
+//
+// Animated PNG: full 32-bit color, unlike GIF's 256, so heat trails keep
+// their gradient. Frames are whole RGBA images of one size, each shown for
+// the same delay. The frame count goes in the header, so it's fixed up
+// front; frames that never arrive are filled in with ones that show the
+// last image a little longer.
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+pub struct ApngEncoder {
+    writer: png::Writer<BufWriter<File>>,
+    width: u32,
+    height: u32,
+    // Frames still to write before `finish`, and whether any have been.
+    left: u32,
+    started: bool,
+    // Scratch for making frames opaque.
+    opaque: Vec<u8>,
+}
+
+impl ApngEncoder {
+    /// Starts an animation of `frames` `width` x `height` frames (at least
+    /// one), each shown for `delay_ms`, looping forever.
+    pub fn create(
+        path: &Path,
+        (width, height): (u32, u32),
+        frames: u32,
+        delay_ms: u16,
+    ) -> io::Result<Self> {
+        let frames = frames.max(1);
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames, 0)?;
+        encoder.set_frame_delay(delay_ms, 1000)?;
+        encoder.add_text_chunk("Software".to_string(), "tgol".to_string())?;
+        Ok(Self {
+            writer: encoder.write_header()?,
+            width,
+            height,
+            left: frames,
+            started: false,
+            opaque: Vec::new(),
+        })
+    }
+
+    /// Frames still to come.
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+
+    /// Adds a frame, RGBA like `Grid::draw` writes; what's see-through
+    /// there (cooled dead cells) comes out opaque. Does nothing once all
+    /// the frames are in.
+    pub fn frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        if self.left == 0 {
+            return Ok(());
+        }
+        self.opaque.clear();
+        self.opaque.extend_from_slice(rgba);
+        for pix in self.opaque.chunks_exact_mut(4) {
+            pix[3] = 0xff;
+        }
+        self.writer.reset_frame_dimension()?;
+        self.writer.reset_frame_position()?;
+        self.writer.set_blend_op(png::BlendOp::Source)?;
+        self.writer.write_image_data(&self.opaque)?;
+        self.left -= 1;
+        self.started = true;
+        Ok(())
+    }
+
+    /// Adds a frame that keeps showing the last one, for one that went
+    /// missing: a single see-through pixel drawn over it.
+    pub fn hold(&mut self) -> io::Result<()> {
+        if self.left == 0 {
+            return Ok(());
+        }
+        if !self.started {
+            // Nothing to hold yet: the first frame has to be a whole one.
+            let blank = vec![0; 4 * self.width as usize * self.height as usize];
+            return self.frame(&blank);
+        }
+        self.writer.set_frame_dimension(1, 1)?;
+        self.writer.set_frame_position(0, 0)?;
+        self.writer.set_blend_op(png::BlendOp::Over)?;
+        self.writer.write_image_data(&[0; 4])?;
+        self.left -= 1;
+        Ok(())
+    }
+
+    /// Fills in any frames that didn't arrive, and closes the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        while self.left > 0 {
+            self.hold()?;
+        }
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_frames_are_filled_in_to_the_declared_count() {
+        let path = std::env::temp_dir().join(format!("tgol-anim-{}.png", std::process::id()));
+        let first = [[0x10, 0x20, 0x30, 0x00]; 6].concat();
+        let second = [[0xff, 0x00, 0x80, 0xff]; 6].concat();
+        let mut apng = ApngEncoder::create(&path, (3, 2), 4, 50).unwrap();
+        apng.frame(&first).unwrap();
+        apng.hold().unwrap();
+        apng.frame(&second).unwrap();
+        assert_eq!(apng.left(), 1);
+        apng.finish().unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let frames = reader.info().animation_control().unwrap().num_frames;
+        assert_eq!(frames, 4);
+        let mut image = vec![0; reader.output_buffer_size()];
+        let mut sizes = Vec::new();
+        let mut whole = Vec::new();
+        for _ in 0..frames {
+            let info = reader.next_frame(&mut image).unwrap();
+            sizes.push((info.width, info.height));
+            if info.width == 3 {
+                whole.push(image[..info.buffer_size()].to_vec());
+            }
+        }
+        assert_eq!(sizes, [(3, 2), (1, 1), (3, 2), (1, 1)]);
+        // See-through cells come out opaque.
+        let opaque = [[0x10, 0x20, 0x30, 0xff]; 6].concat();
+        assert_eq!(whole, [opaque, second]);
+    }
+}
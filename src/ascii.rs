@@ -0,0 +1,98 @@
+//
+// `--ascii`: the simulation printed to the terminal, one character per
+// cell, for boxes without a display. Runs until Ctrl+C.
+//
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tgol::cell::Cell;
+use tgol::grid::Grid;
+
+use crate::cli::Options;
+use crate::stats_csv::{write_stats, StatsCsv};
+use crate::{session_seed, starting_grid};
+
+// Used when the terminal size can't be read (e.g. output is piped).
+const FALLBACK_SIZE: (u16, u16) = (80, 24);
+
+pub fn run(options: &Options) -> io::Result<()> {
+    // One row is kept for the status line.
+    let (cols, rows) = crossterm::terminal::size().unwrap_or(FALLBACK_SIZE);
+    let width = options.width.unwrap_or(cols as u32).max(1);
+    let height = options
+        .height
+        .unwrap_or(rows.saturating_sub(1) as u32)
+        .max(1);
+
+    let mut life = starting_grid(options, (width, height), session_seed(options));
+    let mut stats_csv = options
+        .stats_out
+        .as_deref()
+        .map(|path| StatsCsv::create(path, options.automaton.teams()))
+        .transpose()?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+        .map_err(io::Error::other)?;
+
+    let tick = Duration::from_millis(options.tick_ms);
+    let mut stdout = io::stdout().lock();
+
+    // Clear once and hide the cursor; each frame then just homes the cursor
+    // and overwrites, which flickers far less than clearing every time.
+    write!(stdout, "\x1b[2J\x1b[?25l")?;
+    let result = (|| {
+        let mut frame = String::new();
+        while running.load(Ordering::SeqCst) {
+            render(&life, &mut frame);
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+
+            thread::sleep(tick);
+            write_stats(&mut stats_csv, &life.update());
+        }
+        Ok(())
+    })();
+
+    // Put the terminal back however the loop ended.
+    writeln!(stdout, "\x1b[?25h")?;
+    stdout.flush()?;
+    if let Some(csv) = &mut stats_csv {
+        csv.flush()?;
+    }
+    result
+}
+
+fn render(life: &Grid, frame: &mut String) {
+    frame.clear();
+    frame.push_str("\x1b[H");
+
+    for y in 0..life.height {
+        frame.extend((0..life.width).map(|x| cell_char(&life.grid.get(x + y * life.width))));
+        frame.push_str("\x1b[K\r\n");
+    }
+
+    let population = life.population();
+    frame.push_str(&format!(
+        "gen {}  pop {}  {}  (Ctrl+C to quit)\x1b[K",
+        life.generation(),
+        population,
+        life.rule
+    ));
+}
+
+// Live cells are solid; dead ones fade through the shade blocks as they cool.
+fn cell_char(cell: &Cell) -> char {
+    match (cell.alive, cell.heat) {
+        (true, _) => '█',
+        (false, 171..=255) => '▓',
+        (false, 86..=170) => '▒',
+        (false, 1..=85) => '░',
+        (false, 0) => ' ',
+    }
+}
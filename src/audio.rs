@@ -0,0 +1,216 @@
+//
+// `--audio` (`audio` feature): the grid, heard. Each generation makes a
+// short click whose pitch follows how many cells were born, over a soft
+// pad whose volume follows the population.
+//
+// The simulation hands each generation's numbers over in atomics, so it
+// never waits on the sound, and the sound never waits on it: the output
+// callback reads whatever's newest each buffer. Generations that come
+// faster than buffers (fast-forward) are heard as one click a buffer.
+// Levels are capped and glide to where they're going, so a new soup ([R])
+// swells in rather than blasting out.
+//
+// With no output device, or one that can't be opened, there's a warning
+// in the log and everything else goes on without sound.
+//
+
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use tgol::grid::GenerationStats;
+
+// Loudest the click and the pad get, out of 1; together they stay under
+// `MAX_LEVEL`, which every sample is clamped to besides.
+const CLICK_LEVEL: f32 = 0.2;
+const PAD_LEVEL: f32 = 0.12;
+const MAX_LEVEL: f32 = 0.4;
+
+// The click's pitch: BASE_PITCH with no births, an octave higher each
+// time births go up BIRTHS_PER_OCTAVE times over, up to MAX_OCTAVES.
+const BASE_PITCH: f32 = 220.0;
+const BIRTHS_PER_OCTAVE: f32 = 8.0;
+const MAX_OCTAVES: f32 = 3.0;
+const PAD_PITCH: f32 = 110.0;
+
+// The fraction of the grid alive at which the pad is at its loudest.
+const FULL_PAD: f32 = 0.25;
+
+// How long the click takes to die away, and the pad and muting to get
+// most of the way to a new level, in seconds.
+const CLICK_DECAY: f32 = 0.03;
+const GLIDE: f32 = 0.3;
+
+// What the simulation last said, for the output callback.
+#[derive(Default)]
+struct Shared {
+    // Counts up once a generation; a change means a click is due.
+    generations: AtomicU64,
+    births: AtomicU32,
+    // The population over the grid's size, as `f32::to_bits`.
+    density: AtomicU32,
+    muted: AtomicBool,
+}
+
+/// The sound while it plays; dropping it stops it.
+pub struct Sound {
+    shared: Arc<Shared>,
+    // Kept for as long as there's sound.
+    _stream: Stream,
+}
+
+/// The simulation's end: what each generation sounds like.
+#[derive(Clone)]
+pub struct Feed(Arc<Shared>);
+
+impl Feed {
+    /// Passes on a generation of a grid `cells` big.
+    pub fn generation(&self, stats: &GenerationStats, cells: usize) {
+        let shared = &self.0;
+        let density = stats.population as f32 / cells.max(1) as f32;
+        shared.births.store(stats.births, Ordering::Relaxed);
+        shared.density.store(density.to_bits(), Ordering::Relaxed);
+        shared.generations.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl Sound {
+    /// Starts playing on the default output device, silent until the first
+    /// generation.
+    pub fn start() -> Result<Self, String> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or("there's no audio output device")?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let format = supported.sample_format();
+        let config: StreamConfig = supported.into();
+        let shared = Arc::new(Shared::default());
+        let stream = match format {
+            SampleFormat::F32 => build::<f32>(&device, &config, &shared),
+            SampleFormat::I16 => build::<i16>(&device, &config, &shared),
+            SampleFormat::U16 => build::<u16>(&device, &config, &shared),
+            SampleFormat::I32 => build::<i32>(&device, &config, &shared),
+            other => return Err(format!("the device wants {} samples", other)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        log::info!(
+            "Playing on {} ({} Hz)",
+            device
+                .name()
+                .unwrap_or_else(|_| "the default device".to_string()),
+            config.sample_rate.0
+        );
+        Ok(Self {
+            shared,
+            _stream: stream,
+        })
+    }
+
+    pub fn feed(&self) -> Feed {
+        Feed(Arc::clone(&self.shared))
+    }
+
+    /// Mutes or unmutes, and returns whether it's muted now.
+    pub fn toggle_mute(&self) -> bool {
+        !self.shared.muted.fetch_xor(true, Ordering::Relaxed)
+    }
+}
+
+fn build<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    shared: &Arc<Shared>,
+) -> Result<Stream, String> {
+    let channels = config.channels as usize;
+    let mut synth = Synth::new(config.sample_rate.0 as f32, Arc::clone(shared));
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                synth.read();
+                for frame in data.chunks_mut(channels) {
+                    let sample = T::from_sample(synth.next());
+                    frame.fill(sample);
+                }
+            },
+            // Underruns and the like; there's nothing to do about them
+            // but carry on.
+            |e| log::warn!("Audio: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+// Makes the samples, in the output callback.
+struct Synth {
+    shared: Arc<Shared>,
+    rate: f32,
+    generations: u64,
+    click_pitch: f32,
+    click_phase: f32,
+    click_level: f32,
+    click_decay: f32,
+    pad_phase: f32,
+    pad_level: f32,
+    pad_target: f32,
+    // 1 while unmuted, 0 while muted.
+    gain: f32,
+    gain_target: f32,
+    // How much of the way to their targets the pad and gain go each sample.
+    glide: f32,
+}
+
+impl Synth {
+    fn new(rate: f32, shared: Arc<Shared>) -> Self {
+        Self {
+            generations: shared.generations.load(Ordering::Acquire),
+            shared,
+            rate,
+            click_pitch: BASE_PITCH,
+            click_phase: 0.0,
+            click_level: 0.0,
+            click_decay: (-1.0 / (CLICK_DECAY * rate)).exp(),
+            pad_phase: 0.0,
+            pad_level: 0.0,
+            pad_target: 0.0,
+            gain: 1.0,
+            gain_target: 1.0,
+            glide: 1.0 - (-1.0 / (GLIDE * rate)).exp(),
+        }
+    }
+
+    // Catches up with the simulation, once a buffer.
+    fn read(&mut self) {
+        let generations = self.shared.generations.load(Ordering::Acquire);
+        let density = f32::from_bits(self.shared.density.load(Ordering::Relaxed));
+        self.pad_target = PAD_LEVEL * (density / FULL_PAD).clamp(0.0, 1.0);
+        self.gain_target = if self.shared.muted.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            1.0
+        };
+        if generations != self.generations {
+            self.generations = generations;
+            let births = self.shared.births.load(Ordering::Relaxed) as f32;
+            let octaves = ((1.0 + births).ln() / BIRTHS_PER_OCTAVE.ln()).min(MAX_OCTAVES);
+            self.click_pitch = BASE_PITCH * octaves.exp2();
+            self.click_phase = 0.0;
+            self.click_level = if births > 0.0 { CLICK_LEVEL } else { 0.0 };
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        self.pad_level += (self.pad_target - self.pad_level) * self.glide;
+        self.gain += (self.gain_target - self.gain) * self.glide;
+        let click = self.click_level * (self.click_phase * TAU).sin();
+        let pad = self.pad_level * (self.pad_phase * TAU).sin();
+        self.click_level *= self.click_decay;
+        self.click_phase = (self.click_phase + self.click_pitch / self.rate).fract();
+        self.pad_phase = (self.pad_phase + PAD_PITCH / self.rate).fract();
+        ((click + pad) * self.gain).clamp(-MAX_LEVEL, MAX_LEVEL)
+    }
+}
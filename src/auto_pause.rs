@@ -0,0 +1,112 @@
+//
+// Pausing on the player's behalf while they're away: the window out of
+// sight (--pause-when-hidden) or out of focus (--pause-on-unfocus). Only
+// a pause this put on is taken off again when they're back; one of their
+// own, from before or during, stays.
+//
+// It's all decided on the event loop, between the focus and visibility
+// events and the frames, and the pause and resume go to the simulation
+// in order with everything else sent there, so there's nothing to race.
+//
+
+#[derive(Default)]
+pub struct AutoPause {
+    // Whether the player was away as of the last update.
+    away: bool,
+    // Whether the pause on now is ours.
+    ours: bool,
+}
+
+impl AutoPause {
+    /// Given whether the player's `away` and whether the grid's `paused`,
+    /// whether to pause (Some(true)) or resume (Some(false)) it. It's
+    /// paused as they go, if it's running then, and resumed as they come
+    /// back, if it's still paused by it.
+    pub fn update(&mut self, away: bool, paused: bool) -> Option<bool> {
+        // Resumed since (by a script, say): the pause is gone, and isn't
+        // put back until they go away again.
+        self.ours &= paused;
+        let was_away = std::mem::replace(&mut self.away, away);
+        if away && !was_away && !paused {
+            self.ours = true;
+            Some(true)
+        } else if !away && self.ours {
+            self.ours = false;
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The player paused it themselves: it stays paused once they're back.
+    pub fn paused_manually(&mut self) {
+        self.ours = false;
+    }
+
+    /// Whether the pause on now is one it put on.
+    pub fn pausing(&self) -> bool {
+        self.ours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_only_its_own_pause() {
+        // (was away, away, paused, ours) before, then what `update` says
+        // and whether the pause is ours after.
+        let (f, t) = (false, true);
+        let table = [
+            // Here all along, running or paused.
+            ((f, f, f, f), None, f),
+            ((f, f, t, f), None, f),
+            // Leaving while it runs pauses it; while paused, leaves it be.
+            ((f, t, f, f), Some(true), t),
+            ((f, t, t, f), None, f),
+            // Still away.
+            ((t, t, t, t), None, t),
+            ((t, t, t, f), None, f),
+            ((t, t, f, f), None, f),
+            // Resumed by something else while away: no longer ours.
+            ((t, t, f, t), None, f),
+            ((t, f, f, t), None, f),
+            // Back: only our own pause is taken off.
+            ((t, f, t, t), Some(false), f),
+            ((t, f, t, f), None, f),
+            ((t, f, f, f), None, f),
+        ];
+        for ((was_away, away, paused, ours), expected, ours_after) in table {
+            let mut auto = AutoPause {
+                away: was_away,
+                ours,
+            };
+            let case = (was_away, away, paused, ours);
+            assert_eq!(auto.update(away, paused), expected, "{:?}", case);
+            assert_eq!(auto.pausing(), ours_after, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn manual_pauses_outlast_the_absence() {
+        // Paused before going away.
+        let mut auto = AutoPause::default();
+        assert_eq!(auto.update(false, true), None);
+        assert_eq!(auto.update(true, true), None);
+        assert_eq!(auto.update(false, true), None);
+
+        // Paused again by hand while away, over ours.
+        let mut auto = AutoPause::default();
+        assert_eq!(auto.update(true, false), Some(true));
+        auto.paused_manually();
+        assert!(!auto.pausing());
+        assert_eq!(auto.update(false, true), None);
+
+        // Left alone, ours is taken off.
+        let mut auto = AutoPause::default();
+        assert_eq!(auto.update(true, false), Some(true));
+        assert_eq!(auto.update(true, true), None);
+        assert_eq!(auto.update(false, true), Some(false));
+    }
+}
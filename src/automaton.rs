@@ -0,0 +1,347 @@
+//
+// What the cells are playing. Plain Life has every live cell alike;
+// Immigration and QuadLife put each one on a team (two of them, or four),
+// and a cell born joins the team most of its parents are on, which turns a
+// soup into a battle for territory. Which cells live and die is still up
+// to the rule, so teams only change who holds what, not the shape of a run.
+//
+// Brian's Brain ignores the rule: a cell is off, on or dying (a dead cell
+// whose `Cell::state` is `DYING`). An off cell with exactly two on
+// neighbors turns on, on cells start dying, and dying cells turn off; only
+// on cells count as neighbors. It never settles, and is drawn from a
+// palette rather than trails.
+//
+// Wireworld is for building circuits: a cell is empty, conductor, electron
+// head or electron tail, numbered as Golly does (0, 3, 1 and 2). Heads are
+// the live cells; the other states are dead cells' `Cell::state`. A head
+// becomes a tail, a tail becomes conductor again, and conductor with one or
+// two head neighbors becomes a head, so electrons run along wires. Empty
+// cells stay empty. What edits paint is picked with [T], and patterns keep
+// their states through RLE files, the clipboard and recordings.
+//
+// Langton's Ant has ants walk the grid in place of a rule (see `ant`).
+//
+// A rule table (`--rulefile`, see `rule_table`) can have any number of
+// states, numbered as in the table. Every state but 0 is a live cell, and
+// `Cell::state` says which. Editing paints with the state [T] picks.
+//
+// Anything but plain Life keeps what it needs in the grid's own cells, so
+// it always steps the torus: cell by cell (`Engine::Scalar`, and always
+// for Brian's Brain, Wireworld and rule tables), or else with the bitset
+// engine, whichever engine is picked.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Most teams any automaton has.
+pub const MAX_TEAMS: usize = 4;
+
+/// Live cells of each team, in team order, as shown on screen.
+pub const TEAM_COLORS: [[u8; 4]; MAX_TEAMS] = [
+    crate::ALIVE_COLOR,
+    [0xff, 80, 0, 0xff],
+    [0, 200, 70, 0xff],
+    [0xff, 210, 0, 0xff],
+];
+
+/// Names of the teams, for the title bar and --stats-out.
+pub const TEAM_NAMES: [&str; MAX_TEAMS] = ["blue", "orange", "green", "yellow"];
+
+/// `Cell::state` of a dead Brian's Brain cell that's still dying.
+pub const DYING: u8 = 1;
+
+/// Brian's Brain's colors for off, on and dying cells.
+pub const BRAIN_COLORS: [[u8; 4]; 3] = [
+    [0, 0, 0, 0xff],
+    [0xff, 0xff, 0xff, 0xff],
+    [50, 100, 0xff, 0xff],
+];
+
+/// Wireworld's states, as Golly numbers them. In a grid, heads are the
+/// live cells (whatever their `Cell::state`), so `HEAD` is only for
+/// patterns and painting.
+pub const EMPTY: u8 = 0;
+pub const HEAD: u8 = 1;
+pub const TAIL: u8 = 2;
+pub const CONDUCTOR: u8 = 3;
+
+/// Names of Wireworld's states, for the title bar.
+pub const WIRE_NAMES: [&str; 4] = ["empty", "head", "tail", "conductor"];
+
+/// Wireworld's colors for each state, in state order: black, blue, red and
+/// yellow.
+pub const WIREWORLD_COLORS: [[u8; 4]; 4] = [
+    [0, 0, 0, 0xff],
+    [0, 90, 0xff, 0xff],
+    [0xff, 40, 0, 0xff],
+    [0xff, 200, 0, 0xff],
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Automaton {
+    /// Conway's Life, or whichever Life-like rule is set.
+    #[default]
+    Life,
+    /// Life with two teams.
+    Immigration,
+    /// Life with four teams; three parents all on different teams make a
+    /// child of the fourth.
+    QuadLife,
+    /// Brian's Brain: off, on and dying cells.
+    Brain,
+    /// Wireworld: electrons running along conductor.
+    Wireworld,
+    /// Langton's Ant: ants flipping the cells they walk over.
+    Ant,
+    /// Whatever a Golly rule table says.
+    Table,
+}
+
+impl Automaton {
+    pub const ALL: [Self; 7] = [
+        Self::Life,
+        Self::Immigration,
+        Self::QuadLife,
+        Self::Brain,
+        Self::Wireworld,
+        Self::Ant,
+        Self::Table,
+    ];
+
+    /// How many teams cells are split into: 1 for plain Life.
+    pub fn teams(self) -> usize {
+        match self {
+            Self::Life => 1,
+            Self::Immigration => 2,
+            Self::QuadLife => 4,
+            Self::Brain | Self::Wireworld | Self::Ant | Self::Table => 1,
+        }
+    }
+
+    pub fn has_teams(self) -> bool {
+        self.teams() > 1
+    }
+
+    /// Whether the B/S rule decides which cells live, as in Life.
+    pub fn life_like(self) -> bool {
+        !matches!(
+            self,
+            Self::Brain | Self::Wireworld | Self::Ant | Self::Table
+        )
+    }
+
+    /// What the title bar calls an automaton with no rule to show.
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Brain => "Brian's Brain",
+            Self::Wireworld => "Wireworld",
+            Self::Ant => "Langton's Ant",
+            Self::Table => "Rule table",
+            _ => "Life",
+        }
+    }
+
+    /// The colors cells are drawn in, by state, for an automaton drawn
+    /// from a palette; empty for the others.
+    pub fn palette(self) -> &'static [[u8; 4]] {
+        match self {
+            Self::Brain => &BRAIN_COLORS,
+            Self::Wireworld => &WIREWORLD_COLORS,
+            _ => &[],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Life => "life",
+            Self::Immigration => "immigration",
+            Self::QuadLife => "quadlife",
+            Self::Brain => "brain",
+            Self::Wireworld => "wireworld",
+            Self::Ant => "ant",
+            Self::Table => "table",
+        }
+    }
+}
+
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Automaton {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|automaton| automaton.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown automaton {:?} (expected life, immigration, quadlife, brain, wireworld, ant or table)",
+                    s
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::engine::Engine;
+    use crate::grid::Grid;
+    use crate::pattern::PatternBuffer;
+    use crate::rng::session_rng;
+
+    // Brian's Brain from rows of `#` (on), `+` (dying) and `.` (off).
+    fn brain(rows: &str) -> Grid {
+        let mut g: Grid = rows.replace('+', ".").parse().unwrap();
+        g.automaton = Automaton::Brain;
+        for (i, c) in rows.chars().filter(|&c| c != '\n').enumerate() {
+            if c == '+' {
+                g.grid.put(
+                    i,
+                    Cell {
+                        state: DYING,
+                        ..Cell::default()
+                    },
+                );
+            }
+        }
+        g
+    }
+
+    fn show(g: &Grid) -> String {
+        let mut rows = String::new();
+        for (x, _, cell) in g.cells() {
+            rows.push(match (cell.alive, cell.state) {
+                (true, _) => '#',
+                (false, DYING) => '+',
+                _ => '.',
+            });
+            if x + 1 == g.width {
+                rows.push('\n');
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn brains_pair_of_cells_spreads_out_as_it_dies() {
+        let steps = [
+            "......\n......\n..##..\n......\n......\n......\n",
+            "......\n..##..\n..++..\n..##..\n......\n......\n",
+            "..##..\n..++..\n.#..#.\n..++..\n..##..\n......\n",
+        ];
+        for engine in [Engine::Scalar, Engine::Bitset, Engine::Active] {
+            let mut g = brain(steps[0]);
+            g.engine = engine;
+            for step in &steps[1..] {
+                g.update();
+                assert_eq!(show(&g), *step, "{:?}", engine);
+            }
+        }
+    }
+
+    #[test]
+    fn brains_cells_go_on_then_dying_then_off() {
+        let mut g = brain(".....\n.....\n..#..\n.....\n.....\n");
+        g.update();
+        assert_eq!(show(&g), ".....\n.....\n..+..\n.....\n.....\n");
+        g.update();
+        assert_eq!(show(&g), ".....\n.....\n.....\n.....\n.....\n");
+    }
+
+    #[test]
+    fn kills_leave_brains_and_wires_in_their_own_states() {
+        // Killed on cells start dying, as they would have anyway.
+        let mut g = brain(".....\n.##+.\n.#...\n.....\n");
+        assert_eq!(g.randomly_kill(1.0, &mut session_rng(1)), 3);
+        assert_eq!(show(&g), ".....\n.+++.\n.+...\n.....\n");
+        g.update();
+        assert_eq!(show(&g), ".....\n.....\n.....\n.....\n");
+
+        // Killed heads go back to conductor; tails are dead already.
+        let mut g = Grid::new_empty_grid(5, 1);
+        g.automaton = Automaton::Wireworld;
+        let mut wire = PatternBuffer::new(5, 1);
+        for (x, state) in [CONDUCTOR, HEAD, TAIL, CONDUCTOR].into_iter().enumerate() {
+            wire.set_state(x, 0, state);
+        }
+        g.insert_pattern(&wire, 0, 0);
+        assert_eq!(g.randomly_kill(1.0, &mut session_rng(1)), 1);
+        let states: Vec<_> = g
+            .cells()
+            .map(|(_, _, cell)| (cell.alive, cell.state))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                (false, CONDUCTOR),
+                (false, CONDUCTOR),
+                (false, TAIL),
+                (false, CONDUCTOR),
+                (false, EMPTY),
+            ]
+        );
+    }
+
+    #[test]
+    fn wireworld_electrons_run_along_the_wire() {
+        let mut g = Grid::new_empty_grid(6, 3);
+        g.automaton = Automaton::Wireworld;
+        let mut wire = PatternBuffer::new(6, 1);
+        for (x, state) in [CONDUCTOR, TAIL, HEAD, CONDUCTOR, CONDUCTOR]
+            .into_iter()
+            .enumerate()
+        {
+            wire.set_state(x, 0, state);
+        }
+        g.insert_pattern(&wire, 0, 1);
+        let wire = |g: &Grid| -> String {
+            g.cells()
+                .filter(|&(_, y, _)| y == 1)
+                .map(|(_, _, cell)| match (cell.alive, cell.state) {
+                    (true, _) => 'H',
+                    (false, TAIL) => 't',
+                    (false, CONDUCTOR) => '-',
+                    _ => '.',
+                })
+                .collect()
+        };
+        for expected in ["--tH-.", "---tH.", "----t.", "-----."] {
+            g.update();
+            assert_eq!(wire(&g), expected);
+        }
+    }
+
+    #[test]
+    fn a_lone_ant_lays_a_block_and_comes_back() {
+        // Four right turns over dead cells, back where it started.
+        let mut g = Grid::new_empty_grid(5, 5);
+        g.automaton = Automaton::Ant;
+        g.ant_steps = 4;
+        g.add_ant(2, 2);
+        g.update();
+        assert_eq!(show(&g), ".....\n.....\n..##.\n..##.\n.....\n");
+        assert_eq!((g.ants[0].x, g.ants[0].y), (2, 2));
+
+        // Its cells are colors, so kills leave them be.
+        assert_eq!(g.randomly_kill(1.0, &mut session_rng(1)), 0);
+        assert_eq!(show(&g), ".....\n.....\n..##.\n..##.\n.....\n");
+    }
+
+    #[test]
+    fn brains_dying_cells_dont_count_or_come_back() {
+        // The middle cell has two on neighbors, but it's dying; the cells
+        // above and below it have two on neighbors and a dying one. The
+        // rule has no say.
+        let mut g = brain(".......\n.......\n.#+#...\n.......\n.......\n");
+        g.rule = "B2/S".parse().unwrap();
+        g.update();
+        assert_eq!(show(&g), ".......\n..#....\n.+.+...\n..#....\n.......\n");
+    }
+}
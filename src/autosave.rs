@@ -0,0 +1,159 @@
+//
+// `--autosave <secs>`: the grid saved every so often while the window's
+// open, so a crash or a slip of [Esc] doesn't lose it. Saves go to
+// `autosave-1` .. `autosave-K` next to the last session (K from
+// --autosave-keep), the oldest written over first, in the last session's
+// format.
+//
+// The simulation thread only copies the grid; formatting and writing it
+// happen on a thread of their own, so the grid never waits on the disk.
+// A save that comes in while the last is still being written replaces it
+// in the queue. The event loop holds saves back while a stroke, drag or
+// paste is under way, so none has half an edit in it.
+//
+// A start where the newest autosave is newer than the last session (which
+// is saved on every clean exit) says so, and --restore-autosave picks it up
+// instead.
+//
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+use log::error;
+
+use crate::last_session::{self, LastSession};
+
+/// Autosaves kept, unless told otherwise.
+pub const DEFAULT_KEEP: usize = 3;
+
+const PREFIX: &str = "autosave-";
+
+pub struct Autosaver {
+    sender: Option<Sender<LastSession>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Autosaver {
+    /// Writes to `keep` files in rotation, starting after the newest one
+    /// there is.
+    pub fn start(keep: usize) -> Result<Self, String> {
+        let dir = dir().ok_or("there's no home directory to save to")?;
+        let keep = keep.max(1);
+        let mut slot = newest()
+            .and_then(|(path, _)| slot_of(&path))
+            .filter(|&slot| slot <= keep)
+            .unwrap_or(0);
+        let (sender, saves) = mpsc::channel::<LastSession>();
+        let thread = thread::Builder::new()
+            .name("autosave".to_string())
+            .spawn(move || {
+                while let Some(session) = latest(&saves) {
+                    slot = slot % keep + 1;
+                    let path = dir.join(format!("{}{}", PREFIX, slot));
+                    match session.save(&path) {
+                        Ok(()) => log::debug!("Autosaved to {}", path.display()),
+                        Err(e) => error!("Couldn't autosave to {}: {}", path.display(), e),
+                    }
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues `session` to be written.
+    pub fn save(&self, session: LastSession) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(session);
+        }
+    }
+}
+
+impl Drop for Autosaver {
+    // Finishes the save being written, and any queued.
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Waits for a save, and takes the newest of any more already queued. None
+// once the sender's gone and everything's been taken.
+fn latest(saves: &Receiver<LastSession>) -> Option<LastSession> {
+    let first = saves.recv().ok()?;
+    Some(saves.try_iter().last().unwrap_or(first))
+}
+
+// Where autosaves go: next to the last session.
+fn dir() -> Option<PathBuf> {
+    Some(last_session::path()?.parent()?.to_path_buf())
+}
+
+// The number in an autosave's name.
+fn slot_of(path: &Path) -> Option<usize> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// The newest autosave, and when it was written.
+pub fn newest() -> Option<(PathBuf, SystemTime)> {
+    std::fs::read_dir(dir()?)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| slot_of(path).is_some())
+        .filter_map(|path| Some((path.clone(), modified(&path)?)))
+        .max_by_key(|&(_, at)| at)
+}
+
+/// The newest autosave, if it's newer than the last session (or there's
+/// no last session): most likely left behind by a run that didn't exit
+/// cleanly.
+pub fn unfinished() -> Option<(PathBuf, SystemTime)> {
+    let clean = last_session::path().and_then(|path| modified(&path));
+    newest().filter(|&(_, at)| clean.is_none_or(|clean| at > clean))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tgol::grid::Grid;
+
+    #[test]
+    fn only_autosaves_have_a_slot() {
+        assert_eq!(slot_of(Path::new("/data/tgol/autosave-3")), Some(3));
+        assert_eq!(slot_of(Path::new("autosave-12")), Some(12));
+        assert_eq!(slot_of(Path::new("/data/tgol/last_session")), None);
+        assert_eq!(slot_of(Path::new("autosave-")), None);
+        assert_eq!(slot_of(Path::new("autosave-1.tmp")), None);
+    }
+
+    #[test]
+    fn a_save_queued_behind_others_replaces_them() {
+        let (sender, saves) = mpsc::channel();
+        // Told apart by their fast-forward speeds.
+        let session = |speed| LastSession::new(&Grid::new_empty_grid(4, 4), false, speed, false);
+        for speed in 1..=3 {
+            sender.send(session(Some(speed))).unwrap();
+        }
+        assert_eq!(latest(&saves).unwrap().fast_forward, Some(3));
+
+        sender.send(session(None)).unwrap();
+        drop(sender);
+        assert!(latest(&saves).is_some());
+        assert!(latest(&saves).is_none());
+    }
+}
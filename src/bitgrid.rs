@@ -0,0 +1,257 @@
+//
+// Alive flags packed one bit per cell, 64 cells to a word, each row padded
+// out to a whole number of words. Neighbors are counted for a whole word
+// of cells at a time: the eight neighbor rows (the rows above, beside and
+// below, shifted a cell either way) are added up bit-sliced into a 4-bit
+// count per cell with a tree of carry-save adders, and the rule is applied
+// to the counts with bitwise ops. Each row is shifted once and reused for
+// the three rows it neighbors. Wraps around the edges like the rest of the
+// simulation.
+//
+
+use crate::rule::Rule;
+
+#[derive(Clone, Debug, Default)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    // Padding bits past `width` in each row are always zero.
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let words_per_row = width.div_ceil(64);
+        Self {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+        }
+    }
+
+    /// Packs the alive flags of a `width` wide grid of cells into `self`,
+    /// resizing it if needed.
+    pub fn load(&mut self, cells: &[bool], width: usize) {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        if (self.width, self.height) != (width, height) {
+            *self = Self::new(width, height);
+        }
+
+        for (row, words) in cells
+            .chunks_exact(width)
+            .zip(self.words.chunks_exact_mut(self.words_per_row))
+        {
+            for (chunk, word) in row.chunks(64).zip(words.iter_mut()) {
+                *word = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (bit, &alive)| word | (alive as u64) << bit);
+            }
+        }
+    }
+
+    /// Unpacks the alive flags back into a grid of cells as wide as
+    /// `self`, the inverse of `load`.
+    pub fn store(&self, cells: &mut [bool]) {
+        for (row, words) in cells
+            .chunks_exact_mut(self.width)
+            .zip(self.words.chunks_exact(self.words_per_row))
+        {
+            for (chunk, &word) in row.chunks_mut(64).zip(words) {
+                for (bit, cell) in chunk.iter_mut().enumerate() {
+                    *cell = word >> bit & 1 != 0;
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let word = self.words[y * self.words_per_row + x / 64];
+        word >> (x % 64) & 1 != 0
+    }
+
+    /// Writes the generation after `self` into `next`, resizing it if needed.
+    pub fn step(&self, rule: Rule, next: &mut BitGrid) {
+        if (next.width, next.height) != (self.width, self.height) {
+            *next = Self::new(self.width, self.height);
+        }
+        if self.words.is_empty() {
+            return;
+        }
+
+        // The counts that give birth or survival, with which of the two
+        // they give as all-ones or all-zeros masks. Counts that do neither
+        // needn't be looked at.
+        let counts: Vec<(usize, u64, u64)> = (0..=8)
+            .map(|n| {
+                let mask = |yes: bool| 0u64.wrapping_sub(yes as u64);
+                (n, mask(rule.births(n)), mask(rule.survives(n)))
+            })
+            .filter(|&(_, born, survive)| born | survive != 0)
+            .collect();
+
+        // The rows above, at and below the one being stepped, as they are
+        // and shifted a cell either way. Each row is shifted once, when it
+        // comes in below, and moves up as the rows go by.
+        let wpr = self.words_per_row;
+        let mut shifted = [vec![0; wpr], vec![0; wpr], vec![0; wpr]];
+        let mut west = [vec![0; wpr], vec![0; wpr], vec![0; wpr]];
+        let mut east = [vec![0; wpr], vec![0; wpr], vec![0; wpr]];
+        for (i, row) in [self.height - 1, 0].into_iter().enumerate() {
+            self.load_row(row, &mut shifted[i], &mut west[i], &mut east[i]);
+        }
+
+        for y in 0..self.height {
+            let below = (y + 1) % self.height;
+            self.load_row(below, &mut shifted[2], &mut west[2], &mut east[2]);
+
+            let out = &mut next.words[y * wpr..(y + 1) * wpr];
+            for (w, out) in out.iter_mut().enumerate() {
+                // Bit-sliced counts (bit n of each cell's count is in
+                // `count[n]`), added up by a carry-save tree of full adders.
+                let (a0, a1) = full_add(west[0][w], shifted[0][w], east[0][w]);
+                let (b0, b1) = full_add(west[1][w], east[1][w], west[2][w]);
+                let (c0, c1) = (shifted[2][w] ^ east[2][w], shifted[2][w] & east[2][w]);
+                let (ones, d1) = full_add(a0, b0, c0);
+                let (t1, e2) = full_add(a1, b1, c1);
+                let (twos, f2) = (t1 ^ d1, t1 & d1);
+                let count = [ones, twos, e2 ^ f2, e2 & f2];
+
+                let alive = shifted[1][w];
+                let mut born = 0;
+                let mut survive = 0;
+                for &(n, births, survives) in &counts {
+                    let is_n = (0..4).fold(!0u64, |acc, bit| {
+                        acc & if n >> bit & 1 != 0 {
+                            count[bit]
+                        } else {
+                            !count[bit]
+                        }
+                    });
+                    born |= is_n & births;
+                    survive |= is_n & survives;
+                }
+
+                *out = (alive & survive) | (!alive & born);
+            }
+
+            // Keep the padding clear, whatever the rule did with it.
+            if let Some(last) = out.last_mut() {
+                *last &= self.last_word_mask();
+            }
+
+            shifted.rotate_left(1);
+            west.rotate_left(1);
+            east.rotate_left(1);
+        }
+    }
+
+    // Row `y`, as it is and shifted either way.
+    fn load_row(&self, y: usize, shifted: &mut [u64], west: &mut [u64], east: &mut [u64]) {
+        let words = self.row(y);
+        shifted.copy_from_slice(words);
+        self.shift_west(words, west);
+        self.shift_east(words, east);
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.words_per_row..(y + 1) * self.words_per_row]
+    }
+
+    fn last_word_mask(&self) -> u64 {
+        match self.width % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    // Each cell gets the flag of the cell to its west (x - 1).
+    fn shift_west(&self, words: &[u64], out: &mut [u64]) {
+        let mut carry = 0;
+        for (word, out) in words.iter().zip(out.iter_mut()) {
+            *out = word << 1 | carry;
+            carry = word >> 63;
+        }
+        // x = 0 wraps around to the last column, and the last column's
+        // flag mustn't spill into the padding.
+        let last = self.width - 1;
+        out[0] |= words[last / 64] >> (last % 64) & 1;
+        if let Some(end) = out.last_mut() {
+            *end &= self.last_word_mask();
+        }
+    }
+
+    // Each cell gets the flag of the cell to its east (x + 1).
+    fn shift_east(&self, words: &[u64], out: &mut [u64]) {
+        let mut carry = 0;
+        for (word, out) in words.iter().zip(out.iter_mut()).rev() {
+            *out = word >> 1 | carry;
+            carry = word << 63;
+        }
+        // The last column wraps around to x = 0.
+        let last = self.width - 1;
+        out[last / 64] |= (words[0] & 1) << (last % 64);
+    }
+}
+
+// Adds three bit-sliced bits: their sum's low bit and its carry.
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let ab = a ^ b;
+    (ab ^ c, (a & b) | (ab & c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::grid::Grid;
+    use crate::rng::session_rng;
+
+    #[test]
+    fn steps_like_the_scalar_engine() {
+        // Widths either side of whole words, and a column on its own.
+        let sizes = [(64, 9), (67, 13), (130, 7), (63, 5), (1, 6), (200, 3)];
+        let rules = ["B3/S23", "B36/S23", "B3678/S34678", "B1357/S1357", "B2/S"];
+        for (n, &(width, height)) in sizes.iter().enumerate() {
+            for rule in rules {
+                let mut scalar = Grid::new_empty_grid(width, height);
+                scalar.randomize(&mut session_rng(n as u64), 0.4);
+                scalar.rule = rule.parse().unwrap();
+                scalar.engine = Engine::Scalar;
+
+                let mut bits = BitGrid::default();
+                let mut next = BitGrid::default();
+                for generation in 0..300 {
+                    bits.load(&scalar.grid.alive, width);
+                    bits.step(scalar.rule, &mut next);
+                    scalar.update();
+                    for (i, &alive) in scalar.grid.alive.iter().enumerate() {
+                        assert_eq!(
+                            next.get(i % width, i / width),
+                            alive,
+                            "{} on {}x{}, generation {}",
+                            rule,
+                            width,
+                            height,
+                            generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn padding_stays_clear() {
+        let mut bits = BitGrid::new(67, 4);
+        bits.load(&[true; 67 * 4], 67);
+        let mut next = BitGrid::default();
+        // Everything's born with B0, padding included if it weren't masked.
+        bits.step("B012345678/S012345678".parse().unwrap(), &mut next);
+        for row in next.words.chunks_exact(next.words_per_row) {
+            assert_eq!(row[1] >> 3, 0);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//
+// Rules bookmarked from the rule explorer ([Shift+M]), kept from one
+// session to the next in `$XDG_DATA_HOME/tgol/bookmarks`
+// (`~/.local/share/tgol/bookmarks`), next to the last session, a line
+// apiece:
+//
+//   rule B36/S23
+//   rule B3678/S34678
+//
+// Each line starts with what it bookmarks, so other kinds can share the
+// file. Lines this build doesn't know are skipped, and left as they are:
+// new bookmarks are only ever added to the end.
+//
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tgol::rule::Rule;
+
+use crate::last_session;
+
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    pub rules: Vec<Rule>,
+}
+
+/// `$XDG_DATA_HOME/tgol/bookmarks`, or None without a home directory.
+pub fn path() -> Option<PathBuf> {
+    Some(last_session::path()?.with_file_name("bookmarks"))
+}
+
+impl Bookmarks {
+    /// The bookmarks in the file at `path`; none if there's no file yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("rule "))
+            .filter_map(|rule| rule.trim().parse().ok())
+            .collect();
+        Self { rules }
+    }
+
+    pub fn has_rule(&self, rule: Rule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    /// Bookmarks `rule`, adding it to the end of the file at `path`.
+    /// Returns false if it was bookmarked already.
+    pub fn add_rule(&mut self, rule: Rule, path: &Path) -> io::Result<bool> {
+        if self.has_rule(rule) {
+            return Ok(false);
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "rule {}", rule)?;
+        self.rules.push(rule);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_rules_that_parse_are_read() {
+        let bookmarks = Bookmarks::parse("rule B36/S23\nseed 42\n\nrule B9/S\n  rule B3/S23  \n");
+        let rules: Vec<String> = bookmarks.rules.iter().map(Rule::to_string).collect();
+        assert_eq!(rules, ["B36/S23", "B3/S23"]);
+    }
+
+    #[test]
+    fn rules_are_added_to_the_file_once() {
+        let path = std::env::temp_dir()
+            .join(format!("tgol-bookmarks-{}", std::process::id()))
+            .join("bookmarks");
+        let _ = std::fs::remove_file(&path);
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        let day_night: Rule = "B3678/S34678".parse().unwrap();
+
+        let mut bookmarks = Bookmarks::load(&path).unwrap();
+        assert!(bookmarks.rules.is_empty());
+        assert!(bookmarks.add_rule(highlife, &path).unwrap());
+        assert!(!bookmarks.add_rule(highlife, &path).unwrap());
+        assert!(bookmarks.add_rule(day_night, &path).unwrap());
+
+        let text = std::fs::read_to_string(&path);
+        let loaded = Bookmarks::load(&path);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(text.unwrap(), "rule B36/S23\nrule B3678/S34678\n");
+        assert_eq!(loaded.unwrap().rules, [highlife, day_night]);
+    }
+}
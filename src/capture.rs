@@ -0,0 +1,121 @@
+//
+// [Shift+A]: the next generations the window shows, captured to an
+// animated PNG. Frames are encoded on a thread of their own, so the
+// simulation never waits on the encoder. They're handed over through a
+// short queue, and a frame that finds it full is dropped, with a warning;
+// the animation shows the frame before it for longer instead.
+//
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+
+use log::error;
+
+use tgol::apng::ApngEncoder;
+
+// Frames waiting on the encoder before any more are dropped.
+const QUEUE_FRAMES: usize = 8;
+
+pub struct ApngCapture {
+    frames: Option<SyncSender<Captured>>,
+    thread: Option<JoinHandle<()>>,
+    // Frames still to capture.
+    left: u32,
+    // Frames dropped since the last one queued.
+    dropped: u32,
+    warned: bool,
+}
+
+struct Captured {
+    pixels: Vec<u8>,
+    // Frames dropped just before this one.
+    dropped: u32,
+}
+
+impl ApngCapture {
+    /// Starts writing `frames` frames of a `size` grid to `path`, each
+    /// shown for `delay_ms`.
+    pub fn start(path: PathBuf, size: (u32, u32), frames: u32, delay_ms: u16) -> io::Result<Self> {
+        let encoder = ApngEncoder::create(&path, size, frames, delay_ms)?;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+        let thread = std::thread::Builder::new()
+            .name("apng".to_string())
+            .spawn(move || encode(encoder, receiver, path))?;
+        Ok(Self {
+            frames: Some(sender),
+            thread: Some(thread),
+            left: frames.max(1),
+            dropped: 0,
+            warned: false,
+        })
+    }
+
+    /// Whether there are frames still to capture.
+    pub fn capturing(&self) -> bool {
+        self.frames.is_some()
+    }
+
+    /// Captures `pixels` as the next frame. Once that's the last, the
+    /// encoder's left to finish on its own.
+    pub fn push(&mut self, pixels: &[u8]) {
+        let Some(frames) = &self.frames else {
+            return;
+        };
+        self.left -= 1;
+        let captured = Captured {
+            pixels: pixels.to_vec(),
+            dropped: self.dropped,
+        };
+        match frames.try_send(captured) {
+            Ok(()) => self.dropped = 0,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if !std::mem::replace(&mut self.warned, true) {
+                    log::warn!("The APNG encoder can't keep up; dropping frames");
+                }
+            }
+            // The encoder's given up, and said why.
+            Err(TrySendError::Disconnected(_)) => self.left = 0,
+        }
+        if self.left == 0 {
+            self.frames = None;
+        }
+    }
+
+    /// Waits for the encoder to write out every frame it was given, and
+    /// close the file.
+    pub fn finish(mut self) {
+        self.frames = None;
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("The APNG encoder panicked");
+            }
+        }
+    }
+}
+
+// The encoder thread: frames until the capture's done with (or the file
+// can't be written).
+fn encode(mut encoder: ApngEncoder, frames: Receiver<Captured>, path: PathBuf) {
+    let total = encoder.left();
+    let progress_every = (total / 10).max(1);
+    let write = || -> io::Result<()> {
+        for captured in frames {
+            for _ in 0..captured.dropped {
+                encoder.hold()?;
+            }
+            encoder.frame(&captured.pixels)?;
+            let done = total - encoder.left();
+            if done.is_multiple_of(progress_every) && encoder.left() > 0 {
+                log::info!("APNG: {} of {} frames", done, total);
+            }
+        }
+        Ok(())
+    };
+    match write().and_then(|()| encoder.finish()) {
+        Ok(()) => log::info!("Captured {} frames to {}", total, path.display()),
+        Err(e) => error!("Couldn't write {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,233 @@
+//
+// One cell of the grid: whether it's alive, plus the heat and age used to
+// color it, and whatever else the automaton keeps (see automaton.rs). A
+// grid keeps its cells as `Cells`, an array per field, and hands out
+// `Cell`s as copies.
+//
+
+use crate::decay::Decay;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cell {
+    // Alive: Is this cell active or not
+    pub alive: bool,
+
+    // Heat: Trailing effect of the cell. Decays over time.
+    pub heat: u8,
+
+    // Age: Generations a live cell has survived. Reset when it's born.
+    pub age: u16,
+
+    // State: What else the automaton keeps about the cell. The team in Immigration and
+    // QuadLife (dead cells keep theirs, to tint their trails); in Brian's
+    // Brain, whether a dead cell is dying; with a rule table, a live
+    // cell's state.
+    pub state: u8,
+}
+
+impl Cell {
+    // Initialize a new cell (alive or dead)
+    pub fn new(alive: bool) -> Self {
+        let heat = if alive { 255 } else { 0 };
+        Self {
+            alive,
+            heat,
+            age: 0,
+            state: 0,
+        }
+    }
+
+    // cools off a dead cell by one generation's worth of decay.
+    pub(crate) fn cool_if_dead(&mut self, decay: Decay) {
+        if !self.alive && self.heat > 0 {
+            self.heat = decay.cool(self.heat);
+        }
+    }
+
+    // A live cell lives on another generation, getting older.
+    pub(crate) fn survive(&mut self) {
+        self.heat = 255;
+        self.age = self.age.saturating_add(1);
+    }
+
+    // A dead cell comes alive, at age 0.
+    pub(crate) fn born(&mut self) {
+        self.alive = true;
+        self.heat = 255;
+        self.age = 0;
+    }
+
+    // Edits: a cell that's already alive keeps its age.
+    pub fn set(&mut self, alive: bool) {
+        if alive && !self.alive {
+            self.born();
+        } else if alive {
+            self.heat = 255;
+        } else {
+            self.alive = false;
+        }
+    }
+}
+
+/// Every cell of a grid, row by row, kept as an array per field: the
+/// neighbor counts only read `alive`, so that's all they pull into cache.
+/// `get` and `put` go between this and `Cell`s.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cells {
+    pub alive: Vec<bool>,
+    pub heat: Vec<u8>,
+    pub age: Vec<u16>,
+    pub state: Vec<u8>,
+}
+
+impl Cells {
+    /// `len` dead, cold cells.
+    pub fn new(len: usize) -> Self {
+        Self {
+            alive: vec![false; len],
+            heat: vec![0; len],
+            age: vec![0; len],
+            state: vec![0; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alive.is_empty()
+    }
+
+    /// Cell `i`, put back together.
+    pub fn get(&self, i: usize) -> Cell {
+        Cell {
+            alive: self.alive[i],
+            heat: self.heat[i],
+            age: self.age[i],
+            state: self.state[i],
+        }
+    }
+
+    /// Sets cell `i` to `cell`.
+    pub fn put(&mut self, i: usize, cell: Cell) {
+        self.alive[i] = cell.alive;
+        self.heat[i] = cell.heat;
+        self.age[i] = cell.age;
+        self.state[i] = cell.state;
+    }
+
+    /// Every cell, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.alive
+            .iter()
+            .zip(&self.heat)
+            .zip(&self.age)
+            .zip(&self.state)
+            .map(|(((&alive, &heat), &age), &state)| Cell {
+                alive,
+                heat,
+                age,
+                state,
+            })
+    }
+}
+
+impl FromIterator<Cell> for Cells {
+    fn from_iter<I: IntoIterator<Item = Cell>>(cells: I) -> Self {
+        let mut all = Self::default();
+        for cell in cells {
+            all.alive.push(cell.alive);
+            all.heat.push(cell.heat);
+            all.age.push(cell.age);
+            all.state.push(cell.state);
+        }
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::grid::Grid;
+
+    fn aged(age: u16) -> Cell {
+        Cell {
+            age,
+            ..Cell::new(true)
+        }
+    }
+
+    #[test]
+    fn birth_starts_the_age_over() {
+        let mut cell = Cell {
+            alive: false,
+            age: 40,
+            ..Cell::default()
+        };
+        cell.born();
+        assert_eq!((cell.alive, cell.heat, cell.age), (true, 255, 0));
+
+        let mut cell = Cell {
+            alive: false,
+            age: 40,
+            ..Cell::default()
+        };
+        cell.set(true);
+        assert_eq!((cell.alive, cell.age), (true, 0));
+    }
+
+    #[test]
+    fn surviving_ages_a_cell_up_to_the_limit() {
+        let mut cell = aged(0);
+        for age in 1..=3 {
+            cell.survive();
+            assert_eq!(cell.age, age);
+        }
+
+        let mut cell = aged(u16::MAX - 1);
+        cell.survive();
+        assert_eq!(cell.age, u16::MAX);
+        cell.survive();
+        assert_eq!(cell.age, u16::MAX);
+    }
+
+    #[test]
+    fn setting_a_live_cell_alive_keeps_its_age() {
+        let mut cell = aged(12);
+        cell.heat = 30;
+        cell.set(true);
+        assert_eq!((cell.alive, cell.heat, cell.age), (true, 255, 12));
+
+        // Killing it leaves the age for the trail; coming back resets it.
+        cell.set(false);
+        assert!(!cell.alive);
+        cell.set(true);
+        assert_eq!(cell.age, 0);
+    }
+
+    #[test]
+    fn every_engine_ages_cells_alike() {
+        // A block ages a generation at a time; a blinker's ends are born
+        // every generation and its middle lives on.
+        let rows = "..........\n.##.......\n.##....#..\n.......#..\n.......#..\n..........\n";
+        for engine in [Engine::Scalar, Engine::Bitset, Engine::Active] {
+            let mut g: Grid = rows.parse().unwrap();
+            g.engine = engine;
+            for generation in 1..=5 {
+                g.update();
+                assert_eq!(g.age_at(1, 1), Some(generation), "{:?}", engine);
+                assert_eq!(g.age_at(7, 3), Some(generation), "{:?}", engine);
+                let ends = if generation % 2 == 1 {
+                    [(6, 3), (8, 3)]
+                } else {
+                    [(7, 2), (7, 4)]
+                };
+                for (x, y) in ends {
+                    assert_eq!(g.age_at(x, y), Some(0), "{:?}", engine);
+                }
+            }
+        }
+    }
+}
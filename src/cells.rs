@@ -0,0 +1,81 @@
+//
+// Plaintext patterns (`.cells`), the other format LifeWiki offers:
+//
+//   !Name: Glider
+//   .O
+//   ..O
+//   OOO
+//
+// `O` is a live cell and `.` a dead one, a row per line; lines starting
+// with `!` are comments. `*` is accepted for live cells too, as some older
+// files use it.
+//
+
+use std::fmt;
+
+use crate::pattern::PatternBuffer;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CellsError {
+    /// A character that isn't a cell.
+    UnexpectedChar(char),
+}
+
+impl fmt::Display for CellsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(c) => write!(f, "unexpected character in plaintext: {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for CellsError {}
+
+/// Parses a plaintext pattern. Short rows are padded with dead cells.
+pub fn parse(text: &str) -> Result<PatternBuffer, CellsError> {
+    let rows: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.starts_with('!'))
+        .collect();
+
+    let mut live = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            match c {
+                'O' | '*' => live.push((x, y)),
+                '.' => {}
+                c => return Err(CellsError::UnexpectedChar(c)),
+            }
+        }
+    }
+
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut pattern = PatternBuffer::new(width, rows.len());
+    for (x, y) in live {
+        pattern.set(x, y, true);
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_are_skipped_and_short_rows_padded() {
+        let glider = parse("!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!(glider, PatternBuffer::from_rows(&[".#.", "..#", "###"]));
+        assert_eq!(parse(".*\n*").unwrap(), parse(".O\nO.").unwrap());
+    }
+
+    #[test]
+    fn anything_but_cells_is_refused() {
+        assert_eq!(parse(".O\nxO\n"), Err(CellsError::UnexpectedChar('x')));
+        assert_eq!(parse("#N Glider"), Err(CellsError::UnexpectedChar('#')));
+    }
+}
@@ -0,0 +1,674 @@
+//
+// Command line options.
+//
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tgol::ant::DEFAULT_ANT_STEPS;
+use tgol::automaton::Automaton;
+use tgol::decay::Decay;
+use tgol::density::DEFAULT_BLOCK;
+use tgol::engine::Engine;
+use tgol::history;
+use tgol::noise::Noise;
+use tgol::pattern::PatternBuffer;
+use tgol::png_export::PngOptions;
+use tgol::rule::Rule;
+use tgol::rule_table::RuleTable;
+use tgol::stochastic::Stochastic;
+use tgol::svg::SvgOptions;
+use tgol::symmetry::SoupSymmetry;
+use tgol::topology::Topology;
+use tgol::world::World;
+
+// Largest texture side every wgpu backend supports.
+const MAX_GRID_SIDE: u32 = 8192;
+
+// Most pixels per cell --export-scale allows.
+const MAX_EXPORT_SCALE: u32 = 64;
+
+pub const USAGE: &str = "\
+USAGE:
+    tgol [OPTIONS]
+    tgol convert <INPUT.rle> <OUTPUT.svg>
+
+OPTIONS:
+    -h, --help                Print this help and exit
+        --config <PATH>       Config file [default: ~/.config/tgol/config.toml]
+        --width <N>           Grid width in cells [default: 384, or the terminal's with --ascii/--tui]
+        --height <N>          Grid height in cells [default: 240, or the terminal's with --ascii/--tui]
+        --no-splash           Start without the title intro
+        --fresh               Start from a new soup, not where the last session left off
+        --autosave <SECS>     Save the grid every SECS seconds while the window's open, in case
+                              it doesn't get to close cleanly [default: off]
+        --autosave-keep <K>   Autosaves kept, the oldest written over first [default: 3]
+        --restore-autosave    Start from the newest autosave, not the last session
+        --ascii               Run in the terminal, printing each generation as text
+        --tui                 Interactive terminal frontend (arrows, Enter, space, p, r, q)
+        --tick-ms <MS>        Delay between generations with --ascii/--tui [default: 100]
+        --tps <N>             Generations per second in the window [default: 60]
+        --no-smooth           Switch straight to each generation, rather than fading between
+                              them while --tps is below the display's refresh rate
+        --debug-mouse         Cross-hair the cell the mouse maps to, with the position and
+                              scale it was worked out from (to check clicks land true)
+        --generations <N>     Run N generations without a window, print a summary and exit
+                              (start from just --patterns with --empty)
+        --out <PATH>          Write the grid --generations ends with to PATH as RLE
+        --bench <N>           Run N generations without a window, print timings and exit
+                              (for every engine, unless --engine picks one)
+        --timings             Time stepping, drawing and presenting each frame, and log their
+                              averages and 95th percentiles every few seconds (and show them
+                              in the corner); with --bench, per generation for each run
+        --engine <ENGINE>     How generations are computed: scalar, bitset, active or hashlife
+                              [default: bitset]
+        --world <WORLD>       torus, or infinite to make the window a view onto an unbounded
+                              plane (arrows / middle-drag pan) [default: torus]
+        --topology <NAME>     How the torus's edges are glued: torus, klein-x / klein-y (one pair
+                              flipped), cross-surface (both flipped), plane (no wrapping), or
+                              wall (no wrapping, and live cells past the edges), or torus+X,Y
+                              (see --wrap-shift) [default: torus, or the config's]
+        --wrap-shift <x=X,y=Y>
+                              Twist the torus: what wraps across the left and right edges moves
+                              Y cells down, or across the top and bottom X cells right (one of
+                              the two) [default: x=0,y=0]
+        --pattern <PATH>      Place an RLE, plaintext or Life 1.06 pattern on the starting grid
+                              (repeatable; centered unless followed by --at; - reads stdin,
+                              as does a lone - argument)
+        --at <X,Y>            Where the matching --pattern's top-left corner goes
+        --empty               Start without a random soup, e.g. to hold just some --patterns
+        --rule <RULE>         Life-like rule in B/S notation [default: B3/S23]
+        --automaton <NAME>    life, immigration / quadlife for Life with 2 / 4 teams ([T] picks
+                              the team you draw for), brain for Brian's Brain, or wireworld
+                              ([T] picks what you draw), or ant for Langton's Ant (click to
+                              add ants) [default: life]
+        --rulefile <PATH>     Follow a Golly .rule file's @TABLE instead ([T] picks the state you
+                              draw)
+        --ant-steps <N>       Moves each Langton's Ant makes a generation [default: 20]
+        --stochastic <P>      Leave the rule's births and survivals to chance, like
+                              pb=0.98,ps=0.99 (either one left out is 1) [default: off]
+        --seed <N>            Seed for everything random, to replay a session [default: random]
+        --density <P>         Chance that a cell starts alive in a new soup [default: 0.25]
+        --symmetry <MODE>     Symmetry of new soups: none, mirror-x, mirror-y, both, rot180
+        --decay <RATE>        Heat trails lose per generation: 1-255, or exp:F to scale by F [default: 50]
+        --noise <RATE>        Cells flipped at random every generation: a count, or a percentage
+                              of the grid like 0.01% [default: 0, none]
+        --pre-roll <N>        Generations a new soup runs before it's shown [default: 5]
+        --undo-budget <MIB>   Memory kept for undo/redo, in MiB [default: 64]
+        --kill-prob <P>       Chance that [K] kills any given live cell [default: 0.7]
+        --pause-on-stable     Pause once the grid repeats itself (still lifes and oscillators only)
+        --stable-period <P>   Longest period --pause-on-stable looks for [default: 6]
+        --pause-when-hidden   Pause while the window's minimized or covered up (otherwise the grid
+                              carries on out of sight)
+        --pause-on-unfocus    Pause while another window has the focus, and carry on when it's
+                              back, unless paused by hand meanwhile (or set [window]
+                              pause_on_unfocus in the config)
+        --stats-out <PATH>    Write each generation's population, births and deaths to PATH as CSV
+        --record <PATH>       Write everything done to the grid to PATH, to replay later
+        --replay <PATH>       Play back a session written by --record, and check it ends the same
+        --script <PATH>       Run a Rhai script on the starting grid, and its on_generation()
+                              after every generation (`scripting` feature); see src/script.rs
+        --control-socket <PATH>
+                              Take JSON commands (pause, step, load_rle, ...) from scripts on a
+                              Unix socket at PATH; see src/control.rs
+        --control-port <PORT> Take the same commands on a TCP port on localhost
+        --http-view <PORT>    Serve the grid as MJPEG on http://<bind>:<PORT>/
+        --http-bind <ADDR>    Address for --http-view to listen on [default: 127.0.0.1]
+        --http-fps <FPS>      Frames per second streamed by --http-view [default: 2]
+        --http-scale <SCALE>  Size of streamed frames relative to the grid [default: 0.5]
+        --audio               Play the grid: a click each generation, pitched by its births, over
+                              a pad as loud as the population ([Ctrl+M] mutes; `audio` feature)
+        --density-block <N>   Cells along each side of a block in the density wash ([D])
+                              [default: 8]
+        --svg-cell-size <N>   Size of one cell in exported SVGs [default: 8]
+        --svg-grid-lines      Draw lines between cells in exported SVGs
+        --export-scale <N>    Pixels per cell in PNG exports (Shift+F12) [default: 8]
+        --export-grid-lines   Draw the grid lines ([L]) in PNG exports
+        --apng-frames <N>     Generations [Shift+A] captures as an animated PNG [default: 100]
+        --apng-delay <MS>     How long each of those frames shows for [default: 50]
+        --pipe-frames         Write every generation shown to stdout as raw RGBA frames
+        --ffmpeg <ARGS>       Encode every generation shown to video, with an ffmpeg started with
+                              these output arguments, e.g. \"-c:v libx264 -pix_fmt yuv420p out.mp4\"
+        --dump-frames <DIR>   Write every generation to DIR as frame_000001.png, frame_000002.png...
+                              (created if missing; it has to be empty unless --force)
+        --dump-scale <N>      Pixels per cell in --dump-frames frames [default: 1]
+        --force               Let --dump-frames write into a directory that isn't empty
+";
+
+pub enum Command {
+    /// Convert a pattern file to SVG, without opening a window.
+    Convert { input: PathBuf, output: PathBuf },
+}
+
+/// A `--pattern` to start with, and its `--at`.
+pub struct Placement {
+    pub pattern: PatternBuffer,
+    pub at: Option<(isize, isize)>,
+}
+
+pub struct Options {
+    pub command: Option<Command>,
+    pub help: bool,
+    pub config: Option<PathBuf>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub no_splash: bool,
+    pub fresh: bool,
+    pub autosave: Option<u64>,
+    pub autosave_keep: usize,
+    pub restore_autosave: bool,
+    pub generations: Option<u64>,
+    pub out: Option<PathBuf>,
+    pub bench: Option<u64>,
+    pub timings: bool,
+    pub ascii: bool,
+    pub tui: bool,
+    pub tick_ms: u64,
+    pub tps: Option<u32>,
+    pub no_smooth: bool,
+    pub debug_mouse: bool,
+    pub engine: Option<Engine>,
+    pub world: World,
+    pub topology: Option<Topology>,
+    pub wrap_shift: Option<(i32, i32)>,
+    pub patterns: Vec<Placement>,
+    pub empty: bool,
+    pub rule: Rule,
+    pub automaton: Automaton,
+    /// The --rulefile, loaded; it makes the automaton `Automaton::Table`.
+    pub rule_table: Option<Arc<RuleTable>>,
+    pub ant_steps: u32,
+    pub stochastic: Option<Stochastic>,
+    pub seed: Option<u64>,
+    pub density: f32,
+    pub symmetry: SoupSymmetry,
+    pub decay: Option<Decay>,
+    pub noise: Noise,
+    pub pre_roll: usize,
+    pub undo_budget: usize,
+    pub pause_on_stable: bool,
+    pub pause_when_hidden: bool,
+    pub pause_on_unfocus: bool,
+    pub stable_period: Option<usize>,
+    pub kill_prob: Option<f32>,
+    pub stats_out: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    #[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+    pub script: Option<PathBuf>,
+    pub control_socket: Option<PathBuf>,
+    pub control_port: Option<u16>,
+    pub http_view: Option<u16>,
+    #[cfg_attr(not(feature = "http-view"), allow(dead_code))]
+    pub http_bind: IpAddr,
+    pub http_fps: f32,
+    pub http_scale: f32,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pub audio: bool,
+    pub density_block: usize,
+    pub svg_cell_size: u32,
+    pub svg_grid_lines: bool,
+    pub export_scale: u32,
+    pub export_grid_lines: bool,
+    pub apng_frames: u32,
+    pub apng_delay: u16,
+    pub pipe_frames: bool,
+    pub ffmpeg: Option<Vec<String>>,
+    pub dump_frames: Option<PathBuf>,
+    pub dump_scale: u32,
+    pub force: bool,
+}
+
+impl Options {
+    /// Parses the process arguments. Errors are meant to be shown to the
+    /// user as-is, followed by `USAGE`.
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(pico_args::Arguments::from_env())
+    }
+
+    /// Parses `args`, as if they'd been given on the command line (without
+    /// the program's name).
+    #[cfg(test)]
+    pub fn from_args(args: &[&str]) -> Result<Self, String> {
+        Self::parse(pico_args::Arguments::from_vec(
+            args.iter().map(Into::into).collect(),
+        ))
+    }
+
+    fn parse(mut args: pico_args::Arguments) -> Result<Self, String> {
+        let command = match args.subcommand().map_err(describe)?.as_deref() {
+            Some("convert") => Some(Command::Convert {
+                input: args.free_from_str().map_err(describe)?,
+                output: args.free_from_str().map_err(describe)?,
+            }),
+            Some(other) if !other.starts_with('-') => {
+                return Err(format!("unknown command {:?}", other));
+            }
+            _ => None,
+        };
+
+        // The nth --at goes with the nth --pattern. They're loaded once
+        // everything else has been read, as `-` may be among them.
+        let mut pattern_paths: Vec<PathBuf> =
+            args.values_from_str("--pattern").map_err(describe)?;
+        let mut ats = args.values_from_fn("--at", parse_at).map_err(describe)?;
+
+        let rule_table = args
+            .opt_value_from_str::<_, PathBuf>("--rulefile")
+            .map_err(describe)?
+            .map(|path| RuleTable::load(&path))
+            .transpose()?
+            .map(Arc::new);
+
+        let mut options = Self {
+            command,
+            help: args.contains(["-h", "--help"]),
+            config: args.opt_value_from_str("--config").map_err(describe)?,
+            width: args.opt_value_from_str("--width").map_err(describe)?,
+            height: args.opt_value_from_str("--height").map_err(describe)?,
+            no_splash: args.contains("--no-splash"),
+            fresh: args.contains("--fresh"),
+            autosave: args.opt_value_from_str("--autosave").map_err(describe)?,
+            autosave_keep: args
+                .opt_value_from_str("--autosave-keep")
+                .map_err(describe)?
+                .unwrap_or(crate::autosave::DEFAULT_KEEP),
+            restore_autosave: args.contains("--restore-autosave"),
+            generations: args.opt_value_from_str("--generations").map_err(describe)?,
+            out: args.opt_value_from_str("--out").map_err(describe)?,
+            bench: args.opt_value_from_str("--bench").map_err(describe)?,
+            timings: args.contains("--timings"),
+            ascii: args.contains("--ascii"),
+            tui: args.contains("--tui"),
+            tick_ms: args
+                .opt_value_from_str("--tick-ms")
+                .map_err(describe)?
+                .unwrap_or(100),
+            tps: args.opt_value_from_str("--tps").map_err(describe)?,
+            no_smooth: args.contains("--no-smooth"),
+            debug_mouse: args.contains("--debug-mouse"),
+            engine: args.opt_value_from_str("--engine").map_err(describe)?,
+            world: args
+                .opt_value_from_str("--world")
+                .map_err(describe)?
+                .unwrap_or_default(),
+            topology: args.opt_value_from_str("--topology").map_err(describe)?,
+            wrap_shift: args
+                .opt_value_from_fn("--wrap-shift", parse_wrap_shift)
+                .map_err(describe)?,
+            patterns: Vec::new(),
+            empty: args.contains("--empty"),
+            rule: args
+                .opt_value_from_str("--rule")
+                .map_err(describe)?
+                .unwrap_or_default(),
+            automaton: args
+                .opt_value_from_str("--automaton")
+                .map_err(describe)?
+                .unwrap_or_default(),
+            rule_table,
+            ant_steps: args
+                .opt_value_from_str("--ant-steps")
+                .map_err(describe)?
+                .unwrap_or(DEFAULT_ANT_STEPS),
+            stochastic: args.opt_value_from_str("--stochastic").map_err(describe)?,
+            seed: args.opt_value_from_str("--seed").map_err(describe)?,
+            density: args
+                .opt_value_from_str("--density")
+                .map_err(describe)?
+                .unwrap_or(0.25),
+            symmetry: args
+                .opt_value_from_str("--symmetry")
+                .map_err(describe)?
+                .unwrap_or_default(),
+            decay: args.opt_value_from_str("--decay").map_err(describe)?,
+            noise: args
+                .opt_value_from_str("--noise")
+                .map_err(describe)?
+                .unwrap_or_default(),
+            pre_roll: args
+                .opt_value_from_str("--pre-roll")
+                .map_err(describe)?
+                .unwrap_or(5),
+            undo_budget: args
+                .opt_value_from_str::<_, usize>("--undo-budget")
+                .map_err(describe)?
+                .map_or(history::DEFAULT_BUDGET, |mib| {
+                    mib.saturating_mul(1024 * 1024)
+                }),
+            pause_on_stable: args.contains("--pause-on-stable"),
+            pause_when_hidden: args.contains("--pause-when-hidden"),
+            pause_on_unfocus: args.contains("--pause-on-unfocus"),
+            stable_period: args
+                .opt_value_from_str("--stable-period")
+                .map_err(describe)?,
+            kill_prob: args.opt_value_from_str("--kill-prob").map_err(describe)?,
+            stats_out: args.opt_value_from_str("--stats-out").map_err(describe)?,
+            record: args.opt_value_from_str("--record").map_err(describe)?,
+            replay: args.opt_value_from_str("--replay").map_err(describe)?,
+            script: args.opt_value_from_str("--script").map_err(describe)?,
+            control_socket: args
+                .opt_value_from_str("--control-socket")
+                .map_err(describe)?,
+            control_port: args
+                .opt_value_from_str("--control-port")
+                .map_err(describe)?,
+            http_view: args.opt_value_from_str("--http-view").map_err(describe)?,
+            http_bind: args
+                .opt_value_from_str("--http-bind")
+                .map_err(describe)?
+                .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            http_fps: args
+                .opt_value_from_str("--http-fps")
+                .map_err(describe)?
+                .unwrap_or(2.0),
+            http_scale: args
+                .opt_value_from_str("--http-scale")
+                .map_err(describe)?
+                .unwrap_or(0.5),
+            audio: args.contains("--audio"),
+            density_block: args
+                .opt_value_from_str("--density-block")
+                .map_err(describe)?
+                .unwrap_or(DEFAULT_BLOCK),
+            svg_cell_size: args
+                .opt_value_from_str("--svg-cell-size")
+                .map_err(describe)?
+                .unwrap_or(8),
+            svg_grid_lines: args.contains("--svg-grid-lines"),
+            export_scale: args
+                .opt_value_from_str("--export-scale")
+                .map_err(describe)?
+                .unwrap_or(8),
+            export_grid_lines: args.contains("--export-grid-lines"),
+            apng_frames: args
+                .opt_value_from_str("--apng-frames")
+                .map_err(describe)?
+                .unwrap_or(100),
+            apng_delay: args
+                .opt_value_from_str("--apng-delay")
+                .map_err(describe)?
+                .unwrap_or(50),
+            pipe_frames: args.contains("--pipe-frames"),
+            ffmpeg: args
+                .opt_value_from_str::<_, String>("--ffmpeg")
+                .map_err(describe)?
+                .map(|args| args.split_whitespace().map(str::to_string).collect()),
+            dump_frames: args.opt_value_from_str("--dump-frames").map_err(describe)?,
+            dump_scale: args
+                .opt_value_from_str("--dump-scale")
+                .map_err(describe)?
+                .unwrap_or(1),
+            force: args.contains("--force"),
+        };
+
+        // `tgol -` is short for `tgol --pattern -`.
+        let rest = args.finish();
+        match rest.as_slice() {
+            [] => {}
+            [arg] if arg == "-" => pattern_paths.push(PathBuf::from("-")),
+            [arg, ..] => return Err(format!("unexpected argument {:?}", arg)),
+        }
+        if ats.len() > pattern_paths.len() {
+            return Err("--at needs a --pattern to place".to_string());
+        }
+        ats.resize(pattern_paths.len(), None);
+        let stdin = Path::new("-");
+        if pattern_paths.iter().filter(|path| *path == stdin).count() > 1 {
+            return Err("only one --pattern can be read from stdin".to_string());
+        }
+        for (path, at) in pattern_paths.iter().zip(ats) {
+            let name = if path == stdin {
+                "stdin".to_string()
+            } else {
+                path.display().to_string()
+            };
+            let pattern =
+                crate::load_pattern(path).map_err(|e| format!("couldn't load {}: {}", name, e))?;
+            options.patterns.push(Placement { pattern, at });
+        }
+
+        if options.generations.is_some()
+            && (options.bench.is_some() || options.ascii || options.tui)
+        {
+            return Err(
+                "--generations runs without a window, so not with --bench, --ascii or --tui"
+                    .to_string(),
+            );
+        }
+        if options.control_socket.is_some() && options.control_port.is_some() {
+            return Err("--control-socket and --control-port can't be used together".to_string());
+        }
+
+        if options.out.is_some() && options.generations.is_none() {
+            return Err("--out is written at the end of a --generations run".to_string());
+        }
+
+        if options.bench == Some(0) {
+            return Err("--bench needs at least 1 generation".to_string());
+        }
+
+        if options.stable_period == Some(0) {
+            return Err("--stable-period must be at least 1".to_string());
+        }
+
+        if options.kill_prob.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            return Err("--kill-prob must be between 0 and 1".to_string());
+        }
+
+        if options.tps == Some(0) {
+            return Err("--tps must be at least 1".to_string());
+        }
+
+        if options.rule_table.is_some() {
+            if !matches!(options.automaton, Automaton::Life | Automaton::Table) {
+                return Err(format!(
+                    "--rulefile and --automaton {} can't be used together",
+                    options.automaton
+                ));
+            }
+            options.automaton = Automaton::Table;
+        } else if options.automaton == Automaton::Table {
+            return Err("--automaton table needs a --rulefile".to_string());
+        }
+
+        // --wrap-shift is short for --topology torus+X,Y.
+        if let Some((x, y)) = options.wrap_shift {
+            if options.topology.is_some_and(|t| t != Topology::Torus) {
+                return Err("--wrap-shift only twists a plain --topology torus".to_string());
+            }
+            options.topology = Some(Topology::shifted(x, y)?);
+        }
+
+        options.grid_setup().check()?;
+
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("--record and --replay can't be used together".to_string());
+        }
+        if options.autosave == Some(0) {
+            return Err("--autosave must be at least 1 second".to_string());
+        }
+        if options.autosave_keep == 0 {
+            return Err("--autosave-keep must be at least 1".to_string());
+        }
+        if options.restore_autosave && options.fresh {
+            return Err("--restore-autosave and --fresh can't be used together".to_string());
+        }
+
+        // The recording has what the script did already.
+        if options.script.is_some() && options.replay.is_some() {
+            return Err("--script and --replay can't be used together".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&options.density) {
+            return Err("--density must be between 0 and 1".to_string());
+        }
+
+        if !(options.http_fps > 0.0 && options.http_fps <= 60.0) {
+            return Err("--http-fps must be between 0 and 60".to_string());
+        }
+        if !(options.http_scale > 0.0 && options.http_scale <= 8.0) {
+            return Err("--http-scale must be between 0 and 8".to_string());
+        }
+
+        if options.density_block == 0 {
+            return Err("--density-block must be at least 1".to_string());
+        }
+
+        if options.svg_cell_size == 0 {
+            return Err("--svg-cell-size must be at least 1".to_string());
+        }
+        if options.pipe_frames && options.ffmpeg.is_some() {
+            return Err("--pipe-frames and --ffmpeg can't be used together".to_string());
+        }
+        if options.pipe_frames && (options.ascii || options.tui) {
+            return Err(
+                "--pipe-frames needs stdout to itself, so not --ascii or --tui".to_string(),
+            );
+        }
+        if options.ffmpeg.as_ref().is_some_and(Vec::is_empty) {
+            return Err("--ffmpeg needs at least the file to write".to_string());
+        }
+
+        if options.apng_frames == 0 {
+            return Err("--apng-frames must be at least 1".to_string());
+        }
+        if !(1..=MAX_EXPORT_SCALE).contains(&options.export_scale) {
+            return Err(format!(
+                "--export-scale must be between 1 and {}",
+                MAX_EXPORT_SCALE
+            ));
+        }
+        if !(1..=MAX_EXPORT_SCALE).contains(&options.dump_scale) {
+            return Err(format!(
+                "--dump-scale must be between 1 and {}",
+                MAX_EXPORT_SCALE
+            ));
+        }
+        if options.dump_frames.is_some() && (options.ascii || options.tui) {
+            return Err("--dump-frames needs the window or --generations".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /// What the grid is made from, for `GridSetup::check`.
+    pub fn grid_setup(&self) -> GridSetup {
+        GridSetup {
+            size: self.grid_size(),
+            automaton: self.automaton,
+            rule_table: self.rule_table.is_some(),
+            ant_steps: self.ant_steps,
+            world: self.world,
+            topology: self.topology.unwrap_or_default(),
+            stochastic: self.stochastic.is_some(),
+        }
+    }
+
+    /// Grid size from --width/--height, falling back to the default.
+    pub fn grid_size(&self) -> (u32, u32) {
+        (
+            self.width.unwrap_or(crate::WIDTH),
+            self.height.unwrap_or(crate::HEIGHT),
+        )
+    }
+
+    /// PNG export options, with `grid_line` as the line color if
+    /// --export-grid-lines asks for lines.
+    pub fn png_options(&self, grid_line: [u8; 4]) -> PngOptions {
+        PngOptions {
+            cell_size: self.export_scale,
+            grid_line: self.export_grid_lines.then_some(grid_line),
+        }
+    }
+
+    pub fn svg_options(&self) -> SvgOptions {
+        SvgOptions {
+            cell_size: self.svg_cell_size,
+            grid_lines: self.svg_grid_lines,
+            ..SvgOptions::default()
+        }
+    }
+}
+
+/// What the grid is made from, wherever that's read: the command line,
+/// the last session or a replay. The last two set all of it (see their
+/// `configure`), so they're checked the same way on their own.
+pub struct GridSetup {
+    pub size: (u32, u32),
+    pub automaton: Automaton,
+    pub rule_table: bool,
+    pub ant_steps: u32,
+    pub world: World,
+    pub topology: Topology,
+    pub stochastic: bool,
+}
+
+impl GridSetup {
+    /// Whether the grid can be made: its size, the ant steps, the rule
+    /// file with its automaton, and the automaton, topology and stochastic
+    /// rule with its world.
+    pub fn check(&self) -> Result<(), String> {
+        // The grid is uploaded as a single texture, which GPUs cap in size.
+        let (width, height) = self.size;
+        for (side, value) in [("width", width), ("height", height)] {
+            if !(1..=MAX_GRID_SIDE).contains(&value) {
+                return Err(format!(
+                    "the {} must be between 1 and {} (got {})",
+                    side, MAX_GRID_SIDE, value
+                ));
+            }
+        }
+
+        if self.ant_steps == 0 {
+            return Err("--ant-steps must be at least 1".to_string());
+        }
+        if self.rule_table != (self.automaton == Automaton::Table) {
+            return Err("--rulefile and --automaton table go together".to_string());
+        }
+
+        if self.automaton != Automaton::Life && self.world == World::Infinite {
+            return Err(format!(
+                "--automaton {} only runs on a torus, not --world infinite",
+                self.automaton
+            ));
+        }
+        if self.topology != Topology::Torus && self.world == World::Infinite {
+            return Err("--topology only applies to --world torus".to_string());
+        }
+        if self.stochastic && self.world == World::Infinite {
+            return Err("--stochastic only runs on a torus, not --world infinite".to_string());
+        }
+        Ok(())
+    }
+}
+
+// "100,50", for --at.
+fn parse_at(s: &str) -> Result<Option<(isize, isize)>, String> {
+    let bad = || format!("expected X,Y, got {:?}", s);
+    let (x, y) = s.split_once(',').ok_or_else(bad)?;
+    let x = x.trim().parse().map_err(|_| bad())?;
+    let y = y.trim().parse().map_err(|_| bad())?;
+    Ok(Some((x, y)))
+}
+
+// `x=X,y=Y`, either one left out for no shift that way.
+fn parse_wrap_shift(s: &str) -> Result<(i32, i32), String> {
+    let mut shift = (0, 0);
+    for part in s.split(',') {
+        let bad = || format!("expected x=X,y=Y, got {:?}", s);
+        let (axis, cells) = part.split_once('=').ok_or_else(bad)?;
+        let cells = cells.trim().parse().map_err(|_| bad())?;
+        match axis.trim() {
+            "x" => shift.0 = cells,
+            "y" => shift.1 = cells,
+            _ => return Err(bad()),
+        }
+    }
+    Ok(shift)
+}
+
+fn describe(e: pico_args::Error) -> String {
+    e.to_string()
+}
@@ -0,0 +1,77 @@
+//
+// Text to and from the system clipboard, by asking whichever of the usual
+// command line tools is around: wl-copy / wl-paste, xclip or xsel on
+// Linux, pbcopy / pbpaste on macOS, clip and PowerShell on Windows. The
+// browser build has no clipboard.
+//
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::{Command, Stdio};
+
+#[cfg(not(target_arch = "wasm32"))]
+const READERS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+    ("powershell", &["-NoProfile", "-Command", "Get-Clipboard"]),
+];
+
+/// The clipboard's text, or None if there's no way to read it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_text() -> Option<String> {
+    READERS.iter().find_map(|(program, args)| {
+        let output = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read_text() -> Option<String> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const WRITERS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard", "-in"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip", &[]),
+];
+
+/// Puts `text` on the clipboard. Returns false if there's no way to.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_text(text: &str) -> bool {
+    WRITERS.iter().any(|(program, args)| {
+        // Some of them stay around in the background to serve the text, so
+        // nothing waits on their output.
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write_text(_text: &str) -> bool {
+    false
+}
@@ -0,0 +1,571 @@
+//
+// The config file: a small subset of TOML, enough for flat settings.
+//
+//   # comment
+//   [section]
+//   key = "string"
+//   other = 12
+//   flag = true
+//
+// Read from `--config <path>`, or `$XDG_CONFIG_HOME/tgol/config.toml`
+// (`~/.config/tgol/config.toml`) when that exists.
+//
+// The window watches it while it runs (see `Watcher`): an edit that reads
+// cleanly is applied as far as it can be there and then, and one that
+// doesn't leaves everything as it was, with the error in the log.
+//
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use instant::Instant;
+
+use tgol::automaton::{BRAIN_COLORS, WIREWORLD_COLORS};
+use tgol::decay::Decay;
+use tgol::gradient::{self, Gradient};
+use tgol::heat::HeatColors;
+use tgol::noise::Noise;
+use tgol::topology::Topology;
+
+use crate::keymap::KeyMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::Int(n) => write!(f, "{}", n),
+            Self::Float(x) => write!(f, "{}", x),
+            Self::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub section: String,
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+    /// Where on the line the value starts, from 1.
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Where it was read from, for error messages. None for the defaults.
+    pub path: Option<PathBuf>,
+    pub entries: Vec<Entry>,
+}
+
+impl Config {
+    /// Loads `path` if given (it must exist), else the default location if
+    /// there's a file there, else an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let mut config =
+                    Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+                log::info!("Loaded config from {}", path.display());
+                config.path = Some(path);
+                Ok(config)
+            }
+            Err(e) if required || e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("couldn't read {}: {}", path.display(), e))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for (n, raw) in text.lines().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            // Where `part` (a slice of `raw`) starts on the line, from 1.
+            let column = |part: &str| {
+                let start = part.as_ptr() as usize - raw.as_ptr() as usize;
+                raw[..start].chars().count() + 1
+            };
+
+            if let Some(name) = line.strip_prefix('[') {
+                section = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| {
+                        format!("line {}:{}: unclosed section header", n + 1, column(line))
+                    })?
+                    .trim()
+                    .to_string();
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                format!("line {}:{}: expected `key = value`", n + 1, column(line))
+            })?;
+            let value = value.trim();
+            let at = column(value);
+            let value = parse_value(value)
+                .ok_or_else(|| format!("line {}:{}: can't read value {:?}", n + 1, at, value))?;
+
+            config.entries.push(Entry {
+                section: section.clone(),
+                key: key.trim().to_string(),
+                value,
+                line: n + 1,
+                column: at,
+            });
+        }
+
+        Ok(config)
+    }
+
+    /// Entries of one section, in file order.
+    pub fn section<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Entry> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.section == name)
+    }
+
+    /// The last value given for `key` in `section`, if any.
+    pub fn get(&self, section: &str, key: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.section == section && entry.key == key)
+    }
+
+    pub fn bool(&self, section: &str, key: &str) -> Result<Option<bool>, String> {
+        match self.get(section, key) {
+            None => Ok(None),
+            Some(Entry {
+                value: Value::Bool(b),
+                ..
+            }) => Ok(Some(*b)),
+            Some(entry) => Err(self.error_at(entry, format!("{} must be true or false", key))),
+        }
+    }
+
+    /// A value read with `FromStr`: strings as they are, numbers as
+    /// written.
+    pub fn parsed<T>(&self, section: &str, key: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let Some(entry) = self.get(section, key) else {
+            return Ok(None);
+        };
+        let text = match &entry.value {
+            Value::Str(s) => s.clone(),
+            Value::Int(_) | Value::Float(_) => entry.value.to_string(),
+            Value::Bool(_) => return Err(self.error_at(entry, format!("{} can't be a bool", key))),
+        };
+        text.parse()
+            .map(Some)
+            .map_err(|e| self.error_at(entry, format!("{}: {}", key, e)))
+    }
+
+    /// Prefixes `message` with where `entry` came from.
+    pub fn error_at(&self, entry: &Entry, message: impl fmt::Display) -> String {
+        match &self.path {
+            Some(path) => format!(
+                "{}:{}:{}: {}",
+                path.display(),
+                entry.line,
+                entry.column,
+                message
+            ),
+            None => format!("line {}:{}: {}", entry.line, entry.column, message),
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("tgol").join("config.toml"))
+}
+
+// Drops a trailing `# comment`, leaving `#` inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if let Some(inner) = s.strip_prefix('"') {
+        return inner
+            .strip_suffix('"')
+            .map(|inner| Value::Str(inner.to_string()));
+    }
+    match s {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+    if let Ok(n) = s.parse() {
+        return Some(Value::Int(n));
+    }
+    s.parse().ok().map(Value::Float)
+}
+
+/// What the windowed game reads from the config file. All but the
+/// topology and pausing on stable can change while it runs (see
+/// `Watcher`).
+///
+///   [colors]
+///   age_colors = true
+///   age_gradient = "0:#ffffff, 16:#3200ff, 128:#008ca0"
+///   heat = "inferno"       # classic, ember, inferno, viridis, or stops
+///
+///   [trails]
+///   enabled = false
+///   decay = 4              # or "exp:0.97"
+///
+///   [grid_lines]
+///   color = "#ffffff"
+///   alpha = 0.15
+///
+///   [kill]
+///   probability = 0.25     # of each live cell dying on [K]
+///
+///   [speed]
+///   tps = 30               # generations a second
+///
+///   [noise]
+///   rate = "0.05%"         # what [N] turns on
+///
+///   [window]
+///   pause_on_unfocus = true  # like --pause-on-unfocus
+///
+///   [world]
+///   topology = "klein-x"   # torus, klein-x, klein-y, cross-surface, plane, wall, torus+X,Y
+///
+///   [brain]                # --automaton brain
+///   off = "#000000"
+///   on = "#ffffff"
+///   dying = "#3264ff"
+///
+///   [wireworld]            # --automaton wireworld
+///   empty = "#000000"
+///   head = "#005aff"
+///   tail = "#ff2800"
+///   conductor = "#ffc800"
+#[derive(Clone)]
+pub struct Settings {
+    pub keys: KeyMap,
+    /// Start with live cells colored by age.
+    pub age_colors: bool,
+    pub age_gradient: Gradient,
+    /// Trail colors, and live cells' when not colored by age.
+    pub heat_colors: HeatColors,
+    /// Start with heat trails on.
+    pub trails: bool,
+    pub decay: Decay,
+    /// Color of the [L] grid lines, alpha included.
+    pub grid_line_color: [u8; 4],
+    /// Pause once the grid repeats with at most this period.
+    pub pause_on_stable: Option<usize>,
+    /// Chance that [K] kills any given live cell.
+    pub kill_probability: f32,
+    /// Pause while the window's out of focus.
+    pub pause_on_unfocus: bool,
+    /// Generations a second, unless --tps says.
+    pub tps: u32,
+    /// What [N] turns on, unless --noise says.
+    pub noise: Noise,
+    /// How the torus's edges are glued, unless --topology says.
+    pub topology: Option<Topology>,
+    /// Brian's Brain's off, on and dying cells.
+    pub brain_colors: [[u8; 4]; 3],
+    /// Wireworld's empty cells, heads, tails and conductor.
+    pub wireworld_colors: [[u8; 4]; 4],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keys: KeyMap::default(),
+            age_colors: false,
+            age_gradient: Gradient::default(),
+            heat_colors: HeatColors::default(),
+            trails: true,
+            decay: Decay::default(),
+            grid_line_color: DEFAULT_GRID_LINE_COLOR,
+            pause_on_stable: None,
+            kill_probability: DEFAULT_KILL_PROBABILITY,
+            pause_on_unfocus: false,
+            tps: DEFAULT_TPS,
+            noise: DEFAULT_NOISE,
+            topology: None,
+            brain_colors: BRAIN_COLORS,
+            wireworld_colors: WIREWORLD_COLORS,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        Ok(Self {
+            keys: KeyMap::from_config(config)?,
+            age_colors: config.bool("colors", "age_colors")?.unwrap_or(false),
+            age_gradient: config.parsed("colors", "age_gradient")?.unwrap_or_default(),
+            heat_colors: config.parsed("colors", "heat")?.unwrap_or_default(),
+            trails: config.bool("trails", "enabled")?.unwrap_or(true),
+            decay: config.parsed("trails", "decay")?.unwrap_or_default(),
+            grid_line_color: grid_line_color(config)?,
+            pause_on_stable: pause_on_stable(config)?,
+            kill_probability: kill_probability(config)?,
+            pause_on_unfocus: config.bool("window", "pause_on_unfocus")?.unwrap_or(false),
+            tps: tps(config)?,
+            noise: noise(config)?,
+            topology: config.parsed("world", "topology")?,
+            brain_colors: palette(config, "brain", ["off", "on", "dying"], BRAIN_COLORS)?,
+            wireworld_colors: palette(
+                config,
+                "wireworld",
+                ["empty", "head", "tail", "conductor"],
+                WIREWORLD_COLORS,
+            )?,
+        })
+    }
+}
+
+pub const DEFAULT_STABLE_PERIOD: usize = 6;
+
+fn pause_on_stable(config: &Config) -> Result<Option<usize>, String> {
+    if config.bool("stable", "pause")? != Some(true) {
+        return Ok(None);
+    }
+    let period = config.parsed("stable", "max_period")?;
+    Ok(Some(period.unwrap_or(DEFAULT_STABLE_PERIOD).max(1)))
+}
+
+pub const DEFAULT_KILL_PROBABILITY: f32 = 0.7;
+
+fn kill_probability(config: &Config) -> Result<f32, String> {
+    match config.get("kill", "probability") {
+        None => Ok(DEFAULT_KILL_PROBABILITY),
+        Some(entry) => match config.parsed::<f32>("kill", "probability")? {
+            Some(p) if (0.0..=1.0).contains(&p) => Ok(p),
+            _ => Err(config.error_at(entry, "probability must be between 0 and 1")),
+        },
+    }
+}
+
+pub const DEFAULT_TPS: u32 = 60;
+
+fn tps(config: &Config) -> Result<u32, String> {
+    match config.get("speed", "tps") {
+        None => Ok(DEFAULT_TPS),
+        Some(entry) => match config.parsed::<u32>("speed", "tps")? {
+            Some(tps) if tps > 0 => Ok(tps),
+            _ => Err(config.error_at(entry, "tps must be at least 1")),
+        },
+    }
+}
+
+// One cell in 10,000 a generation.
+pub const DEFAULT_NOISE: Noise = Noise::Percent(0.01);
+
+fn noise(config: &Config) -> Result<Noise, String> {
+    match config.get("noise", "rate") {
+        None => Ok(DEFAULT_NOISE),
+        Some(entry) => match config.parsed::<Noise>("noise", "rate")? {
+            Some(Noise::Off) => Err(config.error_at(entry, "rate can't be 0")),
+            noise => Ok(noise.unwrap_or(DEFAULT_NOISE)),
+        },
+    }
+}
+
+const DEFAULT_GRID_LINE_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0x26];
+
+fn grid_line_color(config: &Config) -> Result<[u8; 4], String> {
+    let mut color = DEFAULT_GRID_LINE_COLOR;
+    if let Some(entry) = config.get("grid_lines", "color") {
+        let Value::Str(s) = &entry.value else {
+            return Err(config.error_at(entry, "color must be a string like \"#ffffff\""));
+        };
+        let [r, g, b, _] = gradient::parse_color(s).map_err(|e| config.error_at(entry, e))?;
+        color = [r, g, b, color[3]];
+    }
+    if let Some(alpha) = config.parsed::<f32>("grid_lines", "alpha")? {
+        color[3] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    Ok(color)
+}
+
+// An automaton's colors for each state, read from `keys` in `section`,
+// with `colors` for those not given.
+fn palette<const N: usize>(
+    config: &Config,
+    section: &str,
+    keys: [&str; N],
+    mut colors: [[u8; 4]; N],
+) -> Result<[[u8; 4]; N], String> {
+    for (color, key) in colors.iter_mut().zip(keys) {
+        let Some(entry) = config.get(section, key) else {
+            continue;
+        };
+        let Value::Str(s) = &entry.value else {
+            return Err(
+                config.error_at(entry, format!("{} must be a string like \"#ffffff\"", key))
+            );
+        };
+        *color = gradient::parse_color(s).map_err(|e| config.error_at(entry, e))?;
+    }
+    Ok(colors)
+}
+
+// How often the config file is looked at for edits.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the config file for edits while the window's open, by its
+/// modification time, which is cheap enough to check every second. A file
+/// that isn't there yet is watched for, too.
+pub struct Watcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // What the file said when it last read cleanly, to tell what an edit
+    // changed.
+    settings: Settings,
+    next_check: Instant,
+}
+
+impl Watcher {
+    /// Watches `path` (`--config`), else the default location; `settings`
+    /// is what it holds now. None if there's nowhere to watch.
+    pub fn new(path: Option<&Path>, settings: Settings) -> Option<Self> {
+        let path = path.map(Path::to_path_buf).or_else(default_path)?;
+        Some(Self {
+            modified: modified(&path),
+            path,
+            settings,
+            next_check: Instant::now() + WATCH_INTERVAL,
+        })
+    }
+
+    /// When `check` next looks at the file.
+    pub fn next_check(&self) -> Instant {
+        self.next_check
+    }
+
+    /// If the file's been written since it was last read, reads it again
+    /// and returns the settings as they were and as they are now. One that
+    /// doesn't read cleanly returns the error, and what it said before
+    /// stands. Only looks once `next_check` has come.
+    pub fn check(&mut self) -> Option<Result<(Settings, Settings), String>> {
+        if Instant::now() < self.next_check {
+            return None;
+        }
+        self.next_check = Instant::now() + WATCH_INTERVAL;
+        // Gone (or mid-save) is left alone: it's read again once it's back.
+        let modified = modified(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        let settings = Config::load(Some(&self.path)).and_then(|c| Settings::from_config(&c));
+        Some(settings.map(|settings| {
+            let old = std::mem::replace(&mut self.settings, settings.clone());
+            (old, settings)
+        }))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(text: &str) -> Result<Settings, String> {
+        Settings::from_config(&Config::parse(text)?)
+    }
+
+    #[test]
+    fn errors_say_where_on_the_line_they_are() {
+        let err = |text: &str| settings(text).err().unwrap();
+        assert_eq!(err("[kill\n"), "line 1:1: unclosed section header");
+        assert_eq!(
+            err("[speed]\n  tps = fast\n"),
+            "line 2:9: can't read value \"fast\""
+        );
+        assert_eq!(
+            err("[speed]\ntps = 0\n"),
+            "line 2:7: tps must be at least 1"
+        );
+        assert_eq!(err("[noise]\nrate = 0\n"), "line 2:8: rate can't be 0");
+    }
+
+    #[test]
+    fn speed_and_noise_default_until_set() {
+        let defaults = settings("").unwrap();
+        assert_eq!((defaults.tps, defaults.noise), (DEFAULT_TPS, DEFAULT_NOISE));
+        let set = settings("[speed]\ntps = 30\n[noise]\nrate = \"0.05%\"\n").unwrap();
+        assert_eq!((set.tps, set.noise), (30, Noise::Percent(0.05)));
+    }
+
+    #[test]
+    fn the_watcher_keeps_the_last_settings_that_read_cleanly() {
+        let path = std::env::temp_dir().join(format!("tgol-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "[speed]\ntps = 30\n").unwrap();
+        let mut watcher =
+            Watcher::new(Some(&path), settings("[speed]\ntps = 30\n").unwrap()).unwrap();
+        // Looks straight away, as if the file had just been written.
+        let mut edit = |text: &str| {
+            std::fs::write(&path, text).unwrap();
+            watcher.modified = None;
+            watcher.next_check = Instant::now();
+            watcher.check()
+        };
+
+        let (old, new) = edit("[speed]\ntps = 20\n").unwrap().unwrap();
+        assert_eq!((old.tps, new.tps), (30, 20));
+        let bad = edit("[speed]\ntps = 0\n").unwrap();
+        assert!(bad.err().unwrap().contains(":2:7: tps must be at least 1"));
+        let (old, new) = edit("[speed]\ntps = 10\n").unwrap().unwrap();
+        assert_eq!((old.tps, new.tps), (20, 10));
+
+        // Untouched, or not time to look yet, is nothing.
+        watcher.next_check = Instant::now();
+        let untouched = watcher.check();
+        std::fs::write(&path, "[speed]\ntps = 5\n").unwrap();
+        watcher.modified = None;
+        let too_soon = watcher.check();
+        let _ = std::fs::remove_file(&path);
+        assert!(untouched.is_none());
+        assert!(too_soon.is_none());
+    }
+}
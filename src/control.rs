@@ -0,0 +1,493 @@
+//
+// Scripting the running window (`--control-socket <path>`, or
+// `--control-port <port>` on localhost): newline-delimited JSON commands,
+// each answered with one line of JSON on the same stream.
+//
+//   {"cmd":"pause"}                                 {"ok":true}
+//   {"cmd":"resume"}                                {"ok":true}
+//   {"cmd":"step","n":10}                           {"ok":true}
+//   {"cmd":"load_rle","data":"bo$2bo$3o!","x":4,"y":4}
+//                                                   {"ok":true}
+//   {"cmd":"set_cell","x":10,"y":20,"alive":true}   {"ok":true}
+//   {"cmd":"get_population"}                        {"ok":true,"generation":12,"population":345}
+//
+// `load_rle` centers the pattern when there's no x and y. Anything that
+// isn't one of these gets {"ok":false,"error":"..."} and the connection
+// stays open.
+//
+// Connections are served one after another on a thread of their own. It
+// hands each command to the event loop, wakes it, and waits for the
+// answer. The event loop passes commands on to the simulation, which
+// applies them between generations like any other edit.
+//
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use tgol::pattern::PatternBuffer;
+use tgol::rle;
+
+pub enum Request {
+    Pause,
+    Resume,
+    Step(u64),
+    LoadRle {
+        pattern: PatternBuffer,
+        at: Option<(isize, isize)>,
+    },
+    SetCell {
+        x: isize,
+        y: isize,
+        alive: bool,
+    },
+    GetPopulation,
+}
+
+/// Where the answer to a request goes.
+pub struct Responder(Sender<String>);
+
+impl Responder {
+    pub fn ok(self) {
+        let _ = self.0.send("{\"ok\":true}".to_string());
+    }
+
+    pub fn population(self, generation: u64, population: usize) {
+        let _ = self.0.send(format!(
+            "{{\"ok\":true,\"generation\":{},\"population\":{}}}",
+            generation, population
+        ));
+    }
+}
+
+pub struct ControlServer {
+    requests: Receiver<(Request, Responder)>,
+    // The Unix socket's file, removed on drop.
+    socket: Option<PathBuf>,
+}
+
+impl ControlServer {
+    /// Listens on a Unix socket at `path`, taking over a stale one left
+    /// behind by a crash. `wake` is called for every request, to wake the
+    /// event loop.
+    #[cfg(unix)]
+    pub fn unix(path: PathBuf, wake: impl Fn() + Send + 'static) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "there's a file there already",
+                ));
+            }
+            if UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "something's listening there already",
+                ));
+            }
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        log::info!("Taking commands on {}", path.display());
+        let (sender, requests) = mpsc::channel();
+        spawn(move || {
+            let streams = listener.incoming().map(|stream| {
+                let stream = stream?;
+                Ok((BufReader::new(stream.try_clone()?), stream))
+            });
+            listen(streams, &sender, &wake);
+        })?;
+        Ok(Self {
+            requests,
+            socket: Some(path),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn unix(_path: PathBuf, _wake: impl Fn() + Send + 'static) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix sockets aren't available here; use --control-port",
+        ))
+    }
+
+    /// Listens on `port` on localhost; see `unix`.
+    pub fn tcp(port: u16, wake: impl Fn() + Send + 'static) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        log::info!("Taking commands on {}", listener.local_addr()?);
+        let (sender, requests) = mpsc::channel();
+        spawn(move || {
+            let streams = listener.incoming().map(|stream| {
+                let stream = stream?;
+                Ok((BufReader::new(stream.try_clone()?), stream))
+            });
+            listen(streams, &sender, &wake);
+        })?;
+        Ok(Self {
+            requests,
+            socket: None,
+        })
+    }
+
+    /// The requests that have come in since the last call, oldest first.
+    pub fn requests(&self) -> impl Iterator<Item = (Request, Responder)> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn spawn(listen: impl FnOnce() + Send + 'static) -> io::Result<()> {
+    thread::Builder::new()
+        .name("control".to_string())
+        .spawn(listen)
+        .map(|_| ())
+}
+
+// Serves connections one at a time, for as long as the event loop's there.
+fn listen<R: BufRead, W: Write>(
+    streams: impl Iterator<Item = io::Result<(R, W)>>,
+    requests: &Sender<(Request, Responder)>,
+    wake: &impl Fn(),
+) {
+    for stream in streams {
+        match stream {
+            Ok((reader, writer)) => {
+                if !serve(reader, writer, requests, wake) {
+                    return;
+                }
+            }
+            Err(e) => log::warn!("Control connection failed: {}", e),
+        }
+    }
+}
+
+// Answers one connection's commands until it closes. Returns false once
+// the event loop is gone, and there's no one left to answer them.
+fn serve(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    requests: &Sender<(Request, Responder)>,
+    wake: &impl Fn(),
+) -> bool {
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            return true;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match parse_request(&line) {
+            Ok(request) => {
+                let (sender, answer) = mpsc::channel();
+                if requests.send((request, Responder(sender))).is_err() {
+                    return false;
+                }
+                wake();
+                match answer.recv() {
+                    Ok(answer) => answer,
+                    Err(_) => return false,
+                }
+            }
+            Err(e) => error_json(&e),
+        };
+        if writeln!(writer, "{}", answer)
+            .and_then(|()| writer.flush())
+            .is_err()
+        {
+            return true;
+        }
+    }
+    true
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"ok\":false,\"error\":{}}}", json_string(message))
+}
+
+fn parse_request(line: &str) -> Result<Request, String> {
+    let fields = parse_object(line)?;
+    let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let int = |key: &str| match get(key) {
+        Some(Json::Num(n)) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => Ok(Some(*n as i64)),
+        Some(_) => Err(format!("{:?} must be a whole number", key)),
+        None => Ok(None),
+    };
+    let required = |key: &str| int(key)?.ok_or_else(|| format!("missing {:?}", key));
+
+    let Some(Json::Str(cmd)) = get("cmd") else {
+        return Err("expected a \"cmd\" string".to_string());
+    };
+    match cmd.as_str() {
+        "pause" => Ok(Request::Pause),
+        "resume" => Ok(Request::Resume),
+        "step" => match int("n")?.unwrap_or(1) {
+            n if n >= 1 => Ok(Request::Step(n as u64)),
+            _ => Err("\"n\" must be at least 1".to_string()),
+        },
+        "load_rle" => {
+            let Some(Json::Str(data)) = get("data") else {
+                return Err("expected the pattern as a \"data\" string".to_string());
+            };
+            let pattern = rle::parse(data).map_err(|e| e.to_string())?;
+            let at = match (int("x")?, int("y")?) {
+                (Some(x), Some(y)) => Some((x as isize, y as isize)),
+                (None, None) => None,
+                _ => return Err("give both \"x\" and \"y\", or neither".to_string()),
+            };
+            Ok(Request::LoadRle { pattern, at })
+        }
+        "set_cell" => {
+            let alive = match get("alive") {
+                Some(Json::Bool(alive)) => *alive,
+                None => true,
+                Some(_) => return Err("\"alive\" must be true or false".to_string()),
+            };
+            Ok(Request::SetCell {
+                x: required("x")? as isize,
+                y: required("y")? as isize,
+                alive,
+            })
+        }
+        "get_population" => Ok(Request::GetPopulation),
+        other => Err(format!("unknown command {:?}", other)),
+    }
+}
+
+// A JSON value, as far as commands need: no arrays or nested objects.
+enum Json {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+    Null,
+}
+
+// Reads a flat JSON object into its keys and values, in order.
+fn parse_object(text: &str) -> Result<Vec<(String, Json)>, String> {
+    let mut chars = text.trim().chars().peekable();
+    let mut fields = Vec::new();
+    let skip_space = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+
+    if chars.next() != Some('{') {
+        return Err("expected a JSON object".to_string());
+    }
+    skip_space(&mut chars);
+    if chars.next_if_eq(&'}').is_none() {
+        loop {
+            skip_space(&mut chars);
+            if chars.next() != Some('"') {
+                return Err("expected a key in quotes".to_string());
+            }
+            let key = parse_string(&mut chars)?;
+            skip_space(&mut chars);
+            if chars.next() != Some(':') {
+                return Err(format!("expected ':' after {:?}", key));
+            }
+            skip_space(&mut chars);
+            let value = match chars.peek() {
+                Some('"') => {
+                    chars.next();
+                    Json::Str(parse_string(&mut chars)?)
+                }
+                Some('{' | '[') => return Err("nested values aren't supported".to_string()),
+                _ => {
+                    let mut word = String::new();
+                    while let Some(c) =
+                        chars.next_if(|c| !matches!(c, ',' | '}') && !c.is_whitespace())
+                    {
+                        word.push(c);
+                    }
+                    match word.as_str() {
+                        "true" => Json::Bool(true),
+                        "false" => Json::Bool(false),
+                        "null" => Json::Null,
+                        _ => Json::Num(
+                            word.parse()
+                                .map_err(|_| format!("can't read value {:?}", word))?,
+                        ),
+                    }
+                }
+            };
+            fields.push((key, value));
+            skip_space(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected ',' or '}'".to_string()),
+            }
+        }
+    }
+    skip_space(&mut chars);
+    match chars.next() {
+        None => Ok(fields),
+        Some(_) => Err("unexpected text after the object".to_string()),
+    }
+}
+
+// The rest of a string whose opening quote has been read.
+fn parse_string(chars: &mut impl Iterator<Item = char>) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(s),
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => s.push('\n'),
+                't' => s.push('\t'),
+                'r' => s.push('\r'),
+                'b' => s.push('\u{8}'),
+                'f' => s.push('\u{c}'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("bad escape \\u{}", hex))?;
+                    s.push(c);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_read_with_their_defaults() {
+        assert!(matches!(
+            parse_request(r#"{"cmd":"step","n":10}"#),
+            Ok(Request::Step(10))
+        ));
+        assert!(matches!(
+            parse_request(r#" { "cmd" : "step" } "#),
+            Ok(Request::Step(1))
+        ));
+        assert!(matches!(
+            parse_request(r#"{"cmd":"set_cell","x":-3,"y":20}"#),
+            Ok(Request::SetCell {
+                x: -3,
+                y: 20,
+                alive: true
+            })
+        ));
+        match parse_request(r#"{"cmd":"load_rle","data":"bo$2bo$3o!\n"}"#) {
+            Ok(Request::LoadRle { pattern, at: None }) => {
+                assert_eq!(pattern, rle::parse("bo$2bo$3o!").unwrap())
+            }
+            _ => panic!("expected a centered load_rle"),
+        }
+    }
+
+    #[test]
+    fn malformed_commands_are_refused() {
+        for line in [
+            "pause",
+            r#"{"cmd":"pause""#,
+            r#"{"cmd":"pause"} extra"#,
+            r#"{"cmd":"fly"}"#,
+            r#"{"n":3}"#,
+            r#"{"cmd":"step","n":0}"#,
+            r#"{"cmd":"step","n":1.5}"#,
+            r#"{"cmd":"set_cell","x":1}"#,
+            r#"{"cmd":"set_cell","x":1,"y":2,"alive":"yes"}"#,
+            r#"{"cmd":"load_rle","data":"bo$2bo$3o!","x":4}"#,
+            r#"{"cmd":"load_rle","data":["bo$2bo$3o!"]}"#,
+        ] {
+            assert!(parse_request(line).is_err(), "{}", line);
+        }
+        assert_eq!(
+            error_json("unknown command \"fly\"\n"),
+            r#"{"ok":false,"error":"unknown command \"fly\"\n"}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn connections_are_answered_one_after_another() {
+        let path = std::env::temp_dir().join(format!("tgol-control-{}.sock", std::process::id()));
+        let server = ControlServer::unix(path.clone(), || {}).unwrap();
+
+        let client = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let ask = |lines: &[&str]| -> Vec<String> {
+                    let stream = UnixStream::connect(&path).unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut writer = &stream;
+                    lines
+                        .iter()
+                        .map(|line| {
+                            writeln!(writer, "{}", line).unwrap();
+                            let mut answer = String::new();
+                            reader.read_line(&mut answer).unwrap();
+                            answer.trim_end().to_string()
+                        })
+                        .collect()
+                };
+                let first = ask(&[
+                    r#"{"cmd":"pause"}"#,
+                    "nonsense",
+                    r#"{"cmd":"get_population"}"#,
+                ]);
+                let second = ask(&[r#"{"cmd":"step","n":2}"#]);
+                (first, second)
+            })
+        };
+        // The event loop's side.
+        while !client.is_finished() {
+            for (request, responder) in server.requests() {
+                match request {
+                    Request::GetPopulation => responder.population(12, 345),
+                    _ => responder.ok(),
+                }
+            }
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let (first, second) = client.join().unwrap();
+
+        assert_eq!(first[0], r#"{"ok":true}"#);
+        assert!(
+            first[1].starts_with(r#"{"ok":false,"error":"#),
+            "{}",
+            first[1]
+        );
+        assert_eq!(first[2], r#"{"ok":true,"generation":12,"population":345}"#);
+        assert_eq!(second, [r#"{"ok":true}"#]);
+
+        drop(server);
+        assert!(!path.exists());
+    }
+}
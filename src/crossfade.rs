@@ -0,0 +1,162 @@
+//
+// Smoothing between generations. When the grid ticks slower than the
+// display refreshes, the window fades each new generation's frame in over
+// the last one, across the time until the next tick is due: cells being
+// born fade in, dying ones fade out, and trails cool smoothly. At or above
+// the refresh rate there's no time to fade over, and frames replace each
+// other outright, as they do with --no-smooth. While paused, the fade holds
+// where it got to.
+//
+
+use std::time::Duration;
+
+use instant::Instant;
+
+pub struct Crossfade {
+    // Time between ticks, or None if frames aren't faded.
+    tick: Option<Duration>,
+    // The frame being faded from; empty once there's no fade going.
+    from: Vec<u8>,
+    // The generation of the frame being faded to.
+    generation: Option<u64>,
+    start: Instant,
+    // How far the fade had got when the grid was paused.
+    frozen: Option<f32>,
+}
+
+impl Crossfade {
+    /// Fades over each `tick` if it's longer than the display's `refresh`.
+    pub fn new(tick: Duration, refresh: Duration, enabled: bool) -> Self {
+        Self {
+            tick: (enabled && tick > refresh).then_some(tick),
+            from: Vec::new(),
+            generation: None,
+            start: Instant::now(),
+            frozen: None,
+        }
+    }
+
+    /// Takes the frame that was shown until `generation`'s came in, and
+    /// hands back a buffer that's no longer needed. Only a new generation
+    /// fades in: an edit to the one shown, or the intro, shows at once.
+    pub fn replaced(&mut self, old: Vec<u8>, generation: u64, fade: bool) -> Vec<u8> {
+        let next = self.generation.is_some_and(|shown| shown != generation);
+        self.generation = Some(generation);
+        if self.tick.is_none() || !fade || !next {
+            self.from.clear();
+            return old;
+        }
+        self.start = Instant::now();
+        self.frozen = None;
+        std::mem::replace(&mut self.from, old)
+    }
+
+    // How far along the fade is, from 0 (all `from`) to 1 (all the new
+    // frame).
+    fn progress(&mut self, paused: bool) -> f32 {
+        let Some(tick) = self.tick.filter(|_| !self.from.is_empty()) else {
+            return 1.0;
+        };
+        if paused {
+            let t = self.start.elapsed().as_secs_f32() / tick.as_secs_f32();
+            return *self.frozen.get_or_insert(t.min(1.0));
+        }
+        if let Some(t) = self.frozen.take() {
+            self.start = Instant::now() - tick.mul_f32(t);
+        }
+        let t = self.start.elapsed().as_secs_f32() / tick.as_secs_f32();
+        if t >= 1.0 {
+            self.from.clear();
+        }
+        t.min(1.0)
+    }
+
+    /// Draws `shown` into `screen`, faded in as far as it's got.
+    pub fn draw(&mut self, screen: &mut [u8], shown: &[u8], paused: bool) {
+        if shown.len() != screen.len() {
+            return;
+        }
+        let t = self.progress(paused);
+        if t >= 1.0 || self.from.len() != shown.len() {
+            screen.copy_from_slice(shown);
+            return;
+        }
+        for ((out, &from), &to) in screen.iter_mut().zip(&self.from).zip(shown) {
+            *out = (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        }
+    }
+
+    /// Whether a fade is still going, and needs redrawing until it's done.
+    pub fn fading(&self) -> bool {
+        !self.from.is_empty() && self.frozen.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(200);
+    const REFRESH: Duration = Duration::from_millis(16);
+
+    // A fade from black to a new generation's frame, begun `ago`.
+    fn fading_in(ago: Duration) -> Crossfade {
+        let mut fade = Crossfade::new(TICK, REFRESH, true);
+        fade.replaced(vec![0; 4], 0, true);
+        fade.replaced(vec![0; 4], 1, true);
+        fade.start = Instant::now() - ago;
+        fade
+    }
+
+    fn drawn(fade: &mut Crossfade, paused: bool) -> u8 {
+        let mut screen = [0; 4];
+        fade.draw(&mut screen, &[200; 4], paused);
+        screen[0]
+    }
+
+    #[test]
+    fn halfway_through_a_tick_the_blend_is_halfway() {
+        let mut fade = fading_in(TICK / 2);
+        assert!((100..=110).contains(&drawn(&mut fade, false)));
+        assert!(fade.fading());
+
+        fade.start = Instant::now() - TICK;
+        assert_eq!(drawn(&mut fade, false), 200);
+        assert!(!fade.fading());
+    }
+
+    #[test]
+    fn pausing_holds_the_fade_where_it_got_to() {
+        let mut fade = fading_in(TICK / 2);
+        let held = drawn(&mut fade, true);
+        assert!(!fade.fading());
+        fade.start = Instant::now() - TICK;
+        assert_eq!(drawn(&mut fade, true), held);
+
+        // Unpaused, it carries on from there.
+        assert!((held..held + 10).contains(&drawn(&mut fade, false)));
+        assert!(fade.fading());
+    }
+
+    #[test]
+    fn only_new_generations_at_a_slow_tick_fade() {
+        // The same generation again, an edit say, shows at once.
+        let mut fade = fading_in(Duration::ZERO);
+        fade.replaced(vec![0; 4], 1, true);
+        assert_eq!(drawn(&mut fade, false), 200);
+
+        // As does one the caller says not to fade.
+        let mut fade = fading_in(Duration::ZERO);
+        fade.replaced(vec![0; 4], 2, false);
+        assert_eq!(drawn(&mut fade, false), 200);
+
+        // Ticks quicker than the display, or --no-smooth, never fade.
+        for (tick, enabled) in [(REFRESH / 2, true), (TICK, false)] {
+            let mut fade = Crossfade::new(tick, REFRESH, enabled);
+            fade.replaced(vec![0; 4], 0, true);
+            fade.replaced(vec![0; 4], 1, true);
+            assert_eq!(drawn(&mut fade, false), 200);
+            assert!(!fade.fading());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//
+// Noticing when the grid has settled into repeating itself: nothing but
+// still lifes and oscillators up to some period. Each generation's live
+// cells are hashed, and the last few hashes kept to look the next one up
+// in. Edits and jumps ahead start the watch over.
+//
+
+use std::collections::VecDeque;
+
+use crate::grid::Grid;
+
+/// A repeat found by `CycleDetector`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub period: usize,
+    /// The first generation of the cycle. The grid it was noticed on is
+    /// `period` generations on from this, so it looks just the same.
+    pub start: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct CycleDetector {
+    max_period: usize,
+    // Hashes of the last `max_period` generations, oldest first.
+    recent: VecDeque<u64>,
+    // The grid's generation and edit count as of the newest hash.
+    generation: u64,
+    edits: u64,
+}
+
+impl CycleDetector {
+    /// Watches for cycles of period 1 (a still grid) to `max_period`.
+    pub fn new(max_period: usize) -> Self {
+        Self {
+            max_period: max_period.max(1),
+            recent: VecDeque::new(),
+            generation: 0,
+            edits: 0,
+        }
+    }
+
+    /// Forgets every generation seen so far.
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+
+    /// Looks at `life` after an update. Returns the cycle the first time
+    /// the grid comes back round to its start, then starts over, so the
+    /// same cycle isn't reported again straight away.
+    pub fn observe(&mut self, life: &Grid) -> Option<Cycle> {
+        if life.edit_count() != self.edits || life.generation() != self.generation + 1 {
+            self.reset();
+        }
+        self.generation = life.generation();
+        self.edits = life.edit_count();
+
+        let hash = fingerprint(life);
+        // The shortest period is the one that's there.
+        if let Some(period) =
+            (1..=self.recent.len()).find(|&k| self.recent[self.recent.len() - k] == hash)
+        {
+            self.reset();
+            return Some(Cycle {
+                period,
+                start: life.generation() - period as u64,
+            });
+        }
+
+        self.recent.push_back(hash);
+        if self.recent.len() > self.max_period {
+            self.recent.pop_front();
+        }
+        None
+    }
+}
+
+// FNV-1a over which cells are alive.
+fn fingerprint(life: &Grid) -> u64 {
+    life.grid
+        .alive
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, &alive| {
+            (hash ^ alive as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blinker() -> Grid {
+        ".....\n..#..\n..#..\n..#..\n.....\n".parse().unwrap()
+    }
+
+    fn cycle(period: usize, start: u64) -> Cycle {
+        Cycle { period, start }
+    }
+
+    fn run(life: &mut Grid, detector: &mut CycleDetector, generations: usize) -> Vec<Cycle> {
+        (0..generations)
+            .filter_map(|_| {
+                life.update();
+                detector.observe(life)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn oscillators_are_caught_on_their_first_repeat() {
+        let mut life = blinker();
+        let mut detector = CycleDetector::new(6);
+        let found = run(&mut life, &mut detector, 3);
+        assert_eq!(found, [cycle(2, 1)]);
+
+        let mut block: Grid = "....\n.##.\n.##.\n....\n".parse().unwrap();
+        let mut detector = CycleDetector::new(6);
+        let found = run(&mut block, &mut detector, 2);
+        assert_eq!(found, [cycle(1, 1)]);
+    }
+
+    #[test]
+    fn periods_past_the_limit_go_unnoticed() {
+        let mut life = blinker();
+        let mut detector = CycleDetector::new(1);
+        assert!(run(&mut life, &mut detector, 20).is_empty());
+    }
+
+    #[test]
+    fn edits_start_the_watch_over() {
+        let mut life = blinker();
+        let mut detector = CycleDetector::new(6);
+        run(&mut life, &mut detector, 2);
+        // An edit that changes nothing still counts as one.
+        life.toggle(0, 0);
+        life.toggle(0, 0);
+        assert!(run(&mut life, &mut detector, 2).is_empty());
+        assert_eq!(run(&mut life, &mut detector, 1), [cycle(2, 3)]);
+    }
+}
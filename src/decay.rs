@@ -0,0 +1,84 @@
+//
+// How fast dead cells cool off, i.e. how long their trails last. Written
+// as a number of heat points lost per generation (`50`, the classic five
+// generation trail; `1` for very long ones), or as `exp:F` to multiply the
+// heat by F each generation, which fades fast at first and then lingers.
+// Heat starts at 255.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decay {
+    /// Heat drops by this much every generation.
+    Linear(u8),
+    /// Heat is multiplied by this (in 0..1) every generation.
+    Exponential(f32),
+}
+
+impl Default for Decay {
+    fn default() -> Self {
+        Self::Linear(50)
+    }
+}
+
+impl Decay {
+    pub fn cool(self, heat: u8) -> u8 {
+        match self {
+            Self::Linear(step) => heat.saturating_sub(step),
+            // Truncating keeps it moving: it always loses at least one.
+            Self::Exponential(factor) => (heat as f32 * factor) as u8,
+        }
+    }
+
+    /// Trails that last about twice as long.
+    pub fn slower(self) -> Self {
+        match self {
+            Self::Linear(step) => Self::Linear((step / 2).max(1)),
+            // Capped short of 1, where nothing would cool.
+            Self::Exponential(factor) => Self::Exponential((1.0 - (1.0 - factor) / 2.0).min(0.999)),
+        }
+    }
+
+    /// Trails that last about half as long.
+    pub fn faster(self) -> Self {
+        match self {
+            Self::Linear(step) => Self::Linear(step.saturating_mul(2)),
+            Self::Exponential(factor) => Self::Exponential((1.0 - (1.0 - factor) * 2.0).max(0.0)),
+        }
+    }
+}
+
+impl fmt::Display for Decay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear(step) => write!(f, "{}", step),
+            Self::Exponential(factor) => write!(f, "exp:{}", factor),
+        }
+    }
+}
+
+impl FromStr for Decay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(factor) = s.strip_prefix("exp:") {
+            return match factor.trim().parse::<f32>() {
+                Ok(factor) if (0.0..1.0).contains(&factor) => Ok(Self::Exponential(factor)),
+                _ => Err(format!(
+                    "bad decay factor {:?} (expected a number from 0 up to, not including, 1)",
+                    factor
+                )),
+            };
+        }
+        match s.parse::<u8>() {
+            Ok(step) if step > 0 => Ok(Self::Linear(step)),
+            _ => Err(format!(
+                "bad decay {:?} (expected heat lost per generation, 1 to 255, or exp:FACTOR)",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//
+// The density wash ([D]): the grid cut into square blocks, each washed
+// over in a false color for how crowded it is, cold blue through green to
+// hot red, so where things are happening on a big grid shows at a glance.
+// Empty blocks are left as they are.
+//
+// Blocks are counted afresh for each frame that's sent, straight off the
+// alive flags, rather than kept up with every birth and death: a frame can
+// be many generations and edits on from the last. It's a pass over the
+// flags and one over the pixels, with no floating point in either.
+//
+
+use crate::gradient::Gradient;
+use crate::grid::Grid;
+
+/// Cells along each side of a block, unless told otherwise.
+pub const DEFAULT_BLOCK: usize = 8;
+
+// How much of the wash shows over the cells, out of 256.
+const WASH_ALPHA: u32 = 112;
+
+// The share of a block alive at which it's as hot as it gets, out of 256:
+// a half-full block is about as busy as Life gets.
+const FULL: u32 = 128;
+
+// Cold to hot, by crowding out of 255.
+const STOPS: [(u16, [u8; 4]); 5] = [
+    (0, [0x30, 0x40, 0xff, 0xff]),
+    (64, [0x00, 0xc0, 0xff, 0xff]),
+    (128, [0x30, 0xff, 0x50, 0xff]),
+    (192, [0xff, 0xe0, 0x00, 0xff]),
+    (255, [0xff, 0x30, 0x10, 0xff]),
+];
+
+/// Live cells counted in blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DensityMap {
+    /// Cells along each side of a block.
+    pub block: usize,
+    /// Blocks across and down; those on the right and bottom edges may be
+    /// cut short by the grid.
+    pub width: usize,
+    pub height: usize,
+    /// Live cells in each block, row by row.
+    pub counts: Vec<u32>,
+}
+
+impl DensityMap {
+    /// Counts `life`'s live cells in blocks `block` cells a side.
+    pub fn new(life: &Grid, block: usize) -> Self {
+        profile_scope!("density");
+
+        let block = block.max(1);
+        let (w, h) = (life.width, life.height);
+        let (width, height) = (w.div_ceil(block), h.div_ceil(block));
+        let mut counts = vec![0; width * height];
+        for (y, row) in life.grid.alive.chunks_exact(w.max(1)).enumerate() {
+            let counts = &mut counts[(y / block) * width..][..width];
+            for (count, cells) in counts.iter_mut().zip(row.chunks(block)) {
+                *count += cells.iter().filter(|&&alive| alive).count() as u32;
+            }
+        }
+        Self {
+            block,
+            width,
+            height,
+            counts,
+        }
+    }
+
+    /// Washes `screen`, the grid drawn a pixel a cell and `grid_width`
+    /// across, with each block's color.
+    pub fn wash(&self, screen: &mut [u8], grid_width: usize) {
+        profile_scope!("density_wash");
+
+        let gradient = Gradient::new(&STOPS);
+        let grid_height = screen.len() / (4 * grid_width).max(1);
+        // Cells in a block, counting those cut short by the edges.
+        let span = |i: usize, cells: usize| (cells - i * self.block).min(self.block) as u32;
+        // Each block's color, premultiplied by the wash's alpha; None for
+        // an empty block.
+        let colors: Vec<Option<[u32; 3]>> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| {
+                (n > 0).then(|| {
+                    let (x, y) = (i % self.width, i / self.width);
+                    let cells = span(x, grid_width) * span(y, grid_height);
+                    let heat = (n * 256 / cells * 255 / FULL).min(255);
+                    let [r, g, b, _] = gradient.color(heat as u16);
+                    [r, g, b].map(|c| c as u32 * WASH_ALPHA)
+                })
+            })
+            .collect();
+
+        for (y, row) in screen.chunks_exact_mut(4 * grid_width).enumerate() {
+            let colors = &colors[(y / self.block) * self.width..][..self.width];
+            for (color, pixels) in colors.iter().zip(row.chunks_mut(4 * self.block)) {
+                let Some(color) = color else {
+                    continue;
+                };
+                for pix in pixels.chunks_exact_mut(4) {
+                    for (p, c) in pix.iter_mut().zip(color) {
+                        *p = ((*p as u32 * (256 - WASH_ALPHA) + c) >> 8) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetry::SoupSymmetry;
+
+    #[test]
+    fn blocks_cut_short_by_the_edges_still_count() {
+        let mut life = Grid::new_empty_grid(101, 53);
+        life.new_soup(5, 0.3, SoupSymmetry::None, 0);
+        let map = DensityMap::new(&life, 8);
+        assert_eq!((map.width, map.height), (13, 7));
+        let total: u32 = map.counts.iter().sum();
+        assert_eq!(total as usize, life.population());
+
+        // The bottom right corner block is 5 cells by 5.
+        let mut life = Grid::new_empty_grid(101, 53);
+        life.set_rect(96, 48, 100, 52, true);
+        let map = DensityMap::new(&life, 8);
+        assert_eq!(map.counts[map.counts.len() - 1], 25);
+        assert_eq!(map.counts.iter().sum::<u32>(), 25);
+    }
+
+    #[test]
+    fn only_blocks_with_live_cells_are_washed() {
+        let mut life = Grid::new_empty_grid(16, 8);
+        life.set_rect(0, 0, 7, 7, true);
+        let map = DensityMap::new(&life, 8);
+        let mut screen = [10, 10, 10, 255].repeat(16 * 8);
+        map.wash(&mut screen, 16);
+
+        // A full block is as hot as it gets: red, over what was there.
+        let blend = |c: u32| ((10 * (256 - WASH_ALPHA) + c * WASH_ALPHA) >> 8) as u8;
+        let hot = [blend(0xff), blend(0x30), blend(0x10)];
+        for (i, pix) in screen.chunks_exact(4).enumerate() {
+            let expected = if i % 16 < 8 { hot } else { [10, 10, 10] };
+            assert_eq!(pix[..3], expected, "pixel {}", i);
+        }
+    }
+}
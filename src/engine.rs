@@ -0,0 +1,73 @@
+//
+// How `Grid::update` computes the next generation. All engines but
+// HashLife give the same result and differ only in speed; HashLife runs
+// the grid in an infinite plane rather than a torus (see hashlife.rs), but
+// can jump ahead any number of generations at once.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// One cell at a time, eight neighbor lookups each.
+    Scalar,
+    /// Alive flags packed into bits, 64 cells counted at once.
+    #[default]
+    Bitset,
+    /// Only cells next to last generation's changes (or edits), so a
+    /// settled grid costs next to nothing.
+    Active,
+    /// Gosper's HashLife: memoized quadtrees. Doesn't wrap.
+    HashLife,
+}
+
+impl Engine {
+    pub const ALL: [Self; 4] = [Self::Scalar, Self::Bitset, Self::Active, Self::HashLife];
+
+    /// The next engine, wrapping around after the last one.
+    pub fn next(self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|&engine| engine == self)
+            .unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the grid wraps around its edges, as it does for all but
+    /// HashLife.
+    pub fn wraps(self) -> bool {
+        self != Self::HashLife
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Scalar => "scalar",
+            Self::Bitset => "bitset",
+            Self::Active => "active",
+            Self::HashLife => "hashlife",
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|engine| engine.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown engine {:?} (expected scalar, bitset, active or hashlife)",
+                    s
+                )
+            })
+    }
+}
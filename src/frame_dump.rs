@@ -0,0 +1,222 @@
+//
+// --dump-frames: every generation as a PNG of its own, frame_000001.png,
+// frame_000002.png and so on, for making videos with other tools. PNGs
+// are slow to encode, so a few threads share the work. They take frames
+// from a short queue, and when it's full the simulation waits for them
+// rather than dropping any. Finishing waits for every queued frame to be
+// written.
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use log::error;
+
+// Frames waiting on the encoders before the simulation has to wait too.
+const QUEUE_FRAMES: usize = 16;
+
+// Most threads encoding at once.
+const MAX_ENCODERS: usize = 4;
+
+// Frames between progress reports in the log.
+const LOG_INTERVAL: u64 = 100;
+
+/// Makes `dir` ready to dump frames into: creates it if it's missing, and
+/// refuses one with anything in it unless `force` says to write over it.
+pub fn prepare(dir: &Path, force: bool) -> Result<(), String> {
+    let describe = |e: io::Error| format!("couldn't use {} for frames: {}", dir.display(), e);
+    if dir.exists() {
+        let used = std::fs::read_dir(dir).map_err(describe)?.next().is_some();
+        if used && !force {
+            return Err(format!(
+                "{} isn't empty (--force to write frames into it anyway)",
+                dir.display()
+            ));
+        }
+        Ok(())
+    } else {
+        std::fs::create_dir_all(dir).map_err(describe)
+    }
+}
+
+pub struct FrameDump {
+    frames: Option<SyncSender<Frame>>,
+    threads: Vec<JoinHandle<()>>,
+    dir: PathBuf,
+    // Frames handed over so far.
+    count: u64,
+}
+
+struct Frame {
+    number: u64,
+    pixels: Vec<u8>,
+}
+
+// How the encoders write frames out.
+#[derive(Clone)]
+struct Encoding {
+    dir: PathBuf,
+    size: (u32, u32),
+    scale: u32,
+}
+
+impl FrameDump {
+    /// Starts the encoders, for frames of a `size` grid scaled up to
+    /// `scale` pixels a cell, written into `dir` (see `prepare`).
+    pub fn start(dir: PathBuf, size: (u32, u32), scale: u32) -> io::Result<Self> {
+        let scale = scale.max(1);
+        if size
+            .0
+            .checked_mul(scale)
+            .zip(size.1.checked_mul(scale))
+            .is_none()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frames too large",
+            ));
+        }
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let encoding = Encoding {
+            dir: dir.clone(),
+            size,
+            scale,
+        };
+        let encoders = std::thread::available_parallelism()
+            .map_or(1, |n| n.get().saturating_sub(1))
+            .clamp(1, MAX_ENCODERS);
+        let threads = (0..encoders)
+            .map(|n| {
+                let (receiver, encoding) = (Arc::clone(&receiver), encoding.clone());
+                std::thread::Builder::new()
+                    .name(format!("frame-dump-{}", n))
+                    .spawn(move || encode(&receiver, &encoding))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            frames: Some(sender),
+            threads,
+            dir,
+            count: 0,
+        })
+    }
+
+    /// Queues `pixels` as the next frame, waiting for room if the encoders
+    /// are behind. Returns false once they've given up.
+    pub fn push(&mut self, pixels: &[u8]) -> bool {
+        let Some(frames) = &self.frames else {
+            return false;
+        };
+        self.count += 1;
+        let frame = Frame {
+            number: self.count,
+            pixels: pixels.to_vec(),
+        };
+        if frames.send(frame).is_err() {
+            // They've said why.
+            self.frames = None;
+            return false;
+        }
+        if self.count.is_multiple_of(LOG_INTERVAL) {
+            log::info!("Dumped {} frames to {}", self.count, self.dir.display());
+        }
+        true
+    }
+
+    /// Waits for every frame queued to be written.
+    pub fn finish(mut self) {
+        self.frames = None;
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                error!("A frame encoder panicked");
+            }
+        }
+        log::info!("Dumped {} frames to {}", self.count, self.dir.display());
+    }
+}
+
+// An encoder thread: frames until there are no more (or one can't be
+// written).
+fn encode(frames: &Mutex<Receiver<Frame>>, encoding: &Encoding) {
+    loop {
+        // Only held while waiting, so the others can encode meanwhile.
+        let next = frames.lock().map(|frames| frames.recv());
+        let Ok(Ok(frame)) = next else {
+            return;
+        };
+        let path = encoding.dir.join(format!("frame_{:06}.png", frame.number));
+        if let Err(e) = write_png(&path, &frame.pixels, encoding) {
+            error!("Couldn't write {}: {}", path.display(), e);
+            return;
+        }
+    }
+}
+
+// Writes one grid-sized RGBA frame to `path`, each pixel scaled up to a
+// square `encoding.scale` on a side.
+fn write_png(path: &Path, pixels: &[u8], encoding: &Encoding) -> io::Result<()> {
+    let (width, height) = encoding.size;
+    let scale = encoding.scale;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width * scale,
+        height * scale,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+
+    let mut row = Vec::with_capacity(4 * (width * scale) as usize);
+    for cells in pixels.chunks_exact(4 * width as usize) {
+        row.clear();
+        for pixel in cells.chunks_exact(4) {
+            for _ in 0..scale {
+                row.extend_from_slice(pixel);
+            }
+        }
+        for _ in 0..scale {
+            stream.write_all(&row)?;
+        }
+    }
+    stream.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_frame_is_written_scaled_in_order() {
+        let dir = std::env::temp_dir().join(format!("tgol-frames-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        prepare(&dir, false).unwrap();
+
+        let mut dump = FrameDump::start(dir.clone(), (3, 2), 2).unwrap();
+        for shade in 1..=20 {
+            assert!(dump.push(&[shade; 4 * 3 * 2]));
+        }
+        dump.finish();
+
+        for shade in 1..=20 {
+            let path = dir.join(format!("frame_{:06}.png", shade));
+            let decoder = png::Decoder::new(File::open(&path).unwrap());
+            let mut reader = decoder.read_info().unwrap();
+            let mut image = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut image).unwrap();
+            assert_eq!((info.width, info.height), (6, 4));
+            assert!(image.iter().all(|&byte| byte == shade));
+        }
+
+        // It's been written into now, so it takes --force to use again.
+        assert!(prepare(&dir, false).is_err());
+        assert!(prepare(&dir, true).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
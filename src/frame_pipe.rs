@@ -0,0 +1,86 @@
+//
+// --pipe-frames and --ffmpeg: every generation the window shows, as raw
+// RGBA bytes (a grid-sized frame, top row first), written either to stdout
+// or into an ffmpeg it starts for the purpose:
+//
+//   ffmpeg -f rawvideo -pix_fmt rgba -s 384x240 -r 60 -i - <ARGS>
+//
+// with the frame rate from --tps, so a second of video is a second of
+// generations. Frames are written from the simulation thread and nothing
+// is dropped: if the encoder falls behind, the writes block and the
+// simulation slows down to match.
+//
+
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+use log::error;
+
+pub struct FramePipe {
+    out: Output,
+    frames: u64,
+}
+
+enum Output {
+    Stdout(io::Stdout),
+    // Written to through its stdin, which `wait` closes first.
+    Ffmpeg(Child),
+}
+
+impl FramePipe {
+    pub fn stdout() -> Self {
+        Self {
+            out: Output::Stdout(io::stdout()),
+            frames: 0,
+        }
+    }
+
+    /// Starts ffmpeg reading `size` frames at `fps`, with `args` saying
+    /// where the video goes and how it's encoded.
+    pub fn ffmpeg(args: &[String], size: (u32, u32), fps: u32) -> io::Result<Self> {
+        let child = Command::new("ffmpeg")
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{}x{}", size.0, size.1)])
+            .args(["-r", &fps.to_string()])
+            .args(["-i", "-"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        Ok(Self {
+            out: Output::Ffmpeg(child),
+            frames: 0,
+        })
+    }
+
+    /// Writes the next frame, waiting for the other end to take it.
+    pub fn write(&mut self, pixels: &[u8]) -> io::Result<()> {
+        match &mut self.out {
+            Output::Stdout(out) => out.write_all(pixels)?,
+            Output::Ffmpeg(child) => {
+                // Always there, having been asked for.
+                let stdin = child.stdin.as_mut().expect("piped stdin");
+                stdin.write_all(pixels)?
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Closes the pipe, and waits for ffmpeg to finish the file.
+    pub fn finish(self) {
+        match self.out {
+            Output::Stdout(mut out) => {
+                if let Err(e) = out.flush() {
+                    error!("Couldn't finish piping frames: {}", e);
+                }
+            }
+            Output::Ffmpeg(mut child) => match child.wait() {
+                Ok(status) if status.success() => {
+                    log::info!("ffmpeg encoded {} frames", self.frames)
+                }
+                Ok(status) => error!("ffmpeg failed ({}); see its output above", status),
+                Err(e) => error!("Couldn't wait for ffmpeg: {}", e),
+            },
+        }
+    }
+}
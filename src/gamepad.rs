@@ -0,0 +1,312 @@
+//
+// Gamepads (`gamepad` feature), alongside the keyboard and mouse:
+//
+//   [Start]            Toggle pause
+//   [A]                Step one generation (pausing first)
+//   [Y]                Randomize
+//   [X]                Kill a random sampling of cells
+//   [B], [Guide]       Leave the current tool, or quit, like [ESCAPE]
+//   [Stick], [D-pad]   Move the cell cursor
+//   [Right trigger]    Draw at the cursor while held
+//   [Left trigger]     Erase at the cursor while held
+//   [LB] / [RB]        Slower / faster: fast-forward off, x10, x100, x1000
+//
+// The cursor starts slow, for picking out a cell, and speeds up the longer
+// it's held going one way, so it crosses a big grid in a second or two. A
+// tap on the D-pad moves it exactly one cell.
+//
+// The pads are read on a thread of their own, which wakes the event loop
+// for each event, so nothing polls while they're idle. Pads plugged in
+// later are picked up as they come; with none at all, or no way to read
+// them, the rest carries on as usual.
+//
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use instant::Instant;
+
+use crate::keymap::Action;
+use crate::session;
+
+// The stick's tilt that counts as pushing it, out of 1.
+const STICK_DEADZONE: f32 = 0.3;
+
+// The cursor's speed in cells a second: where it starts, and where it gets
+// to after being held one way for `RAMP`.
+const SLOWEST: f32 = 6.0;
+const FASTEST: f32 = 240.0;
+const RAMP: Duration = Duration::from_millis(1500);
+
+// How long the D-pad's held before its one-cell step turns into a glide.
+const DPAD_DELAY: Duration = Duration::from_millis(300);
+
+// What the thread passes on.
+enum Message {
+    Connected(GamepadId, String),
+    Disconnected(GamepadId),
+    Input(EventType),
+}
+
+pub struct Gamepad {
+    messages: Receiver<Message>,
+    connected: Vec<GamepadId>,
+    // Buttons pressed since the last update, and those held down.
+    pressed: Vec<Button>,
+    held: Vec<Button>,
+    stick: (f32, f32),
+    // The cursor, in cells (fractions included), kept on a `size` grid.
+    cursor: (f32, f32),
+    size: (usize, usize),
+    // Since when the cursor's been going one way, and when it last moved.
+    steering_since: Option<Instant>,
+    steered: Instant,
+    // The stroke under way: drawing (true) or erasing, and the cell it got
+    // to.
+    stroke: Option<(bool, (isize, isize))>,
+}
+
+impl Gamepad {
+    /// Starts reading gamepads, for a grid `size` cells across and down.
+    /// `wake` is called for each event, to wake the event loop.
+    pub fn start(size: (usize, usize), wake: impl Fn() + Send + 'static) -> Result<Self, String> {
+        let (sender, messages) = mpsc::channel();
+        let (started, start) = mpsc::sync_channel(1);
+        std::thread::Builder::new()
+            .name("gamepad".to_string())
+            .spawn(move || {
+                let mut gilrs = match Gilrs::new() {
+                    Ok(gilrs) => gilrs,
+                    Err(e) => {
+                        let _ = started.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                let _ = started.send(Ok(()));
+                for (id, pad) in gilrs.gamepads() {
+                    let _ = sender.send(Message::Connected(id, pad.name().to_string()));
+                }
+                wake();
+                while let Some(event) = gilrs.next_event_blocking(None) {
+                    let message = match event.event {
+                        EventType::Connected => {
+                            let name = gilrs.gamepad(event.id).name().to_string();
+                            Message::Connected(event.id, name)
+                        }
+                        EventType::Disconnected => Message::Disconnected(event.id),
+                        input => Message::Input(input),
+                    };
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                    wake();
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        start.recv().map_err(|e| e.to_string())??;
+
+        let center = ((size.0 / 2) as f32, (size.1 / 2) as f32);
+        Ok(Self {
+            messages,
+            connected: Vec::new(),
+            pressed: Vec::new(),
+            held: Vec::new(),
+            stick: (0.0, 0.0),
+            cursor: center,
+            size,
+            steering_since: None,
+            steered: Instant::now(),
+            stroke: None,
+        })
+    }
+
+    /// Takes in what the pads have done since the last update, and moves
+    /// the cursor along. Once a frame, before anything's asked of it.
+    pub fn update(&mut self) {
+        self.pressed.clear();
+        while let Ok(message) = self.messages.try_recv() {
+            match message {
+                Message::Connected(id, name) => {
+                    if !self.connected.contains(&id) {
+                        log::info!("Gamepad connected: {}", name);
+                        self.connected.push(id);
+                    }
+                }
+                Message::Disconnected(id) => {
+                    log::info!("Gamepad disconnected");
+                    self.connected.retain(|&pad| pad != id);
+                    if self.connected.is_empty() {
+                        self.held.clear();
+                        self.stick = (0.0, 0.0);
+                    }
+                }
+                Message::Input(EventType::ButtonPressed(button, _)) => {
+                    self.pressed.push(button);
+                    if !self.held.contains(&button) {
+                        self.held.push(button);
+                    }
+                    if let Some((dx, dy)) = dpad(button) {
+                        self.nudge(dx, dy);
+                    }
+                }
+                Message::Input(EventType::ButtonReleased(button, _)) => {
+                    self.held.retain(|&held| held != button);
+                }
+                Message::Input(EventType::AxisChanged(Axis::LeftStickX, x, _)) => self.stick.0 = x,
+                Message::Input(EventType::AxisChanged(Axis::LeftStickY, y, _)) => self.stick.1 = y,
+                Message::Input(_) => {}
+            }
+        }
+        self.steer();
+    }
+
+    /// Whether any pad's connected; the cursor only shows while one is.
+    pub fn connected(&self) -> bool {
+        !self.connected.is_empty()
+    }
+
+    /// Whether any button was pressed since the last update.
+    pub fn any_pressed(&self) -> bool {
+        !self.pressed.is_empty()
+    }
+
+    pub fn pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// The key bindings' actions the buttons pressed since the last update
+    /// stand for.
+    pub fn actions(&self) -> Vec<Action> {
+        self.pressed
+            .iter()
+            .filter_map(|button| match button {
+                Button::Start => Some(Action::TogglePause),
+                Button::North => Some(Action::Randomize),
+                Button::West => Some(Action::Kill),
+                Button::East | Button::Mode => Some(Action::Quit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The cell under the cursor.
+    pub fn cursor(&self) -> (isize, isize) {
+        (self.cursor.0 as isize, self.cursor.1 as isize)
+    }
+
+    /// Whether a trigger's held, drawing or erasing.
+    pub fn stroking(&self) -> bool {
+        self.stroke.is_some()
+    }
+
+    /// Whether the cursor's on the move, and wants updating every frame.
+    pub fn steering(&self) -> bool {
+        self.steering_since.is_some()
+    }
+
+    /// The edits the triggers have made at the cursor since the last call,
+    /// `radius` cells round: drawing with the right one, erasing with the
+    /// left. A stroke ends with a `Commit`, as the mouse's do.
+    pub fn strokes(&mut self, radius: u32) -> Vec<session::Event> {
+        let drawing = if self.held.contains(&Button::RightTrigger2) {
+            Some(true)
+        } else if self.held.contains(&Button::LeftTrigger2) {
+            Some(false)
+        } else {
+            None
+        };
+        let to = self.cursor();
+        let mut events = Vec::new();
+        match (self.stroke, drawing) {
+            (Some((was, from)), Some(alive)) if was == alive => {
+                if from != to {
+                    events.push(session::Event::Line {
+                        from,
+                        to,
+                        radius,
+                        alive,
+                    });
+                }
+            }
+            (stroke, drawing) => {
+                if stroke.is_some() {
+                    events.push(session::Event::Commit);
+                }
+                if let Some(alive) = drawing {
+                    let (x, y) = to;
+                    events.push(session::Event::Disc {
+                        x,
+                        y,
+                        radius,
+                        alive,
+                    });
+                }
+            }
+        }
+        self.stroke = drawing.map(|alive| (alive, to));
+        events
+    }
+
+    // Moves the cursor a whole cell, for a tap on the D-pad.
+    fn nudge(&mut self, dx: f32, dy: f32) {
+        let (x, y) = (self.cursor.0.floor() + dx, self.cursor.1.floor() + dy);
+        self.cursor = self.clamp((x, y));
+    }
+
+    // Glides the cursor the way the stick or D-pad is held, for the time
+    // since the last update.
+    fn steer(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.steered).as_secs_f32();
+        self.steered = now;
+
+        let dpad = self
+            .held
+            .iter()
+            .filter_map(|&button| dpad(button))
+            .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx, y + dy));
+        let (sx, sy) = self.stick;
+        let tilt = sx.hypot(sy);
+        let (direction, delay) = if dpad != (0.0, 0.0) {
+            (dpad, DPAD_DELAY)
+        } else if tilt > STICK_DEADZONE {
+            // Past the deadzone, scaled back up to the full range, and up
+            // the screen for up the stick.
+            let scale = (tilt - STICK_DEADZONE) / (1.0 - STICK_DEADZONE) / tilt;
+            ((sx * scale, -sy * scale), Duration::ZERO)
+        } else {
+            self.steering_since = None;
+            return;
+        };
+
+        let held = now.duration_since(*self.steering_since.get_or_insert(now));
+        let Some(gliding) = held.checked_sub(delay) else {
+            return;
+        };
+        let t = (gliding.as_secs_f32() / RAMP.as_secs_f32()).min(1.0);
+        let speed = SLOWEST + (FASTEST - SLOWEST) * t * t;
+        let (x, y) = self.cursor;
+        let step = speed * elapsed;
+        self.cursor = self.clamp((x + direction.0 * step, y + direction.1 * step));
+    }
+
+    fn clamp(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let max = |cells: usize| cells.saturating_sub(1) as f32;
+        (
+            x.clamp(0.0, max(self.size.0)),
+            y.clamp(0.0, max(self.size.1)),
+        )
+    }
+}
+
+// Which way a D-pad button points.
+fn dpad(button: Button) -> Option<(f32, f32)> {
+    match button {
+        Button::DPadUp => Some((0.0, -1.0)),
+        Button::DPadDown => Some((0.0, 1.0)),
+        Button::DPadLeft => Some((-1.0, 0.0)),
+        Button::DPadRight => Some((1.0, 0.0)),
+        _ => None,
+    }
+}
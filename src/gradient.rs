@@ -0,0 +1,113 @@
+//
+// Colors for live cells by age (generations survived). A gradient is a
+// list of stops, written like
+//
+//   0:#ffffff, 4:#7850ff, 16:#3200ff, 128:#008ca0
+//
+// Ages between two stops blend between their colors; ages past the last
+// stop keep its color.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gradient {
+    // Sorted by age, never empty.
+    stops: Vec<(u16, [u8; 4])>,
+}
+
+impl Default for Gradient {
+    /// Newborn cells flash white, settle into the usual blue, and turn teal
+    /// once they've stayed put for a while.
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                (0, [0xff, 0xff, 0xff, 0xff]),
+                (4, [0x78, 0x50, 0xff, 0xff]),
+                (16, crate::ALIVE_COLOR),
+                (128, [0x00, 0x8c, 0xa0, 0xff]),
+            ],
+        }
+    }
+}
+
+impl Gradient {
+    /// A gradient through `stops`, given in any order. Panics if there are
+    /// none.
+    pub fn new(stops: &[(u16, [u8; 4])]) -> Self {
+        assert!(!stops.is_empty(), "a gradient needs at least one stop");
+        let mut stops = stops.to_vec();
+        stops.sort_by_key(|&(age, _)| age);
+        stops.dedup_by_key(|&mut (age, _)| age);
+        Self { stops }
+    }
+
+    /// The stops, in order of age.
+    pub fn stops(&self) -> impl Iterator<Item = (u16, [u8; 4])> + '_ {
+        self.stops.iter().copied()
+    }
+
+    pub fn color(&self, age: u16) -> [u8; 4] {
+        let next = self.stops.partition_point(|&(stop, _)| stop <= age);
+        if next == 0 {
+            return self.stops[0].1;
+        }
+        let (from_age, from) = self.stops[next - 1];
+        let Some(&(to_age, to)) = self.stops.get(next) else {
+            return from;
+        };
+
+        let t = (age - from_age) as f32 / (to_age - from_age) as f32;
+        let mut color = [0; 4];
+        for (c, (a, b)) in color.iter_mut().zip(from.iter().zip(to.iter())) {
+            *c = (*a as f32 + (*b as f32 - *a as f32) * t).round() as u8;
+        }
+        color
+    }
+}
+
+impl fmt::Display for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (age, [r, g, b, _])) in self.stops.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}:#{:02x}{:02x}{:02x}", age, r, g, b)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut stops = Vec::new();
+        for stop in s.split(',').map(str::trim) {
+            let (age, color) = stop
+                .split_once(':')
+                .ok_or_else(|| format!("expected AGE:#RRGGBB, got {:?}", stop))?;
+            let age = age
+                .trim()
+                .parse()
+                .map_err(|_| format!("bad age {:?} in gradient stop {:?}", age, stop))?;
+            stops.push((age, parse_color(color.trim())?));
+        }
+
+        Ok(Self::new(&stops))
+    }
+}
+
+/// Reads a `#RRGGBB` color, fully opaque.
+pub fn parse_color(s: &str) -> Result<[u8; 4], String> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())
+        .ok_or_else(|| format!("expected a color like #3200ff, got {:?}", s))?;
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map_err(|_| format!("expected a color like #3200ff, got {:?}", s))
+    };
+    Ok([channel(0)?, channel(2)?, channel(4)?, 0xff])
+}
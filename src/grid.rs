@@ -0,0 +1,2771 @@
+//
+// The simulation itself: the grid cells live on, and the edits the
+// frontends make to it. Nothing in here knows about windows or terminals;
+// the drawing methods write into plain RGBA buffers.
+//
+
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::active::{self, ActiveSet};
+use crate::ant::{Ant, Direction, ANT_COLOR, DEFAULT_ANT_STEPS};
+use crate::automaton::{Automaton, CONDUCTOR, DYING, EMPTY, HEAD, MAX_TEAMS, TAIL, TEAM_COLORS};
+use crate::bitgrid::BitGrid;
+use crate::cell::{Cell, Cells};
+use crate::decay::Decay;
+use crate::engine::Engine;
+use crate::gradient::Gradient;
+use crate::hashlife::HashLife;
+use crate::heat::HeatColors;
+use crate::history::Change;
+use crate::minimap::MiniMap;
+use crate::noise::Noise;
+use crate::pattern::PatternBuffer;
+use crate::png_export::{self, PngOptions};
+use crate::rle;
+use crate::rng::{generate_seed, next_seed, session_rng};
+use crate::rule::Rule;
+use crate::rule_table::RuleTable;
+use crate::stochastic::Stochastic;
+use crate::svg::{self, SvgOptions, SvgRegion};
+use crate::symmetry::SoupSymmetry;
+use crate::topology::{Topology, WALL_COLOR};
+use crate::world::{SparseWorld, World, WorldStats};
+use crate::ALIVE_COLOR;
+
+/// What an `update` did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GenerationStats {
+    /// Cells the rule brought to life and killed, not counting noise.
+    pub births: u32,
+    pub deaths: u32,
+    /// Cells `Grid::noise` flipped, either way.
+    pub noise_flips: u32,
+    /// Live cells afterwards, noise and all.
+    pub population: u32,
+    /// How many of those are on each team, with an automaton that has
+    /// teams; all zero otherwise.
+    pub teams: [u32; MAX_TEAMS],
+    /// The generation it brought the grid to.
+    pub generation: u64,
+}
+
+/// One cell, as the cursor readout shows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Probe {
+    /// Where it is, in an infinite world's coordinates if it's in one.
+    pub x: i64,
+    pub y: i64,
+    pub alive: bool,
+    pub heat: u8,
+    /// Live neighbors, as the next generation will count them.
+    pub neighbors: usize,
+}
+
+pub struct Grid {
+    pub grid: Cells,
+    pub rule: Rule,
+    // Whether cells are on teams, and how many.
+    pub automaton: Automaton,
+    // The team cells brought to life by edits join.
+    pub team: u8,
+    // The Wireworld or rule table state edits paint cells with.
+    pub material: u8,
+    // What `Automaton::Table` follows; while it's None, nothing changes.
+    pub rule_table: Option<Arc<RuleTable>>,
+    // Langton's Ant's ants, and the moves each makes a generation.
+    pub ants: Vec<Ant>,
+    pub ant_steps: u32,
+    // Colors of each state, for an automaton drawn from a palette (see
+    // `Automaton::palette`); empty to use its own.
+    pub palette: Vec<[u8; 4]>,
+    pub width: usize,
+    pub height: usize,
+    // How the grid's edges are glued together.
+    pub topology: Topology,
+    pub generation: u64,
+    // How fast dead cells' trails fade.
+    pub decay: Decay,
+    // Whether dead cells leave heat trails. While off, stored heat is kept
+    // as it is (neither drawn nor cooled) until they're back on.
+    pub trails: bool,
+    // Live cells are colored by age with this when set, else all alike.
+    pub age_colors: Option<Gradient>,
+    // The colors of heat trails, and of live cells not colored otherwise.
+    pub heat_colors: HeatColors,
+    // Cells flipped at random after every update, and what picks them:
+    // each generation's come from `noise_seed` and the generation alone.
+    pub noise: Noise,
+    pub noise_seed: u64,
+    // Births and survivals that only happen by chance, drawn from
+    // `noise_seed` too; None for the plain rule.
+    pub stochastic: Option<Stochastic>,
+    // Indices of the cells the last `update` brought to life or killed.
+    changed: Vec<usize>,
+    // Where `update` writes the next generation; swapped with `grid`.
+    grid_back: Cells,
+    // How `update` computes the next generation.
+    pub engine: Engine,
+    // Scratch space for `Engine::Bitset`: this generation's alive flags and
+    // the next one's.
+    bits: BitGrid,
+    bits_next: BitGrid,
+    // What `Engine::Active` needs to look at next update.
+    active: ActiveSet,
+    // Cells whose color may have changed since the last `clear_dirty`, for
+    // `draw_dirty`. Not kept while `all_dirty` is set.
+    dirty: Vec<usize>,
+    all_dirty: bool,
+    // `Engine::HashLife`'s world, kept between updates for as long as the
+    // grid isn't edited. It holds whatever has left the grid, too.
+    hashlife: Option<Box<HashLife>>,
+    // With `World::Infinite`, the plane the grid is a view onto. Set
+    // `world_stale` when the grid has changed in ways the world hasn't
+    // heard about yet; it's brought up to date before it's next used.
+    world: Option<Box<SparseWorld>>,
+    world_stale: bool,
+    // Live cells, while nothing but `update`s changed them since they were
+    // last counted.
+    population: Option<usize>,
+    // Bumped by every change that isn't an `update`.
+    edits: u64,
+}
+
+impl Grid {
+    pub fn update(&mut self) -> GenerationStats {
+        profile_scope!("update");
+
+        // Langton's Ants move on their own, whatever else is set. Other
+        // automata, other topologies and stochastic rules go through the
+        // full scan (bit-sliced where it can be, cell by cell where it
+        // can't), on the grid even in an infinite world. Past those, an
+        // infinite world has its own way of stepping, whatever the engine,
+        // that only knows which cells are alive, and a torus is stepped by
+        // the engine chosen.
+        let engine = if self.automaton != Automaton::Life
+            || self.topology != Topology::Torus
+            || self.stochastic.is_some()
+        {
+            Some(Engine::Bitset)
+        } else if self.world.is_some() {
+            None
+        } else {
+            Some(self.engine)
+        };
+        match engine {
+            _ if self.automaton == Automaton::Ant => self.update_ants(),
+            None => self.update_world(),
+            Some(Engine::Active) => self.update_active(),
+            Some(Engine::HashLife) => self.update_hashlife(1),
+            Some(_) => self.update_full(),
+        }
+        // The others' bookkeeping is only kept up to date by their own
+        // engines.
+        if engine != Some(Engine::Active) {
+            self.active.touch_all();
+        }
+        if engine != Some(Engine::HashLife) {
+            self.hashlife = None;
+        }
+        if self.automaton != Automaton::Life {
+            self.world_stale = true;
+        }
+        self.generation += 1;
+
+        // Only what changed needs looking at, so this is next to free.
+        let births = self.changed.iter().filter(|&&i| self.grid.alive[i]).count();
+        let deaths = self.changed.len() - births;
+        let population = match self.population {
+            Some(before) => before + births - deaths,
+            None => self.grid.alive.iter().filter(|&&alive| alive).count(),
+        };
+        self.population = Some(population);
+        let noise_flips = self.add_noise();
+
+        GenerationStats {
+            births: births as u32,
+            deaths: deaths as u32,
+            noise_flips,
+            population: self.population.unwrap_or(population) as u32,
+            teams: self.team_populations(),
+            generation: self.generation,
+        }
+    }
+
+    // Flips the cells `noise` picks for this generation, returning how many.
+    fn add_noise(&mut self) -> u32 {
+        if self.noise == Noise::Off {
+            return 0;
+        }
+        let seed = self
+            .noise_seed
+            .wrapping_add(self.generation.wrapping_mul(0x9e3779b97f4a7c15));
+        let mut flips = 0;
+        let noise = self.noise;
+        noise.pick(self.grid.len(), &mut session_rng(seed), |i| {
+            let alive = !self.grid.alive[i];
+            let mut cell = self.grid.get(i);
+            if alive && self.automaton.has_teams() {
+                cell.state = self.birth_team(i);
+            }
+            cell.set(alive);
+            self.grid.put(i, cell);
+            self.noticed(i);
+            self.population = self.population.map(|population| {
+                if alive {
+                    population + 1
+                } else {
+                    population - 1
+                }
+            });
+            flips += 1;
+        });
+        flips
+    }
+
+    /// Switches between wrapping around the edges and being a view onto an
+    /// infinite plane. The plane starts out holding the grid, at (0, 0).
+    pub fn set_world(&mut self, world: World) {
+        self.world = match world {
+            World::Torus => None,
+            World::Infinite => Some(Box::default()),
+        };
+        self.world_stale = true;
+    }
+
+    pub fn world(&self) -> World {
+        if self.world.is_some() {
+            World::Infinite
+        } else {
+            World::Torus
+        }
+    }
+
+    /// World coordinates of an infinite world's view; None on a torus.
+    pub fn view(&self) -> Option<(i64, i64)> {
+        self.world.as_ref().map(|world| world.view)
+    }
+
+    /// How an infinite world is doing; None on a torus.
+    pub fn world_stats(&mut self) -> Option<WorldStats> {
+        self.sync_world();
+        let world = self.world.as_ref()?;
+        Some(WorldStats {
+            population: world.population(),
+            bounds: world.bounds(),
+            view: world.view,
+        })
+    }
+
+    /// The mini-map of the grid, or of an infinite world's live cells and
+    /// view, fitting in `max` (width, height) pixels.
+    pub fn minimap(&mut self, max: (usize, usize)) -> MiniMap {
+        self.sync_world();
+        let (width, height) = (self.width, self.height);
+        let Some(world) = self.world.as_ref() else {
+            let extent = (0, 0, width, height);
+            let live = self
+                .grid
+                .alive
+                .iter()
+                .enumerate()
+                .filter(|(_, &alive)| alive);
+            let live = live.map(|(i, _)| ((i % width) as i64, (i / width) as i64));
+            return MiniMap::new(extent, max, extent, live);
+        };
+
+        let (vx, vy) = world.view;
+        let (vx1, vy1) = (vx + width as i64 - 1, vy + height as i64 - 1);
+        let (x0, y0, x1, y1) = world
+            .bounds()
+            .map_or((vx, vy, vx1, vy1), |(x0, y0, x1, y1)| {
+                (x0.min(vx), y0.min(vy), x1.max(vx1), y1.max(vy1))
+            });
+        let extent = (x0, y0, (x1 - x0 + 1) as usize, (y1 - y0 + 1) as usize);
+        MiniMap::new(extent, max, (vx, vy, width, height), world.live_cells())
+    }
+
+    /// Moves the view of an infinite world so its top-left corner is at
+    /// world (x, y). Cells still in view keep their heat and age; those
+    /// coming into view start out fresh. Does nothing on a torus.
+    pub fn set_view(&mut self, x: i64, y: i64) {
+        self.sync_world();
+        let Some(world) = self.world.as_mut() else {
+            return;
+        };
+        let (old_x, old_y) = std::mem::replace(&mut world.view, (x, y));
+        let (width, height) = (self.width as i64, self.height as i64);
+
+        let old = std::mem::take(&mut self.grid);
+        self.grid = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(vx, vy)| {
+                let (ox, oy) = (vx + x - old_x, vy + y - old_y);
+                if (0..width).contains(&ox) && (0..height).contains(&oy) {
+                    old.get((ox + oy * width) as usize)
+                } else {
+                    Cell::new(world.get(vx + x, vy + y))
+                }
+            })
+            .collect();
+
+        // Same as any other wholesale change, except the world is already
+        // up to date.
+        self.touched_all();
+        self.world_stale = false;
+    }
+
+    // Swaps infinite worlds (or the lack of one) with `other`.
+    pub(crate) fn swap_world(&mut self, other: &mut Grid) {
+        std::mem::swap(&mut self.world, &mut other.world);
+        std::mem::swap(&mut self.world_stale, &mut other.world_stale);
+    }
+
+    // Writes the grid into the world's view, if the world's behind.
+    fn sync_world(&mut self) {
+        let Some(world) = self.world.as_mut().filter(|_| self.world_stale) else {
+            return;
+        };
+        world.clear_rect(world.view, self.width, self.height);
+        let (vx, vy) = world.view;
+        for (i, &alive) in self.grid.alive.iter().enumerate() {
+            if alive {
+                let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+                world.set(vx + x, vy + y, true);
+            }
+        }
+        self.world_stale = false;
+    }
+
+    // `World::Infinite`: runs the world on and reads the view back out of
+    // it, cell by cell in place.
+    fn update_world(&mut self) {
+        self.sync_world();
+        let Some(world) = self.world.as_mut() else {
+            return;
+        };
+        world.step(self.rule);
+
+        let mut alive = vec![false; self.grid.len()];
+        for (x, y) in world.live_in(world.view, self.width, self.height) {
+            alive[x + y * self.width] = true;
+        }
+
+        self.changed.clear();
+        for (i, alive) in alive.into_iter().enumerate() {
+            let next = self.next_cell(i, alive);
+            self.grid.put(i, next);
+        }
+        self.changed.sort_unstable();
+    }
+
+    /// Runs `generations` generations. With `Engine::HashLife` it's done in
+    /// one jump, however many there are, and counts as a single
+    /// generation for heat and age; other engines just `update` that many
+    /// times, as does HashLife when there's noise, which a jump would skip.
+    pub fn advance(&mut self, generations: u64) {
+        if self.engine != Engine::HashLife
+            || self.automaton != Automaton::Life
+            || self.topology != Topology::Torus
+            || self.stochastic.is_some()
+            || self.noise != Noise::Off
+        {
+            for _ in 0..generations {
+                self.update();
+            }
+            return;
+        }
+        if generations > 0 {
+            self.update_hashlife(generations);
+            self.active.touch_all();
+            self.population = None;
+            self.generation += generations;
+        }
+    }
+
+    // `Engine::HashLife`: runs the world on and reads the grid back out of
+    // it, cell by cell in place.
+    fn update_hashlife(&mut self, generations: u64) {
+        let mut world = match self.hashlife.take() {
+            Some(world) => world,
+            None => Box::new(HashLife::from_cells(
+                self.rule,
+                self.width,
+                self.height,
+                |x, y| self.grid.alive[x + y * self.width],
+            )),
+        };
+        world.set_rule(self.rule);
+        world.advance(generations);
+
+        let mut alive = vec![false; self.grid.len()];
+        world.for_each_live(self.width, self.height, |x, y| {
+            alive[x + y * self.width] = true
+        });
+        self.hashlife = Some(world);
+
+        self.changed.clear();
+        for (i, alive) in alive.into_iter().enumerate() {
+            let next = self.next_cell(i, alive);
+            self.grid.put(i, next);
+        }
+    }
+
+    // Computes every cell of the next generation.
+    // Langton's Ant: cools the trails, then has every ant make its
+    // `ant_steps` moves, one ant after another.
+    fn update_ants(&mut self) {
+        self.changed.clear();
+        if self.trails {
+            for i in 0..self.grid.len() {
+                if !self.grid.alive[i] && self.grid.heat[i] > 0 {
+                    self.grid.heat[i] = self.decay.cool(self.grid.heat[i]);
+                    self.mark_dirty(i);
+                }
+            }
+        }
+
+        let size = (self.width, self.height);
+        let mut flips = Vec::new();
+        for _ in 0..self.ant_steps {
+            let mut a = 0;
+            while a < self.ants.len() {
+                let ant = &mut self.ants[a];
+                let i = ant.x + ant.y * self.width;
+                let mut cell = self.grid.get(i);
+                let on_grid = ant.step(cell.alive, size, self.topology);
+                cell.set(!cell.alive);
+                if !self.trails && !cell.alive {
+                    cell.heat = 0;
+                }
+                self.grid.put(i, cell);
+                flips.push(i);
+                if on_grid {
+                    a += 1;
+                } else {
+                    self.ants.remove(a);
+                }
+            }
+        }
+
+        // A cell flipped an even number of times is back as it was.
+        flips.sort_unstable();
+        for run in flips.chunk_by(|a, b| a == b) {
+            self.mark_dirty(run[0]);
+            if run.len() % 2 == 1 {
+                self.changed.push(run[0]);
+            }
+        }
+        for i in 0..self.ants.len() {
+            let ant = self.ants[i];
+            self.mark_dirty(ant.x + ant.y * self.width);
+        }
+    }
+
+    /// Sets an ant down on (x, y), facing north. Off the grid, it's
+    /// ignored.
+    pub fn add_ant(&mut self, x: isize, y: isize) {
+        if let Some(i) = self.grid_idx(x, y) {
+            self.ants.push(Ant {
+                x: i % self.width,
+                y: i / self.width,
+                heading: Direction::North,
+            });
+            self.edits += 1;
+            self.mark_dirty(i);
+        }
+    }
+
+    fn update_full(&mut self) {
+        //
+        // The next generation is written into the back buffer and only
+        // swapped in once it's finished. This way we don't get any 'tearing'
+        // if we want to extend this routine to be multithreaded, and nothing
+        // is allocated after the first generation. Every cell of the back
+        // buffer is overwritten, so nothing stale from two generations ago
+        // survives the swap.
+        //
+        if self.grid_back.len() != self.grid.len() {
+            self.grid_back = Cells::new(self.grid.len());
+        }
+
+        //
+        // Compute, figure out what the next grid frame is going to look like.
+        // Row by row, so `changed` comes out in the same order as `cells`.
+        //
+
+        self.changed.clear();
+        if let Some(table) = self
+            .rule_table
+            .clone()
+            .filter(|_| self.automaton == Automaton::Table)
+        {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let state = self.table_state(&table, x, y);
+                    self.step_table_cell(y * self.width + x, state);
+                }
+            }
+        } else if self.engine == Engine::Scalar
+            || !self.automaton.life_like()
+            || self.topology != Topology::Torus
+        {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let neighbors_alive = self.count_neighbors(x, y);
+                    let cell = y * self.width + x;
+                    let alive = self.next_alive(cell, neighbors_alive);
+                    self.step_cell(cell, alive);
+                }
+            }
+        } else {
+            self.bits.load(&self.grid.alive, self.width);
+            self.bits.step(self.rule, &mut self.bits_next);
+            if self.automaton == Automaton::Life && self.stochastic.is_none() {
+                // All `step_cell` would do is copy the flags over.
+                self.bits_next.store(&mut self.grid_back.alive);
+            } else {
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let alive = self.bits_next.get(x, y);
+                        self.step_cell(y * self.width + x, alive);
+                    }
+                }
+            }
+        }
+
+        //
+        // SWAP, Compute finished.. swap out to the new graph.
+        //
+        if self.automaton == Automaton::Life {
+            self.follow_alive();
+        } else {
+            std::mem::swap(&mut self.grid_back, &mut self.grid);
+        }
+    }
+
+    // The rest of `update_full` for plain Life, once the back buffer holds
+    // the next generation's alive flags: brings each cell's heat and age
+    // along in place, as `next_cell` would, and swaps the flags in. Only
+    // `alive` is read across cells, so only it needs a back buffer.
+    fn follow_alive(&mut self) {
+        // Worked out with selects rather than branches where it can be: on
+        // a busy grid, which way a cell goes is anybody's guess.
+        let (decay, trails) = (self.decay, self.trails);
+        let age_colors = self.age_colors.is_some();
+        let len = self.grid.len();
+        let cells = self
+            .grid
+            .alive
+            .iter()
+            .zip(&self.grid_back.alive)
+            .zip(self.grid.heat.iter_mut().zip(self.grid.age.iter_mut()))
+            .enumerate();
+        for (i, ((&was, &alive), (heat, age))) in cells {
+            let cooled = if trails {
+                decay.cool(*heat)
+            } else if was {
+                0
+            } else {
+                *heat
+            };
+            let next_heat = if alive { 255 } else { cooled };
+            let next_age = match (was, alive) {
+                (true, true) => age.saturating_add(1),
+                (false, true) => 0,
+                _ => *age,
+            };
+            let recolored = was != alive
+                || (!alive && next_heat != *heat)
+                || (alive && age_colors && next_age != *age);
+            *heat = next_heat;
+            *age = next_age;
+
+            if was != alive {
+                self.changed.push(i);
+            }
+            if recolored {
+                mark_dirty(&mut self.dirty, &mut self.all_dirty, len, i);
+            }
+        }
+        std::mem::swap(&mut self.grid.alive, &mut self.grid_back.alive);
+    }
+
+    // `Engine::Active`: applies the rule only around last generation's
+    // changes and edits, then ages and cools the tracked cells in place.
+    fn update_active(&mut self) {
+        let n = self.grid.len();
+        let mut set = std::mem::take(&mut self.active);
+        if set.flags.len() != n || set.rule != self.rule {
+            set.rescan = true;
+        }
+
+        set.candidates.clear();
+        if set.rescan {
+            set.rescan = false;
+            set.rule = self.rule;
+            set.touched.clear();
+            set.tracked.clear();
+            set.flags = vec![0; n];
+            set.candidates.extend(0..n);
+            for i in 0..n {
+                if self.grid.alive[i] || self.grid.heat[i] > 0 {
+                    set.tracked.push(i);
+                    set.flags[i] |= active::TRACKED;
+                }
+            }
+        } else {
+            for &i in self.changed.iter().chain(&set.touched) {
+                let (x, y) = ((i % self.width) as isize, (i / self.width) as isize);
+                for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                    // The active engine only runs on a torus.
+                    let Some(j) = self.wrapped_idx(x + dx, y + dy) else {
+                        continue;
+                    };
+                    if set.flags[j] & active::CANDIDATE == 0 {
+                        set.flags[j] |= active::CANDIDATE;
+                        set.candidates.push(j);
+                    }
+                }
+            }
+            for &i in &set.touched {
+                let warm = self.grid.alive[i] || self.grid.heat[i] > 0;
+                if warm && set.flags[i] & active::TRACKED == 0 {
+                    set.tracked.push(i);
+                    set.flags[i] |= active::TRACKED;
+                }
+            }
+            set.touched.clear();
+            for &i in &set.candidates {
+                set.flags[i] &= !active::CANDIDATE;
+            }
+        }
+
+        // Every flip is decided before any cell is written.
+        set.flips.clear();
+        for &i in &set.candidates {
+            let neighbors_alive = self.count_neighbors(i % self.width, i / self.width);
+            let alive = self.grid.alive[i];
+            let next = if alive {
+                self.rule.survives(neighbors_alive)
+            } else {
+                self.rule.births(neighbors_alive)
+            };
+            if next != alive {
+                set.flips.push(i);
+            }
+        }
+        for &i in &set.flips {
+            set.flags[i] |= active::FLIPS;
+            if set.flags[i] & active::TRACKED == 0 {
+                set.tracked.push(i);
+                set.flags[i] |= active::TRACKED;
+            }
+        }
+
+        // Untracked cells are dead and cold, and stay that way.
+        self.changed.clear();
+        let mut tracked = std::mem::take(&mut set.tracked);
+        tracked.retain(|&i| {
+            let alive = self.grid.alive[i] != (set.flags[i] & active::FLIPS != 0);
+            set.flags[i] &= !active::FLIPS;
+            let next = self.next_cell(i, alive);
+            self.grid.put(i, next);
+
+            let keep = next.alive || next.heat > 0;
+            if !keep {
+                set.flags[i] &= !active::TRACKED;
+            }
+            keep
+        });
+        set.tracked = tracked;
+        self.changed.sort_unstable();
+
+        self.active = set;
+    }
+
+    // Whether cell `i`, with `neighbors` live neighbors, is alive next
+    // generation.
+    fn next_alive(&self, i: usize, neighbors: usize) -> bool {
+        let cell = self.grid.get(i);
+        match self.automaton {
+            Automaton::Brain => !cell.alive && cell.state != DYING && neighbors == 2,
+            Automaton::Wireworld => {
+                !cell.alive && cell.state == CONDUCTOR && (1..=2).contains(&neighbors)
+            }
+            _ if cell.alive => self.rule.survives(neighbors),
+            _ => self.rule.births(neighbors),
+        }
+    }
+
+    // Writes cell `i`'s next state into the back buffer, given whether the
+    // rule has it alive next generation, leaving it to chance first if
+    // the rule's stochastic. Plain Life only writes whether it's alive
+    // (see `follow_alive`).
+    fn step_cell(&mut self, i: usize, alive: bool) {
+        let alive = match self.stochastic {
+            Some(stochastic) if self.automaton.life_like() => stochastic.apply(
+                self.grid.alive[i],
+                alive,
+                self.noise_seed,
+                self.generation,
+                i,
+            ),
+            _ => alive,
+        };
+        if self.automaton == Automaton::Life {
+            self.grid_back.alive[i] = alive;
+            return;
+        }
+        let next = self.next_cell(i, alive);
+        self.grid_back.put(i, next);
+    }
+
+    // The state a rule table has the cell at (x, y) go to. Neighbors off
+    // a plane are state 0, and off a wall state 1.
+    fn table_state(&self, table: &RuleTable, x: usize, y: usize) -> u8 {
+        let around = self.neighbors(x, y);
+        let wall = self.topology.walled() as u8;
+        let state = |j: Option<usize>| j.map_or(wall, |j| self.pattern_state(&self.grid.get(j)));
+        let cell = self.pattern_state(&self.grid.get(x + y * self.width));
+        table.next(cell, CLOCKWISE.map(|k| state(around[k])))
+    }
+
+    // Writes cell `i`'s next state into the back buffer, for a rule table
+    // that has it go to `state`.
+    fn step_table_cell(&mut self, i: usize, state: u8) {
+        let mut next = self.next_cell(i, state != 0);
+        if next.alive && next.state != state {
+            next.state = state;
+            self.mark_dirty(i);
+        }
+        self.grid_back.put(i, next);
+    }
+
+    // Cell `i`'s next state, given whether the rule has it alive next
+    // generation. It starts from the current state, carrying heat and age
+    // forward. Births and deaths are recorded in `changed`.
+    fn next_cell(&mut self, i: usize, alive: bool) -> Cell {
+        let was = self.grid.get(i);
+        let mut next = was;
+
+        // RULE #1: Any live cell with a survival count of live neighbours survives.
+        // RULE #2: Any dead cell with a birth count of live neighbours becomes a live cell.
+        // RULE #3: All other live cells die in the next generation. Similarly, all other dead cells stay dead.
+        // (Conway's B3/S23: born with 3, survives with 2 or 3.)
+        match (next.alive, alive) {
+            (true, true) => next.survive(), // RULE # 1
+            (false, true) => {
+                next.born(); // RULE #2
+                if self.automaton.has_teams() {
+                    next.state = self.birth_team(i);
+                }
+                self.changed.push(i);
+            }
+            (was_alive, false) => {
+                if was_alive {
+                    self.changed.push(i);
+                }
+
+                if self.trails {
+                    next.set(false); // RULE #3
+                    next.cool_if_dead(self.decay);
+                } else if was_alive {
+                    // No cooling to do; a cell dying now just leaves no
+                    // trail for when trails come back on.
+                    next.set(false);
+                    next.heat = 0;
+                }
+            }
+        }
+
+        // In Brian's Brain, a cell that's just gone off is dying for a
+        // generation.
+        if self.automaton == Automaton::Brain {
+            next.state = if was.alive && !next.alive { DYING } else { 0 };
+        }
+        // In Wireworld, heads leave tails, which turn back into conductor.
+        // Conductor keeps its state while a head passes along it.
+        if self.automaton == Automaton::Wireworld {
+            next.state = match (was.alive, was.state) {
+                (true, _) => TAIL,
+                (false, TAIL) => CONDUCTOR,
+                (false, state) => state,
+            };
+        }
+
+        // Births, deaths and cooling trails change color, and so does
+        // getting older when live cells are colored by age.
+        let recolored = next.alive != was.alive
+            || next.state != was.state
+            || (!next.alive && next.heat != was.heat)
+            || (next.alive && self.age_colors.is_some() && next.age != was.age);
+        if recolored {
+            self.mark_dirty(i);
+        }
+
+        next
+    }
+
+    // Number of `update`s run so far.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Goes up whenever the grid changes other than by `update`, to tell
+    /// whether it's been edited since some earlier look at it.
+    pub fn edit_count(&self) -> u64 {
+        self.edits
+    }
+
+    /// Every cell with its coordinates, row by row from the top-left.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, Cell)> + '_ {
+        let width = self.width;
+        self.grid
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (i % width, i / width, cell))
+    }
+
+    /// Coordinates of the live cells, in the same order as `cells`.
+    pub fn live_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells()
+            .filter(|(_, _, cell)| cell.alive)
+            .map(|(x, y, _)| (x, y))
+    }
+
+    /// Coordinates of the cells the last `update` brought to life or
+    /// killed, in the same order as `cells`. Edits made since then aren't
+    /// included.
+    pub fn changed_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.changed
+            .iter()
+            .map(|&i| (i % self.width, i / self.width))
+    }
+
+    pub fn population(&self) -> usize {
+        self.population
+            .unwrap_or_else(|| self.grid.alive.iter().filter(|&&alive| alive).count())
+    }
+
+    /// Generations the cell at (x, y) has survived, or None if it's dead or
+    /// off the grid.
+    pub fn age_at(&self, x: usize, y: usize) -> Option<u16> {
+        let cell = self.grid.get(self.grid_idx(x, y)?);
+        cell.alive.then_some(cell.age)
+    }
+
+    /// Age of the oldest live cell, or None if nothing's alive.
+    pub fn max_age(&self) -> Option<u16> {
+        self.grid
+            .alive
+            .iter()
+            .zip(&self.grid.age)
+            .filter(|(&alive, _)| alive)
+            .map(|(_, &age)| age)
+            .max()
+    }
+
+    /// Live neighbors of (x, y), across the grid edges as `topology` glues
+    /// them. Past a wall's edges they're all alive.
+    pub fn count_neighbors(&self, x: usize, y: usize) -> usize {
+        profile_scope!("neighbors");
+
+        //
+        // This is a fancy way to add up all the neighboring
+        // cells. If they are alive.
+        //
+        let wall = self.topology.walled();
+        self.neighbors(x, y)
+            .into_iter()
+            .map(|i| i.map_or(wall, |i| self.grid.alive[i]) as usize)
+            .sum()
+    }
+
+    /// What's at (x, y) on the grid. An infinite world's neighbors are
+    /// counted from the world, past the edges of the view.
+    pub fn probe(&mut self, x: usize, y: usize) -> Probe {
+        let i = x + y * self.width;
+        let (x, y) = (x as i64, y as i64);
+        self.sync_world();
+        let (x, y, neighbors) = match &self.world {
+            Some(world) => {
+                let (x, y) = (world.view.0 + x, world.view.1 + y);
+                let neighbors = NEIGHBOR_OFFSETS
+                    .iter()
+                    .filter(|&&(dx, dy)| world.get(x + dx as i64, y + dy as i64))
+                    .count();
+                (x, y, neighbors)
+            }
+            None => (x, y, self.count_neighbors(x as usize, y as usize)),
+        };
+        Probe {
+            x,
+            y,
+            alive: self.grid.alive[i],
+            heat: self.grid.heat[i],
+            neighbors,
+        }
+    }
+
+    // Indices of the eight cells around (x, y), wrapping around the grid
+    // edges.
+    fn neighbors(&self, x: usize, y: usize) -> [Option<usize>; 8] {
+        if self.topology != Topology::Torus {
+            let size = (self.width, self.height);
+            return NEIGHBOR_OFFSETS.map(|(dx, dy)| {
+                let (x, y) = (x as isize + dx, y as isize + dy);
+                self.topology
+                    .wrap(x, y, size)
+                    .map(|(x, y)| x + y * self.width)
+            });
+        }
+
+        //
+        // final two sets of coords. an (x1, y1)
+        // that indicates the coords of the neighboring
+        // grid (UP-LEFT) and another set of coords (x2, y2)
+        // that represents the coords to the (BOTTOM-RIGHT)
+        //
+
+        // On a grid one cell across (or down), both neighbors that way are
+        // the cell's own column (or row).
+        let before = |i: usize, len: usize| if i == 0 { len - 1 } else { i - 1 };
+        let after = |i: usize, len: usize| if i + 1 == len { 0 } else { i + 1 };
+        let (xm1, xp1) = (before(x, self.width), after(x, self.width));
+        let (ym1, yp1) = (before(y, self.height), after(y, self.height));
+
+        [
+            Some(xm1 + ym1 * self.width),
+            Some(x + ym1 * self.width),
+            Some(xp1 + ym1 * self.width),
+            Some(xm1 + y * self.width),
+            Some(xp1 + y * self.width),
+            Some(xm1 + yp1 * self.width),
+            Some(x + yp1 * self.width),
+            Some(xp1 + yp1 * self.width),
+        ]
+    }
+
+    // The team a cell born at `i` joins: the one most of its live
+    // neighbors are on. When every team but one is tied for that (three
+    // parents on three teams, in QuadLife) it's the one left out; other
+    // ties go to the first team.
+    fn birth_team(&self, i: usize) -> u8 {
+        let mut counts = [0; MAX_TEAMS];
+        for j in self
+            .neighbors(i % self.width, i / self.width)
+            .into_iter()
+            .flatten()
+        {
+            if self.grid.alive[j] {
+                counts[self.grid.state[j] as usize % MAX_TEAMS] += 1;
+            }
+        }
+        let counts = &counts[..self.automaton.teams()];
+        let most = counts.iter().copied().max().unwrap_or(0);
+        let tied = counts.iter().filter(|&&n| n == most).count();
+        let pick = if counts.len() > 2 && tied == counts.len() - 1 {
+            counts.iter().position(|&n| n != most)
+        } else {
+            counts.iter().position(|&n| n == most)
+        };
+        pick.unwrap_or(0) as u8
+    }
+
+    /// Live cells on each team, with an automaton that has teams; all zero
+    /// otherwise.
+    pub fn team_populations(&self) -> [u32; MAX_TEAMS] {
+        let mut teams = [0; MAX_TEAMS];
+        if self.automaton.has_teams() {
+            for (_, &state) in self
+                .grid
+                .alive
+                .iter()
+                .zip(&self.grid.state)
+                .filter(|(&alive, _)| alive)
+            {
+                teams[state as usize % MAX_TEAMS] += 1;
+            }
+        }
+        teams
+    }
+
+    pub fn new_empty_grid(width: usize, height: usize) -> Self {
+        let size = width.checked_mul(height).expect("Grid too big (overflow)");
+        Self {
+            grid: Cells::new(size),
+            rule: Rule::default(),
+            automaton: Automaton::default(),
+            team: 0,
+            material: CONDUCTOR,
+            rule_table: None,
+            ants: Vec::new(),
+            ant_steps: DEFAULT_ANT_STEPS,
+            palette: Vec::new(),
+            width,
+            height,
+            topology: Topology::default(),
+            generation: 0,
+            decay: Decay::default(),
+            trails: true,
+            age_colors: None,
+            heat_colors: HeatColors::default(),
+            noise: Noise::Off,
+            noise_seed: 0,
+            stochastic: None,
+            changed: Vec::new(),
+            grid_back: Cells::new(size),
+            engine: Engine::default(),
+            bits: BitGrid::default(),
+            bits_next: BitGrid::default(),
+            active: ActiveSet::new(),
+            dirty: Vec::new(),
+            all_dirty: true,
+            hashlife: None,
+            world: None,
+            world_stale: false,
+            population: None,
+            edits: 0,
+        }
+    }
+
+    /// A `width` x `height` random soup, drawn from `rng` (see `randomize`).
+    pub fn new_random_with_rng(
+        width: usize,
+        height: usize,
+        rng: &mut randomize::PCG32,
+        density: f32,
+    ) -> Self {
+        let mut grid = Self::new_empty_grid(width, height);
+        grid.randomize(rng, density);
+        grid
+    }
+
+    /// `new_random_with_rng` with a freshly seeded RNG, for when the soup
+    /// doesn't need to be made again.
+    pub fn new_random(width: usize, height: usize, density: f32) -> Self {
+        Self::new_random_with_rng(width, height, &mut session_rng(generate_seed()), density)
+    }
+
+    /// A `width` x `height` grid holding `pattern` with its top-left corner
+    /// at `origin`, wrapping around the edges like any pasted pattern.
+    pub fn from_pattern(
+        width: usize,
+        height: usize,
+        pattern: &PatternBuffer,
+        origin: (isize, isize),
+    ) -> Self {
+        let mut grid = Self::new_empty_grid(width, height);
+        grid.insert_pattern(pattern, origin.0, origin.1);
+        grid
+    }
+
+    /// Fills the grid with a random soup where each cell is alive with
+    /// probability `density`, on a team picked at random if there are
+    /// teams.
+    pub fn randomize(&mut self, rng: &mut randomize::PCG32, density: f32) {
+        let teams = self.automaton.teams() as u32;
+        for i in 0..self.grid.len() {
+            let alive = randomize::f32_half_open_right(rng.next_u32()) < density;
+            let mut cell = Cell::new(alive);
+            if alive && teams > 1 {
+                cell.state = (rng.next_u32() % teams) as u8;
+            }
+            self.grid.put(i, cell);
+        }
+        self.touched_all();
+    }
+
+    /// `randomize`, then one part of the grid reflected onto the rest. With
+    /// an odd width or height the center column or row is random and maps
+    /// onto itself.
+    pub fn randomize_symmetric(
+        &mut self,
+        rng: &mut randomize::PCG32,
+        density: f32,
+        symmetry: SoupSymmetry,
+    ) {
+        self.randomize(rng, density);
+
+        let (w, h) = (self.width, self.height);
+        let mirror_x = |grid: &mut Cells| {
+            for y in 0..h {
+                for x in 0..w / 2 {
+                    grid.put((w - 1 - x) + y * w, grid.get(x + y * w));
+                }
+            }
+        };
+        let mirror_y = |grid: &mut Cells| {
+            for y in 0..h / 2 {
+                for x in 0..w {
+                    grid.put(x + (h - 1 - y) * w, grid.get(x + y * w));
+                }
+            }
+        };
+
+        match symmetry {
+            SoupSymmetry::None => {}
+            SoupSymmetry::MirrorX => mirror_x(&mut self.grid),
+            SoupSymmetry::MirrorY => mirror_y(&mut self.grid),
+            SoupSymmetry::Both => {
+                mirror_x(&mut self.grid);
+                mirror_y(&mut self.grid);
+            }
+            // Reversing the cell order is exactly a half turn.
+            SoupSymmetry::Rot180 => {
+                let n = self.grid.len();
+                for i in 0..n / 2 {
+                    self.grid.put(n - 1 - i, self.grid.get(i));
+                }
+            }
+        }
+    }
+
+    /// Kills each live cell with chance `probability` (so 0.25 kills about
+    /// a quarter of them), drawn from `rng`. Returns how many died.
+    pub fn randomly_kill(&mut self, probability: f32, rng: &mut randomize::PCG32) -> u32 {
+        let mut kill_count: u32 = 0;
+        // An ant's cells are colors, not lives.
+        if self.automaton == Automaton::Ant {
+            return kill_count;
+        }
+
+        for i in 0..self.grid.len() {
+            if !self.grid.alive[i] {
+                continue;
+            }
+            let kill = randomize::f32_half_open_right(rng.next_u32()) < probability;
+            if kill {
+                self.kill_cell(i);
+                kill_count += 1;
+            }
+        }
+        self.touched_all();
+
+        kill_count
+    }
+
+    // Kills live cell `i` the way its automaton would: Brian's Brain's
+    // cells start dying, and Wireworld's heads go back to conductor.
+    fn kill_cell(&mut self, i: usize) {
+        match self.automaton {
+            Automaton::Brain => self.set_pattern_state(i, 2),
+            Automaton::Wireworld => self.set_pattern_state(i, CONDUCTOR),
+            _ => self.set_cell(i, false),
+        }
+    }
+
+    /// Flips every cell. Newly alive cells start hot; newly dead ones keep
+    /// their heat and cool off as usual.
+    pub fn invert(&mut self) {
+        for i in 0..self.grid.len() {
+            self.set_cell(i, !self.grid.alive[i]);
+        }
+        self.touched_all();
+    }
+
+    // const GREEN: [u8; 4] = [0, 255, 0, 255];
+    // const RED: [u8; 4] = [255, 0, 0, 255];
+    // const BLUE: [u8; 4] = [0, 0, 255, 255];
+    // const YELLOW: [u8; 4] = [255, 255, 0, 255];
+
+    pub fn draw(&self, screen: &mut [u8]) {
+        profile_scope!("draw");
+
+        debug_assert_eq!(screen.len(), 4 * self.grid.len());
+
+        for (cell, pix) in self.grid.iter().zip(screen.chunks_exact_mut(4)) {
+            pix.copy_from_slice(&self.cell_color(&cell));
+        }
+        self.draw_ants(screen);
+        self.draw_wall(screen);
+    }
+
+    /// Like `draw`, but only redraws the pixels of the cells in `changes`,
+    /// leaving the rest of `screen` as it was. Pass it `dirty_cells` to
+    /// bring a frame `draw`n earlier up to date.
+    pub fn draw_dirty(&self, screen: &mut [u8], changes: &[usize]) {
+        profile_scope!("draw_dirty");
+
+        debug_assert_eq!(screen.len(), 4 * self.grid.len());
+
+        for &i in changes {
+            screen[4 * i..4 * i + 4].copy_from_slice(&self.cell_color(&self.grid.get(i)));
+        }
+        // Wherever they are, they're dirty.
+        self.draw_ants(screen);
+        self.draw_wall(screen);
+    }
+
+    // Draws a walled grid's border over its outermost cells, so it's plain
+    // what the edges are growing from.
+    fn draw_wall(&self, screen: &mut [u8]) {
+        if !self.topology.walled() {
+            return;
+        }
+        let (w, h) = (self.width, self.height);
+        let top = 0..w;
+        let bottom = (h - 1) * w..h * w;
+        let sides = (0..h).flat_map(|y| [y * w, y * w + w - 1]);
+        for i in top.chain(bottom).chain(sides) {
+            screen[4 * i..4 * i + 4].copy_from_slice(&WALL_COLOR);
+        }
+    }
+
+    // Draws each ant over the cell it's on.
+    fn draw_ants(&self, screen: &mut [u8]) {
+        for ant in &self.ants {
+            let i = ant.x + ant.y * self.width;
+            screen[4 * i..4 * i + 4].copy_from_slice(&ANT_COLOR);
+        }
+    }
+
+    /// Renders the grid at `cell_px` pixels a cell, one RGBA pixel row at a
+    /// time, so pictures too big to hold twice can be written as they're
+    /// drawn. `grid_line` (its alpha is the opacity) lines the top and left
+    /// of every cell. Colors are as `draw` has them, but opaque.
+    pub fn render_scaled_rows<E>(
+        &self,
+        cell_px: u32,
+        grid_line: Option<[u8; 4]>,
+        mut row: impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let cell_px = cell_px.max(1) as usize;
+        let mut pixels = vec![0; 4 * self.width * cell_px];
+        let mut lined = pixels.clone();
+        for y in 0..self.height {
+            let cells = (0..self.width).map(|x| self.grid.get(x + y * self.width));
+            for (cell, span) in cells.zip(pixels.chunks_exact_mut(4 * cell_px)) {
+                let [r, g, b, _] = self.cell_color(&cell);
+                for pix in span.chunks_exact_mut(4) {
+                    pix.copy_from_slice(&[r, g, b, 0xff]);
+                }
+            }
+            let Some(line) = grid_line else {
+                for _ in 0..cell_px {
+                    row(&pixels)?;
+                }
+                continue;
+            };
+
+            let alpha = line[3] as f32 / 255.0;
+            let color = [line[0], line[1], line[2], 0xff];
+            lined.copy_from_slice(&pixels);
+            for pix in lined.chunks_exact_mut(4) {
+                blend(pix, color, alpha);
+            }
+            row(&lined)?;
+            for pix in pixels.chunks_exact_mut(4 * cell_px) {
+                blend(&mut pix[..4], color, alpha);
+            }
+            for _ in 1..cell_px {
+                row(&pixels)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `render_scaled_rows` into a single buffer.
+    pub fn render_scaled(&self, cell_px: u32, grid_line: Option<[u8; 4]>) -> Vec<u8> {
+        let mut image = Vec::new();
+        let _ = self.render_scaled_rows(cell_px, grid_line, |row| {
+            image.extend_from_slice(row);
+            Ok::<(), ()>(())
+        });
+        image
+    }
+
+    /// Cells whose color may have changed since the last `clear_dirty`, or
+    /// None if it could be any of them (a new soup, say), so the next frame
+    /// needs a full `draw`.
+    pub fn dirty_cells(&self) -> Option<&[usize]> {
+        (!self.all_dirty).then_some(&self.dirty[..])
+    }
+
+    /// Call once the screen is up to date, to start collecting dirty cells
+    /// afresh.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+        self.all_dirty = false;
+    }
+
+    fn cell_color(&self, cell: &Cell) -> [u8; 4] {
+        let palette = match &self.palette[..] {
+            [] => self.automaton.palette(),
+            palette => palette,
+        };
+        if self.automaton == Automaton::Brain {
+            let state = match (cell.alive, cell.state) {
+                (true, _) => 1,
+                (false, DYING) => 2,
+                (false, _) => 0,
+            };
+            palette.get(state).copied().unwrap_or(ALIVE_COLOR)
+        } else if self.automaton == Automaton::Wireworld {
+            let state = self.pattern_state(cell) as usize;
+            palette.get(state).copied().unwrap_or(ALIVE_COLOR)
+        } else if self.automaton == Automaton::Table {
+            let colors = self
+                .rule_table
+                .as_ref()
+                .map_or(&[][..], |table| &table.colors);
+            let state = self.pattern_state(cell) as usize;
+            colors.get(state).copied().unwrap_or(ALIVE_COLOR)
+        } else if !cell.alive && !self.trails {
+            [0; 4]
+        } else if self.automaton.has_teams() {
+            // Teams take over from age colors, and tint their trails.
+            let [r, g, b, a] = TEAM_COLORS[cell.state as usize % MAX_TEAMS];
+            if cell.alive {
+                [r, g, b, a]
+            } else {
+                let fade = |c: u8| (cell.heat as u32 * c as u32 * 7 / 8 / 255) as u8;
+                [fade(r), fade(g), fade(b), fade(a)]
+            }
+        } else if !cell.alive {
+            self.heat_colors.color(cell.heat)
+        } else if let Some(gradient) = &self.age_colors {
+            gradient.color(cell.age)
+        } else {
+            self.heat_colors.live()
+        }
+    }
+
+    pub fn toggle(&mut self, x: isize, y: isize) -> bool {
+        if let Some(i) = self.grid_idx(x, y) {
+            let alive = !self.painted(i, true);
+            self.set_cell(i, alive);
+            self.touched(i);
+            alive
+        } else {
+            false
+        }
+    }
+
+    pub fn set_line(
+        &mut self,
+        x0: isize,
+        y0: isize,
+        x1: isize,
+        y1: isize,
+        radius: u32,
+        alive: bool,
+    ) {
+        // Points past the grid edges carry on across them as `set_disc`
+        // paints them, so a stroke can run over the seam, and one clipping a
+        // corner of a plane still paints its in-bounds part.
+        for (x, y) in line_drawing::Bresenham::new((x0, y0), (x1, y1)) {
+            self.set_disc(x, y, radius, alive);
+        }
+    }
+
+    // Sets every cell within `radius` of (x, y) to `alive`. A radius of 0 is
+    // just the cell itself. Cells past the grid edge wrap around as
+    // `edit_idx` takes them, or are clipped.
+    pub fn set_disc(&mut self, x: isize, y: isize, radius: u32, alive: bool) {
+        for (x, y) in disc_points(x, y, radius) {
+            if let Some(i) = self.edit_idx(x, y) {
+                if !self.painted(i, alive) {
+                    self.set_cell(i, alive);
+                    self.touched(i);
+                }
+            }
+        }
+    }
+
+    // Sets every cell in the rectangle spanned by two corners to `alive`.
+    // Past the grid edges it wraps around as `edit_idx` takes it, or is
+    // clipped; one wider or taller than the grid covers it once across.
+    pub fn set_rect(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, alive: bool) {
+        let (xmin, ymin, xmax, ymax) = wrapped_rect((self.width, self.height), (x0, y0), (x1, y1));
+        for y in ymin..=ymax {
+            for x in xmin..=xmax {
+                if let Some(i) = self.edit_idx(x, y) {
+                    self.set_cell(i, alive);
+                    self.touched(i);
+                }
+            }
+        }
+    }
+
+    // Copies the state of the rectangle spanned by two corners, clipped to
+    // the grid.
+    pub fn copy_rect(&self, a: (isize, isize), b: (isize, isize)) -> PatternBuffer {
+        let (xmin, ymin, xmax, ymax) = match self.clamp_rect(a, b) {
+            Some(bounds) => bounds,
+            None => return PatternBuffer::default(),
+        };
+
+        let mut buffer = PatternBuffer::new(xmax - xmin + 1, ymax - ymin + 1);
+        for y in ymin..=ymax {
+            for x in xmin..=xmax {
+                let state = self.pattern_state(&self.grid.get(x + y * self.width));
+                buffer.set_state(x - xmin, y - ymin, state);
+            }
+        }
+
+        buffer
+    }
+
+    /// The live cells' bounding box, as `copy_rect` copies it; an empty
+    /// pattern if nothing's alive.
+    pub fn copy_live_bounds(&self) -> PatternBuffer {
+        match self.live_bounds() {
+            Some((x0, y0, x1, y1)) => {
+                self.copy_rect((x0 as isize, y0 as isize), (x1 as isize, y1 as isize))
+            }
+            None => PatternBuffer::default(),
+        }
+    }
+
+    // Stamps the live cells of a pattern with its top-left corner at (x, y),
+    // OR-ing them over what's already there. Like the simulation, placement
+    // wraps around the grid edges, or carries on past them into an
+    // infinite world. Other automata's states are stamped as they are,
+    // over any but the pattern's empty cells.
+    pub fn insert_pattern(&mut self, pattern: &PatternBuffer, x: isize, y: isize) {
+        self.sync_world();
+        for (px, py) in pattern.live_cells() {
+            let (x, y) = (x + px as isize, y + py as isize);
+            match self.automaton {
+                Automaton::Brain | Automaton::Wireworld | Automaton::Table => {
+                    // Never in an infinite world.
+                    if let Some(i) = self.wrapped_idx(x, y) {
+                        self.set_pattern_state(i, pattern.state(px, py));
+                        self.touched(i);
+                    }
+                }
+                _ => self.revive(x, y),
+            }
+        }
+    }
+
+    /// The rule an RLE file of this grid's cells names: the B/S rule, or
+    /// the name Golly knows the automaton by.
+    pub fn rle_rule(&self) -> String {
+        match self.automaton {
+            Automaton::Brain => "/2/3".to_string(),
+            Automaton::Wireworld => "WireWorld".to_string(),
+            Automaton::Table => match &self.rule_table {
+                Some(table) => table.name.clone(),
+                None => self.rule.to_string(),
+            },
+            _ => self.rule.to_string(),
+        }
+    }
+
+    // A cell's state as a pattern holds it: alive or not, or for Brian's
+    // Brain and Wireworld, Golly's number for the state. A rule table's
+    // live cells are in their own state, or 1 if they were brought to
+    // life without one (by noise, say).
+    fn pattern_state(&self, cell: &Cell) -> u8 {
+        match (self.automaton, cell.alive, cell.state) {
+            (Automaton::Brain, false, DYING) => 2,
+            (Automaton::Wireworld, true, _) => HEAD,
+            (Automaton::Wireworld, false, state) => state,
+            (Automaton::Table, true, state) => state.max(1),
+            (Automaton::Table, false, _) => 0,
+            (_, alive, _) => alive as u8,
+        }
+    }
+
+    // Sets cell `i` to a pattern's `state` (see `pattern_state`).
+    fn set_pattern_state(&mut self, i: usize, state: u8) {
+        let mut cell = self.grid.get(i);
+        match (self.automaton, state) {
+            (Automaton::Brain, 2) => {
+                cell.set(false);
+                cell.state = DYING;
+            }
+            (Automaton::Wireworld, HEAD) => {
+                cell.set(true);
+                cell.state = CONDUCTOR;
+            }
+            (Automaton::Wireworld, TAIL | CONDUCTOR) => {
+                cell.set(false);
+                cell.state = state;
+            }
+            (Automaton::Table, state) => {
+                cell.set(state != 0);
+                cell.state = state;
+            }
+            (_, state) => {
+                cell.set(state != 0);
+                cell.state = 0;
+            }
+        }
+        self.grid.put(i, cell);
+    }
+
+    /// The airbrush: each cell within `radius` of (x, y) comes alive with
+    /// `probability`. Like pattern placement, it wraps around the grid
+    /// edges, or carries on past them into an infinite world.
+    pub fn spray(
+        &mut self,
+        x: isize,
+        y: isize,
+        radius: u32,
+        probability: f32,
+        rng: &mut randomize::PCG32,
+    ) {
+        self.sync_world();
+        for (x, y) in disc_points(x, y, radius) {
+            if randomize::f32_half_open_right(rng.next_u32()) < probability {
+                self.revive(x, y);
+            }
+        }
+    }
+
+    // Sets (x, y) alive for `insert_pattern` and `spray`: wrapped across
+    // the grid edges, or into the world if it's off the view. The world must be
+    // synced first.
+    fn revive(&mut self, x: isize, y: isize) {
+        if self.world.is_none() {
+            if let Some(i) = self.wrapped_idx(x, y) {
+                self.set_cell(i, true);
+                self.touched(i);
+            }
+        } else if let Some(i) = self.grid_idx(x, y) {
+            self.set_cell(i, true);
+            self.touched(i);
+        } else if let Some(world) = self.world.as_mut() {
+            let (vx, vy) = world.view;
+            world.set(vx + x as i64, vy + y as i64, true);
+        }
+    }
+
+    // Inclusive (xmin, ymin, xmax, ymax) bounds of every live cell, or None
+    // if there aren't any.
+    pub fn live_bounds(&self) -> Option<(usize, usize, usize, usize)> {
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+        for (x, y) in self.live_cells() {
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+        bounds
+    }
+
+    // Writes the part of the grid picked by `options.region` as an SVG.
+    pub fn export_svg(&self, path: &Path, options: &SvgOptions) -> std::io::Result<()> {
+        let bounds = match options.region {
+            SvgRegion::Whole => Some((0, 0, self.width - 1, self.height - 1)),
+            SvgRegion::Rect(a, b) => self.clamp_rect(a, b),
+            SvgRegion::LiveBounds => self.live_bounds(),
+        };
+
+        // An empty region still exports a valid, zero-sized document.
+        let (x0, y0, width, height) = match bounds {
+            Some((x0, y0, x1, y1)) => (x0, y0, x1 - x0 + 1, y1 - y0 + 1),
+            None => (0, 0, 0, 0),
+        };
+
+        let svg = svg::render(
+            width,
+            height,
+            |x, y| self.grid.alive[(x0 + x) + (y0 + y) * self.width],
+            options,
+            self.generation,
+            &self.rule.to_string(),
+        );
+        std::fs::write(path, svg)
+    }
+
+    // Writes the grid as a PNG, scaled up as `options` asks.
+    pub fn export_png(&self, path: &Path, options: &PngOptions) -> std::io::Result<()> {
+        png_export::write(self, path, options)
+    }
+
+    // Writes the cells between two corners (or the whole grid) as RLE,
+    // with every state the automaton has.
+    pub fn export_rle(
+        &self,
+        path: &Path,
+        corners: Option<((isize, isize), (isize, isize))>,
+    ) -> std::io::Result<()> {
+        let (a, b) =
+            corners.unwrap_or(((0, 0), (self.width as isize - 1, self.height as isize - 1)));
+        std::fs::write(path, rle::write(&self.copy_rect(a, b), &self.rle_rule()))
+    }
+
+    fn clamp_rect(
+        &self,
+        a: (isize, isize),
+        b: (isize, isize),
+    ) -> Option<(usize, usize, usize, usize)> {
+        clamp_rect((self.width, self.height), a, b)
+    }
+
+    /// Fills the grid with the soup `seed` makes (see `randomize_symmetric`)
+    /// and lets it settle for `pre_roll` generations. Everything random
+    /// about it comes from `seed`, the pre-roll's noise and chances
+    /// included, so a seed makes the same soup whenever it's used.
+    pub fn new_soup(&mut self, seed: u64, density: f32, symmetry: SoupSymmetry, pre_roll: usize) {
+        let mut rng = session_rng(seed);
+        self.randomize_symmetric(&mut rng, density, symmetry);
+
+        // The pre-roll draws as a run of its own would, from the soup's
+        // seed and counting from generation 0.
+        let noise_seed = std::mem::replace(&mut self.noise_seed, next_seed(&mut rng));
+        let generation = std::mem::take(&mut self.generation);
+        self.normalize(pre_roll);
+        self.noise_seed = noise_seed;
+        self.generation += generation;
+    }
+
+    // Lets a fresh soup settle for `generations` before it's shown; the
+    // first few generations of a soup are mostly noise dying off.
+    pub fn normalize(&mut self, generations: usize) {
+        // Pass x amount of generations.
+        for _ in 0..generations {
+            self.update();
+        }
+
+        // Now we need to cool off the heatmap that is leftover
+        // Otherwise is looks messy.
+        for (heat, &alive) in self.grid.heat.iter_mut().zip(&self.grid.alive) {
+            if !alive {
+                *heat = 0;
+            }
+        }
+        self.touched_all();
+    }
+
+    // A copy of every cell, to diff against with `changes_since`.
+    pub fn checkpoint(&self) -> Cells {
+        self.grid.clone()
+    }
+
+    // Every cell that differs from an earlier `checkpoint`.
+    pub fn changes_since(&self, before: &Cells) -> Vec<Change> {
+        debug_assert_eq!(before.len(), self.grid.len());
+
+        before
+            .iter()
+            .zip(self.grid.iter())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (before, after))| Change {
+                index,
+                before,
+                after,
+            })
+            .collect()
+    }
+
+    // Puts a cell back exactly as recorded, heat included.
+    pub fn restore(&mut self, index: usize, cell: Cell) {
+        self.grid.put(index, cell);
+        self.touched(index);
+    }
+
+    /// Call after changing `grid` directly (rather than through the
+    /// methods here), so `Engine::Active` looks at every cell again and the
+    /// next frame is drawn in full.
+    pub fn touch_all(&mut self) {
+        self.touched_all();
+    }
+
+    // Records an edit to cell `i`.
+    fn touched(&mut self, i: usize) {
+        self.population = None;
+        self.edits += 1;
+        self.noticed(i);
+    }
+
+    // Tells the engines, the world and the drawing that cell `i` changed
+    // outside what they computed themselves.
+    fn noticed(&mut self, i: usize) {
+        self.active.touch(i);
+        self.hashlife = None;
+        self.mark_dirty(i);
+        if let Some(world) = self.world.as_mut() {
+            let (vx, vy) = world.view;
+            let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+            world.set(vx + x, vy + y, self.grid.alive[i]);
+        }
+    }
+
+    // Several updates between frames can list a cell many times over; past
+    // a grid's worth it's cheaper to redraw everything.
+    fn mark_dirty(&mut self, i: usize) {
+        mark_dirty(&mut self.dirty, &mut self.all_dirty, self.grid.len(), i);
+    }
+
+    // Records an edit that could have changed any cell.
+    fn touched_all(&mut self) {
+        self.active.touch_all();
+        self.hashlife = None;
+        self.world_stale = true;
+        self.population = None;
+        self.edits += 1;
+        self.all_dirty = true;
+        self.dirty.clear();
+    }
+
+    /// Whether an edit setting cell `i` to `alive` would leave it as it
+    /// is. In Wireworld and rule tables, that's painted with `material`,
+    /// or empty.
+    pub fn painted(&self, i: usize, alive: bool) -> bool {
+        let cell = &self.grid.get(i);
+        match (self.automaton, alive) {
+            (Automaton::Wireworld | Automaton::Table, true) => {
+                self.pattern_state(cell) == self.material
+            }
+            (Automaton::Wireworld | Automaton::Table, false) => self.pattern_state(cell) == EMPTY,
+            _ => cell.alive == alive,
+        }
+    }
+
+    // Sets cell `i` for an edit. One brought to life joins `team`, or in
+    // Wireworld and rule tables is painted with `material`; other
+    // automata's states start over.
+    fn set_cell(&mut self, i: usize, alive: bool) {
+        if matches!(self.automaton, Automaton::Wireworld | Automaton::Table) {
+            let state = if alive { self.material } else { EMPTY };
+            self.set_pattern_state(i, state);
+            return;
+        }
+        let mut cell = self.grid.get(i);
+        if !self.automaton.has_teams() {
+            cell.state = 0;
+        } else if alive && !cell.alive {
+            cell.state = self.team;
+        }
+        cell.set(alive);
+        self.grid.put(i, cell);
+    }
+
+    // Index of (x, y) after bringing it back across the edges as
+    // `topology` glues them, or None if it's off a plane.
+    fn wrapped_idx(&self, x: isize, y: isize) -> Option<usize> {
+        self.topology
+            .wrap(x, y, (self.width, self.height))
+            .map(|(x, y)| x + y * self.width)
+    }
+
+    /// Index of (x, y) for the brush and rectangle tools: brought back
+    /// across the edges as `topology` glues them, or None if that's off a
+    /// plane or off the view of an infinite world.
+    pub fn edit_idx(&self, x: isize, y: isize) -> Option<usize> {
+        if self.world.is_some() {
+            self.grid_idx(x, y)
+        } else {
+            self.wrapped_idx(x, y)
+        }
+    }
+
+    /// Index into `grid` of (x, y), or None if it's off the grid.
+    pub fn grid_idx<I: std::convert::TryInto<usize>>(&self, x: I, y: I) -> Option<usize> {
+        if let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) {
+            if x < self.width && y < self.height {
+                Some(x + y * self.width)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// The grid as text: one line per row, `#` for live cells and `.` for
+/// dead ones. Parses back with `FromStr`.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.grid.alive.chunks_exact(self.width) {
+            for &alive in row {
+                f.write_char(if alive { '#' } else { '.' })?;
+            }
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads rows of `#` (alive) and `.` (dead), as written by `Display`. Short
+/// rows are padded with dead cells; blank lines and `!` comment lines (as in
+/// plaintext `.cells` files) are skipped. The grid is as big as the rows.
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows = Vec::new();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('!') {
+                continue;
+            }
+            if let Some(c) = line.chars().find(|c| !matches!(c, '#' | '.')) {
+                return Err(format!(
+                    "line {}: unexpected {:?} (expected # or .)",
+                    n + 1,
+                    c
+                ));
+            }
+            rows.push(line);
+        }
+
+        let pattern = PatternBuffer::from_rows(&rows);
+        if pattern.width() == 0 {
+            return Err("no cells in the grid".to_string());
+        }
+        Ok(Self::from_pattern(
+            pattern.width(),
+            pattern.height(),
+            &pattern,
+            (0, 0),
+        ))
+    }
+}
+
+// Normalizes two corners (in any order) into inclusive
+// (xmin, ymin, xmax, ymax) bounds clamped to a `width` x `height` grid, or
+// None if the rectangle lies entirely outside of it.
+pub(crate) fn clamp_rect(
+    (width, height): (usize, usize),
+    (x0, y0): (isize, isize),
+    (x1, y1): (isize, isize),
+) -> Option<(usize, usize, usize, usize)> {
+    let (xmin, xmax) = (x0.min(x1), x0.max(x1));
+    let (ymin, ymax) = (y0.min(y1), y0.max(y1));
+    let (w, h) = (width as isize, height as isize);
+
+    if xmax < 0 || ymax < 0 || xmin >= w || ymin >= h {
+        return None;
+    }
+
+    Some((
+        xmin.max(0) as usize,
+        ymin.max(0) as usize,
+        xmax.min(w - 1) as usize,
+        ymax.min(h - 1) as usize,
+    ))
+}
+
+// Where each of a cell's eight neighbors is, in `Grid::neighbors` order.
+const NEIGHBOR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Where each neighbor is in `Grid::neighbors`, listed clockwise from north
+// as rule tables have them.
+const CLOCKWISE: [usize; 8] = [1, 2, 4, 7, 6, 5, 3, 0];
+
+// `Grid::mark_dirty`, for when the grid's cells are borrowed: adds cell
+// `i` to `dirty`, or gives up on the list once it's as long as the grid.
+fn mark_dirty(dirty: &mut Vec<usize>, all_dirty: &mut bool, cells: usize, i: usize) {
+    if *all_dirty {
+        return;
+    }
+    if dirty.len() >= cells {
+        *all_dirty = true;
+        dirty.clear();
+    } else {
+        dirty.push(i);
+    }
+}
+
+// The (xmin, ymin, xmax, ymax) of the rectangle spanned by two corners,
+// cut down to at most a `size` grid's width and height from its top-left
+// corner, so wrapping it around the edges covers no cell twice.
+pub(crate) fn wrapped_rect(
+    (width, height): (usize, usize),
+    (x0, y0): (isize, isize),
+    (x1, y1): (isize, isize),
+) -> (isize, isize, isize, isize) {
+    let (xmin, ymin) = (x0.min(x1), y0.min(y1));
+    let xmax = x0.max(x1).min(xmin + width as isize - 1);
+    let ymax = y0.max(y1).min(ymin + height as isize - 1);
+    (xmin, ymin, xmax, ymax)
+}
+
+// Every point within `radius` of (x, y); just (x, y) itself for radius 0.
+pub fn disc_points(x: isize, y: isize, radius: u32) -> impl Iterator<Item = (isize, isize)> {
+    let r = radius as isize;
+    (-r..=r)
+        .flat_map(move |dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(move |(dx, dy)| dx * dx + dy * dy <= r * r)
+        .map(move |(dx, dy)| (x + dx, y + dy))
+}
+
+// Mixes `color` into an RGBA pixel, `alpha` being how much of it shows.
+pub fn blend(pix: &mut [u8], color: [u8; 4], alpha: f32) {
+    for (p, c) in pix.iter_mut().zip(color) {
+        *p = (*p as f32 * (1.0 - alpha) + c as f32 * alpha) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::session_rng;
+
+    fn grid(rows: &str) -> Grid {
+        rows.parse().unwrap()
+    }
+
+    #[test]
+    fn blinker_has_period_two() {
+        let start = ".....\n..#..\n..#..\n..#..\n.....\n";
+        let mut g = grid(start);
+        g.update();
+        assert_eq!(g.to_string(), ".....\n.....\n.###.\n.....\n.....\n");
+        g.update();
+        assert_eq!(g.to_string(), start);
+    }
+
+    #[test]
+    fn block_is_stable() {
+        let start = "....\n.##.\n.##.\n....\n";
+        let mut g = grid(start);
+        for _ in 0..10 {
+            g.update();
+            assert_eq!(g.to_string(), start);
+        }
+    }
+
+    #[test]
+    fn glider_moves_one_cell_diagonally_every_four_generations() {
+        let glider = PatternBuffer::glider(crate::pattern::Heading::SouthEast);
+        let mut g = Grid::from_pattern(12, 12, &glider, (2, 2));
+        let start: Vec<_> = g.live_cells().collect();
+        for period in 1..=20 {
+            for _ in 0..4 {
+                g.update();
+            }
+            let mut moved: Vec<_> = start
+                .iter()
+                .map(|&(x, y)| ((x + period) % 12, (y + period) % 12))
+                .collect();
+            let mut live: Vec<_> = g.live_cells().collect();
+            live.sort_unstable();
+            moved.sort_unstable();
+            assert_eq!(live, moved, "after {} generations", 4 * period);
+        }
+    }
+
+    #[test]
+    fn generation_stats_count_births_and_deaths() {
+        let engines = [
+            Engine::Scalar,
+            Engine::Bitset,
+            Engine::Active,
+            Engine::HashLife,
+        ];
+        for engine in engines {
+            let mut blinker = grid(".....\n..#..\n..#..\n..#..\n.....\n");
+            let mut block = grid("....\n.##.\n.##.\n....\n");
+            let mut empty = Grid::new_empty_grid(6, 6);
+            for g in [&mut blinker, &mut block, &mut empty] {
+                g.engine = engine;
+            }
+            for generation in 1..=3 {
+                let stats = blinker.update();
+                let counts = (stats.births, stats.deaths, stats.population);
+                assert_eq!(counts, (2, 2, 3), "{:?}", engine);
+                assert_eq!(stats.generation, generation);
+
+                let stats = block.update();
+                assert_eq!((stats.births, stats.deaths, stats.population), (0, 0, 4));
+
+                let stats = empty.update();
+                assert_eq!((stats.births, stats.deaths, stats.population), (0, 0, 0));
+                assert_eq!(stats.noise_flips, 0);
+            }
+        }
+    }
+
+    // Cells on teams: a digit is a live cell on that team.
+    fn teams(automaton: Automaton, rows: &[&str]) -> Grid {
+        let mut g = Grid::new_empty_grid(rows[0].len(), rows.len());
+        g.automaton = automaton;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if let Some(team) = c.to_digit(10) {
+                    g.team = team as u8;
+                    g.toggle(x as isize, y as isize);
+                }
+            }
+        }
+        g
+    }
+
+    #[test]
+    fn births_join_the_team_most_parents_are_on() {
+        let born = |automaton, parents: &str| {
+            let mut g = teams(automaton, &[".....", ".....", parents, ".....", "....."]);
+            g.update();
+            [(2, 1), (2, 3)].map(|(x, y)| {
+                let cell = g.grid.get(g.grid_idx(x, y).unwrap());
+                assert!(cell.alive);
+                cell.state
+            })
+        };
+        assert_eq!(born(Automaton::Immigration, ".110."), [1, 1]);
+        assert_eq!(born(Automaton::Immigration, ".010."), [0, 0]);
+        // Three teams among the parents make one of the fourth.
+        assert_eq!(born(Automaton::QuadLife, ".021."), [3, 3]);
+        assert_eq!(born(Automaton::QuadLife, ".232."), [2, 2]);
+    }
+
+    #[test]
+    fn teams_leave_the_live_cells_to_the_rule() {
+        for automaton in [Automaton::Immigration, Automaton::QuadLife] {
+            // The same soup each time, with the teams ignored or not.
+            let start = |engine, automaton_after| {
+                let mut g = Grid::new_empty_grid(40, 30);
+                g.automaton = automaton;
+                g.randomize(&mut session_rng(31), 0.35);
+                g.automaton = automaton_after;
+                g.engine = engine;
+                g
+            };
+            let mut scalar: Option<Cells> = None;
+            for engine in Engine::ALL {
+                let mut g = start(engine, automaton);
+                let mut plain = start(Engine::Scalar, Automaton::Life);
+                for _ in 0..60 {
+                    g.update();
+                    plain.update();
+                }
+                assert_eq!(alive(&g), alive(&plain), "{} {:?}", automaton, engine);
+                // The engine makes no difference to the teams either.
+                let scalar = scalar.get_or_insert_with(|| g.grid.clone());
+                assert_eq!(&g.grid, scalar, "{} {:?}", automaton, engine);
+            }
+        }
+    }
+
+    #[test]
+    fn count_neighbors_wraps_at_the_corners() {
+        let corners = [(0, 0), (4, 0), (0, 4), (4, 4)];
+        for &(x, y) in &corners {
+            // Only the opposite corner is alive: it's a neighbor across
+            // both edges at once.
+            let mut g = Grid::new_empty_grid(5, 5);
+            g.toggle(4 - x as isize, 4 - y as isize);
+            assert_eq!(g.count_neighbors(x, y), 1, "corner ({}, {})", x, y);
+            assert_eq!(g.count_neighbors(2, 2), 0);
+        }
+
+        // Every corner sees the other three, and no more.
+        let mut g = Grid::new_empty_grid(5, 5);
+        for &(x, y) in &corners {
+            g.toggle(x as isize, y as isize);
+        }
+        for &(x, y) in &corners {
+            assert_eq!(g.count_neighbors(x, y), 3, "corner ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn lines_wrap_across_the_torus_edges() {
+        // Off the right edge and back in on the left.
+        let mut g = Grid::new_empty_grid(6, 4);
+        g.set_line(4, 1, 7, 1, 0, true);
+        assert_eq!(g.to_string(), "......\n##..##\n......\n......\n");
+
+        // Down from above the top edge.
+        let mut g = Grid::new_empty_grid(6, 4);
+        g.set_line(2, -2, 2, 1, 0, true);
+        assert_eq!(g.to_string(), "..#...\n..#...\n..#...\n..#...\n");
+
+        // Out through the bottom-right corner and in at the top-left.
+        let mut g = Grid::new_empty_grid(6, 4);
+        g.set_line(4, 2, 7, 5, 0, true);
+        assert_eq!(g.to_string(), "#.....\n.#....\n....#.\n.....#\n");
+
+        // Up and to the left, off both edges the other way.
+        let mut g = Grid::new_empty_grid(6, 4);
+        g.set_line(1, 1, -2, -2, 0, true);
+        assert_eq!(g.to_string(), "#.....\n.#....\n....#.\n.....#\n");
+    }
+
+    #[test]
+    fn lines_longer_than_the_grid_wrap_over_themselves() {
+        let mut g = Grid::new_empty_grid(6, 4);
+        g.set_line(-3, 2, 14, 2, 0, true);
+        assert_eq!(g.to_string(), "......\n......\n######\n......\n");
+        g.set_line(-3, 2, 14, 2, 0, false);
+        assert_eq!(g.population(), 0);
+    }
+
+    #[test]
+    fn lines_stop_at_the_edges_of_planes_and_walls() {
+        for topology in [Topology::Plane, Topology::Wall] {
+            let line = |x0, y0, x1, y1, radius| {
+                let mut g = Grid::new_empty_grid(6, 4);
+                g.topology = topology;
+                g.set_line(x0, y0, x1, y1, radius, true);
+                g.to_string()
+            };
+            // Off the right edge, and up past the top.
+            assert_eq!(line(4, 1, 9, 1, 0), "......\n....##\n......\n......\n");
+            assert_eq!(line(2, 1, 2, -5, 0), "..#...\n..#...\n......\n......\n");
+            // Out through the bottom-right corner, and in through the
+            // top-left from off the grid.
+            assert_eq!(line(4, 2, 9, 7, 0), "......\n......\n....#.\n.....#\n");
+            assert_eq!(line(-3, -3, 1, 1, 0), "#.....\n.#....\n......\n......\n");
+            // All of it off the grid, and a brush overhanging a corner.
+            assert_eq!(line(-9, -2, 20, -2, 0), "......\n......\n......\n......\n");
+            assert_eq!(line(7, 5, 5, 3, 1), "......\n......\n.....#\n....##\n");
+        }
+    }
+
+    #[test]
+    fn lines_come_back_mirrored_on_a_klein_bottle() {
+        let line = |topology, x0, y0, x1, y1| {
+            let mut g = Grid::new_empty_grid(6, 4);
+            g.topology = topology;
+            g.set_line(x0, y0, x1, y1, 0, true);
+            g.to_string()
+        };
+        // Off the right edge on the top row, back in on the bottom one.
+        assert_eq!(
+            line(Topology::KleinX, 4, 0, 7, 0),
+            "....##\n......\n......\n##....\n"
+        );
+        // Down off the bottom in the second column, back in at the top of
+        // the second to last.
+        assert_eq!(
+            line(Topology::KleinY, 1, 2, 1, 5),
+            "....#.\n....#.\n.#....\n.#....\n"
+        );
+        // Out through the bottom-right corner: mirrored across the side,
+        // then straight across the bottom.
+        assert_eq!(
+            line(Topology::KleinX, 4, 2, 7, 5),
+            "......\n......\n.#..#.\n#....#\n"
+        );
+    }
+
+    #[test]
+    fn rectangles_wrap_over_corners_or_are_clipped() {
+        let rect = |topology, x0, y0, x1, y1| {
+            let mut g = Grid::new_empty_grid(6, 4);
+            g.topology = topology;
+            g.set_rect(x0, y0, x1, y1, true);
+            g.to_string()
+        };
+        assert_eq!(
+            rect(Topology::Torus, 4, 2, 6, 4),
+            "#...##\n......\n#...##\n#...##\n"
+        );
+        assert_eq!(
+            rect(Topology::Plane, 6, 4, 4, 2),
+            "......\n......\n....##\n....##\n"
+        );
+        // Wider than the grid, it fills the row once across.
+        assert_eq!(
+            rect(Topology::Torus, -2, 1, 10, 1),
+            "......\n######\n......\n......\n"
+        );
+    }
+
+    #[test]
+    fn sprays_wrap_around_the_edges() {
+        let mut rng = session_rng(3);
+        let mut g = Grid::new_empty_grid(10, 10);
+        g.spray(0, 0, 2, 0.0, &mut rng);
+        assert_eq!(g.population(), 0);
+        g.spray(0, 0, 2, 1.0, &mut rng);
+        assert_eq!(g.population(), 13);
+        for (x, y) in disc_points(0, 0, 2) {
+            let i = g.grid_idx(x.rem_euclid(10), y.rem_euclid(10)).unwrap();
+            assert!(g.grid.alive[i], "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn one_cell_wide_grids_wrap_onto_themselves() {
+        for (width, height) in [(1, 6), (6, 1), (1, 1)] {
+            let mut scalar = soup(width, height, 7, Engine::Scalar);
+            let mut others =
+                [Engine::Bitset, Engine::Active].map(|engine| soup(width, height, 7, engine));
+            for _ in 0..20 {
+                for y in 0..height {
+                    for x in 0..width {
+                        let mut expected = 0;
+                        for dy in 0..3 {
+                            for dx in 0..3 {
+                                let nx = (x + width + dx - 1) % width;
+                                let ny = (y + height + dy - 1) % height;
+                                if (dx, dy) != (1, 1) && scalar.grid.alive[nx + ny * width] {
+                                    expected += 1;
+                                }
+                            }
+                        }
+                        let count = scalar.count_neighbors(x, y);
+                        assert_eq!(count, expected, "{}x{}", width, height);
+                    }
+                }
+                scalar.update();
+                for g in &mut others {
+                    g.update();
+                    assert_eq!(alive(g), alive(&scalar), "{}x{}", width, height);
+                }
+            }
+        }
+    }
+
+    // A `width` x `height` soup from `seed`, stepped by `engine`.
+    fn soup(width: usize, height: usize, seed: u64, engine: Engine) -> Grid {
+        let mut g = Grid::new_empty_grid(width, height);
+        g.randomize(&mut session_rng(seed), 0.35);
+        g.engine = engine;
+        g
+    }
+
+    fn alive(g: &Grid) -> Vec<bool> {
+        g.grid.iter().map(|cell| cell.alive).collect()
+    }
+
+    #[test]
+    fn text_round_trips_through_display() {
+        let mut g = soup(23, 9, 3, Engine::default());
+        g.update();
+        let text = g.to_string();
+        let parsed = grid(&text);
+        assert_eq!((parsed.width, parsed.height), (23, 9));
+        assert_eq!(alive(&parsed), alive(&g));
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn ragged_rows_are_padded_out_square() {
+        let g = grid("! a comment\n#\n\n.#.#\n..#\n");
+        assert_eq!(g.to_string(), "#...\n.#.#\n..#.\n");
+        assert_eq!(grid(&g.to_string()).to_string(), g.to_string());
+    }
+
+    #[test]
+    fn rows_of_anything_else_are_refused() {
+        assert_eq!(
+            "#.#\n.o.\n".parse::<Grid>().err().as_deref(),
+            Some("line 2: unexpected 'o' (expected # or .)")
+        );
+        assert!("#.#\n#.#.2\n".parse::<Grid>().is_err());
+        assert!("".parse::<Grid>().is_err());
+        assert!("! just a comment\n\n".parse::<Grid>().is_err());
+    }
+
+    #[test]
+    fn cells_run_row_by_row_from_corner_to_corner() {
+        let g = Grid::new_empty_grid(5, 4);
+        let coords: Vec<_> = g.cells().map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(coords.len(), 20);
+        assert_eq!(
+            coords[..6],
+            [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (0, 1)]
+        );
+        assert_eq!(
+            coords[14..],
+            [(4, 2), (0, 3), (1, 3), (2, 3), (3, 3), (4, 3)]
+        );
+    }
+
+    #[test]
+    fn live_cells_include_the_edge_rows_and_columns() {
+        // Corners, and the middle of each edge.
+        let g = grid("#.#.#\n....#\n#....\n#.#.#\n");
+        let live: Vec<_> = g.live_cells().collect();
+        assert_eq!(
+            live,
+            [
+                (0, 0),
+                (2, 0),
+                (4, 0),
+                (4, 1),
+                (0, 2),
+                (0, 3),
+                (2, 3),
+                (4, 3)
+            ]
+        );
+        for (x, y) in live {
+            assert!(g
+                .cells()
+                .any(|(cx, cy, cell)| (cx, cy) == (x, y) && cell.alive));
+        }
+    }
+
+    #[test]
+    fn changed_cells_include_the_edge_rows_and_columns() {
+        // A blinker on the left edge, across the top and bottom rows,
+        // that turns to lie across the left and right columns.
+        for engine in [Engine::Scalar, Engine::Bitset, Engine::Active] {
+            let mut g = grid("#....\n#....\n.....\n#....\n");
+            g.engine = engine;
+            g.update();
+            assert_eq!(
+                g.to_string(),
+                "##..#\n.....\n.....\n.....\n",
+                "{:?}",
+                engine
+            );
+            let changed: Vec<_> = g.changed_cells().collect();
+            assert_eq!(changed, [(1, 0), (4, 0), (0, 1), (0, 3)], "{:?}", engine);
+        }
+    }
+
+    #[test]
+    fn randomize_fills_the_grid_to_the_density_asked_for() {
+        // --density is the chance each cell starts alive, so over a quarter
+        // of a million cells the live fraction is within a point of it.
+        for (seed, density) in [0.1, 0.25, 0.5, 0.9].into_iter().enumerate() {
+            let mut g = Grid::new_empty_grid(512, 512);
+            g.randomize(&mut session_rng(seed as u64), density);
+            let fraction = g.population() as f32 / g.grid.len() as f32;
+            assert!(
+                (fraction - density).abs() < 0.01,
+                "{} at --density {}",
+                fraction,
+                density
+            );
+        }
+
+        let mut g = Grid::new_empty_grid(64, 64);
+        g.randomize(&mut session_rng(1), 0.0);
+        assert_eq!(g.population(), 0);
+        g.randomize(&mut session_rng(1), 1.0);
+        assert_eq!(g.population(), 64 * 64);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_soup_and_kills() {
+        let run = |seed: u64| {
+            let mut rng = session_rng(seed);
+            let mut g = Grid::new_random_with_rng(64, 48, &mut rng, 0.4);
+            let soup = g.grid.clone();
+            let killed = g.randomly_kill(0.25, &mut rng);
+            (soup, killed, alive(&g))
+        };
+
+        let (soup, killed, survivors) = run(99);
+        assert_eq!(run(99), (soup.clone(), killed, survivors.clone()));
+        // The count is of cells that died, about a quarter of the soup.
+        let population = soup.iter().filter(|cell| cell.alive).count() as u32;
+        let left = survivors.iter().filter(|&&alive| alive).count() as u32;
+        assert_eq!(population - left, killed);
+        assert!((killed as f32 / population as f32 - 0.25).abs() < 0.05);
+
+        let (other, other_killed, _) = run(100);
+        assert!(other != soup || other_killed != killed);
+    }
+
+    #[test]
+    fn symmetric_soups_have_their_symmetry() {
+        type Mirror = fn(usize, usize, usize, usize) -> (usize, usize);
+        let flip_x: Mirror = |x, y, w, _| (w - 1 - x, y);
+        let flip_y: Mirror = |x, y, _, h| (x, h - 1 - y);
+        let turn: Mirror = |x, y, w, h| (w - 1 - x, h - 1 - y);
+        let modes: [(SoupSymmetry, &[Mirror]); 4] = [
+            (SoupSymmetry::MirrorX, &[flip_x]),
+            (SoupSymmetry::MirrorY, &[flip_y]),
+            (SoupSymmetry::Both, &[flip_x, flip_y, turn]),
+            (SoupSymmetry::Rot180, &[turn]),
+        ];
+        // Even and odd both ways, so there are middle rows and columns.
+        for (w, h) in [(16, 12), (17, 13), (16, 13), (17, 12)] {
+            for (n, &(symmetry, mirrors)) in modes.iter().enumerate() {
+                let mut g = Grid::new_empty_grid(w, h);
+                g.randomize_symmetric(&mut session_rng(n as u64), 0.5, symmetry);
+                assert!(g.population() > 0);
+                for y in 0..h {
+                    for x in 0..w {
+                        for mirror in mirrors {
+                            let (mx, my) = mirror(x, y, w, h);
+                            assert_eq!(
+                                g.grid.alive[x + y * w],
+                                g.grid.alive[mx + my * w],
+                                "{} on {}x{}, ({}, {}) and ({}, {})",
+                                symmetry,
+                                w,
+                                h,
+                                x,
+                                y,
+                                mx,
+                                my
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kills_take_about_the_share_asked_for() {
+        let full = || {
+            let mut g = Grid::new_empty_grid(200, 100);
+            g.invert();
+            g
+        };
+        let mut g = full();
+        let killed = g.randomly_kill(0.25, &mut session_rng(5));
+        assert_eq!(g.population(), 20_000 - killed as usize);
+        let left = g.population() as f64 / 20_000.0;
+        assert!((left - 0.75).abs() < 0.01, "{} left", left);
+
+        // None and all are exact.
+        let mut g = full();
+        assert_eq!(g.randomly_kill(0.0, &mut session_rng(5)), 0);
+        assert_eq!(g.population(), 20_000);
+        let mut g = full();
+        assert_eq!(g.randomly_kill(1.0, &mut session_rng(5)), 20_000);
+        assert_eq!(g.population(), 0);
+    }
+
+    #[test]
+    fn inverting_twice_gives_back_the_alive_pattern() {
+        let mut g = soup(37, 23, 5, Engine::default());
+        for _ in 0..10 {
+            g.update();
+        }
+        let before = alive(&g);
+
+        g.invert();
+        assert!(alive(&g).iter().zip(&before).all(|(now, was)| now != was));
+        g.invert();
+        assert_eq!(alive(&g), before);
+    }
+
+    #[test]
+    fn active_engine_matches_the_full_scan() {
+        let mut full = soup(48, 32, 11, Engine::Scalar);
+        let mut active = soup(48, 32, 11, Engine::Active);
+        for generation in 0..3000 {
+            // Edits now and then, which the active set has to pick up, and
+            // a fresh soup once it's likely to have settled.
+            let edit = |g: &mut Grid| match generation {
+                500 => {
+                    g.toggle(10, 10);
+                }
+                900 => g.set_line(-5, 3, 60, 20, 1, true),
+                1400 => g.set_rect(40, 25, 52, 36, false),
+                2000 => g.randomize(&mut session_rng(12), 0.3),
+                2500 => g.invert(),
+                _ => {}
+            };
+            edit(&mut full);
+            edit(&mut active);
+            let (a, b) = (full.update(), active.update());
+            assert_eq!(a, b, "generation {}", generation);
+            assert_eq!(full.grid, active.grid, "generation {}", generation);
+        }
+    }
+
+    #[test]
+    fn scaled_renders_are_blocks_of_the_drawn_colors() {
+        let mut g = soup(7, 5, 21, Engine::default());
+        g.update();
+        let mut drawn = vec![0; 4 * 7 * 5];
+        g.draw(&mut drawn);
+        let line = [0x20, 0x40, 0x60, 0xff];
+        let plain = g.render_scaled(3, None);
+        let lined = g.render_scaled(3, Some(line));
+        assert_eq!(plain.len(), 4 * 21 * 15);
+        for y in 0..15 {
+            for x in 0..21 {
+                let i = 4 * (x + y * 21);
+                let cell = 4 * (x / 3 + y / 3 * 7);
+                let color = [drawn[cell], drawn[cell + 1], drawn[cell + 2], 0xff];
+                assert_eq!(plain[i..i + 4], color, "({}, {})", x, y);
+                let expected = if x % 3 == 0 || y % 3 == 0 {
+                    line
+                } else {
+                    color
+                };
+                assert_eq!(lined[i..i + 4], expected, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn dirty_redraws_match_full_draws() {
+        for engine in Engine::ALL {
+            for age_colors in [None, Some(Gradient::default())] {
+                let mut g = soup(40, 30, 9, engine);
+                g.age_colors = age_colors;
+                let mut frame = vec![0; 4 * 40 * 30];
+                let mut full = frame.clone();
+                g.draw(&mut frame);
+                g.clear_dirty();
+                for generation in 0..300 {
+                    match generation {
+                        100 => {
+                            g.toggle(0, 0);
+                        }
+                        150 => g.set_line(-3, 5, 45, 12, 1, true),
+                        200 => g.invert(),
+                        _ => {}
+                    }
+                    g.update();
+                    match g.dirty_cells() {
+                        Some(cells) => g.draw_dirty(&mut frame, cells),
+                        None => g.draw(&mut frame),
+                    }
+                    g.clear_dirty();
+                    g.draw(&mut full);
+                    assert!(frame == full, "{} generation {}", engine, generation);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dirty_cells_cover_several_updates_between_frames() {
+        let mut g = soup(40, 30, 9, Engine::Scalar);
+        let mut frame = vec![0; 4 * 40 * 30];
+        let mut full = frame.clone();
+        g.draw(&mut frame);
+        g.clear_dirty();
+        let mut fell_back = false;
+        for updates in [1, 3, 10, 100, 1, 1000] {
+            for _ in 0..updates {
+                g.update();
+            }
+            match g.dirty_cells() {
+                Some(cells) => {
+                    assert!(cells.len() <= g.grid.len());
+                    g.draw_dirty(&mut frame, cells);
+                }
+                None => {
+                    fell_back = true;
+                    g.draw(&mut frame);
+                }
+            }
+            g.clear_dirty();
+            g.draw(&mut full);
+            assert!(frame == full, "after {} updates", updates);
+        }
+        assert!(fell_back);
+    }
+
+    // The next generation as `update` worked it out before the double
+    // buffer: from a copy of every cell, each one stepped in place.
+    fn cloned_step(g: &Grid) -> Cells {
+        let mut next = g.grid.clone();
+        for (x, y, cell) in g.cells() {
+            let n = g.count_neighbors(x, y);
+            let alive = if cell.alive {
+                g.rule.survives(n)
+            } else {
+                g.rule.births(n)
+            };
+            let mut cell = cell;
+            match (cell.alive, alive) {
+                (true, true) => cell.survive(),
+                (false, true) => cell.born(),
+                (was_alive, false) => {
+                    cell.set(false);
+                    if g.trails {
+                        cell.cool_if_dead(g.decay);
+                    } else if was_alive {
+                        cell.heat = 0;
+                    }
+                }
+            }
+            next.put(x + y * g.width, cell);
+        }
+        next
+    }
+
+    #[test]
+    fn double_buffer_matches_stepping_a_copy() {
+        for engine in [Engine::Scalar, Engine::Bitset] {
+            for trails in [true, false] {
+                let mut g = soup(71, 29, 5, engine);
+                g.trails = trails;
+                for generation in 0..400 {
+                    let expected = cloned_step(&g);
+                    let before = g.grid.clone();
+                    g.update();
+                    assert_eq!(g.grid, expected, "{} generation {}", engine, generation);
+
+                    // Cells that stayed dead aren't written by the rule at
+                    // all, only cooled (or left be without trails).
+                    for i in 0..before.len() {
+                        if !before.alive[i] && !g.grid.alive[i] {
+                            let heat = if trails {
+                                g.decay.cool(before.heat[i])
+                            } else {
+                                before.heat[i]
+                            };
+                            assert_eq!(g.grid.heat[i], heat);
+                            assert_eq!(g.grid.age[i], before.age[i]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // A generation of Life on a torus of `Cell`s, a whole struct to a
+    // cell as the grid used to keep them, worked out without the grid.
+    fn step_cell_array(g: &Grid, cells: &[Cell]) -> Vec<Cell> {
+        let (w, h) = (g.width, g.height);
+        let mut next = cells.to_vec();
+        for (i, cell) in next.iter_mut().enumerate() {
+            let (x, y) = (i % w, i / w);
+            let mut n = 0;
+            for dy in [h - 1, 0, 1] {
+                for dx in [w - 1, 0, 1] {
+                    if (dx, dy) != (0, 0) {
+                        n += cells[(x + dx) % w + (y + dy) % h * w].alive as usize;
+                    }
+                }
+            }
+            let alive = if cell.alive {
+                g.rule.survives(n)
+            } else {
+                g.rule.births(n)
+            };
+            match (cell.alive, alive) {
+                (true, true) => cell.survive(),
+                (false, true) => cell.born(),
+                (was_alive, false) => {
+                    cell.set(false);
+                    if g.trails {
+                        cell.cool_if_dead(g.decay);
+                    } else if was_alive {
+                        cell.heat = 0;
+                    }
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn split_fields_step_like_a_cell_array_under_a_fixed_seed() {
+        for engine in [Engine::Scalar, Engine::Bitset, Engine::Active] {
+            for trails in [true, false] {
+                let mut g = soup(96, 54, 2024, engine);
+                g.trails = trails;
+                let mut cells: Vec<Cell> = g.grid.iter().collect();
+                for generation in 1..=500 {
+                    g.update();
+                    cells = step_cell_array(&g, &cells);
+                    if generation % 50 == 0 {
+                        let grid: Vec<Cell> = g.grid.iter().collect();
+                        assert!(grid == cells, "{}, generation {}", engine, generation);
+                    }
+                }
+                assert_eq!(g.population(), cells.iter().filter(|c| c.alive).count());
+            }
+        }
+    }
+
+    #[test]
+    fn hashlife_jumps_keep_the_noise() {
+        let noisy = || {
+            let mut g = soup(40, 24, 13, Engine::HashLife);
+            g.noise = Noise::Cells(5);
+            g.noise_seed = 14;
+            g
+        };
+        let (mut stepped, mut jumped) = (noisy(), noisy());
+        for _ in 0..50 {
+            stepped.update();
+        }
+        jumped.advance(50);
+        assert_eq!(jumped.generation, 50);
+        assert_eq!(alive(&jumped), alive(&stepped));
+    }
+
+    #[test]
+    fn bitset_engine_matches_scalar_cell_for_cell() {
+        for (n, (width, height)) in [(67, 13), (130, 7), (64, 20)].into_iter().enumerate() {
+            // Trails on and off, and switched after a while, colored by age
+            // or not, and cooling either way: each takes a different way
+            // through `follow_alive`.
+            let cases = [
+                (true, false, Decay::Linear(50)),
+                (false, false, Decay::Linear(50)),
+                (true, true, Decay::Exponential(0.9)),
+            ];
+            for (trails, age_colors, decay) in cases {
+                let mut scalar = soup(width, height, n as u64, Engine::Scalar);
+                let mut bitset = soup(width, height, n as u64, Engine::Bitset);
+                for g in [&mut scalar, &mut bitset] {
+                    g.trails = trails;
+                    g.age_colors = age_colors.then(Gradient::default);
+                    g.decay = decay;
+                }
+                for generation in 0..400 {
+                    if generation == 200 {
+                        scalar.trails = !trails;
+                        bitset.trails = !trails;
+                    }
+                    assert_eq!(scalar.update(), bitset.update());
+                    assert_eq!(
+                        scalar.grid, bitset.grid,
+                        "{}x{}, generation {}",
+                        width, height, generation
+                    );
+                    assert_eq!(scalar.dirty_cells(), bitset.dirty_cells());
+                    scalar.clear_dirty();
+                    bitset.clear_dirty();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_idx_rejects_cells_off_the_grid() {
+        let g = Grid::new_empty_grid(4, 3);
+        assert_eq!(g.grid_idx(0, 0), Some(0));
+        assert_eq!(g.grid_idx(3, 2), Some(11));
+        assert_eq!(g.grid_idx(4, 0), None);
+        assert_eq!(g.grid_idx(0, 3), None);
+        assert_eq!(g.grid_idx(-1, 0), None);
+        assert_eq!(g.grid_idx(0, -1), None);
+        assert_eq!(g.grid_idx(isize::MAX, isize::MIN), None);
+        assert_eq!(g.grid_idx(usize::MAX, 0), None);
+    }
+
+    #[test]
+    fn copying_the_live_cells_takes_their_bounding_box() {
+        let glider = PatternBuffer::from_rows(&[".#.", "..#", "###"]);
+        let mut g = Grid::new_empty_grid(10, 10);
+        g.insert_pattern(&glider, 3, 4);
+        let copied = g.copy_live_bounds();
+        assert_eq!(copied, glider);
+        assert_eq!(rle::parse(&rle::write(&copied, &g.rle_rule())), Ok(glider));
+
+        // An empty grid copies as a pattern with no cells, not a panic.
+        let empty = Grid::new_empty_grid(10, 10).copy_live_bounds();
+        assert_eq!((empty.width(), empty.height()), (0, 0));
+        let text = rle::write(&empty, "B3/S23");
+        assert!(text.starts_with("x = 0, y = 0"), "{}", text);
+        assert_eq!(rle::parse(&text), Ok(empty));
+    }
+
+    #[test]
+    fn probes_count_neighbors_across_the_edges() {
+        // A glider wrapped over the corner of a torus.
+        let mut g = Grid::new_empty_grid(10, 10);
+        g.insert_pattern(&PatternBuffer::from_rows(&[".#.", "..#", "###"]), 8, 8);
+        let probe = g.probe(9, 9);
+        assert_eq!(
+            (probe.x, probe.y, probe.alive, probe.neighbors),
+            (9, 9, false, 5)
+        );
+        let probe = g.probe(0, 0);
+        assert_eq!((probe.alive, probe.neighbors), (true, 2));
+        assert_eq!(probe.heat, g.grid.heat[0]);
+    }
+}
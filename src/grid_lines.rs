@@ -0,0 +1,140 @@
+//
+// [L]: faint lines between cells, drawn at screen resolution on top of the
+// scaled-up grid. They're only drawn once cells are at least
+// `MIN_CELL_SIZE` pixels wide, so a 1:1 view never turns into solid lines.
+// The lines come from the same clip rectangle `pixels` uses to map the
+// mouse to cells, so they stay on the cell boundaries.
+//
+
+use pixels::wgpu::{self, util::DeviceExt};
+use pixels::PixelsContext;
+
+const MIN_CELL_SIZE: u32 = 4;
+
+// Color, clip rectangle and cell size, as in the shader's `Locals`.
+const LOCALS_SIZE: usize = 12 * std::mem::size_of::<f32>();
+
+pub struct GridLines {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    locals: wgpu::Buffer,
+    color: [f32; 4],
+}
+
+impl GridLines {
+    /// Sets up the pipeline on `pixels`' device. It has to be built again
+    /// if `pixels` is.
+    pub fn new(pixels: &pixels::Pixels, color: [u8; 4]) -> Self {
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("grid_lines.wgsl"));
+
+        let locals = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("grid_lines_locals"),
+            contents: &[0; LOCALS_SIZE],
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_lines_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_lines_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: locals.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("grid_lines_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("grid_lines_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: pixels.render_texture_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            locals,
+            color: color.map(|c| c as f32 / 255.0),
+        }
+    }
+
+    /// Draws the lines over what's already in `target`, if the cells of a
+    /// `grid_width` wide grid are big enough on screen.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        context: &PixelsContext,
+        grid_width: u32,
+    ) {
+        let (x, y, width, height) = context.scaling_renderer.clip_rect();
+        // `pixels` only scales by whole numbers, so this is exact.
+        let cell_size = width / grid_width.max(1);
+        if cell_size < MIN_CELL_SIZE {
+            return;
+        }
+
+        let locals = [
+            self.color,
+            [x as f32, y as f32, width as f32, height as f32],
+            [cell_size as f32, 0.0, 0.0, 0.0],
+        ];
+        let bytes: Vec<u8> = locals
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        context.queue.write_buffer(&self.locals, 0, &bytes);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("grid_lines_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_scissor_rect(x, y, width, height);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,33 @@
+// Grid lines over the scaled-up grid: a full-screen triangle whose
+// fragments are kept only on the first pixel row and column of each cell
+// (and the last ones of the grid, to close it off).
+
+struct Locals {
+    color: vec4<f32>,
+    // Where the grid is on screen: x, y, width, height in pixels.
+    clip: vec4<f32>,
+    // Screen pixels per cell.
+    cell_size: vec4<f32>,
+}
+@group(0) @binding(0) var<uniform> r_locals: Locals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let local = floor(position.xy) - r_locals.clip.xy;
+    let size = r_locals.cell_size.x;
+    let on_line = local.x - floor(local.x / size) * size < 0.5
+        || local.y - floor(local.y / size) * size < 0.5
+        || local.x >= r_locals.clip.z - 1.0
+        || local.y >= r_locals.clip.w - 1.0;
+    if (!on_line) {
+        discard;
+    }
+    return r_locals.color;
+}
@@ -0,0 +1,445 @@
+//
+// Gosper's HashLife, behind `Engine::HashLife`. The world is a quadtree
+// whose nodes are hash-consed, so identical regions (empty space above
+// all) are stored once, and every node remembers what its center turns
+// into a power of two generations on. Patterns with any regularity can
+// then be run ahead millions of generations at once.
+//
+// Unlike the rest of the simulation this world doesn't wrap: it's an
+// infinite dead plane with the grid's top-left corner at (0, 0), clipped
+// back to the grid's bounds when read out. What leaves the grid carries
+// on out of sight instead of coming back round the other side.
+//
+
+use std::collections::HashMap;
+
+use crate::rule::Rule;
+
+type Id = u32;
+
+const DEAD: Id = 0;
+const ALIVE: Id = 1;
+
+// Past this many nodes, everything the root doesn't use is thrown out.
+const MAX_NODES: usize = 1 << 22;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    // A node at level k is 2^k cells on a side; leaves (single cells) are
+    // level 0.
+    level: u8,
+    // nw, ne, sw, se. Unused by leaves.
+    children: [Id; 4],
+    population: u64,
+}
+
+#[derive(Debug)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    table: HashMap<[Id; 4], Id>,
+    // (node, log2 of the generations) -> its center that many generations on.
+    results: HashMap<(Id, u8), Id>,
+    // The empty node of each level.
+    empty: Vec<Id>,
+    root: Id,
+    // World coordinates of the root's top-left corner.
+    origin: (i64, i64),
+}
+
+impl HashLife {
+    /// A world holding the live cells of a `width` x `height` grid.
+    pub fn from_cells(
+        rule: Rule,
+        width: usize,
+        height: usize,
+        alive: impl Fn(usize, usize) -> bool,
+    ) -> Self {
+        let mut world = Self {
+            rule,
+            nodes: Vec::new(),
+            table: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+            root: DEAD,
+            origin: (0, 0),
+        };
+        world.add_leaves();
+
+        let mut level = 3;
+        while (1usize << level) < width.max(height) {
+            level += 1;
+        }
+        world.root = world.build(level, 0, 0, width, height, &alive);
+        world
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Changes the rule, forgetting every result worked out under the old one.
+    pub fn set_rule(&mut self, rule: Rule) {
+        if rule != self.rule {
+            self.rule = rule;
+            self.results.clear();
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// Runs the world `generations` generations on, in steps of the powers
+    /// of two that make it up.
+    pub fn advance(&mut self, generations: u64) {
+        for j in 0..u64::BITS as u8 {
+            if generations >> j & 1 == 0 {
+                continue;
+            }
+            if self.nodes.len() > MAX_NODES {
+                self.collect_garbage();
+            }
+
+            // The result is the root's center half, so pad it until the
+            // live cells are well inside that: a pattern can't grow faster
+            // than a cell per generation.
+            while self.level(self.root) < j + 2 || !self.is_padded() {
+                self.expand();
+            }
+            self.expand();
+
+            let quarter = 1i64 << (self.level(self.root) - 2);
+            self.root = self.step(self.root, j);
+            self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        }
+    }
+
+    /// Calls `f` with every live cell inside the `width` x `height`
+    /// rectangle at the world's origin.
+    pub fn for_each_live(&self, width: usize, height: usize, mut f: impl FnMut(usize, usize)) {
+        let (x, y) = self.origin;
+        self.visit(self.root, x, y, width as i64, height as i64, &mut f);
+    }
+
+    fn add_leaves(&mut self) {
+        for population in [0, 1] {
+            self.nodes.push(Node {
+                level: 0,
+                children: [DEAD; 4],
+                population,
+            });
+        }
+        self.empty = vec![DEAD];
+    }
+
+    fn level(&self, id: Id) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    fn children(&self, id: Id) -> [Id; 4] {
+        self.nodes[id as usize].children
+    }
+
+    // The one node with these children.
+    fn join(&mut self, children: [Id; 4]) -> Id {
+        if let Some(&id) = self.table.get(&children) {
+            return id;
+        }
+        let id = self.nodes.len() as Id;
+        self.nodes.push(Node {
+            level: self.level(children[0]) + 1,
+            children,
+            population: children.iter().fold(0u64, |sum, &c| {
+                sum.saturating_add(self.nodes[c as usize].population)
+            }),
+        });
+        self.table.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> Id {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let id = self.join([below; 4]);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    // The node for the 2^level square at (x0, y0) of the grid; anything
+    // past the grid is dead.
+    fn build(
+        &mut self,
+        level: u8,
+        x0: usize,
+        y0: usize,
+        width: usize,
+        height: usize,
+        alive: &impl Fn(usize, usize) -> bool,
+    ) -> Id {
+        if x0 >= width || y0 >= height {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return if alive(x0, y0) { ALIVE } else { DEAD };
+        }
+        let half = 1 << (level - 1);
+        let nw = self.build(level - 1, x0, y0, width, height, alive);
+        let ne = self.build(level - 1, x0 + half, y0, width, height, alive);
+        let sw = self.build(level - 1, x0, y0 + half, width, height, alive);
+        let se = self.build(level - 1, x0 + half, y0 + half, width, height, alive);
+        self.join([nw, ne, sw, se])
+    }
+
+    // Puts the root in the middle of a dead node twice its size.
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        let e = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            self.join([e, e, e, nw]),
+            self.join([e, e, ne, e]),
+            self.join([e, sw, e, e]),
+            self.join([se, e, e, e]),
+        ];
+        self.root = self.join(children);
+
+        let half = 1i64 << (level - 1);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+
+    // Whether every live cell is in the root's center half.
+    fn is_padded(&self) -> bool {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let outer = |id: Id, inner: usize| {
+            self.children(id)
+                .iter()
+                .enumerate()
+                .all(|(i, &c)| i == inner || self.nodes[c as usize].population == 0)
+        };
+        outer(nw, 3) && outer(ne, 2) && outer(sw, 1) && outer(se, 0)
+    }
+
+    // A level k node's center, a level k - 1 node.
+    fn center(&mut self, id: Id) -> Id {
+        let [nw, ne, sw, se] = self.children(id);
+        let children = [
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ];
+        self.join(children)
+    }
+
+    // The center of a level k node (k >= 2) after 2^j generations, for
+    // j <= k - 2.
+    fn step(&mut self, id: Id, j: u8) -> Id {
+        let level = self.level(id);
+        debug_assert!(level >= 2 && j <= level - 2);
+
+        if self.nodes[id as usize].population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.step_4x4(id)
+        } else {
+            let [a, b, c, d] = self.children(id);
+            let [_, a_ne, a_sw, a_se] = self.children(a);
+            let [b_nw, _, b_sw, b_se] = self.children(b);
+            let [c_nw, c_ne, _, c_se] = self.children(c);
+            let [d_nw, d_ne, d_sw, _] = self.children(d);
+
+            // Nine overlapping level k - 1 squares, row by row.
+            let squares = [
+                a,
+                self.join([a_ne, b_nw, a_se, b_sw]),
+                b,
+                self.join([a_sw, a_se, c_nw, c_ne]),
+                self.join([a_se, b_sw, c_ne, d_nw]),
+                self.join([b_sw, b_se, d_nw, d_ne]),
+                c,
+                self.join([c_ne, d_nw, c_se, d_sw]),
+                d,
+            ];
+
+            // Full speed takes both halves of 2^j steps; otherwise the
+            // first half stands still and the second does all of it.
+            let full = j == level - 2;
+            let mut r = [DEAD; 9];
+            for (r, &square) in r.iter_mut().zip(&squares) {
+                *r = if full {
+                    self.step(square, j - 1)
+                } else {
+                    self.center(square)
+                };
+            }
+            let second = if full { j - 1 } else { j };
+
+            let mut quadrants = [DEAD; 4];
+            for (quadrant, corner) in quadrants.iter_mut().zip([0, 1, 3, 4]) {
+                let node = self.join([r[corner], r[corner + 1], r[corner + 3], r[corner + 4]]);
+                *quadrant = self.step(node, second);
+            }
+            self.join(quadrants)
+        };
+
+        self.results.insert((id, j), result);
+        result
+    }
+
+    // The center 2x2 of a 4x4 node, a generation on.
+    fn step_4x4(&mut self, id: Id) -> Id {
+        let mut cells = [[false; 4]; 4];
+        for (q, &quadrant) in self.children(id).iter().enumerate() {
+            for (i, &leaf) in self.children(quadrant).iter().enumerate() {
+                let x = (q % 2) * 2 + i % 2;
+                let y = (q / 2) * 2 + i / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+
+        let mut next = [DEAD; 4];
+        for (i, next) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + i % 2, 1 + i / 2);
+            // The 3x3 block around the cell, less the cell itself.
+            let neighbors = cells[y - 1..=y + 1]
+                .iter()
+                .flat_map(|row| &row[x - 1..=x + 1])
+                .filter(|&&alive| alive)
+                .count()
+                - cells[y][x] as usize;
+            let alive = if cells[y][x] {
+                self.rule.survives(neighbors)
+            } else {
+                self.rule.births(neighbors)
+            };
+            *next = if alive { ALIVE } else { DEAD };
+        }
+        self.join(next)
+    }
+
+    // Walks the live cells of node `id`, whose top-left corner is at world
+    // (x, y), that fall inside the `width` x `height` rectangle at (0, 0).
+    fn visit(
+        &self,
+        id: Id,
+        x: i64,
+        y: i64,
+        width: i64,
+        height: i64,
+        f: &mut impl FnMut(usize, usize),
+    ) {
+        let node = self.nodes[id as usize];
+        let size = 1i64 << node.level;
+        if node.population == 0 || x >= width || y >= height || x + size <= 0 || y + size <= 0 {
+            return;
+        }
+        if node.level == 0 {
+            f(x as usize, y as usize);
+            return;
+        }
+        let half = size / 2;
+        for (i, &child) in node.children.iter().enumerate() {
+            let dx = half * (i % 2) as i64;
+            let dy = half * (i / 2) as i64;
+            self.visit(child, x + dx, y + dy, width, height, f);
+        }
+    }
+
+    // Rebuilds the tables with only the nodes the root is made of.
+    fn collect_garbage(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.table.clear();
+        self.results.clear();
+        self.add_leaves();
+
+        let mut moved = HashMap::new();
+        self.root = self.copy(&nodes, self.root, &mut moved);
+    }
+
+    fn copy(&mut self, nodes: &[Node], id: Id, moved: &mut HashMap<Id, Id>) -> Id {
+        let node = nodes[id as usize];
+        if node.level == 0 {
+            return id;
+        }
+        if let Some(&new) = moved.get(&id) {
+            return new;
+        }
+        let children = node.children.map(|child| self.copy(nodes, child, moved));
+        let new = self.join(children);
+        moved.insert(id, new);
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LIBRARY;
+    use crate::world::SparseWorld;
+    use std::collections::BTreeSet;
+
+    // Big enough that nothing the gun fires in 300 generations leaves it.
+    const SIDE: usize = 256;
+    const AT: usize = 16;
+
+    fn gun() -> crate::pattern::PatternBuffer {
+        let gun = LIBRARY.iter().find(|p| p.name == "Gosper glider gun");
+        crate::rle::parse(gun.unwrap().rle).unwrap()
+    }
+
+    fn hashlife() -> HashLife {
+        let gun = gun();
+        let (w, h) = (gun.width(), gun.height());
+        HashLife::from_cells(Rule::default(), SIDE, SIDE, |x, y| {
+            (AT..AT + w).contains(&x) && (AT..AT + h).contains(&y) && gun.get(x - AT, y - AT)
+        })
+    }
+
+    fn live(world: &HashLife) -> BTreeSet<(usize, usize)> {
+        let mut cells = BTreeSet::new();
+        world.for_each_live(SIDE, SIDE, |x, y| {
+            cells.insert((x, y));
+        });
+        cells
+    }
+
+    #[test]
+    fn jumps_match_the_naive_engine_on_a_glider_gun() {
+        let rule = Rule::default();
+        let mut naive = SparseWorld::default();
+        for (x, y) in gun().live_cells() {
+            naive.set((AT + x) as i64, (AT + y) as i64, true);
+        }
+
+        // Powers of two and odd counts, up to a few hundred generations.
+        let mut stepped = hashlife();
+        let mut generation = 0;
+        for jump in [1, 2, 4, 7, 64, 100, 128, 333] {
+            let mut jumped = hashlife();
+            jumped.advance(generation + jump);
+            for _ in 0..jump {
+                stepped.advance(1);
+                naive.step(rule);
+            }
+            generation += jump;
+
+            let expected: BTreeSet<_> = naive.live_in((0, 0), SIDE, SIDE).collect();
+            assert_eq!(
+                naive.population(),
+                expected.len(),
+                "generation {}",
+                generation
+            );
+            assert_eq!(live(&stepped), expected, "generation {}", generation);
+            assert_eq!(live(&jumped), expected, "generation {}", generation);
+            assert_eq!(jumped.population(), expected.len() as u64);
+        }
+    }
+}
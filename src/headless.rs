@@ -0,0 +1,143 @@
+//
+// `--generations`: a batch run without a window, for scripted experiments
+// and for checking the engines from CI. The grid starts as the window
+// would start it (a soup from --seed, and any --patterns) and runs exactly
+// that many generations as fast as it can. Then it prints a summary, and
+// writes the grid to --out if asked. With --dump-frames every generation
+// is drawn and written out on the way, the starting one included, and with
+// --stats-out each one's counts are written as they're stepped. A --script
+// runs on the grid before the first, and after each one.
+//
+// Ctrl+C stops it early, once the frames already queued and the counts so
+// far are written. The grid isn't what was asked for then, so --out is
+// left alone and it exits with an error.
+//
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use tgol::grid::Grid;
+#[cfg(feature = "scripting")]
+use tgol::history::History;
+
+use crate::cli::Options;
+use crate::config::Settings;
+use crate::frame_dump::FrameDump;
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+use crate::stats_csv::{write_stats, StatsCsv};
+use crate::{apply_settings, session_seed, starting_grid};
+
+pub fn run(options: &Options, settings: &Settings, generations: u64) -> Result<(), String> {
+    let seed = session_seed(options);
+    let size = options.grid_size();
+    let mut life = starting_grid(options, size, seed);
+    apply_settings(&mut life, settings);
+
+    // Nothing's undone or recorded here, but scripts edit through both.
+    #[cfg(feature = "scripting")]
+    let (mut script, mut history, mut recorder) = (
+        options
+            .script
+            .as_deref()
+            .map(|path| Script::load(path, seed))
+            .transpose()?,
+        History::default(),
+        None,
+    );
+    #[cfg(feature = "scripting")]
+    if let Some(script) = &mut script {
+        script.init(&mut life, &mut history, &mut recorder);
+    }
+    #[cfg(not(feature = "scripting"))]
+    if options.script.is_some() {
+        return Err("--script needs tgol to be built with the `scripting` feature".to_string());
+    }
+
+    let mut dump = options
+        .dump_frames
+        .as_ref()
+        .map(|dir| {
+            FrameDump::start(dir.clone(), size, options.dump_scale)
+                .map_err(|e| format!("couldn't dump frames to {}: {}", dir.display(), e))
+        })
+        .transpose()?;
+    let mut stats_csv = options
+        .stats_out
+        .as_deref()
+        .map(|path| {
+            StatsCsv::create(path, options.automaton.teams())
+                .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+        })
+        .transpose()?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+        .map_err(|e| format!("couldn't catch Ctrl+C: {}", e))?;
+
+    let start = Instant::now();
+    let first = life.generation();
+    let mut screen = vec![0; 4 * life.grid.len()];
+    let mut result = Ok(());
+    for n in 0..=generations {
+        if n > 0 {
+            write_stats(&mut stats_csv, &life.update());
+            #[cfg(feature = "scripting")]
+            if let Some(script) = &mut script {
+                script.on_generation(&mut life, &mut history, &mut recorder);
+            }
+        }
+        if let Some(frames) = &mut dump {
+            match life.dirty_cells() {
+                Some(cells) => life.draw_dirty(&mut screen, cells),
+                None => life.draw(&mut screen),
+            }
+            life.clear_dirty();
+            if !frames.push(&screen) {
+                result = Err("couldn't write the frames".to_string());
+                break;
+            }
+        }
+        if n < generations && !running.load(Ordering::SeqCst) {
+            result = Err(format!("interrupted at generation {}", life.generation()));
+            break;
+        }
+    }
+    // Frames still being written count towards the time: they're part of
+    // the run.
+    if let Some(frames) = dump {
+        frames.finish();
+    }
+    if let Some(csv) = &mut stats_csv {
+        if let Err(e) = csv.flush() {
+            result = result.and(Err(format!("couldn't write --stats-out: {}", e)));
+        }
+    }
+    let secs = start.elapsed().as_secs_f64();
+
+    let ran = life.generation() - first;
+    println!(
+        "{} generations in {:.3} s ({:.1} generations/s), ending at generation {} with {} alive",
+        ran,
+        secs,
+        ran as f64 / secs,
+        life.generation(),
+        life.population()
+    );
+    result?;
+
+    if let Some(path) = &options.out {
+        write_out(&life, path)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+// The grid as RLE (an infinite world's view of it).
+fn write_out(life: &Grid, path: &Path) -> Result<(), String> {
+    life.export_rle(path, None)
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
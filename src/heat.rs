@@ -0,0 +1,203 @@
+//
+// Colors for heat trails. A heat palette is a gradient over heat, from 0
+// (fully cooled) to 255 (the heat a cell has while it's alive), so live
+// cells sit at its top and their trails fade down it. It's either one of
+// the built-ins by name, or stops written like an age gradient's:
+//
+//   0:#000000, 96:#3c0f6e, 192:#c83cb4, 255:#ffffff
+//
+// Fully cooled cells are always left as the background, whatever the
+// palette says for 0. The colors are looked up in a table made once per
+// palette, not worked out per cell.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::gradient::Gradient;
+use crate::ALIVE_COLOR;
+
+// A gradient's stops, by heat.
+type Stops = &'static [(u16, [u8; 4])];
+
+/// The built-in palettes, by name. "classic" is the first and the default.
+const BUILT_IN: [(&str, Stops); 4] = [
+    // Dim purple trails, faded in alpha too, under the usual blue cells.
+    (
+        "classic",
+        &[(0, [0; 4]), (254, [155, 0, 225, 225]), (255, ALIVE_COLOR)],
+    ),
+    // Black through deep purple and magenta to white-hot live cells.
+    (
+        "ember",
+        &[
+            (0, [0x00, 0x00, 0x00, 0xff]),
+            (96, [0x3c, 0x0f, 0x6e, 0xff]),
+            (192, [0xc8, 0x3c, 0xb4, 0xff]),
+            (255, [0xff, 0xff, 0xff, 0xff]),
+        ],
+    ),
+    // Matplotlib's perceptually uniform colormaps, sampled every tenth.
+    (
+        "inferno",
+        &[
+            (0, [0x00, 0x00, 0x04, 0xff]),
+            (26, [0x16, 0x0b, 0x39, 0xff]),
+            (51, [0x42, 0x0a, 0x68, 0xff]),
+            (77, [0x6a, 0x17, 0x6e, 0xff]),
+            (102, [0x93, 0x26, 0x67, 0xff]),
+            (128, [0xbc, 0x37, 0x54, 0xff]),
+            (153, [0xdd, 0x51, 0x3a, 0xff]),
+            (179, [0xf3, 0x78, 0x19, 0xff]),
+            (204, [0xfc, 0xa5, 0x0a, 0xff]),
+            (230, [0xf6, 0xd7, 0x46, 0xff]),
+            (255, [0xfc, 0xff, 0xa4, 0xff]),
+        ],
+    ),
+    (
+        "viridis",
+        &[
+            (0, [0x44, 0x01, 0x54, 0xff]),
+            (26, [0x48, 0x24, 0x75, 0xff]),
+            (51, [0x41, 0x44, 0x87, 0xff]),
+            (77, [0x35, 0x5f, 0x8d, 0xff]),
+            (102, [0x2a, 0x78, 0x8e, 0xff]),
+            (128, [0x21, 0x91, 0x8c, 0xff]),
+            (153, [0x22, 0xa8, 0x84, 0xff]),
+            (179, [0x44, 0xbf, 0x70, 0xff]),
+            (204, [0x7a, 0xd1, 0x51, 0xff]),
+            (230, [0xbd, 0xdf, 0x26, 0xff]),
+            (255, [0xfd, 0xe7, 0x25, 0xff]),
+        ],
+    ),
+];
+
+/// Names of the built-in palettes, for help text.
+pub fn built_in_names() -> impl Iterator<Item = &'static str> {
+    BUILT_IN.iter().map(|&(name, _)| name)
+}
+
+/// A heat palette, and the color of each heat under it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeatColors {
+    // The built-in it is, if it's one.
+    name: Option<&'static str>,
+    gradient: Gradient,
+    table: [[u8; 4]; 256],
+}
+
+impl Default for HeatColors {
+    fn default() -> Self {
+        let (name, stops) = BUILT_IN[0];
+        Self::built_in(name, stops)
+    }
+}
+
+impl HeatColors {
+    pub fn new(gradient: Gradient) -> Self {
+        let mut table = [[0; 4]; 256];
+        for (heat, color) in table.iter_mut().enumerate().skip(1) {
+            *color = gradient.color(heat as u16);
+        }
+        Self {
+            name: None,
+            gradient,
+            table,
+        }
+    }
+
+    fn built_in(name: &'static str, stops: Stops) -> Self {
+        Self {
+            name: Some(name),
+            ..Self::new(Gradient::new(stops))
+        }
+    }
+
+    /// The color of a dead cell with this much heat left.
+    pub fn color(&self, heat: u8) -> [u8; 4] {
+        self.table[heat as usize]
+    }
+
+    /// The color of live cells (unless they're colored by age or team).
+    pub fn live(&self) -> [u8; 4] {
+        self.table[255]
+    }
+}
+
+impl fmt::Display for HeatColors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => f.write_str(name),
+            None => self.gradient.fmt(f),
+        }
+    }
+}
+
+impl FromStr for HeatColors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(&(name, stops)) = BUILT_IN.iter().find(|&&(name, _)| name == s) {
+            return Ok(Self::built_in(name, stops));
+        }
+        if !s.contains(':') {
+            let names: Vec<_> = built_in_names().collect();
+            return Err(format!(
+                "unknown heat palette {:?} (expected {}, or gradient stops)",
+                s,
+                names.join(", ")
+            ));
+        }
+        let gradient: Gradient = s.parse()?;
+        if let Some((heat, _)) = gradient.stops().find(|&(heat, _)| heat > 255) {
+            return Err(format!("heat {} is past 255 in {:?}", heat, s));
+        }
+        Ok(Self::new(gradient))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic_is_the_old_purple_fade() {
+        // The old fade was scaled to 255 and rounded down, so rounding
+        // along the gradient can land a step or two off it.
+        let classic = HeatColors::default();
+        assert_eq!(classic.to_string(), "classic");
+        assert_eq!(classic.live(), ALIVE_COLOR);
+        for heat in 0..255 {
+            let fade = |max: u32| (heat as u32 * max / 255) as u8;
+            let old = [fade(155), 0, fade(225), fade(225)];
+            let new = classic.color(heat);
+            for (old, new) in old.into_iter().zip(new) {
+                assert!(old.abs_diff(new) <= 2, "heat {}", heat);
+            }
+        }
+    }
+
+    #[test]
+    fn cooled_cells_are_the_background_whatever_the_palette() {
+        for name in built_in_names() {
+            let colors: HeatColors = name.parse().unwrap();
+            assert_eq!(colors.to_string(), name);
+            assert_eq!(colors.color(0), [0; 4], "{}", name);
+        }
+        let inferno: HeatColors = "inferno".parse().unwrap();
+        assert_eq!(inferno.color(1), [0x01, 0x00, 0x06, 0xff]);
+        assert_eq!(inferno.color(128), [0xbc, 0x37, 0x54, 0xff]);
+        assert_eq!(inferno.live(), [0xfc, 0xff, 0xa4, 0xff]);
+    }
+
+    #[test]
+    fn stops_are_read_and_bad_palettes_refused() {
+        let colors: HeatColors = "0:#000000, 255:#ff0000".parse().unwrap();
+        assert_eq!(colors.to_string(), "0:#000000, 255:#ff0000");
+        assert_eq!(colors.live(), [0xff, 0, 0, 0xff]);
+        for bad in ["lava", "0:#000000, 300:#ffffff", ""] {
+            assert!(bad.parse::<HeatColors>().is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//
+// Undo/redo of direct user edits (clicks, drags, stamps, K, R).
+//
+// Simulation steps are never recorded: each edit is captured by diffing the
+// grid right before and right after the edit itself, so generations that
+// run in the middle of a long drag don't end up in the undo entry.
+//
+// Finished edits are stored packed: changes sorted by cell index, each one
+// a varint index delta, a flags byte (both alive flags), the two states
+// (teams, or a rule table's states), the two heats and the two ages, all
+// deflated.
+// Undo and redo entries together are kept under a byte budget by dropping
+// the oldest undo entries.
+//
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::cell::Cell;
+use crate::grid::Grid;
+
+/// Default for `History::with_budget`.
+pub const DEFAULT_BUDGET: usize = 64 * 1024 * 1024;
+
+// Fast rather than small; strokes are pushed while drawing.
+const DEFLATE_LEVEL: u8 = 1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Change {
+    pub index: usize,
+    pub before: Cell,
+    pub after: Cell,
+}
+
+#[derive(Debug, Default)]
+struct Edit {
+    changes: Vec<Change>,
+    // Position in `changes` of each touched index, to merge repeat touches.
+    positions: HashMap<usize, usize>,
+}
+
+impl Edit {
+    fn merge(&mut self, change: Change) {
+        match self.positions.get(&change.index) {
+            Some(&pos) => self.changes[pos].after = change.after,
+            None => {
+                self.positions.insert(change.index, self.changes.len());
+                self.changes.push(change);
+            }
+        }
+    }
+}
+
+/// A finished edit, packed as described at the top of this file.
+#[derive(Debug)]
+struct PackedEdit {
+    bytes: Vec<u8>,
+}
+
+impl PackedEdit {
+    fn pack(mut changes: Vec<Change>) -> Self {
+        changes.sort_unstable_by_key(|change| change.index);
+
+        let mut raw = Vec::with_capacity(changes.len() * 10);
+        let mut previous = 0;
+        for change in &changes {
+            write_varint(&mut raw, (change.index - previous) as u64);
+            previous = change.index;
+
+            raw.push(change.before.alive as u8 | (change.after.alive as u8) << 1);
+            raw.push(change.before.state);
+            raw.push(change.after.state);
+            raw.push(change.before.heat);
+            raw.push(change.after.heat);
+            raw.extend_from_slice(&change.before.age.to_le_bytes());
+            raw.extend_from_slice(&change.after.age.to_le_bytes());
+        }
+
+        Self {
+            bytes: miniz_oxide::deflate::compress_to_vec(&raw, DEFLATE_LEVEL),
+        }
+    }
+
+    fn unpack(&self) -> Vec<Change> {
+        // We wrote these bytes ourselves; failing to read them back is a bug.
+        let raw =
+            miniz_oxide::inflate::decompress_to_vec(&self.bytes).expect("corrupt undo history");
+
+        let mut changes = Vec::new();
+        let mut pos = 0;
+        let mut index = 0;
+        while pos < raw.len() {
+            index += read_varint(&raw, &mut pos) as usize;
+            let flags = raw[pos];
+            let (before_state, after_state) = (raw[pos + 1], raw[pos + 2]);
+            let (before_heat, after_heat) = (raw[pos + 3], raw[pos + 4]);
+            let before_age = u16::from_le_bytes([raw[pos + 5], raw[pos + 6]]);
+            let after_age = u16::from_le_bytes([raw[pos + 7], raw[pos + 8]]);
+            pos += 9;
+
+            changes.push(Change {
+                index,
+                before: Cell {
+                    alive: flags & 1 != 0,
+                    heat: before_heat,
+                    age: before_age,
+                    state: before_state,
+                },
+                after: Cell {
+                    alive: flags & 2 != 0,
+                    heat: after_heat,
+                    age: after_age,
+                    state: after_state,
+                },
+            });
+        }
+
+        changes
+    }
+}
+
+// LEB128: 7 bits per byte, high bit set on all but the last byte.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<PackedEdit>,
+    redo: Vec<PackedEdit>,
+    // The gesture currently being recorded, if any.
+    open: Option<Edit>,
+    // Packed size of everything in `undo` and `redo`, and the cap on it.
+    bytes: usize,
+    budget: usize,
+    // Set once an entry had to be dropped to stay under the budget.
+    truncated: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_budget(DEFAULT_BUDGET)
+    }
+}
+
+impl History {
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            bytes: 0,
+            budget,
+            truncated: false,
+        }
+    }
+
+    /// Forgets every edit, for when the cells they were recorded against
+    /// aren't where they were (the view of an infinite world moved).
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.bytes = 0;
+    }
+
+    /// Whether older edits were dropped to stay under the byte budget.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Runs `f` on the grid and records what it changed into the open
+    /// gesture (starting one if needed). Call `commit` to finish it.
+    pub fn record<R>(&mut self, grid: &mut Grid, f: impl FnOnce(&mut Grid) -> R) -> R {
+        let before = grid.checkpoint();
+        let result = f(grid);
+
+        let edit = self.open.get_or_insert_with(Edit::default);
+        for change in grid.changes_since(&before) {
+            edit.merge(change);
+        }
+
+        result
+    }
+
+    /// `record` followed by `commit`, for edits that are over in one go.
+    pub fn record_one<R>(&mut self, grid: &mut Grid, f: impl FnOnce(&mut Grid) -> R) -> R {
+        let result = self.record(grid, f);
+        self.commit();
+        result
+    }
+
+    /// Closes the open gesture as a single undo step.
+    pub fn commit(&mut self) {
+        if let Some(edit) = self.open.take() {
+            if edit.changes.is_empty() {
+                return;
+            }
+
+            for dropped in self.redo.drain(..) {
+                self.bytes -= dropped.bytes.len();
+            }
+
+            let packed = PackedEdit::pack(edit.changes);
+            self.bytes += packed.bytes.len();
+            self.undo.push_back(packed);
+            self.enforce_budget();
+        }
+    }
+
+    // Drops the oldest undo entries until everything fits the budget. The
+    // newest entry is always kept, even if it alone is over.
+    fn enforce_budget(&mut self) {
+        while self.bytes > self.budget && self.undo.len() > 1 {
+            if let Some(dropped) = self.undo.pop_front() {
+                self.bytes -= dropped.bytes.len();
+                if !self.truncated {
+                    log::warn!("Undo history is over its budget; dropping the oldest edits");
+                }
+                self.truncated = true;
+            }
+        }
+    }
+
+    /// Reverts the last edit. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, grid: &mut Grid) -> bool {
+        self.commit();
+        match self.undo.pop_back() {
+            Some(edit) => {
+                for change in edit.unpack() {
+                    grid.restore(change.index, change.before);
+                }
+                self.redo.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-applies the last undone edit. Returns false if there was none.
+    pub fn redo(&mut self, grid: &mut Grid) -> bool {
+        self.commit();
+        match self.redo.pop() {
+            Some(edit) => {
+                for change in edit.unpack() {
+                    grid.restore(change.index, change.after);
+                }
+                self.undo.push_back(edit);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::session_rng;
+    use randomize::PCG32;
+
+    fn below(rng: &mut PCG32, n: u32) -> u32 {
+        rng.next_u32() % n
+    }
+
+    fn any_cell(rng: &mut PCG32) -> Cell {
+        let bits = rng.next_u32();
+        Cell {
+            alive: bits & 1 != 0,
+            heat: (bits >> 1) as u8,
+            age: (bits >> 9) as u16,
+            state: rng.next_u32() as u8,
+        }
+    }
+
+    #[test]
+    fn packed_edits_unpack_to_the_same_changes() {
+        let mut rng = session_rng(7);
+        for _ in 0..50 {
+            // Gaps big and small, so the varints take one byte or several.
+            let mut index = 0;
+            let mut changes: Vec<Change> = (0..below(&mut rng, 300))
+                .map(|_| {
+                    let gap = if rng.next_u32() & 1 == 0 { 4 } else { 1 << 20 };
+                    index += 1 + below(&mut rng, gap) as usize;
+                    Change {
+                        index,
+                        before: any_cell(&mut rng),
+                        after: any_cell(&mut rng),
+                    }
+                })
+                .collect();
+            let sorted = changes.clone();
+            // Packing sorts them.
+            changes.reverse();
+
+            let unpacked = PackedEdit::pack(changes).unpack();
+            assert_eq!(unpacked.len(), sorted.len());
+            for (unpacked, change) in unpacked.iter().zip(&sorted) {
+                assert_eq!(
+                    (unpacked.index, unpacked.before, unpacked.after),
+                    (change.index, change.before, change.after)
+                );
+            }
+        }
+    }
+
+    // An edit somewhere on the grid, or off its edges.
+    fn scribble(grid: &mut Grid, rng: &mut PCG32) {
+        let (w, h) = (grid.width as u32, grid.height as u32);
+        let mut point = || {
+            let x = below(rng, w + 8) as isize - 4;
+            let y = below(rng, h + 8) as isize - 4;
+            (x, y)
+        };
+        let ((x0, y0), (x1, y1)) = (point(), point());
+        let alive = rng.next_u32() & 1 != 0;
+        match below(rng, 4) {
+            0 => {
+                grid.toggle(x0, y0);
+            }
+            1 => grid.set_line(x0, y0, x1, y1, below(rng, 3), alive),
+            2 => grid.set_disc(x0, y0, below(rng, 4), alive),
+            _ => grid.set_rect(x0, y0, x1, y1, alive),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_retrace_arbitrary_edits() {
+        for seed in 0..8 {
+            let mut rng = session_rng(seed);
+            let mut grid = Grid::new_empty_grid(37, 23);
+            grid.randomize(&mut rng, 0.3);
+            // Heat and ages of all sorts to put back.
+            for _ in 0..20 {
+                grid.update();
+            }
+
+            let mut history = History::default();
+            let mut snapshots = vec![grid.checkpoint()];
+            for _ in 0..30 {
+                // Gestures of a few strokes, each an undo step.
+                for _ in 0..1 + below(&mut rng, 4) {
+                    history.record(&mut grid, |grid| scribble(grid, &mut rng));
+                }
+                history.commit();
+                if grid.grid != *snapshots.last().unwrap() {
+                    snapshots.push(grid.checkpoint());
+                }
+            }
+
+            for (step, snapshot) in snapshots.iter().enumerate().rev().skip(1) {
+                assert!(history.undo(&mut grid));
+                assert!(grid.grid == *snapshot, "seed {}, undoing to {}", seed, step);
+            }
+            assert!(!history.undo(&mut grid));
+
+            for (step, snapshot) in snapshots.iter().enumerate().skip(1) {
+                assert!(history.redo(&mut grid));
+                assert!(grid.grid == *snapshot, "seed {}, redoing to {}", seed, step);
+            }
+            assert!(!history.redo(&mut grid));
+        }
+    }
+
+    #[test]
+    fn undo_restores_rule_table_states() {
+        let mut grid = Grid::new_empty_grid(4, 4);
+        grid.grid.put(
+            5,
+            Cell {
+                alive: true,
+                heat: 255,
+                age: 3,
+                state: 200,
+            },
+        );
+        let before = grid.checkpoint();
+
+        let mut history = History::default();
+        history.record_one(&mut grid, |grid| grid.toggle(1, 1));
+        assert!(history.undo(&mut grid));
+        assert!(grid.grid == before);
+    }
+}
@@ -0,0 +1,283 @@
+//
+// Read-only observer over HTTP (`--http-view <port>`, `http-view` feature).
+//
+//   /            a page embedding the stream
+//   /stream      the grid as an MJPEG (multipart/x-mixed-replace) stream
+//   /state.json  current generation, population and pause state
+//
+// The event loop hands over a copy of the frame at most `fps` times a
+// second; downscaling and JPEG encoding happen on a background thread, and
+// each viewer gets its own thread that just forwards the latest JPEG.
+//
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use jpeg_encoder::{ColorType, Encoder};
+
+const BOUNDARY: &str = "tgolframe";
+const JPEG_QUALITY: u8 = 80;
+
+// Viewers that stop reading for this long are dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    pub generation: u64,
+    pub population: usize,
+    pub paused: bool,
+}
+
+impl Stats {
+    fn to_json(self) -> String {
+        format!(
+            "{{\"generation\":{},\"population\":{},\"paused\":{}}}",
+            self.generation, self.population, self.paused
+        )
+    }
+}
+
+// A raw frame waiting to be encoded.
+struct Snapshot {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Default)]
+struct Shared {
+    // Latest frame from the event loop, taken by the encoder thread.
+    snapshot: Option<Snapshot>,
+    stats: Stats,
+    // Latest encoded frame, and a counter bumped every time it changes.
+    jpeg: Arc<Vec<u8>>,
+    sequence: u64,
+}
+
+struct State {
+    shared: Mutex<Shared>,
+    // Signaled when a new snapshot arrives (for the encoder).
+    snapshot_ready: Condvar,
+    // Signaled when a new JPEG is ready (for the viewers).
+    jpeg_ready: Condvar,
+}
+
+pub struct HttpView {
+    state: Arc<State>,
+    interval: Duration,
+    last_publish: Option<Instant>,
+}
+
+impl HttpView {
+    /// Binds the listener and starts the server and encoder threads.
+    pub fn start(bind: IpAddr, port: u16, fps: f32, scale: f32) -> io::Result<Self> {
+        let addr = SocketAddr::new(bind, port);
+        let listener = TcpListener::bind(addr)?;
+        log::info!("HTTP view on http://{}/", addr);
+
+        let state = Arc::new(State {
+            shared: Mutex::new(Shared::default()),
+            snapshot_ready: Condvar::new(),
+            jpeg_ready: Condvar::new(),
+        });
+
+        let encoder_state = Arc::clone(&state);
+        thread::Builder::new()
+            .name("http-view-encoder".into())
+            .spawn(move || encode_loop(&encoder_state, scale))?;
+
+        let server_state = Arc::clone(&state);
+        thread::Builder::new()
+            .name("http-view-listener".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let state = Arc::clone(&server_state);
+                            let spawned = thread::Builder::new()
+                                .name("http-view-client".into())
+                                .spawn(move || serve_client(stream, &state));
+                            if let Err(e) = spawned {
+                                log::warn!("HTTP view: couldn't spawn client thread: {}", e);
+                            }
+                        }
+                        Err(e) => log::warn!("HTTP view: accept failed: {}", e),
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            state,
+            interval: Duration::from_secs_f32(1.0 / fps),
+            last_publish: None,
+        })
+    }
+
+    /// Whether it's time to `publish` another frame. Cheap; call every frame.
+    pub fn wants_frame(&self) -> bool {
+        self.last_publish
+            .is_none_or(|last| last.elapsed() >= self.interval)
+    }
+
+    /// Hands a copy of the RGBA frame over to the encoder thread.
+    pub fn publish(&mut self, frame: &[u8], width: u32, height: u32, stats: Stats) {
+        self.last_publish = Some(Instant::now());
+
+        let mut shared = self.state.shared.lock().unwrap();
+        shared.stats = stats;
+        shared.snapshot = Some(Snapshot {
+            rgba: frame.to_vec(),
+            width,
+            height,
+        });
+        self.state.snapshot_ready.notify_one();
+    }
+}
+
+fn encode_loop(state: &State, scale: f32) {
+    loop {
+        let snapshot = {
+            let mut shared = state.shared.lock().unwrap();
+            loop {
+                if let Some(snapshot) = shared.snapshot.take() {
+                    break snapshot;
+                }
+                shared = state.snapshot_ready.wait(shared).unwrap();
+            }
+        };
+
+        match encode(&snapshot, scale) {
+            Ok(jpeg) => {
+                let mut shared = state.shared.lock().unwrap();
+                shared.jpeg = Arc::new(jpeg);
+                shared.sequence += 1;
+                state.jpeg_ready.notify_all();
+            }
+            Err(e) => log::error!("HTTP view: JPEG encoding failed: {}", e),
+        }
+    }
+}
+
+// Nearest-neighbor scales the snapshot by `scale` and encodes it.
+fn encode(snapshot: &Snapshot, scale: f32) -> Result<Vec<u8>, jpeg_encoder::EncodingError> {
+    let out_w = ((snapshot.width as f32 * scale).round() as u32).clamp(1, u16::MAX as u32);
+    let out_h = ((snapshot.height as f32 * scale).round() as u32).clamp(1, u16::MAX as u32);
+
+    let mut rgb = Vec::with_capacity((out_w * out_h * 3) as usize);
+    for y in 0..out_h {
+        let sy = (y * snapshot.height / out_h) as usize;
+        for x in 0..out_w {
+            let sx = (x * snapshot.width / out_w) as usize;
+            let i = 4 * (sx + sy * snapshot.width as usize);
+            rgb.extend_from_slice(&snapshot.rgba[i..i + 3]);
+        }
+    }
+
+    let mut jpeg = Vec::new();
+    Encoder::new(&mut jpeg, JPEG_QUALITY).encode(
+        &rgb,
+        out_w as u16,
+        out_h as u16,
+        ColorType::Rgb,
+    )?;
+    Ok(jpeg)
+}
+
+fn serve_client(stream: TcpStream, state: &State) {
+    let peer = stream.peer_addr().ok();
+    if let Err(e) = handle_request(stream, state) {
+        // Viewers closing the tab show up here; not worth more than debug.
+        log::debug!("HTTP view: {:?} disconnected: {}", peer, e);
+    }
+}
+
+fn handle_request(mut stream: TcpStream, state: &State) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_read_timeout(Some(WRITE_TIMEOUT))?;
+
+    // Only the request line matters; skip the headers.
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    match path {
+        "/" => respond(
+            &mut stream,
+            "200 OK",
+            "text/html",
+            b"<!doctype html><title>TGOL</title>\
+              <body style=\"margin:0;background:#000\">\
+              <img src=\"/stream\" style=\"width:100%;image-rendering:pixelated\">",
+        ),
+        "/state.json" => {
+            let stats = state.shared.lock().unwrap().stats;
+            respond(
+                &mut stream,
+                "200 OK",
+                "application/json",
+                stats.to_json().as_bytes(),
+            )
+        }
+        "/stream" => stream_frames(&mut stream, state),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+fn stream_frames(stream: &mut TcpStream, state: &State) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n",
+        BOUNDARY
+    )?;
+
+    let mut seen = 0;
+    loop {
+        let jpeg = {
+            let mut shared = state.shared.lock().unwrap();
+            while shared.sequence == seen {
+                shared = state.jpeg_ready.wait(shared).unwrap();
+            }
+            seen = shared.sequence;
+            Arc::clone(&shared.jpeg)
+        };
+
+        write!(
+            stream,
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        )?;
+        stream.write_all(&jpeg)?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+    }
+}
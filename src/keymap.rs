@@ -0,0 +1,320 @@
+//
+// Keyboard bindings. The event loop asks about actions ("was TogglePause
+// pressed?"), and this maps them to keys. Defaults can be overridden in the
+// `[keys]` section of the config file:
+//
+//   [keys]
+//   toggle_pause = "F5"
+//   kill = "Ctrl+K"
+//   redo = "Ctrl+Y, Ctrl+Shift+Z"
+//
+// A binding is a key name plus the exact modifiers that must be held (so
+// `R` doesn't fire on Shift+R). Key names are winit's `VirtualKeyCode`
+// names; an unknown one is an error that lists the valid ones.
+//
+
+use std::fmt;
+
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+use crate::config::{Config, Value};
+
+macro_rules! actions {
+    ($($action:ident $name:literal [$($binding:literal),*] $doc:literal,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Action {
+            $(#[doc = $doc] $action,)*
+        }
+
+        impl Action {
+            pub const ALL: &'static [Action] = &[$(Action::$action,)*];
+
+            /// The name used in the config file.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Action::$action => $name,)*
+                }
+            }
+
+            fn default_bindings(self) -> &'static [&'static str] {
+                match self {
+                    $(Action::$action => &[$($binding),*],)*
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    Quit "quit" ["Escape"] "Close the help, leave the current tool or mode, or quit if there's none.",
+    Help "help" ["F1", "Shift+Slash"] "Show or hide the list of key bindings.",
+    Pause "pause" ["Space"] "Pause (repeats while held, for frame stepping).",
+    FastForward "fast_forward" ["F"] "Fast-forward while held.",
+    CycleFastForward "cycle_fast_forward" ["Shift+F"] "Cycle fast-forward: x10, x100, x1000, off.",
+    TogglePause "toggle_pause" ["P"] "Toggle pause.",
+    Randomize "randomize" ["R"] "Fill the grid with a new soup.",
+    RandomizeAgain "randomize_again" ["Shift+R"] "Make the last soup again, from the same seed.",
+    CycleSymmetry "cycle_symmetry" ["O"] "Cycle the symmetry of new soups.",
+    DenserSoup "denser_soup" ["Shift+Up"] "Raise the density of new soups.",
+    SparserSoup "sparser_soup" ["Shift+Down"] "Lower the density of new soups.",
+    Kill "kill" ["K"] "Kill a random sampling of cells.",
+    CycleKillProbability "cycle_kill_probability" ["Shift+K"] "Cycle the share of cells [K] kills: 10%, 25%, 50%, 75%.",
+    Invert "invert" ["I"] "Invert every cell.",
+    AgeColors "age_colors" ["A"] "Toggle coloring live cells by age.",
+    Trails "trails" ["H"] "Toggle heat trails.",
+    Noise "noise" ["N"] "Toggle noise: cells flipped at random every generation.",
+    GridLines "grid_lines" ["L"] "Toggle lines between cells.",
+    MiniMap "minimap" ["Tab"] "Toggle the mini-map.",
+    DensityWash "density_wash" ["D"] "Toggle the density wash: blocks colored by how crowded they are.",
+    Ships "ships" ["Shift+G"] "Spot gliders and small spaceships, and count them in the title.",
+    CursorReadout "cursor_readout" ["C"] "Show the cell under the mouse: where it is, its heat and live neighbors.",
+    CycleEngine "cycle_engine" ["E"] "Cycle the engine computing generations.",
+    CycleTopology "cycle_topology" ["B"] "Cycle how the grid's edges are glued (or walled).",
+    CycleTeam "cycle_team" ["T"] "Cycle the team drawing brings to life (Immigration and QuadLife), or what it paints (Wireworld and rule tables).",
+    Jump "jump" ["J"] "Jump ahead 2^20 generations (hashlife engine only).",
+    Undo "undo" ["Ctrl+Z"] "Undo the last edit.",
+    Redo "redo" ["Ctrl+Y", "Ctrl+Shift+Z"] "Redo the last undone edit.",
+    SelectMode "select_mode" ["V"] "Toggle selection mode.",
+    Copy "copy" ["Ctrl+C"] "Copy the selection (or all the live cells) to the clipboard as RLE.",
+    Paste "paste" ["Ctrl+V"] "Arm the copied cells, or a pattern off the system clipboard, for pasting.",
+    Glider "glider" ["G"] "Arm / disarm the glider stamp.",
+    HeadingNorthEast "heading_north_east" ["Up"] "Glider heads north-east (else pans up, in an infinite world).",
+    HeadingSouthEast "heading_south_east" ["Right"] "Glider heads south-east (else pans right).",
+    HeadingSouthWest "heading_south_west" ["Down"] "Glider heads south-west (else pans down).",
+    HeadingNorthWest "heading_north_west" ["Left"] "Glider heads north-west (else pans left).",
+    Pattern1 "pattern_1" ["Key1"] "Arm library pattern 1.",
+    Pattern2 "pattern_2" ["Key2"] "Arm library pattern 2.",
+    Pattern3 "pattern_3" ["Key3"] "Arm library pattern 3.",
+    Pattern4 "pattern_4" ["Key4"] "Arm library pattern 4.",
+    Pattern5 "pattern_5" ["Key5"] "Arm library pattern 5.",
+    Pattern6 "pattern_6" ["Key6"] "Arm library pattern 6.",
+    Pattern7 "pattern_7" ["Key7"] "Arm library pattern 7.",
+    Pattern8 "pattern_8" ["Key8"] "Arm library pattern 8.",
+    Pattern9 "pattern_9" ["Key9"] "Arm library pattern 9.",
+    PlaceStamp "place_stamp" ["Return"] "Place the armed stamp in the center.",
+    RotateCcw "rotate_ccw" ["Comma"] "Rotate the paste counter-clockwise.",
+    RotateCw "rotate_cw" ["Period"] "Rotate the paste clockwise.",
+    MirrorX "mirror_x" ["X"] "Mirror the paste left to right.",
+    MirrorY "mirror_y" ["Y"] "Mirror the paste top to bottom.",
+    ExportSvg "export_svg" ["Shift+S"] "Export the selection (or the grid) as SVG.",
+    ExportSvgLive "export_svg_live" ["Ctrl+Shift+S"] "Export the live cells' bounds as SVG.",
+    ExportPng "export_png" ["Shift+F12"] "Export the grid as a scaled-up PNG.",
+    ExportRle "export_rle" ["Shift+C"] "Export the selection (or the grid) as RLE.",
+    CaptureApng "capture_apng" ["Shift+A"] "Capture the next generations as an animated PNG.",
+    ProfilerDump "profiler_dump" ["F12"] "Log the profiler summary.",
+    ProfilerDumpJson "profiler_dump_json" ["Ctrl+F12"] "Log the profiler summary and write JSON.",
+    MutateRule "mutate_rule" ["M"] "Flip one random birth/survival count.",
+    RevertRule "revert_rule" ["U"] "Go back to the previous rule.",
+    BookmarkRule "bookmark_rule" ["Shift+M"] "Bookmark the current rule.",
+    Mute "mute" ["Ctrl+M"] "Mute or unmute --audio.",
+    Fullscreen "fullscreen" ["F11"] "Toggle fullscreen.",
+    BrushSmaller "brush_smaller" ["LBracket"] "Shrink the brush.",
+    BrushLarger "brush_larger" ["RBracket"] "Grow the brush.",
+    SlowerDecay "slower_decay" ["Shift+LBracket"] "Make trails last longer.",
+    FasterDecay "faster_decay" ["Shift+RBracket"] "Make trails fade sooner.",
+}
+
+impl Action {
+    /// Library pattern actions, in library order.
+    pub const PATTERNS: [Action; 9] = [
+        Action::Pattern1,
+        Action::Pattern2,
+        Action::Pattern3,
+        Action::Pattern4,
+        Action::Pattern5,
+        Action::Pattern6,
+        Action::Pattern7,
+        Action::Pattern8,
+        Action::Pattern9,
+    ];
+
+    // Held keys keep firing these, at the OS repeat rate.
+    fn repeats(self) -> bool {
+        matches!(self, Action::Pause)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    key: VirtualKeyCode,
+    ctrl: bool,
+    shift: bool,
+}
+
+impl Binding {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut binding = Self {
+            key: VirtualKeyCode::Escape,
+            ctrl: false,
+            shift: false,
+        };
+
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", modifier, s)),
+            }
+        }
+
+        binding.key = key_by_name(key).ok_or_else(|| {
+            let names: Vec<&str> = KEY_NAMES.iter().map(|(name, _)| *name).collect();
+            format!(
+                "unknown key {:?}; valid keys are: {}",
+                key,
+                names.join(", ")
+            )
+        })?;
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct KeyMap {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut bindings = Vec::new();
+        for &action in Action::ALL {
+            for binding in action.default_bindings() {
+                let binding = Binding::parse(binding).expect("default bindings are valid");
+                bindings.push((action, binding));
+            }
+        }
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    /// The defaults, with any actions named in `[keys]` rebound. Warns about
+    /// keys that end up bound to more than one action.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut keymap = Self::default();
+
+        for entry in config.section("keys") {
+            let action = Action::ALL
+                .iter()
+                .copied()
+                .find(|action| action.name() == entry.key)
+                .ok_or_else(|| config.error_at(entry, format!("unknown action {:?}", entry.key)))?;
+
+            let Value::Str(keys) = &entry.value else {
+                return Err(config.error_at(entry, "bindings must be a string, e.g. \"Ctrl+K\""));
+            };
+
+            keymap.bindings.retain(|(bound, _)| *bound != action);
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                let binding = Binding::parse(key).map_err(|e| config.error_at(entry, e))?;
+                keymap.bindings.push((action, binding));
+            }
+        }
+
+        for (i, (action, binding)) in keymap.bindings.iter().enumerate() {
+            for (other, _) in keymap.bindings[i + 1..]
+                .iter()
+                .filter(|(other, b)| b == binding && other != action)
+            {
+                log::warn!(
+                    "{} is bound to both {} and {}",
+                    binding,
+                    action.name(),
+                    other.name()
+                );
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Every action that has a key, with its keys (as the config writes
+    /// them, comma-separated) and its name spelled out, for the help.
+    pub fn help(&self) -> Vec<(String, String)> {
+        Action::ALL
+            .iter()
+            .filter_map(|&action| {
+                let keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(bound, _)| *bound == action)
+                    .map(|(_, binding)| binding.to_string())
+                    .collect();
+                (!keys.is_empty()).then(|| (keys.join(", "), action.name().replace('_', " ")))
+            })
+            .collect()
+    }
+
+    /// Whether a key bound to `action` was pressed this frame, with exactly
+    /// the binding's modifiers held.
+    pub fn pressed(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .any(|(_, binding)| {
+                let pressed = if action.repeats() {
+                    input.key_pressed_os(binding.key)
+                } else {
+                    input.key_pressed(binding.key)
+                };
+                pressed
+                    && input.held_control() == binding.ctrl
+                    && input.held_shift() == binding.shift
+            })
+    }
+
+    /// Whether a key bound to `action` is down, with exactly the binding's
+    /// modifiers held.
+    pub fn held(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .any(|(_, binding)| {
+                input.key_held(binding.key)
+                    && input.held_control() == binding.ctrl
+                    && input.held_shift() == binding.shift
+            })
+    }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+            $((stringify!($key), VirtualKeyCode::$key),)*
+        ];
+    };
+}
+
+key_names! {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape, Space, Return, Tab, Back, Delete, Insert, Home, End, PageUp, PageDown,
+    Up, Down, Left, Right,
+    Comma, Period, Slash, Backslash, Semicolon, Apostrophe, Grave, Minus, Equals,
+    LBracket, RBracket,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+    Numpad9, NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadEnter,
+}
+
+fn key_by_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
@@ -0,0 +1,477 @@
+//
+// Where the window left off: saved on the way out, and picked up again on
+// the next start instead of a new soup (unless it's --fresh). It lives in
+// `$XDG_DATA_HOME/tgol/last_session` (`~/.local/share/tgol/last_session`),
+// as lines much like a --record header:
+//
+//   tgol-session 1
+//   size 384 240
+//   rule B3/S23
+//   paused true
+//   fast-forward 100
+//   ...
+//   cells ...#./..#../.###.
+//   states 00010/00100/00000
+//   ants 2,1,north 0,2,west
+//
+// (`states` only for an automaton other than plain Life: each cell's
+// `Cell::state`, such as its team; `ants` only for Langton's Ant, as each
+// ant's x, y and heading; `rulefile` only for a rule table, which is read
+// again from there. States are a digit apiece, so a table with more than
+// ten states isn't saved.)
+//
+// The first line's number is the format version. A file that doesn't parse,
+// or is from a version this build doesn't know, is left alone and the
+// session starts from a new soup. When the format changes, `load` is where
+// the older versions get read into the new one.
+//
+// An infinite world only keeps what was in view.
+//
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tgol::ant::{Ant, DEFAULT_ANT_STEPS};
+use tgol::automaton::Automaton;
+use tgol::decay::Decay;
+use tgol::engine::Engine;
+use tgol::grid::Grid;
+use tgol::noise::Noise;
+use tgol::pattern::PatternBuffer;
+use tgol::rule::Rule;
+use tgol::rule_table::RuleTable;
+use tgol::stochastic::Stochastic;
+use tgol::topology::Topology;
+use tgol::world::World;
+
+use crate::cli::{GridSetup, Options, Placement};
+
+/// The format written by this build.
+pub const VERSION: u32 = 1;
+
+/// The line files start with, before the version.
+const HEADER: &str = "tgol-session";
+
+pub struct LastSession {
+    pub size: (u32, u32),
+    pub rule: Rule,
+    pub automaton: Automaton,
+    pub rule_table: Option<Arc<RuleTable>>,
+    pub ant_steps: u32,
+    pub stochastic: Option<Stochastic>,
+    pub decay: Decay,
+    pub noise: Noise,
+    pub trails: bool,
+    pub age_colors: bool,
+    pub engine: Engine,
+    pub world: World,
+    pub topology: Topology,
+    pub paused: bool,
+    pub fast_forward: Option<u32>,
+    pub cells: PatternBuffer,
+    // Every cell's state, row by row; empty for plain Life.
+    pub states: Vec<u8>,
+    pub ants: Vec<Ant>,
+}
+
+/// `$XDG_DATA_HOME/tgol/last_session`, or None without a home directory.
+pub fn path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+    Some(base.join("tgol").join("last_session"))
+}
+
+impl LastSession {
+    /// What there is to save of `life` and the window's settings.
+    pub fn new(life: &Grid, paused: bool, fast_forward: Option<u32>, age_colors: bool) -> Self {
+        let corner = (life.width as isize - 1, life.height as isize - 1);
+        Self {
+            size: (life.width as u32, life.height as u32),
+            rule: life.rule,
+            automaton: life.automaton,
+            rule_table: life.rule_table.clone(),
+            ant_steps: life.ant_steps,
+            stochastic: life.stochastic,
+            decay: life.decay,
+            noise: life.noise,
+            trails: life.trails,
+            age_colors,
+            engine: life.engine,
+            world: life.world(),
+            topology: life.topology,
+            paused,
+            fast_forward,
+            cells: life.copy_rect((0, 0), corner),
+            states: if life.automaton != Automaton::Life {
+                life.grid.state.clone()
+            } else {
+                Vec::new()
+            },
+            ants: life.ants.clone(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if self
+            .rule_table
+            .as_ref()
+            .is_some_and(|table| table.states > 10)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only rule tables of up to ten states can be saved",
+            ));
+        }
+        let mut text = format!("{} {}\n", HEADER, VERSION);
+        // Writing to a String can't fail.
+        let _ = writeln!(text, "size {} {}", self.size.0, self.size.1);
+        let _ = writeln!(text, "rule {}", self.rule);
+        let _ = writeln!(text, "automaton {}", self.automaton);
+        if let Some(table) = &self.rule_table {
+            let _ = writeln!(text, "rulefile {}", table.path.display());
+        }
+        if self.automaton == Automaton::Ant {
+            let _ = writeln!(text, "ant-steps {}", self.ant_steps);
+        }
+        if let Some(stochastic) = self.stochastic {
+            let _ = writeln!(text, "stochastic {}", stochastic);
+        }
+        let _ = writeln!(text, "decay {}", self.decay);
+        let _ = writeln!(text, "noise {}", self.noise);
+        let _ = writeln!(text, "trails {}", self.trails);
+        let _ = writeln!(text, "age-colors {}", self.age_colors);
+        let _ = writeln!(text, "engine {}", self.engine);
+        let _ = writeln!(text, "world {}", self.world);
+        let _ = writeln!(text, "topology {}", self.topology);
+        let _ = writeln!(text, "paused {}", self.paused);
+        if let Some(speed) = self.fast_forward {
+            let _ = writeln!(text, "fast-forward {}", speed);
+        }
+        let _ = writeln!(text, "cells {}", crate::session::rows_text(&self.cells));
+        if !self.states.is_empty() {
+            let rows: Vec<String> = self
+                .states
+                .chunks(self.size.0 as usize)
+                .map(|row| row.iter().map(|&state| char::from(b'0' + state)).collect())
+                .collect();
+            let _ = writeln!(text, "states {}", rows.join("/"));
+        }
+        if !self.ants.is_empty() {
+            let ants: Vec<String> = self
+                .ants
+                .iter()
+                .map(|ant| format!("{},{},{}", ant.x, ant.y, ant.heading))
+                .collect();
+            let _ = writeln!(text, "ants {}", ants.join(" "));
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written next to it and moved over, so a crash halfway through
+        // doesn't leave half a session behind.
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, text)?;
+        std::fs::rename(&partial, path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut lines = text.lines().enumerate();
+
+        let version = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix(HEADER))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| format!("doesn't start with {:?} and a version", HEADER))?;
+        if version != VERSION {
+            return Err(format!(
+                "format version {} (this build reads {})",
+                version, VERSION
+            ));
+        }
+
+        let mut session = Self {
+            size: (0, 0),
+            rule: Rule::default(),
+            automaton: Automaton::default(),
+            rule_table: None,
+            ant_steps: DEFAULT_ANT_STEPS,
+            stochastic: None,
+            decay: Decay::default(),
+            noise: Noise::Off,
+            trails: true,
+            age_colors: false,
+            engine: Engine::default(),
+            world: World::default(),
+            topology: Topology::default(),
+            paused: false,
+            fast_forward: None,
+            cells: PatternBuffer::default(),
+            states: Vec::new(),
+            ants: Vec::new(),
+        };
+        let mut cells = None;
+        let mut states = None;
+
+        for (n, line) in lines {
+            let at = |e: String| format!("line {}: {}", n + 1, e);
+            let (key, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            let rest = rest.trim();
+            fn parsed<T: FromStr>(word: &str) -> Result<T, String> {
+                word.parse().map_err(|_| format!("bad value {:?}", word))
+            }
+
+            match key {
+                "" => {}
+                "size" => {
+                    let (w, h) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| at("expected a width and height".into()))?;
+                    session.size = (parsed(w).map_err(at)?, parsed(h.trim()).map_err(at)?);
+                }
+                "rule" => session.rule = rest.parse::<Rule>().map_err(|e| at(e.to_string()))?,
+                "automaton" => session.automaton = rest.parse().map_err(at)?,
+                "rulefile" => {
+                    let table = RuleTable::load(Path::new(rest)).map_err(at)?;
+                    session.rule_table = Some(Arc::new(table));
+                }
+                "ant-steps" => session.ant_steps = parsed(rest).map_err(at)?,
+                "stochastic" => session.stochastic = Some(rest.parse().map_err(at)?),
+                "decay" => session.decay = rest.parse().map_err(at)?,
+                "noise" => session.noise = rest.parse().map_err(at)?,
+                "trails" => session.trails = parsed(rest).map_err(at)?,
+                "age-colors" => session.age_colors = parsed(rest).map_err(at)?,
+                "engine" => session.engine = rest.parse().map_err(at)?,
+                "world" => session.world = rest.parse().map_err(at)?,
+                "topology" => session.topology = rest.parse().map_err(at)?,
+                "paused" => session.paused = parsed(rest).map_err(at)?,
+                "fast-forward" => session.fast_forward = Some(parsed(rest).map_err(at)?),
+                "cells" => cells = Some(rest),
+                "states" => states = Some(rest),
+                "ants" => {
+                    for ant in rest.split_whitespace() {
+                        let bad = || at(format!("expected X,Y,HEADING, not {:?}", ant));
+                        let mut fields = ant.split(',');
+                        let mut field = || fields.next().ok_or_else(bad);
+                        let (x, y, heading) = (field()?, field()?, field()?);
+                        session.ants.push(Ant {
+                            x: parsed(x).map_err(at)?,
+                            y: parsed(y).map_err(at)?,
+                            heading: heading.parse().map_err(at)?,
+                        });
+                    }
+                }
+                other => return Err(at(format!("unknown line {:?}", other))),
+            }
+        }
+
+        // Checked as the command line is, as it's started the same way.
+        session.grid_setup().check()?;
+        let (width, height) = session.size;
+        let rows: Vec<&str> = cells.ok_or("no cells")?.split('/').collect();
+        let fits = rows.len() == height as usize
+            && rows.iter().all(|row| {
+                row.len() == width as usize && row.bytes().all(|c| b"#.23456789".contains(&c))
+            });
+        if !fits {
+            return Err(format!("the cells aren't a {} x {} grid", width, height));
+        }
+        session.cells = PatternBuffer::from_rows(&rows);
+
+        if session.automaton != Automaton::Life {
+            let rows: Vec<&str> = states.ok_or("no states")?.split('/').collect();
+            let fits = rows.len() == height as usize
+                && rows.iter().all(|row| {
+                    row.len() == width as usize && row.bytes().all(|c| c.is_ascii_digit())
+                });
+            if !fits {
+                return Err(format!("the states aren't a {} x {} grid", width, height));
+            }
+            session.states = rows
+                .iter()
+                .flat_map(|row| row.bytes().map(|c| c - b'0'))
+                .collect();
+        }
+        if session
+            .ants
+            .iter()
+            .any(|ant| ant.x >= width as usize || ant.y >= height as usize)
+        {
+            return Err(format!("an ant is off the {} x {} grid", width, height));
+        }
+        Ok(session)
+    }
+
+    fn grid_setup(&self) -> GridSetup {
+        GridSetup {
+            size: self.size,
+            automaton: self.automaton,
+            rule_table: self.rule_table.is_some(),
+            ant_steps: self.ant_steps,
+            world: self.world,
+            topology: self.topology,
+            stochastic: self.stochastic.is_some(),
+        }
+    }
+
+    /// Sets up `options` to start the way this session left off.
+    pub fn configure(&self, options: &mut Options) {
+        options.width = Some(self.size.0);
+        options.height = Some(self.size.1);
+        options.rule = self.rule;
+        options.automaton = self.automaton;
+        options.rule_table = self.rule_table.clone();
+        options.ant_steps = self.ant_steps;
+        options.stochastic = self.stochastic;
+        options.decay = Some(self.decay);
+        options.noise = self.noise;
+        options.engine = Some(self.engine);
+        options.world = self.world;
+        options.topology = Some(self.topology);
+        options.empty = true;
+        options.patterns = vec![Placement {
+            pattern: self.cells.clone(),
+            at: Some((0, 0)),
+        }];
+        options.no_splash = true;
+    }
+
+    /// Puts the cells of `life`, started as `configure` set it up, back in
+    /// their states (their teams, say), and its ants where they were.
+    pub fn restore_states(&self, life: &mut Grid) {
+        if self.automaton == Automaton::Ant {
+            life.ants = self.ants.clone();
+            life.touch_all();
+        }
+        if self.states.len() == life.grid.len() {
+            life.grid.state.copy_from_slice(&self.states);
+            life.touch_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `text` written to a file of its own and loaded back.
+    fn load_text(name: &str, text: &str) -> Result<LastSession, String> {
+        let path =
+            std::env::temp_dir().join(format!("tgol-last-session-{}-{}", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        let session = LastSession::load(&path);
+        let _ = std::fs::remove_file(&path);
+        session
+    }
+
+    // A session saved from a small soup, as written.
+    fn saved(name: &str) -> String {
+        let mut life = Grid::new_empty_grid(10, 8);
+        life.randomize(&mut tgol::rng::session_rng(3), 0.4);
+        let path = std::env::temp_dir().join(format!(
+            "tgol-last-session-{}-{}.saved",
+            std::process::id(),
+            name
+        ));
+        LastSession::new(&life, true, Some(10), false)
+            .save(&path)
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        text
+    }
+
+    #[test]
+    fn a_saved_session_loads() {
+        let session = load_text("saved", &saved("saved")).unwrap();
+        assert_eq!(session.size, (10, 8));
+        assert!(session.paused);
+        assert_eq!(session.fast_forward, Some(10));
+    }
+
+    #[test]
+    fn truncated_files_are_refused() {
+        let text = saved("truncated");
+        // Cells are the last line, so every cut short of their end leaves
+        // something missing or malformed.
+        let end = text.trim_end().len();
+        for cut in 0..end {
+            assert!(
+                load_text("truncated", &text[..cut]).is_err(),
+                "cut at byte {}: {:?}",
+                cut,
+                &text[..cut]
+            );
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let text = saved("version");
+        for header in [
+            "tgol-session 0",
+            "tgol-session 2",
+            "tgol-session",
+            "session 1",
+        ] {
+            let text = text.replacen("tgol-session 1", header, 1);
+            assert!(load_text("version", &text).is_err(), "{}", header);
+        }
+    }
+
+    #[test]
+    fn cells_that_dont_fit_the_size_are_refused() {
+        let text = saved("size");
+        for size in [
+            "size 11 8",
+            "size 10 7",
+            "size 9 9",
+            "size 10",
+            "size ten 8",
+        ] {
+            let text = text.replacen("size 10 8", size, 1);
+            assert!(load_text("size", &text).is_err(), "{}", size);
+        }
+    }
+
+    #[test]
+    fn sizes_out_of_the_command_lines_range_are_refused() {
+        for (width, height) in [(0, 3), (3, 0), (8193, 1), (1, 100_000)] {
+            let row = ".".repeat(width);
+            let rows = vec![row.as_str(); height].join("/");
+            let text = format!(
+                "tgol-session 1\nsize {} {}\nengine scalar\ncells {}\n",
+                width, height, rows
+            );
+            assert!(load_text("range", &text).is_err(), "{} x {}", width, height);
+        }
+
+        // One cell wide is as small as the command line goes.
+        let text = "tgol-session 1\nsize 1 3\nengine scalar\ncells #/#/#\n";
+        assert_eq!(load_text("range", text).unwrap().size, (1, 3));
+    }
+
+    #[test]
+    fn setups_the_command_line_refuses_are_refused() {
+        let text = saved("setup");
+        for line in [
+            "automaton immigration\nworld infinite",
+            "automaton quadlife\nworld infinite",
+            "automaton brain\nworld infinite",
+            "automaton ant\nant-steps 0",
+            "automaton table",
+            "topology klein-x\nworld infinite",
+            "stochastic pb=0.5,ps=0.5\nworld infinite",
+        ] {
+            let text = format!("{}{}\n", text, line);
+            assert!(load_text("setup", &text).is_err(), "{}", line);
+        }
+    }
+}
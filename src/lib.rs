@@ -0,0 +1,53 @@
+//
+// [T]HE [G]AME [O]F [L]IFE, the library: the simulation, patterns and
+// exporters, with nothing that needs a window or a terminal. The `tgol`
+// binary puts a frontend on top of it.
+//
+
+#![forbid(unsafe_code)]
+
+#[macro_use]
+pub mod profiler;
+
+mod active;
+pub mod ant;
+pub mod apng;
+pub mod automaton;
+pub mod bitgrid;
+pub mod cell;
+pub mod cells;
+pub mod cycle;
+pub mod decay;
+pub mod density;
+pub mod engine;
+pub mod font;
+pub mod gradient;
+pub mod grid;
+pub mod hashlife;
+pub mod heat;
+pub mod history;
+pub mod library;
+pub mod life106;
+pub mod minimap;
+pub mod noise;
+pub mod overlay;
+pub mod pattern;
+pub mod pattern_text;
+pub mod png_export;
+pub mod rle;
+pub mod rng;
+pub mod rule;
+pub mod rule_table;
+pub mod ships;
+pub mod splash;
+pub mod stochastic;
+pub mod svg;
+pub mod symmetry;
+pub mod timings;
+pub mod topology;
+pub mod viewport;
+pub mod world;
+
+/// Colors of live cells and of what's behind fully cooled dead cells.
+pub const ALIVE_COLOR: [u8; 4] = [50, 0, 0xff, 0xff];
+pub const BACKGROUND_COLOR: [u8; 4] = [0, 0, 0, 0xff];
@@ -0,0 +1,81 @@
+//
+// Classic patterns compiled into the binary, selected with the number keys.
+// Wireworld has circuits of its own instead.
+//
+
+use crate::automaton::Automaton;
+
+pub struct LibraryPattern {
+    pub name: &'static str,
+    pub rle: &'static str,
+}
+
+/// Patterns bound to keys 1 through 9, in order.
+pub const LIBRARY: [LibraryPattern; 9] = [
+    LibraryPattern {
+        name: "Glider",
+        rle: "x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!",
+    },
+    LibraryPattern {
+        name: "Lightweight spaceship",
+        rle: "x = 5, y = 4, rule = B3/S23\nbo2bo$o4b$o3bo$4o!",
+    },
+    LibraryPattern {
+        name: "Middleweight spaceship",
+        rle: "x = 6, y = 5, rule = B3/S23\n3bo2b$bo3bo$o5b$o4bo$5o!",
+    },
+    LibraryPattern {
+        name: "Pulsar",
+        rle: "x = 13, y = 13, rule = B3/S23\n\
+              2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$\
+              2b3o3b3o2b$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    LibraryPattern {
+        name: "Pentadecathlon",
+        rle: "x = 10, y = 3, rule = B3/S23\n2bo4bo2b$2ob4ob2o$2bo4bo!",
+    },
+    LibraryPattern {
+        name: "Gosper glider gun",
+        rle: "x = 36, y = 9, rule = B3/S23\n\
+              24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+              2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    LibraryPattern {
+        name: "R-pentomino",
+        rle: "x = 3, y = 3, rule = B3/S23\nb2o$2o$bo!",
+    },
+    LibraryPattern {
+        name: "Acorn",
+        rle: "x = 7, y = 3, rule = B3/S23\nbo5b$3bo3b$2o2b3o!",
+    },
+    LibraryPattern {
+        name: "Diehard",
+        rle: "x = 8, y = 3, rule = B3/S23\n6bob$2o6b$bo3b3o!",
+    },
+];
+
+/// Wireworld's patterns, bound to keys 1 and on: a clock sending out an
+/// electron every 8 generations, and the same clock feeding a diode that
+/// lets them through, or one the other way round that stops them.
+pub const WIREWORLD_LIBRARY: [LibraryPattern; 3] = [
+    LibraryPattern {
+        name: "Clock",
+        rle: "x = 12, y = 3, rule = WireWorld\n.BAC$C3.8C$.3C!",
+    },
+    LibraryPattern {
+        name: "Diode",
+        rle: "x = 20, y = 3, rule = WireWorld\n.BAC9.2C$C3.10C.5C$.3C9.2C!",
+    },
+    LibraryPattern {
+        name: "Diode, reversed",
+        rle: "x = 20, y = 3, rule = WireWorld\n.BAC9.2C$C3.9C.6C$.3C9.2C!",
+    },
+];
+
+/// The patterns the number keys arm for `automaton`.
+pub fn patterns(automaton: Automaton) -> &'static [LibraryPattern] {
+    match automaton {
+        Automaton::Wireworld => &WIREWORLD_LIBRARY,
+        _ => &LIBRARY,
+    }
+}
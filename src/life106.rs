@@ -0,0 +1,110 @@
+//
+// Life 1.06 patterns (`.lif`, `.life`): a header line, then the
+// coordinates of each live cell, one "x y" pair per line:
+//
+//   #Life 1.06
+//   0 -1
+//   1 0
+//   -1 1
+//   0 1
+//   1 1
+//
+// The coordinates can be anywhere (and negative); the pattern is sized to
+// fit them.
+//
+
+use std::fmt;
+
+use crate::pattern::{self, PatternBuffer};
+
+/// The line Life 1.06 files start with.
+pub const HEADER: &str = "#Life 1.06";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Life106Error {
+    /// The first line isn't `#Life 1.06`.
+    MissingHeader,
+    /// A line that isn't a pair of coordinates.
+    BadLine(String),
+    /// The cells span more than `pattern::MAX_SIDE` on a side.
+    TooLarge,
+}
+
+impl fmt::Display for Life106Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "Life 1.06 files start with {:?}", HEADER),
+            Self::BadLine(line) => write!(f, "bad Life 1.06 line: {:?}", line),
+            Self::TooLarge => write!(
+                f,
+                "Life 1.06 pattern is over {} cells on a side",
+                pattern::MAX_SIDE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Life106Error {}
+
+/// Parses a Life 1.06 pattern, moved so its top-left live cell's row and
+/// column are at 0.
+pub fn parse(text: &str) -> Result<PatternBuffer, Life106Error> {
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some(HEADER) {
+        return Err(Life106Error::MissingHeader);
+    }
+
+    let mut live: Vec<(i64, i64)> = Vec::new();
+    for line in lines.filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut coords = line.split_whitespace().map(str::parse::<i64>);
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => live.push((x, y)),
+            _ => return Err(Life106Error::BadLine(line.to_string())),
+        }
+    }
+
+    if live.is_empty() {
+        return Ok(PatternBuffer::default());
+    }
+    let (x0, y0) = live.iter().fold((i64::MAX, i64::MAX), |(x0, y0), &(x, y)| {
+        (x0.min(x), y0.min(y))
+    });
+    let (x1, y1) = live.iter().fold((i64::MIN, i64::MIN), |(x1, y1), &(x, y)| {
+        (x1.max(x), y1.max(y))
+    });
+    let side = |from: i64, to: i64| to.checked_sub(from).and_then(|d| usize::try_from(d).ok());
+    let (width, height) = match (side(x0, x1), side(y0, y1)) {
+        (Some(w), Some(h)) if w < pattern::MAX_SIDE && h < pattern::MAX_SIDE => (w + 1, h + 1),
+        _ => return Err(Life106Error::TooLarge),
+    };
+
+    let mut pattern = PatternBuffer::new(width, height);
+    for (x, y) in live {
+        pattern.set((x - x0) as usize, (y - y0) as usize, true);
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_are_moved_to_the_corner() {
+        let glider = parse("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(glider, PatternBuffer::from_rows(&[".#.", "..#", "###"]));
+    }
+
+    #[test]
+    fn bad_and_oversized_files_are_refused() {
+        assert_eq!(parse("0 0\n"), Err(Life106Error::MissingHeader));
+        assert_eq!(
+            parse("#Life 1.06\n0 0 0\n"),
+            Err(Life106Error::BadLine("0 0 0".to_string()))
+        );
+        let far = format!("#Life 1.06\n0 0\n{} 0\n", pattern::MAX_SIDE);
+        assert_eq!(parse(&far), Err(Life106Error::TooLarge));
+        let farthest = format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX);
+        assert_eq!(parse(&farthest), Err(Life106Error::TooLarge));
+    }
+}
@@ -3,163 +3,2148 @@
 //
 
 #![forbid(unsafe_code)]
+// The browser build has no command line, so the CLI-only modes go unused.
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+#[cfg(not(target_arch = "wasm32"))]
+mod ascii;
+#[cfg(feature = "audio")]
+mod audio;
+mod auto_pause;
+mod autosave;
+mod bookmarks;
+mod capture;
+mod cli;
+mod clipboard;
+mod config;
+mod control;
+mod crossfade;
+mod frame_dump;
+mod frame_pipe;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod grid_lines;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(feature = "http-view")]
+mod http_view;
+mod keymap;
+mod last_session;
+#[cfg(feature = "scripting")]
+mod script;
+mod session;
+mod sim;
+mod stats_csv;
+mod surface;
+mod touch;
+#[cfg(not(target_arch = "wasm32"))]
+mod tui;
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use log::{debug, error};
 use pixels::{Error, Pixels, SurfaceTexture};
 use winit::{
-    dpi::LogicalSize,
-    event::{Event, VirtualKeyCode},
+    dpi::{LogicalSize, PhysicalSize},
+    event::{ElementState, Event, TouchPhase, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, Window, WindowBuilder},
 };
 use winit_input_helper::WinitInputHelper;
 
+use tgol::automaton::{Automaton, CONDUCTOR, HEAD, TAIL, TEAM_NAMES, WIRE_NAMES};
+use tgol::cell::Cells;
+use tgol::cycle::CycleDetector;
+use tgol::engine::Engine;
+use tgol::grid::{Grid, Probe};
+use tgol::history::History;
+use tgol::library;
+use tgol::minimap::MiniMap;
+use tgol::noise::Noise;
+use tgol::overlay::Overlay;
+use tgol::pattern::{Heading, PatternBuffer};
+use tgol::rng::{generate_seed, next_seed, session_rng};
+use tgol::rule::Rule;
+use tgol::ships::Ship;
+use tgol::splash::Splash;
+use tgol::stochastic::Stochastic;
+use tgol::svg::{self, SvgOptions, SvgRegion};
+use tgol::timings::{self, Phase, Timings};
+use tgol::topology::Topology;
+use tgol::viewport::Viewport;
+use tgol::world::World;
+use tgol::{pattern_text, profile_scope, profiler, rle};
+
+use crate::auto_pause::AutoPause;
+use crate::autosave::Autosaver;
+use crate::bookmarks::Bookmarks;
+use crate::cli::{Command, Options};
+use crate::config::{Config, Settings};
+use crate::control::{ControlServer, Request};
+use crate::crossfade::Crossfade;
+use crate::frame_dump::FrameDump;
+use crate::frame_pipe::FramePipe;
+use crate::grid_lines::GridLines;
+use crate::keymap::{Action, KeyMap};
+use crate::last_session::LastSession;
+use crate::session::{Recorder, Replay};
+use crate::sim::{Command as SimCommand, Reply, Simulation, Worker};
+use crate::stats_csv::StatsCsv;
+use crate::surface::{SurfaceState, SurfaceStep};
+use crate::touch::{Touch, Touches};
+
+// Grid size unless --width/--height say otherwise.
 const WIDTH: u32 = 16 * 24;
 const HEIGHT: u32 = 10 * 24;
 
-fn get_window_size() -> LogicalSize<f64> {
-    LogicalSize::new(WIDTH as f64, HEIGHT as f64)
+// How many rules the explorer remembers for [U] to step back through.
+const RULE_TRAIL_LEN: usize = 10;
+
+// Step for Shift+Up/Down on the soup density.
+const DENSITY_STEP: f32 = 0.05;
+
+// Largest brush radius (in cells) reachable with the brush size keys.
+const MAX_BRUSH_RADIUS: u32 = 32;
+
+// The airbrush (Alt+drag) to start with, and the step Alt+Shift+[ / ]
+// changes its probability by.
+const SPRAY_RADIUS: u32 = 6;
+const SPRAY_PROBABILITY: f32 = 0.15;
+const SPRAY_PROBABILITY_STEP: f32 = 0.05;
+
+// The mouse's part of the help ([F1]), after the keys; these can't be
+// rebound.
+const MOUSE_HELP: [(&str, &str); 7] = [
+    ("Click, Drag", "draw"),
+    ("Ctrl+Drag", "fill a rectangle"),
+    ("Ctrl+Right-Drag", "clear a rectangle"),
+    ("Alt+Drag", "airbrush"),
+    ("Ctrl+Scroll", "brush size"),
+    ("Middle-Drag", "pan an infinite world"),
+    ("Click the mini-map", "move the view there"),
+];
+
+// Cells per frame the arrows pan an infinite world by.
+const PAN_STEP: i64 = 4;
+
+// How often input that acts for as long as it's held (the airbrush, the
+// arrows panning) repeats while nothing else wakes the window.
+const HELD_INPUT_INTERVAL: Duration = Duration::from_micros(16_667);
+
+// How often --timings reports.
+const TIMINGS_INTERVAL: Duration = Duration::from_secs(5);
+
+// How often the display is taken to refresh when the monitor doesn't say.
+const DEFAULT_REFRESH: Duration = Duration::from_micros(16_667);
+
+// How far [J] jumps ahead with the hashlife engine.
+const JUMP_GENERATIONS: u64 = 1 << 20;
+
+// Generations per frame that Shift+F cycles through; [F] uses the last one
+// picked.
+const FAST_FORWARD_SPEEDS: [u32; 3] = [10, 100, 1000];
+
+// Chances of each live cell dying that Shift+K cycles [K] through.
+const KILL_PROBABILITIES: [f32; 4] = [0.1, 0.25, 0.5, 0.75];
+
+// Smallest window we allow. The grid itself can be clipped below its native
+// size, but a status line still needs roughly this much room to be readable.
+const MIN_WINDOW_WIDTH: u32 = 160;
+const MIN_WINDOW_HEIGHT: u32 = 100;
+
+// Grids smaller than the default get scaled up by a whole factor, so a
+// 64x64 grid doesn't open a postage stamp of a window.
+fn get_window_size(width: u32, height: u32) -> LogicalSize<f64> {
+    let scale = (WIDTH / width).min(HEIGHT / height).max(1);
+    LogicalSize::new((width * scale) as f64, (height * scale) as f64)
 }
 
+fn window_title(
+    (width, height): (u32, u32),
+    rule: &str,
+    stochastic: Option<Stochastic>,
+    stamp: Option<&str>,
+    world_stats: Option<&str>,
+    fast_forward: Option<u32>,
+    history_truncated: bool,
+) -> String {
+    let mut title = format!("TGOL [{} x {}] {}", width, height, rule);
+    if let Some(stochastic) = stochastic {
+        title.push_str(&format!(" (stochastic {})", stochastic));
+    }
+    if let Some(stats) = world_stats {
+        title.push_str(" - ");
+        title.push_str(stats);
+    }
+    if let Some(speed) = fast_forward {
+        title.push_str(&format!(" >> x{}", speed));
+    }
+    if let Some(name) = stamp {
+        title.push_str(" - ");
+        title.push_str(name);
+    }
+    if history_truncated {
+        title.push_str(" (undo history truncated)");
+    }
+    title
+}
+
+// Milliseconds on a steady clock. std's `Instant` isn't there in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    static START: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+fn get_min_window_size() -> LogicalSize<f64> {
+    LogicalSize::new(MIN_WINDOW_WIDTH as f64, MIN_WINDOW_HEIGHT as f64)
+}
+
+/// The surface size for a `size` window: clamped to the minimum window
+/// size.
+fn surface_size(window: &Window, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let min: PhysicalSize<u32> = get_min_window_size().to_physical(window.scale_factor());
+    PhysicalSize::new(size.width.max(min.width), size.height.max(min.height))
+}
+
+/// Where a `grid`-sized frame lands in the window as it is now, the size
+/// the surface gets before the next render, even if it hasn't yet.
+fn viewport(window: &Window, grid: (u32, u32)) -> Viewport {
+    let size = surface_size(window, window.inner_size());
+    Viewport::new(grid, (size.width, size.height))
+}
+
+/// Applies any pending surface resize, clamped to the minimum window size,
+/// or rebuilds `Pixels` if resizing hasn't stopped the renders failing.
+///
+/// Returns `Ok(false)` while the window is minimized (zero-sized) and there
+/// is nothing to render into; the resize stays pending until it's restored.
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn resize_pipeline(
+    pixels: &mut Pixels,
+    window: &Window,
+    surface: &mut SurfaceState,
+    (grid_width, grid_height): (u32, u32),
+) -> Result<bool, Error> {
+    let min = get_min_window_size().to_physical(window.scale_factor());
+    let step = surface.next_step(min);
+    match step {
+        SurfaceStep::Keep => return Ok(true),
+        SurfaceStep::Wait => return Ok(false),
+        SurfaceStep::Resize(size) => pixels.resize_surface(size.width, size.height),
+        #[cfg(not(target_arch = "wasm32"))]
+        SurfaceStep::Rebuild(size) => {
+            log::warn!(
+                "Recreating pixels surface ({} x {})",
+                size.width,
+                size.height
+            );
+            let surface_texture = SurfaceTexture::new(size.width, size.height, window);
+            *pixels = Pixels::new(grid_width, grid_height, surface_texture)?;
+        }
+        // `Pixels` can only be built asynchronously in the browser, so
+        // there a failing surface just gets resized again.
+        #[cfg(target_arch = "wasm32")]
+        SurfaceStep::Rebuild(size) => {
+            pixels.resize_surface(size.width, size.height);
+            surface.applied(SurfaceStep::Resize(size));
+            return Ok(true);
+        }
+    }
+
+    surface.applied(step);
+    Ok(true)
+}
+
+/// An in-progress Ctrl+drag rectangle, from where the button went down to
+/// where the cursor is now.
+struct RectDrag {
+    anchor: (isize, isize),
+    cursor: (isize, isize),
+    button: usize,
+    alive: bool,
+}
+
+/// A region picked out in selection mode, from the corner where the drag
+/// started to the corner where it ended (or is now, while `dragging`).
+struct Selection {
+    anchor: (isize, isize),
+    cursor: (isize, isize),
+    dragging: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Error> {
     env_logger::init();
+
+    let mut options = match Options::from_env() {
+        Ok(options) if options.help => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(options) => options,
+        Err(e) => {
+            eprint!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(Command::Convert { input, output }) = &options.command {
+        if let Err(e) = convert(input, output, &options.svg_options()) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(generations) = options.bench {
+        bench(&options, generations);
+        return Ok(());
+    }
+
+    if options.tui {
+        if let Err(e) = tui::run(&options) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if options.ascii {
+        if let Err(e) = ascii::run(&options) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut settings =
+        match Config::load(options.config.as_deref()).and_then(|c| Settings::from_config(&c)) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        };
+
+    // --dump-frames refuses a directory with something in it before
+    // anything's run, window or not.
+    if let Some(dir) = &options.dump_frames {
+        if let Err(e) = frame_dump::prepare(dir, options.force) {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    }
+
+    // Edits to the config file are picked up while the window's open.
+    let config_watch = config::Watcher::new(options.config.as_deref(), settings.clone());
+
+    // A batch run always starts afresh, from the command line and the
+    // config file alone (the command line winning).
+    if let Some(generations) = options.generations {
+        if let Some(decay) = options.decay {
+            settings.decay = decay;
+        }
+        if options.topology.is_none() && options.world == World::Torus {
+            options.topology = settings.topology;
+        }
+        if let Err(e) = headless::run(&options, &settings, generations) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // A replay starts the way its recording did, whatever else was asked.
+    let replay = match options.replay.as_deref().map(Replay::load).transpose() {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(replay) = &replay {
+        replay.configure(&mut options);
+        settings.trails = replay.trails;
+    }
+
+    // Otherwise it starts where the last session left off, unless it's
+    // --fresh or being recorded (a recording starts from a soup), or from
+    // the newest autosave with --restore-autosave.
+    let unfinished = autosave::unfinished();
+    let from = if options.restore_autosave {
+        let newest = autosave::newest().map(|(path, _)| path);
+        if newest.is_none() {
+            log::warn!("There's no autosave to restore");
+        }
+        newest
+    } else {
+        if let Some((path, at)) = &unfinished {
+            let minutes = at.elapsed().unwrap_or_default().as_secs() / 60;
+            log::warn!(
+                "{} (written {} min ago) is newer than the last session, so the last run \
+                 may not have closed cleanly; start with --restore-autosave to pick it up",
+                path.display(),
+                minutes
+            );
+        }
+        last_session::path()
+    };
+    let resumed = from
+        .filter(|path| path.exists())
+        .filter(|_| !options.fresh && replay.is_none() && options.record.is_none())
+        .and_then(|path| match LastSession::load(&path) {
+            Ok(session) => {
+                log::info!(
+                    "Picked up the last session from {} (--fresh to start over)",
+                    path.display()
+                );
+                Some(session)
+            }
+            Err(e) => {
+                log::warn!(
+                    "Couldn't pick up the last session from {} ({}); starting fresh",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        });
+    if let Some(session) = &resumed {
+        session.configure(&mut options);
+        settings.trails = session.trails;
+        settings.age_colors = session.age_colors;
+    }
+
+    // The command line wins over the config file.
+    if let Some(decay) = options.decay {
+        settings.decay = decay;
+    }
+    if let Some(probability) = options.kill_prob {
+        settings.kill_probability = probability;
+    }
+    if let Some(tps) = options.tps {
+        settings.tps = tps;
+    }
+    settings.pause_on_unfocus |= options.pause_on_unfocus;
+    if options.noise != Noise::Off {
+        settings.noise = options.noise;
+    }
+    if options.topology.is_none() && options.world == World::Torus {
+        options.topology = settings.topology;
+    }
+    if options.pause_on_stable || settings.pause_on_stable.is_some() {
+        settings.pause_on_stable = options
+            .stable_period
+            .or(settings.pause_on_stable)
+            .or(Some(config::DEFAULT_STABLE_PERIOD));
+    }
+
     let event_loop = EventLoop::new();
-    let mut input = WinitInputHelper::new();
+    let window = build_window(&event_loop, &options);
+
+    let pixels = {
+        let (width, height) = options.grid_size();
+        let window_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        Pixels::new(width, height, surface_texture)?
+    };
 
-    let window = {
-        let size = get_window_size();
+    run(
+        options,
+        settings,
+        config_watch,
+        replay,
+        resumed,
+        event_loop,
+        window,
+        pixels,
+    )
+}
 
-        WindowBuilder::new()
-            .with_title(format!("TGOL [{} x {}]", WIDTH, HEIGHT))
-            .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
+// In the browser there are no arguments, terminal modes or files: the
+// window becomes a canvas in the page and `pixels` has to be set up
+// asynchronously.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use winit::platform::web::WindowExtWebSys;
+
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Info).expect("couldn't set up console logging");
+
+    let options = match Options::from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
     };
 
-    let mut pixels = {
+    let event_loop = EventLoop::new();
+    let window = build_window(&event_loop, &options);
+
+    web_sys::window()
+        .and_then(|page| page.document())
+        .and_then(|document| document.body())
+        .and_then(|body| body.append_child(&window.canvas()).ok())
+        .expect("couldn't add the canvas to the page");
+
+    wasm_bindgen_futures::spawn_local(async move {
+        let (width, height) = options.grid_size();
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
+        match Pixels::new_async(width, height, surface_texture).await {
+            Ok(pixels) => run(
+                options,
+                Settings::default(),
+                None,
+                None,
+                None,
+                event_loop,
+                window,
+                pixels,
+            ),
+            Err(e) => error!("Couldn't set up pixels: {}", e),
+        }
+    });
+}
+
+fn build_window(event_loop: &EventLoop<()>, options: &Options) -> Window {
+    let grid_size = options.grid_size();
+
+    WindowBuilder::new()
+        .with_title(window_title(
+            grid_size,
+            &options.rule.to_string(),
+            options.stochastic,
+            None,
+            None,
+            None,
+            false,
+        ))
+        .with_inner_size(get_window_size(grid_size.0, grid_size.1))
+        .with_min_inner_size(get_min_window_size())
+        .build(event_loop)
+        .unwrap()
+}
+
+/// The windowed game. Runs the event loop until the window is closed.
+///
+/// The grid itself lives on the simulation thread (see sim.rs): input is
+/// sent there as commands, and each redraw shows its latest frame with the
+/// tool overlays drawn on top.
+#[allow(clippy::too_many_arguments)]
+fn run(
+    options: Options,
+    mut settings: Settings,
+    mut config_watch: Option<config::Watcher>,
+    replay: Option<Replay>,
+    resumed: Option<LastSession>,
+    event_loop: EventLoop<()>,
+    window: Window,
+    mut pixels: Pixels,
+) -> ! {
+    let mut input = WinitInputHelper::new();
+    let mut keys = std::mem::take(&mut settings.keys);
+
+    let grid_size = options.grid_size();
+    let center = ((grid_size.0 / 2) as isize, (grid_size.1 / 2) as isize);
+    let overlay = Overlay::new(grid_size.0 as usize, grid_size.1 as usize);
+
+    let mut surface = SurfaceState::default();
+
+    // Fast-forward: latched on by Shift+F, or while [F] is held.
+    let mut fast_forward: Option<u32> = resumed.as_ref().and_then(|s| s.fast_forward);
+    let mut fast_forward_speed = fast_forward.unwrap_or(FAST_FORWARD_SPEEDS[0]);
+    let mut speed = 1;
+    let mut kill_probability = settings.kill_probability;
+    let mut shown_fast_forward: Option<u32> = None;
+    let mut brushing = false;
+    let mut shown_truncated = false;
+    let mut rect_drag: Option<RectDrag> = None;
+    let mut brush_radius: u32 = 0;
+    // The airbrush, and whether it's being dragged.
+    let mut spray_radius = SPRAY_RADIUS;
+    let mut spray_probability = SPRAY_PROBABILITY;
+    let mut spraying = false;
+    let mut cursor: (isize, isize) = (0, 0);
+    let mut age_colors = settings.age_colors;
+
+    // Grid line overlay ([L]), set up the first time it's shown.
+    let mut show_grid_lines = false;
+    let mut grid_lines: Option<GridLines> = None;
+
+    // The help ([F1]), made from the key bindings in use.
+    let mut show_help = false;
+    let mut help = help_entries(&keys);
+
+    // Mini-map ([Tab]), as of the last frame that came with one.
+    let mut show_minimap = false;
+    // The density wash ([D]).
+    let mut show_density = false;
+    // Ships tinted and counted ([Shift+G]).
+    let mut show_ships = false;
+    // The cursor readout ([C]): the cell the simulation was last asked
+    // about, and what it said as of the last frame.
+    let mut show_readout = false;
+    let mut probed: Option<(isize, isize)> = None;
+    let mut probe: Option<Probe> = None;
+    let mut minimap: Option<MiniMap> = None;
+
+    // Whether the window's minimized, covered up or out of focus, and
+    // what paused it while it was (--pause-when-hidden, --pause-on-unfocus).
+    let mut minimized = false;
+    let mut occluded = false;
+    let mut focused = true;
+    let mut auto_pause = AutoPause::default();
+
+    // Cell under the mouse, None while the cursor is outside the window.
+    let mut hover: Option<(isize, isize)> = None;
+    let mut cursor_inside = false;
+    // The cell the mouse was mapped to on the last frame, unclamped, for
+    // brush strokes to go on from.
+    let mut last_stroke_cell: Option<(isize, isize)> = None;
+
+    // Selection mode ([V]) and the copy/paste buffers.
+    let mut select_mode = false;
+    let mut selection: Option<Selection> = None;
+    let mut clipboard: Option<PatternBuffer> = None;
+    // The system clipboard's text when the selection was copied, so [Ctrl+V]
+    // knows which of the two is newer.
+    let mut copied_over: Option<String> = None;
+    // Pattern files dropped on the window since the last frame, and those
+    // waiting their turn to be placed after the first.
+    let mut dropped: Vec<PathBuf> = Vec::new();
+    let mut dropped_queue: VecDeque<PatternBuffer> = VecDeque::new();
+    let mut paste: Option<PatternBuffer> = None;
+
+    // Name of the armed stamp (the glider or a library pattern), shown in
+    // the title bar. Stamps place through `paste` but, unlike a one-off
+    // paste, stay armed after each click.
+    let mut stamp: Option<&'static str> = None;
+    let mut shown_stamp: Option<&'static str> = None;
+    let mut glider_heading: Option<Heading> = None;
+
+    // Infinite world: middle-drag's starting cell and view, and the stats
+    // last put in the title.
+    let mut pan_drag: Option<((isize, isize), (i64, i64))> = None;
+    // Fingers on a touchscreen.
+    let mut touches = Touches::default();
+    let mut shown_world_stats: Option<String> = None;
+
+    // Immigration and QuadLife: the team cells drawn join.
+    let mut team: u8 = 0;
+    // Wireworld and rule tables: what drawing paints.
+    let mut material = match options.automaton {
+        Automaton::Table => 1,
+        _ => CONDUCTOR,
     };
 
-    let mut paused = false;
-    let mut draw_state: Option<bool> = None;
+    // One RNG drives everything random in the session (soups, [K], the
+    // rule explorer), so a session can be replayed from its seed.
+    let seed = session_seed(&options);
+    let mut rng = session_rng(seed);
+    // The last soup's seed, for [Shift+R]. The first soup is made from the
+    // session's seed by `Grid::new_soup`, as [Shift+R] makes it again.
+    let mut soup_seed = seed;
+
+    let mut life = starting_grid(&options, grid_size, seed);
+    if let Some(session) = &resumed {
+        session.restore_states(&mut life);
+    }
+    apply_settings(&mut life, &settings);
+    let world = life.world();
+
+    // --record: every change to the grid goes through `perform`, which
+    // writes it down first.
+    let recorder = options.record.as_deref().and_then(|path| {
+        Recorder::create(path, &options, seed, &life)
+            .map_err(|e| error!("Couldn't record to {}: {}", path.display(), e))
+            .ok()
+    });
+    let mut density = options.density;
+    let mut symmetry = options.symmetry;
+
+    let mut worker = Worker::new(life, History::with_budget(options.undo_budget));
+    worker.recorder = recorder;
+    #[cfg(feature = "scripting")]
+    if let Some(path) = &options.script {
+        match script::Script::load(path, seed) {
+            Ok(script) => worker.start_script(script),
+            Err(e) => error!("Couldn't load the script: {}", e),
+        }
+    }
+    #[cfg(not(feature = "scripting"))]
+    if options.script.is_some() {
+        error!("--script needs tgol to be built with the `scripting` feature");
+    }
+    worker.record_path = options.record.clone();
+    worker.stats_csv = options.stats_out.as_deref().and_then(|path| {
+        StatsCsv::create(path, options.automaton.teams())
+            .map_err(|e| error!("Couldn't write stats to {}: {}", path.display(), e))
+            .ok()
+    });
+    let size = (worker.life.width as u32, worker.life.height as u32);
+    worker.frame_pipe = if let Some(args) = &options.ffmpeg {
+        FramePipe::ffmpeg(args, size, settings.tps)
+            .map_err(|e| {
+                error!(
+                    "Couldn't start ffmpeg for --ffmpeg ({}); is it installed and on the PATH?",
+                    e
+                )
+            })
+            .ok()
+    } else {
+        options.pipe_frames.then(FramePipe::stdout)
+    };
+    worker.frame_dump = options.dump_frames.as_ref().and_then(|dir| {
+        FrameDump::start(dir.clone(), size, options.dump_scale)
+            .map_err(|e| error!("Couldn't dump frames to {}: {}", dir.display(), e))
+            .ok()
+    });
+    worker.cycles = settings.pause_on_stable.map(CycleDetector::new);
+    // --autosave: due every so often, but held back while an edit is half
+    // done (see below). A replay isn't a session to save.
+    let autosave_every = options
+        .autosave
+        .filter(|_| options.replay.is_none())
+        .map(Duration::from_secs);
+    // --timings: stepping and drawing are timed on the simulation's
+    // thread, presenting here, and the lot reported every few seconds.
+    let timings = options.timings.then(|| Arc::new(Timings::default()));
+    worker.timings = timings.clone();
+    let mut next_timings = instant::Instant::now() + TIMINGS_INTERVAL;
+    let mut timings_shown: Option<String> = None;
+    if autosave_every.is_some() {
+        worker.autosaver = Autosaver::start(options.autosave_keep)
+            .map_err(|e| error!("Couldn't start autosaving: {}", e))
+            .ok();
+    }
+    let mut next_autosave = autosave_every.map(|every| instant::Instant::now() + every);
+    // --audio: played on a thread of the sound system's, fed by the
+    // simulation's. Without a device there's just no sound.
+    #[cfg(feature = "audio")]
+    let sound = if options.audio {
+        audio::Sound::start()
+            .map_err(|e| log::warn!("Couldn't start the sound ({}); carrying on without it", e))
+            .ok()
+    } else {
+        None
+    };
+    #[cfg(feature = "audio")]
+    if let Some(sound) = &sound {
+        worker.sound = Some(sound.feed());
+    }
+    #[cfg(not(feature = "audio"))]
+    if options.audio {
+        error!("--audio needs tgol to be built with the `audio` feature");
+    }
+    worker.replay = replay;
+    // The intro plays over the soup `life` was just filled with, and what
+    // a script did to it. The key or click that skips it isn't passed on
+    // to the handlers below.
+    worker.splash = (!options.no_splash).then(|| Splash::start(&mut worker.life));
+    let mut swallow_input = false;
+
+    // The grid as of the last frame, as far as the handlers below need to
+    // know. What they change themselves is updated here straight away, so
+    // the next key press sees it even before the simulation has.
+    let mut status = worker.status();
+    let proxy = event_loop.create_proxy();
+    let mut sim = Simulation::start(worker, settings.tps, move || {
+        // Only fails once the event loop is gone.
+        let _ = proxy.send_event(());
+    });
+    // --control-socket / --control-port: commands from scripts, answered
+    // in order. Those that need the simulation's answer wait here for it.
+    let mut control = {
+        let proxy = event_loop.create_proxy();
+        let wake = move || {
+            // Only fails once the event loop is gone.
+            let _ = proxy.send_event(());
+        };
+        match (&options.control_socket, options.control_port) {
+            (Some(path), _) => ControlServer::unix(path.clone(), wake)
+                .map_err(|e| error!("Couldn't listen on {}: {}", path.display(), e))
+                .ok(),
+            (None, Some(port)) => ControlServer::tcp(port, wake)
+                .map_err(|e| error!("Couldn't listen on port {}: {}", port, e))
+                .ok(),
+            (None, None) => None,
+        }
+    };
+    let mut awaiting_status: VecDeque<control::Responder> = VecDeque::new();
+
+    // Ctrl+C in the terminal closes the window as [Esc] would, so every
+    // frame queued for --dump-frames still gets written and the control
+    // socket is cleaned up.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.dump_frames.is_some() || options.control_socket.is_some() {
+        let (flag, proxy) = (Arc::clone(&interrupted), event_loop.create_proxy());
+        let handler = ctrlc::set_handler(move || {
+            flag.store(true, Ordering::SeqCst);
+            let _ = proxy.send_event(());
+        });
+        if let Err(e) = handler {
+            error!("Couldn't catch Ctrl+C: {}", e);
+        }
+    }
+    // Gamepads, read on a thread of their own; they come and go as they're
+    // plugged in. Without a way to read them there's just the keyboard.
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = {
+        let proxy = event_loop.create_proxy();
+        let wake = move || {
+            // Only fails once the event loop is gone.
+            let _ = proxy.send_event(());
+        };
+        gamepad::Gamepad::start((overlay.width, overlay.height), wake)
+            .map_err(|e| log::warn!("Couldn't read gamepads ({}); carrying on without them", e))
+            .ok()
+    };
+    if resumed.is_some_and(|session| session.paused) {
+        sim.send(SimCommand::Pause);
+        status.paused = true;
+    }
+    // The last frame's pixels, drawn again (under fresh overlays) until
+    // there's a newer one, faded in over the one before while the grid
+    // ticks slower than the display refreshes.
+    let mut shown: Vec<u8> = Vec::new();
+    let refresh = window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .map_or(DEFAULT_REFRESH, |millihertz| {
+            Duration::from_secs_f64(1000.0 / millihertz as f64)
+        });
+    let tick = Duration::from_secs_f64(1.0 / settings.tps as f64);
+    let mut crossfade = Crossfade::new(tick, refresh, !options.no_smooth);
+
+    // Rule explorer ([M] mutates, [U] steps back, [Shift+M] bookmarks),
+    // with its trail shown until [Esc].
+    let mut rule_trail: Vec<Rule> = Vec::new();
+    let mut show_rule_trail = false;
+    let bookmarks_path = bookmarks::path();
+    let mut bookmarks = bookmarks_path
+        .as_deref()
+        .map(|path| {
+            Bookmarks::load(path).unwrap_or_else(|e| {
+                error!("Couldn't read bookmarks from {}: {}", path.display(), e);
+                Bookmarks::default()
+            })
+        })
+        .unwrap_or_default();
+    let mut rule_title = if options.automaton.life_like() {
+        status.rule.to_string()
+    } else if let Some(table) = &options.rule_table {
+        table.name.clone()
+    } else {
+        options.automaton.title().to_string()
+    };
+    let mut shown_rule_title = rule_title.clone();
+
+    #[cfg(feature = "http-view")]
+    let mut http_view = options.http_view.and_then(|port| {
+        http_view::HttpView::start(
+            options.http_bind,
+            port,
+            options.http_fps,
+            options.http_scale,
+        )
+        .map_err(|e| error!("Couldn't start the HTTP view on port {}: {}", port, e))
+        .ok()
+    });
 
-    let mut life = Grid::new_empty_grid(WIDTH as usize, HEIGHT as usize);
-    life.randomize();
+    #[cfg(not(feature = "http-view"))]
+    if options.http_view.is_some() {
+        error!("--http-view needs tgol to be built with the `http-view` feature");
+    }
 
     event_loop.run(move |event, _, control_flow| {
         // log::info!("<loop>");
 
+        if let Event::LoopDestroyed = event {
+            // The event loop never returns, so nothing it holds is dropped
+            // unless it's done here.
+            control = None;
+            // A replay isn't a session of its own to pick up again.
+            if let Some(worker) = sim.shutdown().filter(|_| options.replay.is_none()) {
+                let session =
+                    LastSession::new(&worker.life, worker.paused(), fast_forward, age_colors);
+                if let Some(path) = last_session::path() {
+                    if let Err(e) = session.save(&path) {
+                        error!("Couldn't save the session to {}: {}", path.display(), e);
+                    }
+                }
+            }
+            return;
+        }
+
+        if interrupted.load(Ordering::SeqCst) {
+            log::info!("Interrupted. Quitting..");
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
+
+        // WinitInputHelper keeps the last cursor position after it leaves
+        // the window, so track that ourselves.
+        if let Event::WindowEvent { event, .. } = &event {
+            match event {
+                WindowEvent::CursorEntered { .. } => cursor_inside = true,
+                WindowEvent::CursorLeft { .. } => cursor_inside = false,
+                WindowEvent::DroppedFile(path) => dropped.push(path.clone()),
+                // Minimizing is one or the other, depending on the platform.
+                WindowEvent::Resized(size) => minimized = size.width == 0 || size.height == 0,
+                WindowEvent::Occluded(covered) => occluded = *covered,
+                WindowEvent::Focused(has_focus) => focused = *has_focus,
+                WindowEvent::KeyboardInput { input, .. }
+                    if input.state == ElementState::Pressed && status.intro =>
+                {
+                    sim.send(SimCommand::SkipIntro);
+                    status.intro = false;
+                    swallow_input = true;
+                }
+                WindowEvent::MouseInput { state, .. }
+                    if *state == ElementState::Pressed && status.intro =>
+                {
+                    sim.send(SimCommand::SkipIntro);
+                    status.intro = false;
+                    swallow_input = true;
+                }
+                WindowEvent::Touch(touch) if touch.phase == TouchPhase::Started && status.intro => {
+                    sim.send(SimCommand::SkipIntro);
+                    status.intro = false;
+                    swallow_input = true;
+                }
+                // Scaled to cells as the mouse is, strokes going off the
+                // grid as they do.
+                WindowEvent::Touch(touch) => {
+                    let location = (touch.location.x, touch.location.y);
+                    let cell = viewport(&window, grid_size).cell_at(location);
+                    match touch.phase {
+                        TouchPhase::Started => touches.started(touch.id, cell),
+                        TouchPhase::Moved => touches.moved(touch.id, cell),
+                        TouchPhase::Ended | TouchPhase::Cancelled => touches.ended(touch.id, cell),
+                    }
+                }
+                _ => {}
+            }
+        }
+
         if let Event::RedrawRequested(_) = event {
-            if !paused {
-                life.update();
-                life.draw(pixels.get_frame_mut());
+            match resize_pipeline(&mut pixels, &window, &mut surface, grid_size) {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => {
+                    error!("Failed to recreate pixels: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
             }
 
-            if pixels
-                .render()
-                .map_err(|e| error!("pixels.render() failed: {}", e))
-                .is_err()
-            {
-                *control_flow = ControlFlow::Exit;
-                return;
+            // Always redrawn from the last frame, even when paused: the
+            // overlays are drawn on top of the grid and must not smear
+            // across frames.
+            crossfade.draw(pixels.get_frame_mut(), &shown, status.paused);
+
+            // Observers get the grid without any of the tool overlays.
+            #[cfg(feature = "http-view")]
+            if let Some(view) = http_view.as_mut().filter(|view| view.wants_frame()) {
+                let stats = http_view::Stats {
+                    generation: status.generation,
+                    population: status.population,
+                    paused: status.paused,
+                };
+                view.publish(pixels.get_frame(), grid_size.0, grid_size.1, stats);
+            }
+
+            if !status.intro {
+                profile_scope!("overlays");
+                let screen = pixels.get_frame_mut();
+                let topology = status.topology;
+                // How brush and rectangle edits carry on past the grid
+                // edges: as the topology glues them, but not past the view
+                // of an infinite world.
+                let edit_topology = match world {
+                    World::Torus => topology,
+                    World::Infinite => Topology::Plane,
+                };
+
+                if let Some(sel) = &selection {
+                    overlay.tint_rect(screen, sel.anchor, sel.cursor);
+                }
+
+                if let Some(buffer) = &paste {
+                    let (x, y) = buffer.origin_centered_on(cursor);
+                    overlay.draw_pattern_preview(screen, buffer, x, y, topology);
+                }
+
+                // Shows what a click would hit: the brush (or airbrush)
+                // footprint while drawing, otherwise just the cell.
+                if let Some((x, y)) = hover {
+                    let radius = if paste.is_some() || select_mode || rect_drag.is_some() {
+                        0
+                    } else if spraying || input.held_alt() {
+                        spray_radius
+                    } else {
+                        brush_radius
+                    };
+                    overlay.highlight_disc(screen, x, y, radius, edit_topology);
+                }
+                // --debug-mouse: the cell the cursor maps to, to check it's
+                // the one under it.
+                let debug_mouse = input.mouse().filter(|_| options.debug_mouse && !show_help);
+                let debug_mouse = debug_mouse.map(|(mx, my)| {
+                    let viewport = viewport(&window, grid_size);
+                    let (x, y) = viewport.cell_at((mx as f64, my as f64));
+                    overlay.draw_crosshair(screen, x, y);
+                    format!(
+                        "{:.0},{:.0} px  cell {},{}  x{}",
+                        mx,
+                        my,
+                        x,
+                        y,
+                        viewport.scale()
+                    )
+                });
+                #[cfg(feature = "gamepad")]
+                if let Some(pad) = gamepad.as_ref().filter(|pad| pad.connected()) {
+                    let (x, y) = pad.cursor();
+                    overlay.highlight_disc(screen, x, y, brush_radius, edit_topology);
+                }
+
+                if let Some(drag) = &rect_drag {
+                    overlay.draw_rect_outline(
+                        screen,
+                        drag.anchor,
+                        drag.cursor,
+                        drag.alive,
+                        edit_topology,
+                    );
+                }
+
+                if status.paused {
+                    overlay.draw_pause_icon(screen, auto_pause.pausing());
+                }
+
+                if let Some(map) = minimap.as_ref().filter(|_| show_minimap) {
+                    overlay.draw_minimap(screen, map);
+                }
+
+                if show_rule_trail && !show_help {
+                    let mut trail: Vec<(String, bool)> = rule_trail
+                        .iter()
+                        .map(|&rule| (rule.to_string(), bookmarks.has_rule(rule)))
+                        .collect();
+                    trail.push((format!("[{}]", status.rule), bookmarks.has_rule(status.rule)));
+                    overlay.draw_rule_trail(screen, &trail);
+                }
+
+                if let Some(cell) = probe.filter(|_| probed.is_some() && !show_help) {
+                    let state = if cell.alive { "alive" } else { "dead" };
+                    let text = format!(
+                        "{},{}  {}  heat {}  neighbors {}",
+                        cell.x, cell.y, state, cell.heat, cell.neighbors
+                    );
+                    overlay.draw_readout(screen, &text);
+                } else if let Some(text) = debug_mouse.as_ref().or(timings_shown.as_ref()) {
+                    overlay.draw_readout(screen, text);
+                }
+
+                if show_help {
+                    overlay.draw_help(screen, &help);
+                }
+            }
+
+            if std::mem::take(&mut surface.rebuilt) {
+                grid_lines = None;
+            }
+            if show_grid_lines && grid_lines.is_none() {
+                grid_lines = Some(GridLines::new(&pixels, settings.grid_line_color));
+            }
+
+            let rendered = timings::time(timings.as_deref(), Phase::Render, || {
+                profile_scope!("present");
+                match grid_lines.as_ref().filter(|_| show_grid_lines) {
+                    Some(lines) => pixels.render_with(|encoder, target, context| {
+                        context.scaling_renderer.render(encoder, target);
+                        lines.render(encoder, target, context, grid_size.0);
+                        Ok(())
+                    }),
+                    None => pixels.render(),
+                }
+            });
+            profiler::end_frame();
+
+            match rendered {
+                Ok(()) => surface.render_ok(),
+                Err(e) => {
+                    error!("pixels.render() failed: {}", e);
+                    if !surface.render_failed(window.inner_size()) {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                }
             }
         }
 
         if input.update(&event) {
+            // The newest frame is taken whether or not it gets drawn, so
+            // the grid carries on while the window's hidden, and it's up to
+            // date the moment it's shown again.
+            if let Some(frame) = sim.latest_frame() {
+                // Until the simulation has seen every command sent, keep
+                // what they changed rather than going back to its older
+                // idea of them.
+                let sent = (!sim.caught_up(&frame.status)).then(|| status.clone());
+                status = frame.status;
+                if let Some(sent) = sent {
+                    status.intro &= sent.intro;
+                    status.paused = sent.paused;
+                    status.rule = sent.rule;
+                    status.engine = sent.engine;
+                    status.topology = sent.topology;
+                    status.decay = sent.decay;
+                    status.trails = sent.trails;
+                    status.noise = sent.noise;
+                    status.view = sent.view;
+                }
+                minimap = frame.minimap;
+                probe = frame.probe;
+                // A generation stepped to while paused shows at once.
+                let old = std::mem::replace(&mut shown, frame.pixels);
+                let fade = !status.intro && !status.paused;
+                sim.recycle(crossfade.replaced(old, status.generation, fade));
+            }
+
+            // --pause-when-hidden, --pause-on-unfocus: paused while out of
+            // sight or out of focus, and going again once it's back, unless
+            // it was paused already or paused by hand meanwhile.
+            let hidden = options.pause_when_hidden && (minimized || occluded);
+            let unfocused = settings.pause_on_unfocus && !focused;
+            match auto_pause.update(hidden || unfocused, status.paused) {
+                Some(true) => {
+                    let why = if hidden { "hidden" } else { "out of focus" };
+                    log::info!("Window {}; pausing until it's back", why);
+                    sim.send(SimCommand::Pause);
+                    status.paused = true;
+                }
+                Some(false) => {
+                    log::info!("Window back; carrying on");
+                    sim.send(SimCommand::Resume);
+                    status.paused = false;
+                }
+                None => {}
+            }
+
+            for reply in sim.replies() {
+                match reply {
+                    Reply::Copied { cells, rule } => {
+                        let size = format!("{} x {}", cells.width(), cells.height());
+                        let copied = match selection {
+                            Some(_) => format!("{} selection", size),
+                            None => format!("live cells ({})", size),
+                        };
+                        if crate::clipboard::write_text(&rle::write(&cells, &rule)) {
+                            log::info!("Copied the {} to the clipboard as RLE", copied);
+                        } else {
+                            log::info!(
+                                "Copied the {} (no wl-copy, xclip, xsel, pbcopy or clip to put it \
+                                 on the system clipboard)",
+                                copied
+                            );
+                        }
+                        clipboard = Some(cells);
+                        copied_over = crate::clipboard::read_text();
+                    }
+                    Reply::Status(sim_status) => {
+                        if let Some(responder) = awaiting_status.pop_front() {
+                            responder.population(sim_status.generation, sim_status.population);
+                        }
+                    }
+                }
+            }
+
+            // Commands from scripts, passed on to the simulation in the
+            // order they came, so they land between generations.
+            for (request, responder) in control.iter().flat_map(ControlServer::requests) {
+                match request {
+                    Request::Pause => {
+                        sim.send(SimCommand::Pause);
+                        status.paused = true;
+                        auto_pause.paused_manually();
+                    }
+                    Request::Resume => {
+                        sim.send(SimCommand::Resume);
+                        status.paused = false;
+                    }
+                    Request::Step(generations) => {
+                        sim.send(SimCommand::Event(session::Event::Advance(generations)));
+                    }
+                    Request::LoadRle { pattern, at } => {
+                        let (x, y) = at.unwrap_or_else(|| pattern.origin_centered_on(center));
+                        sim.send(SimCommand::Event(session::Event::Paste { x, y, pattern }));
+                    }
+                    Request::SetCell { x, y, alive } => {
+                        let disc = session::Event::Disc {
+                            x,
+                            y,
+                            radius: 0,
+                            alive,
+                        };
+                        sim.send(SimCommand::Event(disc));
+                        sim.send(SimCommand::Event(session::Event::Commit));
+                    }
+                    Request::GetPopulation => {
+                        sim.send(SimCommand::Status);
+                        awaiting_status.push_back(responder);
+                        continue;
+                    }
+                }
+                responder.ok();
+            }
+
             // ===========================
             // Keyboard events
             // ===========================
 
-            // [ESCAPE]     = Quit
-            if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
-                log::info!("Escape pressed. Quitting..");
+            // [ESCAPE]     = Leave the current tool/mode, or quit if there's none
+            if input.quit() {
+                log::info!("Window closed. Quitting..");
                 *control_flow = ControlFlow::Exit;
                 return;
             }
 
+            if swallow_input {
+                swallow_input = false;
+                window.request_redraw();
+                return;
+            }
+
+            // What the gamepads did since the last frame, the buttons that
+            // stand for keys among it. Any button skips the intro, like a
+            // key, and isn't passed on.
+            #[cfg(feature = "gamepad")]
+            let pad_actions = match &mut gamepad {
+                Some(pad) => {
+                    pad.update();
+                    if status.intro && pad.any_pressed() {
+                        sim.send(SimCommand::SkipIntro);
+                        status.intro = false;
+                        window.request_redraw();
+                        return;
+                    }
+                    pad.actions()
+                }
+                None => Vec::new(),
+            };
+            #[cfg(not(feature = "gamepad"))]
+            let pad_actions: Vec<Action> = Vec::new();
+
+            // The [KEY] comments below are the default bindings; see keymap.rs.
+
+            if keys.pressed(&input, Action::Quit) || pad_actions.contains(&Action::Quit) {
+                if show_help {
+                    show_help = false;
+                } else if show_rule_trail {
+                    show_rule_trail = false;
+                } else if paste.is_some() || rect_drag.is_some() || select_mode {
+                    log::info!("Leaving current mode..");
+                    paste = None;
+                    dropped_queue.clear();
+                    stamp = None;
+                    glider_heading = None;
+                    rect_drag = None;
+                    select_mode = false;
+                    selection = None;
+                } else {
+                    log::info!("Quitting..");
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+            }
+
+            // [F1], [?]    = Show / hide the key bindings
+            if keys.pressed(&input, Action::Help) {
+                show_help = !show_help;
+            }
+
             // [SPACE]      = Pause (for frame step)
-            if input.key_pressed_os(VirtualKeyCode::Space) {
-                log::info!("'SPACE' pressed. Pausing..");
-                paused = true;
+            if keys.pressed(&input, Action::Pause) {
+                log::info!("Pausing..");
+                sim.send(SimCommand::Pause);
+                status.paused = true;
+                auto_pause.paused_manually();
+            }
+
+            // [F]          = Fast-forward while held
+            // [Shift+F]    = Cycle fast-forward: x10, x100, x1000, off
+            let fast_forward_held = keys.held(&input, Action::FastForward);
+            if keys.pressed(&input, Action::CycleFastForward) {
+                fast_forward = match fast_forward {
+                    None => Some(FAST_FORWARD_SPEEDS[0]),
+                    Some(speed) => FAST_FORWARD_SPEEDS
+                        .iter()
+                        .copied()
+                        .skip_while(|&s| s != speed)
+                        .nth(1),
+                };
+                if let Some(speed) = fast_forward {
+                    fast_forward_speed = speed;
+                }
+                log::info!("Fast-forward: {:?}", fast_forward);
             }
 
             // [P]          = Toggle Pause
-            if input.key_pressed(VirtualKeyCode::P) {
-                log::info!("'P' pressed. Toggling pause..");
-                paused = !paused;
+            if keys.pressed(&input, Action::TogglePause)
+                || pad_actions.contains(&Action::TogglePause)
+            {
+                log::info!("Toggling pause..");
+                sim.send(SimCommand::TogglePause);
+                status.paused = !status.paused;
             }
 
             // [R]          = Randomize TGOL
-            if input.key_pressed(VirtualKeyCode::R) {
-                log::info!("'R' pressed. Randomizing..");
-                life.randomize();
+            // [Shift+R]    = The same soup again (at today's density and
+            //                symmetry)
+            let again = keys.pressed(&input, Action::RandomizeAgain);
+            let randomize =
+                keys.pressed(&input, Action::Randomize) || pad_actions.contains(&Action::Randomize);
+            if randomize || again {
+                if !again {
+                    soup_seed = next_seed(&mut rng);
+                }
+                log::info!(
+                    "Randomizing with soup seed {} (--seed {} starts with this soup)..",
+                    soup_seed,
+                    soup_seed
+                );
+                let event = session::Event::Randomize {
+                    seed: soup_seed,
+                    density,
+                    symmetry,
+                    pre_roll: options.pre_roll,
+                };
+                sim.send(SimCommand::Event(event));
+            }
+
+            // [O]          = Cycle the symmetry of soups made by [R]
+            if keys.pressed(&input, Action::CycleSymmetry) {
+                symmetry = symmetry.next();
+                log::info!("Soup symmetry: {}", symmetry);
             }
 
             // [K]          = KILL Random cells
-            if input.key_pressed(VirtualKeyCode::K) {
-                let kill_count = life.randomly_kill();
-                log::info!("'K' pressed. Randomly killed {:?} cells..", kill_count);
+            if keys.pressed(&input, Action::Kill) || pad_actions.contains(&Action::Kill) {
+                if options.automaton == Automaton::Ant {
+                    log::info!("Can't kill cells with Langton's Ant: they're only colors");
+                } else {
+                    let event = session::Event::Kill {
+                        seed: next_seed(&mut rng),
+                        probability: kill_probability,
+                    };
+                    sim.send(SimCommand::Event(event));
+                }
+            }
+
+            // [Shift+K]    = Cycle how many cells [K] kills
+            if keys.pressed(&input, Action::CycleKillProbability) {
+                kill_probability = KILL_PROBABILITIES
+                    .into_iter()
+                    .find(|&p| p > kill_probability)
+                    .unwrap_or(KILL_PROBABILITIES[0]);
+                log::info!("[K] kills {:.0}% of live cells", kill_probability * 100.0);
+            }
+
+            // [I]          = Invert every cell
+            if keys.pressed(&input, Action::Invert) {
+                log::info!("Inverting..");
+                sim.send(SimCommand::Event(session::Event::Invert));
+            }
+
+            // [A]          = Toggle coloring live cells by age
+            if keys.pressed(&input, Action::AgeColors) {
+                age_colors = !age_colors;
+                let gradient = age_colors.then(|| settings.age_gradient.clone());
+                sim.send(SimCommand::AgeColors(gradient));
+                log::info!("Age colors: {}", age_colors);
+            }
+
+            // [H]          = Toggle heat trails
+            if keys.pressed(&input, Action::Trails) {
+                status.trails = !status.trails;
+                sim.send(SimCommand::Event(session::Event::Trails(status.trails)));
+                log::info!("Heat trails: {}", status.trails);
+            }
+
+            // [N]          = Toggle noise (the --noise rate, or the config's)
+            if keys.pressed(&input, Action::Noise) {
+                status.noise = match status.noise {
+                    Noise::Off => settings.noise,
+                    _ => Noise::Off,
+                };
+                sim.send(SimCommand::Event(session::Event::Noise(status.noise)));
+                log::info!("Noise: {}", status.noise);
+            }
+
+            // [L]          = Toggle grid lines (once cells are 4+ pixels wide)
+            if keys.pressed(&input, Action::GridLines) {
+                show_grid_lines = !show_grid_lines;
+                log::info!("Grid lines: {}", show_grid_lines);
+            }
+
+            // [Tab]        = Toggle the mini-map
+            if keys.pressed(&input, Action::MiniMap) {
+                show_minimap = !show_minimap;
+                sim.send(SimCommand::MiniMap(show_minimap));
+                log::info!("Mini-map: {}", show_minimap);
+            }
+
+            // [D]          = Toggle the density wash
+            if keys.pressed(&input, Action::DensityWash) {
+                show_density = !show_density;
+                let block = show_density.then_some(options.density_block);
+                sim.send(SimCommand::DensityWash(block));
+                log::info!("Density wash: {}", show_density);
+            }
+
+            // [Shift+G]    = Toggle spotting gliders and small spaceships
+            if keys.pressed(&input, Action::Ships) {
+                show_ships = !show_ships;
+                sim.send(SimCommand::Ships(show_ships));
+                log::info!("Spotting ships: {}", show_ships);
+            }
+
+            // [C]          = Toggle the cursor readout
+            if keys.pressed(&input, Action::CursorReadout) {
+                show_readout = !show_readout;
+                log::info!("Cursor readout: {}", show_readout);
+            }
+
+            // [E]          = Cycle the engine: scalar, bitset, active, hashlife
+            if keys.pressed(&input, Action::CycleEngine) {
+                status.engine = status.engine.next();
+                sim.send(SimCommand::Event(session::Event::Engine(status.engine)));
+                log::info!("Engine: {}", status.engine);
+            }
+
+            // [B]          = Cycle the edges: torus, klein-x, klein-y,
+            //                cross-surface, plane, wall
+            if keys.pressed(&input, Action::CycleTopology) {
+                if world == World::Infinite {
+                    log::info!("An infinite world has no edges to glue");
+                } else {
+                    status.topology = status.topology.next();
+                    let event = session::Event::Topology(status.topology);
+                    sim.send(SimCommand::Event(event));
+                    log::info!("Topology: {}", status.topology);
+                }
+            }
+
+            // [J]          = Jump ahead 2^20 generations (hashlife engine only)
+            if keys.pressed(&input, Action::Jump) {
+                if options.automaton != Automaton::Life {
+                    log::info!(
+                        "Can't jump ahead with {}: it's stepped one generation at a time",
+                        options.automaton
+                    );
+                } else if status.topology != Topology::Torus {
+                    log::info!(
+                        "Can't jump ahead on a {}: it's stepped one generation at a time",
+                        status.topology
+                    );
+                } else if status.noise != Noise::Off {
+                    log::info!("Can't jump ahead with noise on ([N] turns it off): it flips cells a generation at a time");
+                } else if status.engine == Engine::HashLife {
+                    let event = session::Event::Advance(JUMP_GENERATIONS);
+                    sim.send(SimCommand::Event(event));
+                } else {
+                    log::info!(
+                        "Jumping ahead needs the hashlife engine ([E] or --engine hashlife)"
+                    );
+                }
+            }
+
+            // [T]          = Cycle the team drawing brings to life (with teams),
+            //                or what it paints: conductor, head, tail (Wireworld), or
+            //                the next state (rule tables)
+            if keys.pressed(&input, Action::CycleTeam) && options.automaton.has_teams() {
+                team = (team + 1) % options.automaton.teams() as u8;
+                sim.send(SimCommand::Event(session::Event::Team(team)));
+                log::info!("Drawing for {}", TEAM_NAMES[team as usize]);
+            }
+            if keys.pressed(&input, Action::CycleTeam) && options.automaton == Automaton::Wireworld
+            {
+                material = match material {
+                    CONDUCTOR => HEAD,
+                    HEAD => TAIL,
+                    _ => CONDUCTOR,
+                };
+                sim.send(SimCommand::Event(session::Event::Material(material)));
+                log::info!("Drawing {}", WIRE_NAMES[material as usize]);
+            }
+            match &options.rule_table {
+                Some(table) if keys.pressed(&input, Action::CycleTeam) => {
+                    material = (material as usize % (table.states - 1) + 1) as u8;
+                    sim.send(SimCommand::Event(session::Event::Material(material)));
+                    log::info!("Drawing state {}", material);
+                }
+                _ => {}
+            }
+
+            // [Ctrl+Z]     = Undo the last edit
+            // [Ctrl+Y]     = Redo (also Ctrl+Shift+Z)
+            if keys.pressed(&input, Action::Redo) {
+                sim.send(SimCommand::Event(session::Event::Redo));
+            } else if keys.pressed(&input, Action::Undo) {
+                sim.send(SimCommand::Event(session::Event::Undo));
+            }
+
+            // [V]          = Toggle selection mode
+            if keys.pressed(&input, Action::SelectMode) {
+                select_mode = !select_mode;
+                selection = None;
+                log::info!("Selection mode: {}", select_mode);
+            }
+
+            // [Ctrl+C]     = Copy the selection, or with none the live cells'
+            //                bounding box, and put it on the system clipboard
+            //                as RLE
+            if keys.pressed(&input, Action::Copy) {
+                let corners = selection.as_ref().map(|sel| (sel.anchor, sel.cursor));
+                sim.send(SimCommand::Copy(corners));
+            }
+
+            // [Ctrl+V]     = Paste the copied selection, or an RLE / plaintext pattern
+            //                off the system clipboard if that's newer (click to
+            //                place, right-click to cancel; centered if the mouse
+            //                is outside the window)
+            if keys.pressed(&input, Action::Paste) {
+                let buffer = match crate::clipboard::read_text() {
+                    Some(text) if clipboard.is_none() || copied_over.as_ref() != Some(&text) => {
+                        pattern_text::parse(&text)
+                            .map_err(|e| error!("The clipboard doesn't hold a pattern: {}", e))
+                            .ok()
+                    }
+                    _ => clipboard.clone(),
+                };
+                if let Some(buffer) = buffer {
+                    stamp = None;
+                    glider_heading = None;
+                    if hover.is_some() {
+                        log::info!("Pasting {} x {}..", buffer.width(), buffer.height());
+                        paste = Some(buffer);
+                    } else {
+                        let (x, y) = buffer.origin_centered_on(center);
+                        log::info!(
+                            "Pasted {} x {} in the center",
+                            buffer.width(),
+                            buffer.height()
+                        );
+                        let event = session::Event::Paste {
+                            x,
+                            y,
+                            pattern: buffer,
+                        };
+                        sim.send(SimCommand::Event(event));
+                    }
+                }
+            }
+
+            // [Drop files] = Load RLE, plaintext or Life 1.06 patterns and pause. The
+            //                first is placed under the mouse (or centered), any
+            //                others are armed in turn (click to place each)
+            let mut loaded = dropped.drain(..).filter_map(|path| {
+                load_pattern(&path)
+                    .map_err(|e| error!("Couldn't load {}: {}", path.display(), e))
+                    .ok()
+            });
+            if let Some(pattern) = loaded.next() {
+                sim.send(SimCommand::Pause);
+                status.paused = true;
+                auto_pause.paused_manually();
+                let (x, y) = pattern.origin_centered_on(hover.unwrap_or(center));
+                log::info!(
+                    "Placed a dropped {} x {} pattern",
+                    pattern.width(),
+                    pattern.height()
+                );
+                sim.send(SimCommand::Event(session::Event::Paste { x, y, pattern }));
+            }
+            dropped_queue.extend(loaded);
+            if paste.is_none() && !dropped_queue.is_empty() {
+                stamp = None;
+                glider_heading = None;
+                paste = dropped_queue.pop_front();
+            }
+
+            // [G]          = Arm / disarm the glider stamp (arrows pick its heading)
+            if keys.pressed(&input, Action::Glider) {
+                glider_heading = match glider_heading {
+                    Some(_) => None,
+                    None => Some(Heading::SouthEast),
+                };
+                paste = glider_heading.map(PatternBuffer::glider);
+                stamp = glider_heading.map(|_| "Glider");
+                log::info!("Glider stamp: {:?}", glider_heading);
+            }
+
+            // [Shift+UP]   = Denser soups for [R]
+            // [Shift+DOWN] = Sparser soups for [R]
+            let step = if keys.pressed(&input, Action::DenserSoup) {
+                DENSITY_STEP
+            } else if keys.pressed(&input, Action::SparserSoup) {
+                -DENSITY_STEP
+            } else {
+                0.0
+            };
+
+            if step != 0.0 {
+                // Rounded so repeated steps stay on the 0.05 grid.
+                density = ((density + step) / DENSITY_STEP).round() * DENSITY_STEP;
+                density = density.clamp(0.0, 1.0);
+                log::info!("Soup density: {:.2}", density);
+            }
+
+            // [Arrows]     = Pan an infinite world (while no glider is armed)
+            let mut panning = false;
+            if glider_heading.is_none() && world == World::Infinite {
+                let held = |action| keys.held(&input, action) as i64;
+                let dx = held(Action::HeadingSouthEast) - held(Action::HeadingNorthWest);
+                let dy = held(Action::HeadingSouthWest) - held(Action::HeadingNorthEast);
+                panning = (dx, dy) != (0, 0);
+                if panning {
+                    if let Some((x, y)) = status.view {
+                        let (x, y) = (x + dx * PAN_STEP, y + dy * PAN_STEP);
+                        sim.send(SimCommand::Event(session::Event::View(x, y)));
+                        status.view = Some((x, y));
+                    }
+                }
+            }
+
+            if let Some(heading) = &mut glider_heading {
+                let picked = if keys.pressed(&input, Action::HeadingNorthEast) {
+                    Some(Heading::NorthEast)
+                } else if keys.pressed(&input, Action::HeadingSouthEast) {
+                    Some(Heading::SouthEast)
+                } else if keys.pressed(&input, Action::HeadingSouthWest) {
+                    Some(Heading::SouthWest)
+                } else if keys.pressed(&input, Action::HeadingNorthWest) {
+                    Some(Heading::NorthWest)
+                } else {
+                    None
+                };
+
+                if let Some(picked) = picked {
+                    *heading = picked;
+                    paste = Some(PatternBuffer::glider(picked));
+                    log::info!("Glider heading: {:?}", picked);
+                }
+            }
+
+            // [1-9]        = Arm a library pattern (click to place, Enter to center)
+            for (&action, entry) in Action::PATTERNS
+                .iter()
+                .zip(library::patterns(options.automaton))
+            {
+                if keys.pressed(&input, action) {
+                    match rle::parse(entry.rle) {
+                        Ok(pattern) => {
+                            log::info!("Armed {}", entry.name);
+                            paste = Some(pattern);
+                            stamp = Some(entry.name);
+                            glider_heading = None;
+                        }
+                        Err(e) => error!("Built-in pattern {} is broken: {}", entry.name, e),
+                    }
+                }
+            }
+
+            // [ENTER]      = Place the armed stamp in the center of the grid
+            if keys.pressed(&input, Action::PlaceStamp) && stamp.is_some() {
+                if let Some(buffer) = &paste {
+                    let (x, y) = buffer.origin_centered_on(center);
+                    let event = session::Event::Paste {
+                        x,
+                        y,
+                        pattern: buffer.clone(),
+                    };
+                    sim.send(SimCommand::Event(event));
+                }
+            }
+
+            // [, / .]      = Rotate the pending paste counter-clockwise / clockwise
+            // [X / Y]      = Mirror the pending paste horizontally / vertically
+            if let Some(buffer) = &mut paste {
+                if keys.pressed(&input, Action::RotateCcw) {
+                    buffer.rotate_ccw();
+                }
+                if keys.pressed(&input, Action::RotateCw) {
+                    buffer.rotate_cw();
+                }
+                if keys.pressed(&input, Action::MirrorX) {
+                    buffer.flip_horizontal();
+                }
+                if keys.pressed(&input, Action::MirrorY) {
+                    buffer.flip_vertical();
+                }
+            }
+
+            // [Shift+S]      = Export the selection (or whole grid) as SVG
+            // [Ctrl+Shift+S] = Export the live-cell bounding box as SVG
+            let export_live = keys.pressed(&input, Action::ExportSvgLive);
+            if export_live || keys.pressed(&input, Action::ExportSvg) {
+                let region = match &selection {
+                    Some(sel) => SvgRegion::Rect(sel.anchor, sel.cursor),
+                    None if export_live => SvgRegion::LiveBounds,
+                    None => SvgRegion::Whole,
+                };
+                sim.send(SimCommand::ExportSvg(SvgOptions {
+                    region,
+                    ..options.svg_options()
+                }));
+            }
+
+            // [Shift+C]    = Export the selection (or whole grid) as RLE
+            if keys.pressed(&input, Action::ExportRle) {
+                let corners = selection.as_ref().map(|sel| (sel.anchor, sel.cursor));
+                sim.send(SimCommand::ExportRle(corners));
+            }
+
+            // [Shift+A]    = Capture the next --apng-frames generations as an APNG
+            if keys.pressed(&input, Action::CaptureApng) {
+                sim.send(SimCommand::CaptureApng {
+                    frames: options.apng_frames,
+                    delay_ms: options.apng_delay,
+                });
+            }
+
+            // [Shift+F12]  = Export the grid as a PNG, --export-scale pixels a cell
+            if keys.pressed(&input, Action::ExportPng) {
+                let options = options.png_options(settings.grid_line_color);
+                sim.send(SimCommand::ExportPng(options));
+            }
+
+            // [F12]        = Dump the profiler summary (Ctrl+F12 also writes JSON)
+            // Each thread keeps its own profile. Only the simulation's
+            // is written as JSON, so one doesn't overwrite the other.
+            let dump_json = keys.pressed(&input, Action::ProfilerDumpJson);
+            if dump_json || keys.pressed(&input, Action::ProfilerDump) {
+                profiler::dump(false);
+                sim.send(SimCommand::ProfilerDump(dump_json));
+            }
+
+            // Brian's Brain and Wireworld have no rule for [M] and [U] to
+            // change.
+            let rule_keys = options.automaton.life_like();
+            if !rule_keys
+                && (keys.pressed(&input, Action::MutateRule)
+                    || keys.pressed(&input, Action::RevertRule)
+                    || keys.pressed(&input, Action::BookmarkRule))
+            {
+                log::info!("{} has no rule to change", options.automaton.title());
+            }
+
+            // [M]          = Mutate the rule by one random B/S bit
+            if rule_keys && keys.pressed(&input, Action::MutateRule) {
+                let old = status.rule;
+                status.rule.flip_bit(rng.next_u32() % 18);
+                sim.send(SimCommand::Event(session::Event::Rule(status.rule)));
+
+                if rule_trail.len() == RULE_TRAIL_LEN {
+                    rule_trail.remove(0);
+                }
+                rule_trail.push(old);
+
+                log::info!("Rule {} -> {}", old, status.rule);
+                log_rule_trail(&rule_trail, status.rule);
+                rule_title = format!("{} -> {}", old, status.rule);
+                show_rule_trail = true;
+            }
+
+            // [U]          = Go back to the previous rule
+            if rule_keys && keys.pressed(&input, Action::RevertRule) {
+                if let Some(previous) = rule_trail.pop() {
+                    log::info!("Rule {} -> {}", status.rule, previous);
+                    status.rule = previous;
+                    sim.send(SimCommand::Event(session::Event::Rule(previous)));
+                    log_rule_trail(&rule_trail, status.rule);
+                    rule_title = status.rule.to_string();
+                    show_rule_trail = true;
+                }
+            }
+
+            // [Shift+M]    = Bookmark the current rule
+            if rule_keys && keys.pressed(&input, Action::BookmarkRule) {
+                show_rule_trail = true;
+                match bookmarks_path.as_deref() {
+                    Some(path) => match bookmarks.add_rule(status.rule, path) {
+                        Ok(true) => log::info!("Bookmarked {} in {}", status.rule, path.display()),
+                        Ok(false) => log::info!("{} is bookmarked already", status.rule),
+                        Err(e) => error!("Couldn't bookmark to {}: {}", path.display(), e),
+                    },
+                    None => error!("Couldn't bookmark {}: no home directory", status.rule),
+                }
+            }
+
+            // [Ctrl+M]     = Mute / unmute --audio
+            if keys.pressed(&input, Action::Mute) {
+                #[cfg(feature = "audio")]
+                if let Some(sound) = &sound {
+                    let muted = sound.toggle_mute();
+                    log::info!("Sound: {}", if muted { "muted" } else { "on" });
+                } else {
+                    log::info!("There's no sound to mute (start with --audio)");
+                }
+                #[cfg(not(feature = "audio"))]
+                log::info!("There's no sound to mute (start with --audio)");
+            }
+
+            // [F11]        = Toggle fullscreen
+            if keys.pressed(&input, Action::Fullscreen) {
+                let fullscreen = match window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                log::info!("Fullscreen: {}", fullscreen.is_some());
+                window.set_fullscreen(fullscreen);
+                surface.request(window.inner_size());
+            }
+
+            // [ / ]        = Shrink / grow the brush (also Ctrl+scroll)
+            let scroll = if input.held_control() {
+                input.scroll_diff()
+            } else {
+                0.0
+            };
+            let brush_change = if keys.pressed(&input, Action::BrushSmaller) || scroll < 0.0 {
+                -1
+            } else if keys.pressed(&input, Action::BrushLarger) || scroll > 0.0 {
+                1
+            } else {
+                0
+            };
+            if brush_change != 0 && input.held_alt() {
+                // [Alt+[ / ]]   = Shrink / grow the airbrush
+                let radius = spray_radius
+                    .saturating_add_signed(brush_change)
+                    .clamp(1, MAX_BRUSH_RADIUS);
+                if radius != spray_radius {
+                    spray_radius = radius;
+                    log::info!("Airbrush radius: {}", spray_radius);
+                }
+            } else if brush_change != 0 {
+                let radius = brush_radius
+                    .saturating_add_signed(brush_change)
+                    .min(MAX_BRUSH_RADIUS);
+                if radius != brush_radius {
+                    brush_radius = radius;
+                    log::info!("Brush radius: {}", brush_radius);
+                }
+            }
+
+            // [Alt+Shift+[ / ]] = Thinner / thicker airbrush
+            let spray_change = if !input.held_alt() {
+                0.0
+            } else if keys.pressed(&input, Action::SlowerDecay) {
+                -SPRAY_PROBABILITY_STEP
+            } else if keys.pressed(&input, Action::FasterDecay) {
+                SPRAY_PROBABILITY_STEP
+            } else {
+                0.0
+            };
+            if spray_change != 0.0 {
+                spray_probability =
+                    (spray_probability + spray_change).clamp(SPRAY_PROBABILITY_STEP, 1.0);
+                log::info!("Airbrush: {:.0}% of cells", spray_probability * 100.0);
+            }
+
+            // [Shift+[ / ]] = Longer / shorter heat trails
+            let decay = if input.held_alt() {
+                None
+            } else if keys.pressed(&input, Action::SlowerDecay) {
+                Some(status.decay.slower())
+            } else if keys.pressed(&input, Action::FasterDecay) {
+                Some(status.decay.faster())
+            } else {
+                None
+            };
+            if let Some(decay) = decay {
+                status.decay = decay;
+                sim.send(SimCommand::Event(session::Event::Decay(decay)));
+                log::info!("Trail decay: {}", decay);
             }
 
             // ================================
             // Mouse events
             // ================================
-            let (mouse_cell, mouse_prev_cell) = input
+            // Cells under the mouse now and as of the last frame. Brush
+            // strokes follow it off the window and across the grid edges;
+            // everything else stays on the grid. The last frame's is the
+            // cell it was mapped to then, so a stroke picks up where it got
+            // to even if the window was resized since.
+            let viewport = viewport(&window, grid_size);
+            let stroke_cell = input
                 .mouse()
-                .map(|(mx, my)| {
-                    let (dx, dy) = input.mouse_diff();
-                    let prev_x = mx - dx;
-                    let prev_y = my - dy;
-
-                    let (mx_i, my_i) = pixels
-                        .window_pos_to_pixel((mx, my))
-                        .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
-
-                    let (px_i, py_i) = pixels
-                        .window_pos_to_pixel((prev_x, prev_y))
-                        .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
-
-                    (
-                        (mx_i as isize, my_i as isize),
-                        (px_i as isize, py_i as isize),
-                    )
-                })
+                .map(|(mx, my)| viewport.cell_at((mx as f64, my as f64)))
                 .unwrap_or_default();
+            let stroke_prev_cell = last_stroke_cell.unwrap_or(stroke_cell);
+            last_stroke_cell = input.mouse().map(|_| stroke_cell);
+            let mouse_cell = viewport.clamp(stroke_cell);
+            cursor = mouse_cell;
+            // Where in the world the mini-map under the cursor points, if
+            // it's shown and the cursor is over it.
+            let minimap_target = minimap
+                .as_ref()
+                .filter(|_| show_minimap)
+                .and_then(|map| overlay.minimap_target(map, mouse_cell.0, mouse_cell.1));
+            hover = input
+                .mouse()
+                .filter(|_| cursor_inside && minimap_target.is_none())
+                .map(|_| mouse_cell);
+            // The readout's cell is the one a click would hit.
+            let wanted = hover.filter(|_| show_readout);
+            if wanted != probed {
+                sim.send(SimCommand::Probe(wanted));
+                probed = wanted;
+            }
 
-            if input.mouse_pressed(0) {
+            // [Middle-Drag] = Pan an infinite world
+            if input.mouse_pressed(2) {
+                pan_drag = status.view.map(|view| (mouse_cell, view));
+            } else if !input.mouse_held(2) {
+                pan_drag = None;
+            }
+            if let Some(((ax, ay), (x, y))) = pan_drag {
+                let (dx, dy) = ((mouse_cell.0 - ax) as i64, (mouse_cell.1 - ay) as i64);
+                if status.view != Some((x - dx, y - dy)) {
+                    let event = session::Event::View(x - dx, y - dy);
+                    sim.send(SimCommand::Event(event));
+                    status.view = Some((x - dx, y - dy));
+                }
+            }
+
+            if let Some(drag) = &mut rect_drag {
+                drag.cursor = mouse_cell;
+
+                if input.mouse_released(drag.button) {
+                    debug!("Rect {:?} => {:?}", drag.anchor, drag.cursor);
+                    let ((x0, y0), (x1, y1)) = (drag.anchor, drag.cursor);
+                    let event = session::Event::Rect {
+                        from: (x0, y0),
+                        to: (x1, y1),
+                        alive: drag.alive,
+                    };
+                    sim.send(SimCommand::Event(event));
+                    rect_drag = None;
+                } else if !input.mouse_held(drag.button) {
+                    rect_drag = None;
+                }
+            } else if let Some((x, y)) = minimap_target.filter(|_| input.mouse_pressed(0)) {
+                // [Click on the mini-map] = Center an infinite world's view
+                //                           there
+                if status.view.is_some() {
+                    let view = (x - grid_size.0 as i64 / 2, y - grid_size.1 as i64 / 2);
+                    sim.send(SimCommand::Event(session::Event::View(view.0, view.1)));
+                    status.view = Some(view);
+                }
+            } else if let Some(buffer) = &paste {
+                if input.mouse_pressed(0) {
+                    let (x, y) = buffer.origin_centered_on(mouse_cell);
+                    debug!("Paste at {:?}", (x, y));
+                    let event = session::Event::Paste {
+                        x,
+                        y,
+                        pattern: buffer.clone(),
+                    };
+                    sim.send(SimCommand::Event(event));
+                    if stamp.is_none() {
+                        paste = dropped_queue.pop_front();
+                    }
+                } else if input.mouse_pressed(1) {
+                    debug!("Paste cancelled");
+                    paste = dropped_queue.pop_front();
+                    stamp = None;
+                    glider_heading = None;
+                }
+            } else if select_mode {
+                if input.mouse_pressed(0) {
+                    selection = Some(Selection {
+                        anchor: mouse_cell,
+                        cursor: mouse_cell,
+                        dragging: true,
+                    });
+                } else if let Some(sel) = selection.as_mut().filter(|sel| sel.dragging) {
+                    sel.cursor = mouse_cell;
+                    sel.dragging = input.mouse_held(0);
+                }
+            } else if spraying || (input.held_alt() && input.mouse_pressed(0)) {
+                // [Alt+Drag]         = Airbrush: cells around the cursor come
+                //                      alive at random, every frame
+                //                      it's held
+                if input.mouse_held(0) || input.mouse_pressed(0) {
+                    let (x, y) = mouse_cell;
+                    let event = session::Event::Spray {
+                        x,
+                        y,
+                        radius: spray_radius,
+                        probability: spray_probability,
+                        seed: next_seed(&mut rng),
+                    };
+                    sim.send(SimCommand::Event(event));
+                    spraying = true;
+                } else {
+                    debug!("Spray end");
+                    spraying = false;
+                    sim.send(SimCommand::Event(session::Event::Commit));
+                }
+            } else if input.held_control() && (input.mouse_pressed(0) || input.mouse_pressed(1)) {
+                // [Ctrl+Drag]        = Fill rectangle (left) / clear rectangle (right)
+                let button = if input.mouse_pressed(0) { 0 } else { 1 };
+                debug!("Rect start at {:?}", mouse_cell);
+                rect_drag = Some(RectDrag {
+                    anchor: mouse_cell,
+                    cursor: mouse_cell,
+                    button,
+                    alive: button == 0,
+                });
+            } else if options.automaton == Automaton::Ant && input.mouse_pressed(0) {
+                // [Click]            = Add an ant (Langton's Ant)
+                let (x, y) = mouse_cell;
+                sim.send(SimCommand::Event(session::Event::Ant(x, y)));
+            } else if input.mouse_pressed(0) {
                 debug!("Mouse click at {:?}", mouse_cell);
-                draw_state = Some(life.toggle(mouse_cell.0, mouse_cell.1));
-            } else if let Some(draw_alive) = draw_state {
+                let (x, y) = mouse_cell;
+                // Toggles the clicked cell, and the brush follows it.
+                sim.send(SimCommand::BrushDown {
+                    finger: None,
+                    x,
+                    y,
+                    radius: brush_radius,
+                });
+                brushing = true;
+            } else if brushing {
                 let release = input.mouse_released(0);
                 let held = input.mouse_held(0);
 
-                // debug!("Draw at {:?} => {:?}", mouse_prev_cell, mouse_cell);
-                // debug!("Mouse held {:?}, release {:?}", held, release);
-
                 // If they either released (finishing the drawing) or are still
                 // in the middle of drawing, keep going.
                 if release || held {
-                    // debug!("Draw line of {:?}", draw_alive);
-                    life.set_line(
-                        mouse_prev_cell.0,
-                        mouse_prev_cell.1,
-                        mouse_cell.0,
-                        mouse_cell.1,
-                        draw_alive,
-                    );
-
-                    // life.draw(pixels.get_frame_mut());
+                    sim.send(SimCommand::BrushTo {
+                        finger: None,
+                        from: stroke_prev_cell,
+                        to: stroke_cell,
+                        radius: brush_radius,
+                    });
                 }
 
                 // If they let go or are otherwise not clicking anymore, stop drawing.
                 if release || !held {
                     debug!("Draw end");
-                    draw_state = None;
+                    brushing = false;
+                    sim.send(SimCommand::BrushUp(None));
+                    sim.send(SimCommand::Event(session::Event::Commit));
+                }
+            }
+
+            // ====================================
+            // TOUCH events (see touch.rs)
+            // ====================================
+
+            for touch in touches.update() {
+                match touch {
+                    // [Tap], [Drag]      = Toggle the cell, and paint the
+                    //                      same under the finger
+                    Touch::Down { finger, x, y } => {
+                        let (x, y) = viewport.clamp((x, y));
+                        sim.send(SimCommand::BrushDown {
+                            finger: Some(finger),
+                            x,
+                            y,
+                            radius: brush_radius,
+                        });
+                    }
+                    Touch::Line { finger, from, to } => sim.send(SimCommand::BrushTo {
+                        finger: Some(finger),
+                        from,
+                        to,
+                        radius: brush_radius,
+                    }),
+                    Touch::Up { finger } => sim.send(SimCommand::BrushUp(Some(finger))),
+                    Touch::Commit => sim.send(SimCommand::Event(session::Event::Commit)),
+                    // [Two fingers]      = Pan an infinite world, or
+                    //                      toggle pause
+                    Touch::Gesture if status.view.is_none() => {
+                        log::info!("Toggling pause..");
+                        sim.send(SimCommand::TogglePause);
+                        status.paused = !status.paused;
+                    }
+                    Touch::Gesture => {}
+                    Touch::Pan(dx, dy) => {
+                        if let Some((x, y)) = status.view {
+                            let view = (x - dx as i64, y - dy as i64);
+                            sim.send(SimCommand::Event(session::Event::View(view.0, view.1)));
+                            status.view = Some(view);
+                        }
+                    }
+                }
+            }
+
+            // ====================================
+            // GAMEPAD events (see gamepad.rs for the rest of the buttons)
+            // ====================================
+
+            #[cfg(feature = "gamepad")]
+            if let Some(pad) = &mut gamepad {
+                // [A]                = Step one generation
+                if pad.pressed(gilrs::Button::South) {
+                    sim.send(SimCommand::Pause);
+                    status.paused = true;
+                    auto_pause.paused_manually();
+                    sim.send(SimCommand::Event(session::Event::Advance(1)));
+                }
+
+                // [LB] / [RB]        = Fast-forward down / up a speed
+                let slower = pad.pressed(gilrs::Button::LeftTrigger);
+                if slower || pad.pressed(gilrs::Button::RightTrigger) {
+                    let at = fast_forward
+                        .and_then(|speed| FAST_FORWARD_SPEEDS.iter().position(|&s| s == speed));
+                    let to = match (at, slower) {
+                        (None, true) => None,
+                        (None, false) => Some(0),
+                        (Some(0), true) => None,
+                        (Some(i), true) => Some(i - 1),
+                        (Some(i), false) => Some((i + 1).min(FAST_FORWARD_SPEEDS.len() - 1)),
+                    };
+                    fast_forward = to.map(|i| FAST_FORWARD_SPEEDS[i]);
+                    if let Some(speed) = fast_forward {
+                        fast_forward_speed = speed;
+                    }
+                    log::info!("Fast-forward: {:?}", fast_forward);
+                }
+
+                // [Right/Left trigger] = Draw / erase at the cursor
+                for event in pad.strokes(brush_radius) {
+                    sim.send(SimCommand::Event(event));
                 }
             }
+            #[cfg(feature = "gamepad")]
+            let (pad_stroking, pad_steering) = gamepad
+                .as_ref()
+                .map_or((false, false), |pad| (pad.stroking(), pad.steering()));
+            #[cfg(not(feature = "gamepad"))]
+            let (pad_stroking, pad_steering) = (false, false);
 
             // ====================================
             // WINDOW RESIZE events
@@ -172,280 +2157,675 @@ fn main() -> Result<(), Error> {
                     size.height
                 );
 
-                pixels.resize_surface(size.width, size.height);
+                // A zero size means the window was minimized; the request
+                // stays pending until it's restored.
+                surface.request(size);
+            }
+
+            if let Some(factor) = input.scale_factor_changed() {
+                log::info!("Scale factor changed: {:?}", factor);
+                surface.request(window.inner_size());
+            }
+
+            let fast_forward_shown =
+                fast_forward.or(fast_forward_held.then_some(fast_forward_speed));
+            if fast_forward_shown.unwrap_or(1) != speed {
+                speed = fast_forward_shown.unwrap_or(1);
+                sim.send(SimCommand::Speed(speed));
             }
 
+            let world_stats = status.world_stats.map(|stats| {
+                let bounds = match stats.bounds {
+                    Some((x0, y0, x1, y1)) => {
+                        format!("{} x {} at ({}, {})", x1 - x0 + 1, y1 - y0 + 1, x0, y0)
+                    }
+                    None => "empty".to_string(),
+                };
+                let (x, y) = stats.view;
+                format!(
+                    "pop {}, bounds {}, view ({}, {})",
+                    stats.population, bounds, x, y
+                )
+            });
+            // Teams, Wireworld and rule tables only run on a torus, so it's
+            // never both.
+            let world_stats = world_stats.or_else(|| match options.automaton {
+                Automaton::Wireworld => Some(format!("drawing {}", WIRE_NAMES[material as usize])),
+                Automaton::Table => Some(format!("drawing state {}", material)),
+                _ => None,
+            });
+            let world_stats = world_stats.or_else(|| {
+                let teams = options.automaton.teams();
+                (teams > 1).then(|| {
+                    let counts: Vec<String> = (0..teams)
+                        .map(|t| format!("{} {}", TEAM_NAMES[t], status.teams[t]))
+                        .collect();
+                    format!(
+                        "{}, drawing {}",
+                        counts.join(", "),
+                        TEAM_NAMES[team as usize]
+                    )
+                })
+            });
+            // Gliders always, once they're looked for, and the rest as
+            // they turn up.
+            let ships = status.ships.map(|counts| {
+                let seen: Vec<String> = Ship::ALL
+                    .iter()
+                    .zip(counts)
+                    .filter(|&(&ship, n)| ship == Ship::Glider || n > 0)
+                    .map(|(ship, n)| format!("{} {}", n, ship.plural()))
+                    .collect();
+                seen.join(", ")
+            });
+            let world_stats = match (world_stats, ships) {
+                (Some(stats), Some(ships)) => Some(format!("{}, {}", stats, ships)),
+                (stats, ships) => stats.or(ships),
+            };
+            if stamp != shown_stamp
+                || rule_title != shown_rule_title
+                || fast_forward_shown != shown_fast_forward
+                || world_stats != shown_world_stats
+                || status.history_truncated != shown_truncated
+            {
+                window.set_title(&window_title(
+                    grid_size,
+                    &rule_title,
+                    options.stochastic,
+                    stamp,
+                    world_stats.as_deref(),
+                    fast_forward_shown,
+                    status.history_truncated,
+                ));
+                shown_stamp = stamp;
+                shown_fast_forward = fast_forward_shown;
+                shown_world_stats = world_stats;
+                shown_rule_title = rule_title.clone();
+                shown_truncated = status.history_truncated;
+            }
+
+            // The config file, when it's been edited: whatever changed in
+            // it is applied as if from the keys, except what's only read at
+            // startup. One that doesn't read cleanly changes nothing.
+            if let Some(reloaded) = config_watch.as_mut().and_then(config::Watcher::check) {
+                match reloaded {
+                    Ok((old, new)) => {
+                        let mut applied = Vec::new();
+                        if new.keys != old.keys {
+                            keys = new.keys.clone();
+                            help = help_entries(&keys);
+                            applied.push("keys");
+                        }
+                        let colors = |s: &Settings| {
+                            (s.heat_colors.clone(), s.brain_colors, s.wireworld_colors)
+                        };
+                        if colors(&new) != colors(&old) {
+                            sim.send(SimCommand::Colors {
+                                heat_colors: Box::new(new.heat_colors.clone()),
+                                palette: palette(options.automaton, &new),
+                            });
+                            applied.push("colors");
+                        }
+                        if (new.age_colors, &new.age_gradient)
+                            != (old.age_colors, &old.age_gradient)
+                        {
+                            if new.age_colors != old.age_colors {
+                                age_colors = new.age_colors;
+                            }
+                            settings.age_gradient = new.age_gradient.clone();
+                            let gradient = age_colors.then(|| settings.age_gradient.clone());
+                            sim.send(SimCommand::AgeColors(gradient));
+                            applied.push("age colors");
+                        }
+                        if new.trails != old.trails {
+                            status.trails = new.trails;
+                            sim.send(SimCommand::Event(session::Event::Trails(status.trails)));
+                            applied.push("trails");
+                        }
+                        if new.decay != old.decay {
+                            status.decay = new.decay;
+                            sim.send(SimCommand::Event(session::Event::Decay(new.decay)));
+                            applied.push("decay");
+                        }
+                        if new.grid_line_color != old.grid_line_color {
+                            settings.grid_line_color = new.grid_line_color;
+                            grid_lines = None;
+                            applied.push("grid lines");
+                        }
+                        if new.kill_probability != old.kill_probability {
+                            kill_probability = new.kill_probability;
+                            applied.push("kill probability");
+                        }
+                        if new.tps != old.tps {
+                            sim.send(SimCommand::Tps(new.tps));
+                            let tick = Duration::from_secs_f64(1.0 / new.tps as f64);
+                            crossfade = Crossfade::new(tick, refresh, !options.no_smooth);
+                            applied.push("tps");
+                        }
+                        if new.pause_on_unfocus != old.pause_on_unfocus {
+                            settings.pause_on_unfocus =
+                                new.pause_on_unfocus || options.pause_on_unfocus;
+                            applied.push("pause on unfocus");
+                        }
+                        if new.noise != old.noise {
+                            settings.noise = new.noise;
+                            if status.noise != Noise::Off {
+                                status.noise = new.noise;
+                                sim.send(SimCommand::Event(session::Event::Noise(new.noise)));
+                            }
+                            applied.push("noise");
+                        }
+                        if applied.is_empty() {
+                            log::info!("Reloaded the config; nothing that applies changed");
+                        } else {
+                            log::info!("Reloaded the config: {}", applied.join(", "));
+                        }
+                        let restart = [
+                            ("[world] topology", new.topology != old.topology),
+                            ("[stable]", new.pause_on_stable != old.pause_on_stable),
+                        ];
+                        for (what, _) in restart.iter().filter(|(_, changed)| *changed) {
+                            log::warn!("{} changed: restart required to apply it", what);
+                        }
+                    }
+                    Err(e) => error!("Kept the config as it was: {}", e),
+                }
+            }
+
+            // --timings, once it's due.
+            if let Some(timings) = &timings {
+                let now = instant::Instant::now();
+                if now >= next_timings {
+                    let report = timings.report();
+                    log::info!(
+                        "Timings over the latest {} samples of each:",
+                        timings::WINDOW
+                    );
+                    for line in report.table() {
+                        log::info!("{}", line);
+                    }
+                    timings_shown = Some(report.to_string());
+                    next_timings = now + TIMINGS_INTERVAL;
+                }
+            }
+
+            // --autosave, once it's due and no stroke, drag or paste is
+            // under way.
+            let editing = brushing
+                || spraying
+                || rect_drag.is_some()
+                || pan_drag.is_some()
+                || pad_stroking
+                || touches.active()
+                || (paste.is_some() && stamp.is_none());
+            if next_autosave.is_some_and(|at| instant::Instant::now() >= at) && !editing {
+                sim.send(SimCommand::Autosave {
+                    fast_forward,
+                    age_colors,
+                });
+                next_autosave = autosave_every.map(|every| instant::Instant::now() + every);
+            }
+
+            // Sleeps until the next frame or input, but keeps waking while
+            // the airbrush, the arrows or a gamepad's stick are held down,
+            // or a generation is fading in, and for the next autosave, a
+            // look at the config file and a finger that's waiting to draw.
+            *control_flow = if spraying || panning || pad_steering {
+                ControlFlow::WaitUntil(instant::Instant::now() + HELD_INPUT_INTERVAL)
+            } else if crossfade.fading() {
+                ControlFlow::WaitUntil(instant::Instant::now() + refresh)
+            } else {
+                sim.control_flow()
+            };
+            let wake_at = next_autosave
+                .into_iter()
+                .chain(config_watch.as_ref().map(config::Watcher::next_check))
+                .chain(touches.next_check())
+                .min();
+            if let (ControlFlow::Wait, Some(at)) = (*control_flow, wake_at) {
+                *control_flow = ControlFlow::WaitUntil(at);
+            }
             window.request_redraw();
         }
     });
 }
 
-/// Generate a pseudorandom seed for the game's PRNG.
-fn generate_seed() -> (u64, u64) {
-    use byteorder::{ByteOrder, NativeEndian};
-    use getrandom::getrandom;
-
-    let mut seed = [0_u8; 16];
-
-    getrandom(&mut seed).expect("failed to getrandom");
+/// `tgol convert`: renders an RLE pattern file to an SVG file.
+fn convert(
+    input: &Path,
+    output: &Path,
+    options: &SvgOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(input)
+        .map_err(|e| format!("couldn't read {}: {}", input.display(), e))?;
+    let pattern = rle::parse(&text)?;
+    // The rule the header names, written the usual way if it's Life-like.
+    let rule = match rle::rule(&text) {
+        Some(rule) => rule
+            .parse::<Rule>()
+            .map_or_else(|_| rule.to_string(), |rule| rule.to_string()),
+        None => Rule::default().to_string(),
+    };
 
-    (
-        NativeEndian::read_u64(&seed[0..8]),
-        NativeEndian::read_u64(&seed[8..16]),
-    )
+    let svg = svg::render(
+        pattern.width(),
+        pattern.height(),
+        |x, y| pattern.get(x, y),
+        options,
+        0,
+        &rule,
+    );
+    std::fs::write(output, svg)
+        .map_err(|e| format!("couldn't write {}: {}", output.display(), e))?;
+
+    Ok(())
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-struct Cell {
-    // Alive: Is this cell active or not
-    alive: bool,
+// Seed for --bench when --seed isn't given, so runs are comparable.
+const BENCH_SEED: u64 = 0;
+
+/// `--bench`: runs `generations` updates on a soup without opening a window
+/// and prints how long they took, for each engine (or just `--engine`).
+/// Every engine starts from the same soup, and those that wrap around the
+/// edges are checked to agree.
+/// Then the same for drawing each generation, in full and just the dirty
+/// cells, and then undo history on a big grid.
+fn bench(options: &Options, generations: u64) {
+    let (width, height) = options.grid_size();
+    // An infinite world steps the same way whatever the engine.
+    let engines = match options.engine {
+        _ if options.world == World::Infinite => vec![Engine::default()],
+        Some(engine) => vec![engine],
+        None => Engine::ALL.to_vec(),
+    };
 
-    // Heat: Trailing effect of the cell. Decays over time.
-    heat: u8,
-}
+    println!("{} x {} grid, {} generations", width, height, generations);
 
-impl Cell {
-    // Initialize a new cell (alive or dead)
-    fn new(alive: bool) -> Self {
-        let heat = if alive { 255 } else { 0 };
-        Self {
-            alive: alive,
-            heat: heat,
+    let mut reference: Option<(Engine, Cells)> = None;
+    for engine in engines {
+        let mut life = starting_grid(options, (width, height), options.seed.unwrap_or(BENCH_SEED));
+        life.engine = engine;
+
+        let timings = options
+            .timings
+            .then(|| Timings::with_window(generations as usize));
+        let start = Instant::now();
+        for _ in 0..generations {
+            timings::time(timings.as_ref(), Phase::Update, || life.update());
         }
-    }
+        let secs = start.elapsed().as_secs_f64();
 
-    // cools off a cell, returns T if the cell was alive
-    // but has died. Otherwise false.
-    fn cool_if_dead(&mut self, subtract_count: u8) {
-        if !self.alive && self.heat > 0 {
-            self.heat = self.heat.saturating_sub(subtract_count);
+        let cells = life.grid.len() as f64 * generations as f64;
+        let name = match options.world {
+            World::Infinite => World::Infinite.to_string(),
+            World::Torus => engine.to_string(),
+        };
+        println!(
+            "{:>8}: {:.3} s, {:.1} generations/s, {:.0} cells/s",
+            name,
+            secs,
+            generations as f64 / secs,
+            cells / secs
+        );
+
+        print_timings(timings.as_ref());
+
+        if !engine.wraps() {
+            println!("          ({} doesn't wrap, so it isn't compared)", engine);
+            continue;
+        }
+        match &reference {
+            Some((first, grid)) if *grid != life.grid => {
+                println!("warning: {} and {} engines disagree", first, engine);
+            }
+            Some(_) => {}
+            None => reference = Some((engine, life.grid)),
         }
     }
 
-    fn set(&mut self, alive: bool) {
-        self.alive = alive;
-
-        if self.alive {
-            self.heat = 255;
+    // Drawing every generation: the whole frame, then only the dirty cells.
+    let mut frames = Vec::new();
+    for dirty in [false, true] {
+        let mut life = starting_grid(options, (width, height), options.seed.unwrap_or(BENCH_SEED));
+        let mut frame = vec![0; 4 * life.grid.len()];
+        life.draw(&mut frame);
+        life.clear_dirty();
+
+        let timings = options
+            .timings
+            .then(|| Timings::with_window(generations as usize));
+        let start = Instant::now();
+        for _ in 0..generations {
+            timings::time(timings.as_ref(), Phase::Update, || life.update());
+            timings::time(timings.as_ref(), Phase::Draw, || match life.dirty_cells() {
+                Some(cells) if dirty => life.draw_dirty(&mut frame, cells),
+                _ => life.draw(&mut frame),
+            });
+            life.clear_dirty();
         }
+        let secs = start.elapsed().as_secs_f64();
+
+        println!(
+            "{:>8}: {:.3} s with drawing, {:.1} frames/s",
+            if dirty { "dirty" } else { "full" },
+            secs,
+            generations as f64 / secs
+        );
+        print_timings(timings.as_ref());
+        frames.push(frame);
+    }
+    if frames[0] != frames[1] {
+        println!("warning: full and dirty redraws disagree");
     }
-}
-
-const CELL_ALIVE_THRESHOLD: f32 = 0.3;
 
-struct Grid {
-    grid: Vec<Cell>,
-    width: usize,
-    height: usize,
+    bench_history();
 }
 
-impl Grid {
-    fn update(&mut self) {
-        //
-        // Allocate a new grid (only swap out after computation has finished.
-        // This way we don't get any 'tearing' if we want to extend this routine
-        // to be multithreaded. For situations like iterating over a clock.
-        //
-        let size = self
-            .width
-            .checked_mul(self.height)
-            .expect("Grid too big (overflow)");
-
-        // let mut grid_tmp: Vec<Cell> = vec![Cell::default(); size];
-        let mut grid_tmp = self.grid.clone();
-
-        //
-        // Compute, figure out what the next grid frame is going to look like.
-        //
-
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let neighbors_alive = self.count_neighbors(x, y);
-
-                if let Some(cell) = self.grid_idx(x, y) {
-                    // RULE #1: Any live cell with two or three live neighbours survives.
-                    // RULE #2: Any dead cell with three live neighbours becomes a live cell.
-                    // RULE #3: All other live cells die in the next generation. Similarly, all other dead cells stay dead.
-                    if self.grid[cell].alive {
-                        if neighbors_alive == 2 || neighbors_alive == 3 {
-                            grid_tmp[cell].set(true); // RULE # 1
-                            continue;
-                        }
-                    } else {
-                        if neighbors_alive == 3 {
-                            grid_tmp[cell].set(true); // RULE #2
-                            continue;
-                        }
-                    }
+// Grid the undo history is timed on, whatever the --bench grid is.
+const HISTORY_BENCH_SIZE: usize = 4096;
+// Strokes pushed, each a 4000 x 25 band: 100k cells.
+const HISTORY_BENCH_STROKES: usize = 20;
+
+// Pushing big strokes onto the undo history, then undoing and redoing
+// them all, timed one at a time as [Ctrl+Z] and [Ctrl+Y] would be.
+fn bench_history() {
+    let size = HISTORY_BENCH_SIZE;
+    let mut life = Grid::new_empty_grid(size, size);
+    let mut history = History::default();
+    println!(
+        "{} x {} grid, {} strokes of 100000 cells",
+        size, size, HISTORY_BENCH_STROKES
+    );
+
+    let mut timed = |f: &mut dyn FnMut(&mut Grid, &mut History, usize)| {
+        (0..HISTORY_BENCH_STROKES)
+            .map(|n| {
+                let start = Instant::now();
+                f(&mut life, &mut history, n);
+                start.elapsed()
+            })
+            .collect::<Vec<Duration>>()
+    };
+    let push = timed(&mut |life, history, n| {
+        let y = n as isize * 25;
+        history.record_one(life, |grid| grid.set_rect(0, y, 3999, y + 24, true));
+    });
+    let undo = timed(&mut |life, history, _| {
+        history.undo(life);
+    });
+    let redo = timed(&mut |life, history, _| {
+        history.redo(life);
+    });
 
-                    grid_tmp[cell].set(false); // RULE #3
-                    grid_tmp[cell].cool_if_dead(50);
-                } else {
-                    assert!(false);
-                }
-            }
-        }
+    for (name, times) in [("push", push), ("undo", undo), ("redo", redo)] {
+        let total: Duration = times.iter().sum();
+        let max = times.iter().max().copied().unwrap_or_default();
+        println!(
+            "{:>8}: {:.3} ms mean, {:.3} ms max",
+            name,
+            total.as_secs_f64() * 1000.0 / times.len() as f64,
+            max.as_secs_f64() * 1000.0
+        );
+    }
+    let alive = life.grid.alive.iter().filter(|&&alive| alive).count();
+    if alive != HISTORY_BENCH_STROKES * 100_000 || history.truncated() {
+        println!("warning: undo and redo didn't give back every stroke");
+    }
+}
 
-        //
-        // SWAP, Compute finished.. swap out to the new graph.
-        //
-        std::mem::swap(&mut grid_tmp, &mut self.grid);
-    }
-
-    fn count_neighbors(&self, x: usize, y: usize) -> usize {
-        //
-        // final two sets of coords. an (x1, y1)
-        // that indicates the coords of the neighboring
-        // grid (UP-LEFT) and another set of coords (x2, y2)
-        // that represents the coords to the (BOTTOM-RIGHT)
-        //
-
-        let (xm1, xp1) = if x == 0 {
-            (self.width - 1, x + 1)
-        } else if x == self.width - 1 {
-            (x - 1, 0)
-        } else {
-            (x - 1, x + 1)
-        };
+// --bench --timings: the run's per-generation timings, under its line.
+fn print_timings(timings: Option<&Timings>) {
+    for line in timings.iter().flat_map(|timings| timings.report().table()) {
+        println!("          {}", line);
+    }
+}
 
-        let (ym1, yp1) = if y == 0 {
-            (self.height - 1, y + 1)
-        } else if y == self.height - 1 {
-            (y - 1, 0)
-        } else {
-            (y - 1, y + 1)
-        };
+// Largest pattern file that's read.
+const MAX_PATTERN_FILE_BYTES: u64 = 16 << 20;
 
-        //
-        // This is a fancy way to add up all the neighboring
-        // cells. If they are alive.
-        //
-        self.grid[xm1 + ym1 * self.width].alive as usize
-            + self.grid[x + ym1 * self.width].alive as usize
-            + self.grid[xp1 + ym1 * self.width].alive as usize
-            + self.grid[xm1 + y * self.width].alive as usize
-            + self.grid[xp1 + y * self.width].alive as usize
-            + self.grid[xm1 + yp1 * self.width].alive as usize
-            + self.grid[x + yp1 * self.width].alive as usize
-            + self.grid[xp1 + yp1 * self.width].alive as usize
-    }
-
-    fn new_empty_grid(width: usize, height: usize) -> Self {
-        let size = width.checked_mul(height).expect("Grid too big (overflow)");
-        Self {
-            grid: vec![Cell::default(); size],
-            width,
-            height,
-        }
+// Reads a pattern file in any format tgol knows, telling which from its
+// contents, or with a path of `-` a pattern piped in on stdin.
+fn load_pattern(path: &Path) -> Result<PatternBuffer, String> {
+    if path == Path::new("-") {
+        return pattern_text::parse(&read_stdin()?);
     }
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > MAX_PATTERN_FILE_BYTES {
+        return Err(format!("over {} MiB", MAX_PATTERN_FILE_BYTES >> 20));
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    pattern_text::parse(&text)
+}
 
-    fn randomize(&mut self) {
-        let mut rand: randomize::PCG32 = generate_seed().into();
-
-        for cell in self.grid.iter_mut() {
-            let alive = randomize::f32_half_open_right(rand.next_u32()) > CELL_ALIVE_THRESHOLD;
-            *cell = Cell::new(alive);
-        }
+// All of stdin, as long as it's piped in: a terminal would wait for
+// typing that never comes.
+fn read_stdin() -> Result<String, String> {
+    use std::io::{IsTerminal, Read};
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err(
+            "stdin is a terminal; pipe a pattern in, like `curl .../glider.rle | tgol -`"
+                .to_string(),
+        );
+    }
+    let mut text = String::new();
+    stdin
+        .lock()
+        .take(MAX_PATTERN_FILE_BYTES + 1)
+        .read_to_string(&mut text)
+        .map_err(|e| e.to_string())?;
+    if text.len() as u64 > MAX_PATTERN_FILE_BYTES {
+        return Err(format!("over {} MiB", MAX_PATTERN_FILE_BYTES >> 20));
+    }
+    Ok(text)
+}
 
-        self.normalize(5);
+// Logs the explorer's breadcrumb trail, oldest rule first.
+fn log_rule_trail(trail: &[Rule], current: Rule) {
+    let mut line = String::new();
+    for rule in trail {
+        line.push_str(&format!("{} > ", rule));
     }
+    log::info!("Rule trail: {}[{}]", line, current);
+}
 
-    fn randomly_kill(&mut self) -> u32 {
-        let mut rand: randomize::PCG32 = generate_seed().into();
-        let mut kill_count: u32 = 0;
+// The --seed seed, or a fresh one. Logged either way, so a good session can
+// be replayed.
+fn session_seed(options: &Options) -> u64 {
+    let seed = options.seed.unwrap_or_else(generate_seed);
+    log::info!(
+        "Seed: {} (pass --seed {} to replay this session)",
+        seed,
+        seed
+    );
+    seed
+}
 
-        for cell in self.grid.iter_mut() {
-            if cell.alive {
-                let kill = randomize::f32_half_open_right(rand.next_u32()) > CELL_ALIVE_THRESHOLD;
-                if kill {
-                    cell.set(false);
-                    kill_count += 1;
-                }
-            }
-        }
+// Where each --pattern goes on a `width` x `height` grid: its --at, or
+// centered.
+fn placements(
+    options: &Options,
+    (width, height): (usize, usize),
+) -> impl Iterator<Item = (&PatternBuffer, isize, isize)> + '_ {
+    let center = ((width / 2) as isize, (height / 2) as isize);
+    options.patterns.iter().map(move |placement| {
+        let (x, y) = placement
+            .at
+            .unwrap_or_else(|| placement.pattern.origin_centered_on(center));
+        (&placement.pattern, x, y)
+    })
+}
 
-        kill_count
+// The config's colors and trails, for a grid about to be shown.
+fn apply_settings(life: &mut Grid, settings: &Settings) {
+    life.palette = palette(life.automaton, settings);
+    life.decay = settings.decay;
+    life.trails = settings.trails;
+    life.heat_colors = settings.heat_colors.clone();
+    if settings.age_colors {
+        life.age_colors = Some(settings.age_gradient.clone());
     }
+}
 
-    // const GREEN: [u8; 4] = [0, 255, 0, 255];
-    // const RED: [u8; 4] = [255, 0, 0, 255];
-    // const BLUE: [u8; 4] = [0, 0, 255, 255];
-    // const YELLOW: [u8; 4] = [255, 255, 0, 255];
-
-    fn draw(&self, screen: &mut [u8]) {
-        debug_assert_eq!(screen.len(), 4 * self.grid.len());
+// The config's colors for an automaton drawn from a palette; empty for one
+// that isn't.
+fn palette(automaton: Automaton, settings: &Settings) -> Vec<[u8; 4]> {
+    match automaton {
+        Automaton::Brain => settings.brain_colors.to_vec(),
+        Automaton::Wireworld => settings.wireworld_colors.to_vec(),
+        _ => Vec::new(),
+    }
+}
 
-        for (cell, pix) in self.grid.iter().zip(screen.chunks_exact_mut(4)) {
-            let color = if !cell.alive {
-                [
-                    cell.heat.saturating_sub(100),
-                    0,
-                    cell.heat.saturating_sub(30),
-                    cell.heat.saturating_sub(30),
-                ]
-            } else {
-                [50, 0, 0xff, 0xff]
-            };
+// The help ([F1]): the key bindings in use, then the mouse's.
+fn help_entries(keys: &KeyMap) -> Vec<(String, String)> {
+    keys.help()
+        .into_iter()
+        .chain(MOUSE_HELP.map(|(keys, what)| (keys.to_string(), what.to_string())))
+        .collect()
+}
 
-            pix.copy_from_slice(&color);
-        }
+// A `width` x `height` grid holding the settled soup `seed` makes the way
+// the options ask (rule, density, symmetry, pre-roll), unless it's --empty,
+// and then any --patterns. It's `Grid::new_soup`'s, noise and chances in
+// the pre-roll included, so [Shift+R] with the same seed makes it again.
+// Generations are counted from there: the settled soup is generation 0.
+// The patterns wrap around a torus's edges, or land off the view of an
+// infinite world.
+fn starting_grid(options: &Options, (width, height): (u32, u32), seed: u64) -> Grid {
+    let mut life = Grid::new_empty_grid(width as usize, height as usize);
+    life.rule = options.rule;
+    life.automaton = options.automaton;
+    if let Some(table) = &options.rule_table {
+        life.rule_table = Some(Arc::clone(table));
+        life.material = 1;
+    }
+    life.ant_steps = options.ant_steps;
+    life.decay = options.decay.unwrap_or_default();
+    life.engine = options.engine.unwrap_or_default();
+    life.set_world(options.world);
+    life.topology = options.topology.unwrap_or_default();
+    life.noise = options.noise;
+    life.noise_seed = seed;
+    life.stochastic = options.stochastic;
+    // Langton's Ant starts from a blank grid, with one ant in the middle.
+    if options.automaton == Automaton::Ant {
+        life.add_ant(width as isize / 2, height as isize / 2);
+    } else if !options.empty {
+        life.new_soup(seed, options.density, options.symmetry, options.pre_roll);
+        life.generation = 0;
     }
+    for (pattern, x, y) in placements(options, (life.width, life.height)) {
+        life.insert_pattern(pattern, x, y);
+    }
+    life
+}
 
-    fn toggle(&mut self, x: isize, y: isize) -> bool {
-        if let Some(i) = self.grid_idx(x, y) {
-            if self.grid[i].alive {
-                self.grid[i].set(false);
-                false
-            } else {
-                self.grid[i].set(true);
-                true
-            }
-        } else {
-            false
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_rulefile_runs_as_the_table_automaton() {
+        let path = std::env::temp_dir().join(format!("tgol-rulefile-{}.rule", std::process::id()));
+        let table = "@RULE Test\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:permute\n\
+                     0,1,1,1,0,0,0,0,0,1\n";
+        std::fs::write(&path, table).unwrap();
+        let rulefile = path.to_str().unwrap();
+        let options = Options::from_args(&["--rulefile", rulefile]);
+        let brain = Options::from_args(&["--rulefile", rulefile, "--automaton", "brain"]);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(options.unwrap().automaton, Automaton::Table);
+        assert!(brain.is_err());
+        assert!(Options::from_args(&["--automaton", "table"]).is_err());
     }
 
-    fn set_line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, alive: bool) {
-        let x0 = x0.max(0).min(self.width as isize);
-        let y0 = y0.max(0).min(self.height as isize);
-        for (x, y) in line_drawing::Bresenham::new((x0, y0), (x1, y1)) {
-            if let Some(i) = self.grid_idx(x, y) {
-                if !self.grid[i].alive {
-                    self.grid[i].set(true);
-                }
-            } else {
-                break;
-            }
+    #[test]
+    fn generations_count_from_the_settled_soup() {
+        let options = Options::from_args(&["--seed", "4", "--pre-roll", "7"]).unwrap();
+        let mut life = starting_grid(&options, (40, 30), 4);
+        assert_eq!(life.generation(), 0);
+        for generation in 1..=3 {
+            assert_eq!(life.update().generation, generation);
         }
     }
 
-    fn normalize(&mut self, generations: usize) {
-        // Kill of a random amount of the cells. The grid starts too noisy.
-        self.randomly_kill();
-
-        // Pass x amount of generations.
-        for _ in 0..generations {
-            self.update();
-        }
+    #[test]
+    fn shift_r_with_the_same_seed_makes_the_starting_soup_again() {
+        let args = [
+            "--seed",
+            "9",
+            "--noise",
+            "3",
+            "--stochastic",
+            "pb=0.9",
+            "--pre-roll",
+            "12",
+        ];
+        let options = Options::from_args(&args).unwrap();
+        let start = starting_grid(&options, (48, 32), 9);
+
+        let mut life = starting_grid(&options, (48, 32), 9);
+        life.normalize(20);
+        life.new_soup(9, options.density, options.symmetry, options.pre_roll);
+        assert_eq!(life.grid.alive, start.grid.alive);
+        assert_eq!(life.grid.heat, start.grid.heat);
+    }
 
-        // Now we need to cool off the heatmap that is leftover
-        // Otherwise is looks messy.
-        for cell in self.grid.iter_mut() {
-            if !cell.alive {
-                cell.heat = 0;
-            }
+    #[test]
+    fn converted_patterns_keep_their_rule() {
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let files = [
+            ("x = 3, y = 1, rule = B36/S23\n3o!\n", "B36/S23"),
+            ("#C rule-less\nx = 3, y = 1\n3o!\n", "B3/S23"),
+            ("x = 3, y = 1, rule = WireWorld\n3A!\n", "WireWorld"),
+        ];
+        for (n, (rle, rule)) in files.into_iter().enumerate() {
+            let input = dir.join(format!("tgol-convert-{}-{}.rle", id, n));
+            let output = dir.join(format!("tgol-convert-{}-{}.svg", id, n));
+            std::fs::write(&input, rle).unwrap();
+            let converted = convert(&input, &output, &SvgOptions::default());
+            let svg = std::fs::read_to_string(&output);
+            let _ = std::fs::remove_file(&input);
+            let _ = std::fs::remove_file(&output);
+            converted.unwrap();
+            let metadata = format!(r#"generation="0" rule="{}""#, rule);
+            assert!(svg.unwrap().contains(&metadata), "{}", rle);
         }
     }
 
-    fn grid_idx<I: std::convert::TryInto<usize>>(&self, x: I, y: I) -> Option<usize> {
-        if let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) {
-            if x < self.width && y < self.height {
-                Some(x + y * self.width)
-            } else {
-                None
-            }
-        } else {
-            None
+    #[test]
+    fn pattern_files_load_whatever_their_format_or_extension() {
+        // Dropped files and --pattern files are told by their contents.
+        let glider = PatternBuffer::from_rows(&[".#.", "..#", "###"]);
+        let files = [
+            (
+                "rle",
+                "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n",
+            ),
+            ("cells", "!Name: Glider\n.O\n..O\nOOO\n"),
+            ("lif", "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n"),
+            ("txt", "x = 3, y = 3\r\nbob$2bo$3o!\r\n"),
+        ];
+        for (extension, text) in files {
+            let path = std::env::temp_dir().join(format!(
+                "tgol-pattern-{}.{}",
+                std::process::id(),
+                extension
+            ));
+            std::fs::write(&path, text).unwrap();
+            let loaded = load_pattern(&path);
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(loaded, Ok(glider.clone()), ".{}", extension);
         }
+
+        let path = std::env::temp_dir().join(format!("tgol-pattern-{}.png", std::process::id()));
+        std::fs::write(&path, [0x89, b'P', b'N', b'G', 0, 0xff]).unwrap();
+        let loaded = load_pattern(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(loaded.is_err());
     }
 }
@@ -0,0 +1,137 @@
+//
+// The mini-map ([Tab]): the whole grid, or as much of an infinite world as
+// has anything in it, shrunk into a corner of the window. Each map pixel
+// stands for a square block of cells and is as bright as the share of
+// them that's alive, and any live cell at all lifts it well off the
+// backdrop, so a lone glider far out still shows. The view is outlined on
+// top; in a bounded world that's the whole map. It's counted up on the
+// simulation thread and drawn over frames by `Overlay`.
+//
+
+/// Brightness of a map pixel with a single live cell in its block.
+const MIN_LIT: u64 = 96;
+
+/// A shrunk-down picture of the world, and where the view is on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MiniMap {
+    pub width: usize,
+    pub height: usize,
+    /// Cells along each side of the block a map pixel stands for.
+    pub scale: usize,
+    /// World coordinates of the top-left map pixel's block.
+    pub origin: (i64, i64),
+    /// Brightness of each map pixel, row by row; 0 for an empty block.
+    pub density: Vec<u8>,
+    /// The view's (x, y, width, height), in world cells.
+    pub view: (i64, i64, usize, usize),
+}
+
+impl MiniMap {
+    /// Maps the (x, y, width, height) `extent` of the world in blocks big
+    /// enough to fit in `max` (width, height) pixels, from the world
+    /// coordinates of its live cells. Cells outside `extent` are left out.
+    pub fn new(
+        extent: (i64, i64, usize, usize),
+        max: (usize, usize),
+        view: (i64, i64, usize, usize),
+        live: impl Iterator<Item = (i64, i64)>,
+    ) -> Self {
+        let (x0, y0, w, h) = extent;
+        let scale = w
+            .div_ceil(max.0.max(1))
+            .max(h.div_ceil(max.1.max(1)))
+            .max(1);
+        let (width, height) = (w.div_ceil(scale), h.div_ceil(scale));
+
+        let mut counts = vec![0u32; width * height];
+        for (x, y) in live {
+            let (dx, dy) = (x - x0, y - y0);
+            if (0..w as i64).contains(&dx) && (0..h as i64).contains(&dy) {
+                let (bx, by) = (dx as usize / scale, dy as usize / scale);
+                counts[bx + by * width] += 1;
+            }
+        }
+
+        let block = (scale as u64).pow(2);
+        let density = counts
+            .iter()
+            .map(|&n| match n as u64 {
+                0 => 0,
+                n => (MIN_LIT + (255 - MIN_LIT) * n / block).min(255) as u8,
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            scale,
+            origin: (x0, y0),
+            density,
+            view,
+        }
+    }
+
+    /// World coordinates of the middle of the block map pixel (x, y)
+    /// stands for.
+    pub fn world_at(&self, x: usize, y: usize) -> (i64, i64) {
+        let half = (self.scale / 2) as i64;
+        (
+            self.origin.0 + (x * self.scale) as i64 + half,
+            self.origin.1 + (y * self.scale) as i64 + half,
+        )
+    }
+
+    /// Inclusive (xmin, ymin, xmax, ymax) map pixels the view covers, which
+    /// can run off the map.
+    pub fn view_rect(&self) -> (i64, i64, i64, i64) {
+        let (x, y, w, h) = self.view;
+        let scale = self.scale as i64;
+        let (ox, oy) = self.origin;
+        (
+            (x - ox).div_euclid(scale),
+            (y - oy).div_euclid(scale),
+            (x + w as i64 - 1 - ox).div_euclid(scale),
+            (y + h as i64 - 1 - oy).div_euclid(scale),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_as_bright_as_their_share_alive() {
+        let mut live = vec![(0, 0), (400, 0), (-1, 5)];
+        live.extend((4..8).flat_map(|x| (0..4).map(move |y| (x, y))));
+        live.extend((8..12).flat_map(|x| (0..2).map(move |y| (x, y))));
+        let view = (0, 0, 384, 240);
+        let map = MiniMap::new(view, (96, 64), view, live.into_iter());
+        assert_eq!((map.width, map.height, map.scale), (96, 60, 4));
+        // One cell is well off the backdrop; then a full block, half a
+        // block. What's off the map is left out.
+        assert_eq!(map.density[..4], [105, 255, 175, 0]);
+        assert_eq!(map.density.iter().filter(|&&lit| lit > 0).count(), 3);
+    }
+
+    #[test]
+    fn pixels_map_back_to_the_middle_of_their_block() {
+        let map = MiniMap::new(
+            (-10, -20, 40, 40),
+            (10, 10),
+            (-10, -20, 8, 4),
+            std::iter::empty(),
+        );
+        assert_eq!(map.scale, 4);
+        assert_eq!(map.world_at(0, 0), (-8, -18));
+        assert_eq!(map.world_at(1, 2), (-4, -10));
+        assert_eq!(map.view_rect(), (0, 0, 1, 0));
+
+        // A view hanging off the map's edge runs off it.
+        let map = MiniMap {
+            view: (-14, -20, 8, 4),
+            ..map
+        };
+        assert_eq!(map.view_rect(), (-1, 0, 0, 0));
+    }
+}
@@ -0,0 +1,133 @@
+//
+// `--noise`: cells flipped at random every generation, so the grid never
+// quite settles. Written as a number of cells per generation (`20`), or as
+// the chance any one cell flips (`0.01%`). `0` is off.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Noise {
+    #[default]
+    Off,
+    /// This many cells (picked with replacement) flip every generation.
+    Cells(u32),
+    /// Every cell flips with this chance, in percent, every generation.
+    Percent(f64),
+}
+
+impl Noise {
+    /// Calls `flip` with the index of each of `cells` cells to flip this
+    /// generation. Does nothing at all while off.
+    pub fn pick(self, cells: usize, rng: &mut randomize::PCG32, mut flip: impl FnMut(usize)) {
+        if cells == 0 {
+            return;
+        }
+        match self {
+            Self::Off => {}
+            Self::Cells(count) => {
+                let range = randomize::RandRangeU32::new(0, (cells - 1) as u32);
+                for _ in 0..count {
+                    flip(range.sample(rng) as usize);
+                }
+            }
+            Self::Percent(percent) => {
+                // Skips straight from one flipped cell to the next (the
+                // gaps are geometrically distributed), so a low chance on a
+                // big grid costs next to nothing.
+                let log_miss = (-percent / 100.0).ln_1p();
+                let mut i = 0_usize;
+                loop {
+                    let u = randomize::f64_half_open_right(crate::rng::next_seed(rng));
+                    let gap = ((-u).ln_1p() / log_miss) as usize;
+                    i = match i.checked_add(gap) {
+                        Some(i) if i < cells => i,
+                        _ => return,
+                    };
+                    flip(i);
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "0"),
+            Self::Cells(count) => write!(f, "{}", count),
+            Self::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl FromStr for Noise {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(percent) = s.strip_suffix('%') {
+            return match percent.trim().parse::<f64>() {
+                Ok(0.0) => Ok(Self::Off),
+                Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(Self::Percent(percent)),
+                _ => Err(format!(
+                    "bad noise {:?} (expected a percentage of cells, up to 100%)",
+                    s
+                )),
+            };
+        }
+        match s.parse::<u32>() {
+            Ok(0) => Ok(Self::Off),
+            Ok(count) => Ok(Self::Cells(count)),
+            _ => Err(format!(
+                "bad noise {:?} (expected cells per generation, or a percentage like 0.01%)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::session_rng;
+
+    fn flips(noise: Noise, cells: usize) -> Vec<usize> {
+        let mut picked = Vec::new();
+        noise.pick(cells, &mut session_rng(8), |i| picked.push(i));
+        picked
+    }
+
+    #[test]
+    fn percentages_pick_each_cell_at_most_once() {
+        assert_eq!(
+            flips(Noise::Percent(100.0), 50),
+            (0..50).collect::<Vec<_>>()
+        );
+        let some = flips(Noise::Percent(10.0), 10_000);
+        assert!(some.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((800..1200).contains(&some.len()), "{} flips", some.len());
+        assert!(flips(Noise::Off, 50).is_empty());
+    }
+
+    #[test]
+    fn counts_pick_that_many_cells_on_the_grid() {
+        let picked = flips(Noise::Cells(40), 7);
+        assert_eq!(picked.len(), 40);
+        assert!(picked.iter().all(|&i| i < 7));
+        assert!(flips(Noise::Cells(40), 0).is_empty());
+    }
+
+    #[test]
+    fn noise_reads_back_as_written() {
+        for text in ["0", "20", "0.01%", "100%"] {
+            let noise: Noise = text.parse().unwrap();
+            assert_eq!(noise.to_string(), text);
+        }
+        for bad in ["-1", "101%", "0.5", "lots"] {
+            assert!(bad.parse::<Noise>().is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//
+// The tool overlays drawn over a frame of the grid: the brush footprint,
+// the selection, the rectangle and paste previews, the mini-map, the
+// cursor readout, the --debug-mouse crosshair, the rule explorer's trail
+// and the help. They only need the grid's size (and the mini-map), not its
+// cells, so the window can draw them over frames rendered elsewhere.
+//
+
+use crate::font::{self, ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT};
+use crate::grid::{blend, clamp_rect, disc_points, wrapped_rect};
+use crate::minimap::MiniMap;
+use crate::pattern::PatternBuffer;
+use crate::topology::Topology;
+
+/// Draws onto RGBA frames of a `width` x `height` grid.
+#[derive(Clone, Copy, Debug)]
+pub struct Overlay {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Overlay {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+
+    /// Index into the frame's cells of (x, y), or None if it's off the grid.
+    pub fn cell_idx(&self, x: isize, y: isize) -> Option<usize> {
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        (x < self.width && y < self.height).then_some(x + y * self.width)
+    }
+
+    // Index into the frame's cells of (x, y) as `Grid::edit_idx` takes
+    // it, across the edges as `topology` glues them (`Topology::Plane` for
+    // an infinite world's view).
+    fn edit_idx(&self, x: isize, y: isize, topology: Topology) -> Option<usize> {
+        let (x, y) = topology.wrap(x, y, (self.width, self.height))?;
+        Some(x + y * self.width)
+    }
+
+    // Inverts the colors of the cells `Grid::set_disc` would touch.
+    pub fn highlight_disc(
+        &self,
+        screen: &mut [u8],
+        x: isize,
+        y: isize,
+        radius: u32,
+        topology: Topology,
+    ) {
+        for (x, y) in disc_points(x, y, radius) {
+            if let Some(i) = self.edit_idx(x, y, topology) {
+                let pix = &mut screen[4 * i..4 * i + 4];
+                for c in &mut pix[..3] {
+                    *c = !*c;
+                }
+                pix[3] = 0xff;
+            }
+        }
+    }
+
+    // Draws a translucent magenta crosshair through (x, y), with the cell
+    // itself solid, for checking where the mouse lands. The parts off the
+    // grid are left out.
+    pub fn draw_crosshair(&self, screen: &mut [u8], x: isize, y: isize) {
+        const COLOR: [u8; 4] = [0xff, 0, 0xff, 0xff];
+        for i in 0..self.width.max(self.height) as isize {
+            for (cx, cy) in [(i, y), (x, i)] {
+                if let Some(i) = self.cell_idx(cx, cy) {
+                    blend(&mut screen[4 * i..4 * i + 4], COLOR, 0.25);
+                }
+            }
+        }
+        if let Some(i) = self.cell_idx(x, y) {
+            screen[4 * i..4 * i + 4].copy_from_slice(&COLOR);
+        }
+    }
+
+    // Outlines the rectangle spanned by two corners, as a preview of what
+    // `Grid::set_rect` would touch. Green for fill, red for clear.
+    pub fn draw_rect_outline(
+        &self,
+        screen: &mut [u8],
+        a: (isize, isize),
+        b: (isize, isize),
+        alive: bool,
+        topology: Topology,
+    ) {
+        let color = if alive {
+            [0, 0xff, 0, 0xff]
+        } else {
+            [0xff, 0, 0, 0xff]
+        };
+
+        let (xmin, ymin, xmax, ymax) = wrapped_rect((self.width, self.height), a, b);
+        for y in ymin..=ymax {
+            for x in xmin..=xmax {
+                if x == xmin || x == xmax || y == ymin || y == ymax {
+                    if let Some(i) = self.edit_idx(x, y, topology) {
+                        screen[4 * i..4 * i + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+    }
+
+    // Tints the rectangle spanned by two corners to highlight a selection.
+    pub fn tint_rect(&self, screen: &mut [u8], a: (isize, isize), b: (isize, isize)) {
+        if let Some((xmin, ymin, xmax, ymax)) = clamp_rect((self.width, self.height), a, b) {
+            for y in ymin..=ymax {
+                for x in xmin..=xmax {
+                    let i = 4 * (x + y * self.width);
+                    blend(&mut screen[i..i + 4], [0xff, 0xff, 0, 0xff], 0.3);
+                }
+            }
+        }
+    }
+
+    // Draws a small pause sign (two bars) in the top-right corner, sized to
+    // the grid so it stays about as big on screen whatever the scaling.
+    // White, or amber for a pause put on while the window was away
+    // (`auto`), which comes off again by itself.
+    pub fn draw_pause_icon(&self, screen: &mut [u8], auto: bool) {
+        let color = if auto {
+            [0xff, 0xb4, 0x28, 0xff]
+        } else {
+            [0xff, 0xff, 0xff, 0xff]
+        };
+        let size = (self.width.min(self.height) / 16).clamp(3, 12);
+        let bar = size.div_ceil(3);
+        let margin = size / 2;
+        let Some(left) = self.width.checked_sub(margin + size) else {
+            return;
+        };
+        for y in margin..(margin + size).min(self.height) {
+            for x in (left..left + bar).chain(left + size - bar..left + size) {
+                let i = 4 * (x + y * self.width);
+                blend(&mut screen[i..i + 4], color, 0.7);
+            }
+        }
+    }
+
+    // Draws a translucent ghost of where `Grid::insert_pattern` would put a
+    // pattern's live cells, across the edges as `topology` glues them.
+    pub fn draw_pattern_preview(
+        &self,
+        screen: &mut [u8],
+        pattern: &PatternBuffer,
+        x: isize,
+        y: isize,
+        topology: Topology,
+    ) {
+        let size = (self.width, self.height);
+        for (px, py) in pattern.live_cells() {
+            let Some((x, y)) = topology.wrap(x + px as isize, y + py as isize, size) else {
+                continue;
+            };
+            let i = 4 * (x + y * self.width);
+            blend(&mut screen[i..i + 4], [0, 0xff, 0xff, 0xff], 0.6);
+        }
+    }
+
+    // Top-left corner of the mini-map's panel (the map with a pixel of
+    // border all round) in the bottom-right corner of the grid, or None if
+    // it doesn't fit.
+    fn minimap_corner(&self, map: &MiniMap) -> Option<(usize, usize)> {
+        let margin = (self.width.min(self.height) / 32).clamp(1, 6);
+        Some((
+            self.width.checked_sub(map.width + 2 + margin)?,
+            self.height.checked_sub(map.height + 2 + margin)?,
+        ))
+    }
+
+    // Draws the mini-map in the bottom-right corner: live cells in white on
+    // a dark, opaque panel, so it reads the same whatever is behind it,
+    // with the view outlined in yellow.
+    pub fn draw_minimap(&self, screen: &mut [u8], map: &MiniMap) {
+        const PANEL: [u8; 4] = [16, 16, 24, 0xff];
+        const BORDER: [u8; 4] = [160, 160, 160, 0xff];
+        const VIEW: [u8; 4] = [0xff, 210, 0, 0xff];
+        let Some((left, top)) = self.minimap_corner(map) else {
+            return;
+        };
+
+        let (vx0, vy0, vx1, vy1) = map.view_rect();
+        let (vx0, vy0, vx1, vy1) = (vx0 - 1, vy0 - 1, vx1 + 1, vy1 + 1);
+        for py in 0..map.height + 2 {
+            for px in 0..map.width + 2 {
+                // Map pixel (x, y) is panel pixel (x + 1, y + 1).
+                let (x, y) = (px as i64 - 1, py as i64 - 1);
+                let in_view = (vx0..=vx1).contains(&x) && (vy0..=vy1).contains(&y);
+                let on_view = in_view && (x == vx0 || x == vx1 || y == vy0 || y == vy1);
+                let on_border = px == 0 || py == 0 || px == map.width + 1 || py == map.height + 1;
+
+                let color = if on_view {
+                    VIEW
+                } else if on_border {
+                    BORDER
+                } else {
+                    let lit = map.density[px - 1 + (py - 1) * map.width];
+                    let mut color = PANEL;
+                    blend(&mut color, [0xff; 4], lit as f32 / 255.0);
+                    color
+                };
+                let i = 4 * (left + px + (top + py) * self.width);
+                screen[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// The world cell the mini-map pixel at (x, y) stands for, if (x, y) is
+    /// on the mini-map's panel (borders count, as the nearest pixel).
+    pub fn minimap_target(&self, map: &MiniMap, x: isize, y: isize) -> Option<(i64, i64)> {
+        let (left, top) = self.minimap_corner(map)?;
+        let (px, py) = (x - left as isize, y - top as isize);
+        let on_panel =
+            (0..map.width as isize + 2).contains(&px) && (0..map.height as isize + 2).contains(&py);
+        on_panel.then(|| {
+            let clamp = |p: isize, len: usize| (p - 1).clamp(0, len as isize - 1) as usize;
+            map.world_at(clamp(px, map.width), clamp(py, map.height))
+        })
+    }
+
+    // Writes `text` with its top-left corner at (x, y), `scale` pixels to a
+    // glyph pixel, clipped to the frame.
+    fn draw_text(
+        &self,
+        screen: &mut [u8],
+        (x, y): (usize, usize),
+        text: &str,
+        scale: usize,
+        color: [u8; 4],
+    ) {
+        for (n, c) in text.chars().enumerate() {
+            let left = x + n * ADVANCE * scale;
+            for (gy, bits) in font::glyph(c).into_iter().enumerate() {
+                for gx in (0..GLYPH_WIDTH).filter(|gx| bits & (0b100 >> gx) != 0) {
+                    for py in y + gy * scale..y + (gy + 1) * scale {
+                        for px in left + gx * scale..left + (gx + 1) * scale {
+                            if px < self.width && py < self.height {
+                                let i = 4 * (px + py * self.width);
+                                screen[i..i + 4].copy_from_slice(&color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes `text` on a dark panel in the bottom-left corner, sized to
+    /// the grid like the pause sign.
+    pub fn draw_readout(&self, screen: &mut [u8], text: &str) {
+        const TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+        let scale = (self.width.min(self.height) / 200).clamp(1, 4);
+        let margin = (self.width.min(self.height) / 32).clamp(1, 6);
+        // A glyph pixel of panel all round the text.
+        let width = ((text.chars().count() * ADVANCE + 1) * scale).min(self.width);
+        let height = (GLYPH_HEIGHT + 2) * scale;
+        let Some(top) = self.height.checked_sub(height + margin) else {
+            return;
+        };
+        let left = margin.min(self.width - width);
+        for y in top..top + height {
+            for x in left..left + width {
+                let i = 4 * (x + y * self.width);
+                blend(&mut screen[i..i + 4], [0, 0, 0, 0xff], 0.7);
+            }
+        }
+        self.draw_text(screen, (left + scale, top + scale), text, scale, TEXT_COLOR);
+    }
+
+    /// Lists the rule explorer's trail on a dark panel in the top-left
+    /// corner, a rule a line and oldest first, sized to the grid like the
+    /// readout. Rules marked true in `trail` are bookmarked, and shown in
+    /// green.
+    pub fn draw_rule_trail(&self, screen: &mut [u8], trail: &[(String, bool)]) {
+        const TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+        const BOOKMARKED_COLOR: [u8; 4] = [0x60, 0xff, 0x60, 0xff];
+        let scale = (self.width.min(self.height) / 200).clamp(1, 4);
+        let margin = (self.width.min(self.height) / 32).clamp(1, 6);
+        let longest = trail.iter().map(|(rule, _)| rule.chars().count()).max();
+        let Some(longest) = longest else {
+            return;
+        };
+        // A glyph pixel of panel all round the text.
+        let width = ((longest * ADVANCE + 1) * scale).min(self.width);
+        let height = (trail.len() * LINE_HEIGHT + 1) * scale;
+        if height + margin > self.height {
+            return;
+        }
+        let (left, top) = (margin.min(self.width - width), margin);
+        for y in top..top + height {
+            for x in left..left + width {
+                let i = 4 * (x + y * self.width);
+                blend(&mut screen[i..i + 4], [0, 0, 0, 0xff], 0.7);
+            }
+        }
+        for (n, (rule, bookmarked)) in trail.iter().enumerate() {
+            let color = if *bookmarked {
+                BOOKMARKED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            let y = top + scale + n * LINE_HEIGHT * scale;
+            self.draw_text(screen, (left + scale, y), rule, scale, color);
+        }
+    }
+
+    /// Dims the whole frame and lists `entries` (keys, and what they do)
+    /// over it, the keys in yellow, in however many columns let the text
+    /// come out biggest.
+    pub fn draw_help(&self, screen: &mut [u8], entries: &[(String, String)]) {
+        const KEY_COLOR: [u8; 4] = [0xff, 210, 0, 0xff];
+        const TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+        for pix in screen.chunks_exact_mut(4) {
+            blend(pix, [0, 0, 0, 0xff], 0.8);
+        }
+
+        // In glyphs: an entry is its keys, two spaces and what they do, and
+        // columns are two spaces apart. The margin is one glyph all round.
+        let longest = entries
+            .iter()
+            .map(|(keys, what)| keys.chars().count() + 2 + what.chars().count())
+            .max()
+            .unwrap_or(0);
+        let column = longest + 2;
+        let size = |columns: usize| {
+            let rows = entries.len().div_ceil(columns);
+            (columns * column * ADVANCE, (rows + 2) * LINE_HEIGHT)
+        };
+        // How many times over each layout would fit: the most times whole,
+        // then the most room to spare. If none fits even once, that's the
+        // one that loses least off the edges.
+        let fits = |columns: usize| {
+            let (width, height) = size(columns);
+            (self.width as f32 / width as f32).min(self.height as f32 / height as f32)
+        };
+        let columns = (1..=entries.len().max(1))
+            .max_by(|&a, &b| {
+                let (a, b) = (fits(a), fits(b));
+                a.floor().total_cmp(&b.floor()).then(a.total_cmp(&b))
+            })
+            .unwrap_or(1);
+        let scale = (fits(columns) as usize).max(1);
+
+        let (width, height) = size(columns);
+        let left = self.width.saturating_sub(width * scale) / 2 + ADVANCE * scale;
+        let top = self.height.saturating_sub(height * scale) / 2 + LINE_HEIGHT * scale;
+        let rows = entries.len().div_ceil(columns).max(1);
+        for (n, (keys, what)) in entries.iter().enumerate() {
+            let x = left + (n / rows) * column * ADVANCE * scale;
+            let y = top + (n % rows) * LINE_HEIGHT * scale;
+            self.draw_text(screen, (x, y), keys, scale, KEY_COLOR);
+            let x = x + (keys.chars().count() + 2) * ADVANCE * scale;
+            self.draw_text(screen, (x, y), what, scale, TEXT_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The cells of a black frame that the pause sign lights up.
+    fn pause_sign(width: usize, height: usize) -> Vec<(usize, usize)> {
+        let mut screen = vec![0; 4 * width * height];
+        Overlay::new(width, height).draw_pause_icon(&mut screen, false);
+        (0..width * height)
+            .filter(|i| screen[4 * i..4 * i + 4] != [0; 4])
+            .map(|i| (i % width, i / width))
+            .collect()
+    }
+
+    #[test]
+    fn the_pause_sign_is_two_bars_in_the_top_right() {
+        for (width, height) in [(64, 48), (300, 200), (1000, 40)] {
+            let cells = pause_sign(width, height);
+            let size = (width.min(height) / 16).clamp(3, 12);
+            assert_eq!(
+                cells.len(),
+                2 * size.div_ceil(3) * size,
+                "{}x{}",
+                width,
+                height
+            );
+            assert!(cells
+                .iter()
+                .all(|&(x, y)| x >= width - 2 * size && y < 2 * size));
+        }
+        // Grids too small for it are left alone, or get what fits.
+        assert!(pause_sign(3, 3).is_empty());
+        assert!(!pause_sign(4, 2).is_empty());
+    }
+
+    #[test]
+    fn minimap_clicks_land_on_the_block_under_them() {
+        let map = MiniMap::new((0, 0, 40, 40), (20, 20), (0, 0, 40, 40), std::iter::empty());
+        let overlay = Overlay::new(100, 80);
+        // The panel's top-left pixel is border, 2 cells in from the corner.
+        assert_eq!(overlay.minimap_target(&map, 76, 56), Some((1, 1)));
+        assert_eq!(overlay.minimap_target(&map, 77, 57), Some((1, 1)));
+        assert_eq!(overlay.minimap_target(&map, 96, 76), Some((39, 39)));
+        assert_eq!(overlay.minimap_target(&map, 75, 56), None);
+
+        // A grid too small for the map has none.
+        let overlay = Overlay::new(20, 20);
+        let mut screen = vec![0; 4 * 20 * 20];
+        overlay.draw_minimap(&mut screen, &map);
+        assert!(screen.iter().all(|&byte| byte == 0));
+        assert_eq!(overlay.minimap_target(&map, 10, 10), None);
+    }
+
+    #[test]
+    fn the_readout_is_a_panel_in_the_bottom_left() {
+        let (width, height) = (100, 80);
+        let mut screen = [100, 100, 100, 255].repeat(width * height);
+        Overlay::new(width, height).draw_readout(&mut screen, "x 3");
+        let changed: Vec<(usize, usize)> = (0..width * height)
+            .filter(|i| screen[4 * i..4 * i + 3] != [100; 3])
+            .map(|i| (i % width, i / width))
+            .collect();
+
+        // At this size: a glyph pixel a grid cell, 2 cells from the edges.
+        let panel_width = 3 * ADVANCE + 1;
+        let top = height - 2 - (GLYPH_HEIGHT + 2);
+        assert_eq!(changed.len(), panel_width * (GLYPH_HEIGHT + 2));
+        assert!(changed
+            .iter()
+            .all(|&(x, y)| (2..2 + panel_width).contains(&x) && (top..height - 2).contains(&y)));
+        assert!(screen.chunks_exact(4).any(|pix| pix == [0xff; 4]));
+
+        // A grid too small for it is left alone.
+        let mut screen = vec![7; 4 * 6 * 6];
+        Overlay::new(6, 6).draw_readout(&mut screen, "x 3");
+        assert!(screen.iter().all(|&byte| byte == 7));
+    }
+
+    #[test]
+    fn help_grows_with_the_screen_and_is_clipped_when_it_cant_fit() {
+        let entries = [("F1", "Help"), ("Space", "Pause"), ("K", "Kill")]
+            .map(|(keys, what)| (keys.to_string(), what.to_string()));
+        // Pixels drawn in the keys' color.
+        let keys_lit = |width: usize, height: usize| {
+            let mut screen = vec![0; 4 * width * height];
+            Overlay::new(width, height).draw_help(&mut screen, &entries);
+            screen
+                .chunks_exact(4)
+                .filter(|pix| *pix == [0xff, 210, 0, 0xff])
+                .count()
+        };
+        let small = keys_lit(120, 80);
+        assert!(small > 0);
+        assert_eq!(keys_lit(240, 160), 4 * small);
+        for (width, height) in [(10, 10), (1, 1), (300, 4)] {
+            keys_lit(width, height);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//
+// Blocks of cells that live outside of a `Grid`: the copy/paste clipboard,
+// stamps waiting to be placed, etc.
+//
+
+/// Longest side a pattern read from a file may have, so a bad one can't
+/// ask for all the memory there is.
+pub const MAX_SIDE: usize = 1 << 14;
+
+/// Which way a glider travels. Gliders only move diagonally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heading {
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
+}
+
+/// A rectangular block of cells detached from any grid. Only each cell's
+/// state is kept: 0 for dead and 1 for alive, or more for automata with
+/// more states (Wireworld's, numbered as Golly does); heat only means
+/// something for cells inside a `Grid`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatternBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+}
+
+impl PatternBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width
+            .checked_mul(height)
+            .expect("Pattern too big (overflow)");
+        Self {
+            width,
+            height,
+            cells: vec![0; size],
+        }
+    }
+
+    /// Builds a pattern from rows of `#` (alive) and `.` (dead), or digits
+    /// `2` to `9` for other states. Short rows are padded with dead cells.
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut pattern = Self::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let state = match c {
+                    '#' => 1,
+                    '2'..='9' => c as u8 - b'0',
+                    _ => 0,
+                };
+                pattern.set_state(x, y, state);
+            }
+        }
+        pattern
+    }
+
+    /// The 5-cell glider, oriented to travel towards `heading`.
+    pub fn glider(heading: Heading) -> Self {
+        let mut glider = Self::from_rows(&[".#.", "..#", "###"]);
+        let turns = match heading {
+            Heading::SouthEast => 0,
+            Heading::SouthWest => 1,
+            Heading::NorthWest => 2,
+            Heading::NorthEast => 3,
+        };
+        for _ in 0..turns {
+            glider.rotate_cw();
+        }
+        glider
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        self.set_state(x, y, alive as u8);
+    }
+
+    pub fn set_state(&mut self, x: usize, y: usize, state: u8) {
+        debug_assert!(x < self.width && y < self.height);
+        self.cells[x + y * self.width] = state;
+    }
+
+    /// Cells that aren't dead (state 0).
+    pub fn live_count(&self) -> usize {
+        self.cells.iter().filter(|&&state| state != 0).count()
+    }
+
+    /// Whether any cell has a state other than dead or alive.
+    pub fn multi_state(&self) -> bool {
+        self.cells.iter().any(|&state| state > 1)
+    }
+
+    /// Coordinates of every live cell, row by row from the top-left.
+    pub fn live_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &state)| state != 0)
+            .map(move |(i, _)| (i % width, i / width))
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.state(x, y) != 0
+    }
+
+    pub fn state(&self, x: usize, y: usize) -> u8 {
+        self.cells[x + y * self.width]
+    }
+
+    // Builds a `width` x `height` buffer where each cell is read from this
+    // one at the coordinates `source` maps it to.
+    fn remap(
+        &self,
+        width: usize,
+        height: usize,
+        source: impl Fn(usize, usize) -> (usize, usize),
+    ) -> Self {
+        let mut out = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(x, y);
+                out.set_state(x, y, self.state(sx, sy));
+            }
+        }
+        out
+    }
+
+    /// Rotates 90° clockwise; width and height swap.
+    pub fn rotate_cw(&mut self) {
+        let h = self.height;
+        *self = self.remap(self.height, self.width, |x, y| (y, h - 1 - x));
+    }
+
+    /// Rotates 90° counter-clockwise; width and height swap.
+    pub fn rotate_ccw(&mut self) {
+        let w = self.width;
+        *self = self.remap(self.height, self.width, |x, y| (w - 1 - y, x));
+    }
+
+    /// Mirrors left to right.
+    pub fn flip_horizontal(&mut self) {
+        let w = self.width;
+        *self = self.remap(self.width, self.height, |x, y| (w - 1 - x, y));
+    }
+
+    /// Mirrors top to bottom.
+    pub fn flip_vertical(&mut self) {
+        let h = self.height;
+        *self = self.remap(self.width, self.height, |x, y| (x, h - 1 - y));
+    }
+
+    /// Top-left corner to place this pattern at so it's centered on `at`.
+    pub fn origin_centered_on(&self, at: (isize, isize)) -> (isize, isize) {
+        (
+            at.0 - (self.width / 2) as isize,
+            at.1 - (self.height / 2) as isize,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Not square, and no two of its turns alike.
+    fn lopsided() -> PatternBuffer {
+        PatternBuffer::from_rows(&["#.2", "..#"])
+    }
+
+    #[test]
+    fn rotating_clockwise_turns_the_left_column_into_the_top_row() {
+        let mut pattern = lopsided();
+        pattern.rotate_cw();
+        assert_eq!(pattern, PatternBuffer::from_rows(&[".#", "..", "#2"]));
+    }
+
+    #[test]
+    fn four_turns_either_way_come_back_round() {
+        let mut cw = lopsided();
+        let mut ccw = lopsided();
+        for turn in 1..=4 {
+            cw.rotate_cw();
+            ccw.rotate_ccw();
+            assert_eq!(cw == lopsided(), turn == 4, "{} turns clockwise", turn);
+            assert_eq!(
+                ccw == lopsided(),
+                turn == 4,
+                "{} turns counter-clockwise",
+                turn
+            );
+        }
+    }
+
+    #[test]
+    fn turning_back_undoes_a_turn() {
+        let mut pattern = lopsided();
+        pattern.rotate_cw();
+        pattern.rotate_ccw();
+        assert_eq!(pattern, lopsided());
+        pattern.rotate_ccw();
+        pattern.rotate_cw();
+        assert_eq!(pattern, lopsided());
+    }
+
+    #[test]
+    fn flipping_twice_undoes_a_flip() {
+        let mut pattern = lopsided();
+        pattern.flip_horizontal();
+        assert_eq!(pattern, PatternBuffer::from_rows(&["2.#", "#.."]));
+        pattern.flip_horizontal();
+        pattern.flip_vertical();
+        assert_eq!(pattern, PatternBuffer::from_rows(&["..#", "#.2"]));
+        pattern.flip_vertical();
+        assert_eq!(pattern, lopsided());
+    }
+}
@@ -0,0 +1,115 @@
+//
+// Patterns as text, in whichever format they turn out to be. Files,
+// patterns piped in on stdin and the system clipboard all come through
+// here, so they're read the same way: the format is told by the first line
+// that says anything, not by a file's extension.
+//
+//   #Life 1.06              Life 1.06
+//   !Name: Glider           plaintext (comments start with `!`)
+//   .O.                     plaintext (only `.`, `O` and `*`)
+//   #N Glider               RLE (comments start with `#`)
+//   x = 3, y = 3            RLE
+//   bo$2bo$3o!              RLE (anything else)
+//
+
+use crate::pattern::PatternBuffer;
+use crate::{cells, life106, rle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life106,
+}
+
+/// The format `text` looks to be in. Text with nothing in it is taken to
+/// be RLE, which is the likeliest.
+pub fn sniff(text: &str) -> Format {
+    let Some(line) = text.lines().map(str::trim).find(|line| !line.is_empty()) else {
+        return Format::Rle;
+    };
+    if line.starts_with(life106::HEADER) {
+        Format::Life106
+    } else if line.starts_with('!') || line.chars().all(|c| matches!(c, '.' | 'O' | '*')) {
+        Format::Plaintext
+    } else {
+        Format::Rle
+    }
+}
+
+/// Reads a pattern in whatever format `text` is in (see `sniff`). One with
+/// no live cells is refused, as it's most likely something else that
+/// happened to parse.
+pub fn parse(text: &str) -> Result<PatternBuffer, String> {
+    let text = text.trim_start();
+    let pattern = match sniff(text) {
+        Format::Rle => rle::parse(text).map_err(|e| e.to_string())?,
+        Format::Plaintext => cells::parse(text).map_err(|e| e.to_string())?,
+        Format::Life106 => life106::parse(text).map_err(|e| e.to_string())?,
+    };
+    if pattern.live_count() == 0 {
+        return Err("no live cells".to_string());
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same glider in each format, as LifeWiki gives it.
+    const RLE: &str = "#N Glider\n#C A comment\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+    const BARE_RLE: &str = "bob$2bo$3o!";
+    const PLAINTEXT: &str = "!Name: Glider\n!\n.O\n..O\nOOO\n";
+    const BARE_PLAINTEXT: &str = ".O.\n..*\n***\n";
+    const LIFE106: &str = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+
+    fn glider() -> PatternBuffer {
+        PatternBuffer::from_rows(&[".#.", "..#", "###"])
+    }
+
+    #[test]
+    fn formats_are_told_by_their_first_line() {
+        assert_eq!(sniff(RLE), Format::Rle);
+        assert_eq!(sniff(BARE_RLE), Format::Rle);
+        assert_eq!(sniff(PLAINTEXT), Format::Plaintext);
+        assert_eq!(sniff(BARE_PLAINTEXT), Format::Plaintext);
+        assert_eq!(sniff(LIFE106), Format::Life106);
+        assert_eq!(sniff("\n   \n#Life 1.06\n0 0\n"), Format::Life106);
+        assert_eq!(sniff(""), Format::Rle);
+    }
+
+    #[test]
+    fn every_format_reads_the_same_glider() {
+        for text in [RLE, BARE_RLE, PLAINTEXT, BARE_PLAINTEXT, LIFE106] {
+            assert_eq!(parse(text), Ok(glider()), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn pasted_text_reads_the_same() {
+        // As the clipboard tends to hand it over: indented, after blank
+        // lines, with Windows line endings.
+        for text in [RLE, PLAINTEXT, LIFE106] {
+            let pasted = format!("\r\n  \r\n{}", text.replace('\n', "\r\n"));
+            assert_eq!(parse(&pasted), Ok(glider()), "{:?}", pasted);
+        }
+    }
+
+    #[test]
+    fn garbage_is_refused() {
+        for text in [
+            "",
+            "   \n\n",
+            "hello, world",
+            "<html><body>not a pattern</body></html>",
+            "#Life 1.06\n0 zero\n",
+            "..O\n.#.\n",
+            "!Name: nothing\n...\n...\n",
+            "x = 3, y = 3\n3b$3b$3b!",
+            "\u{0}\u{1}\u{fffd}\u{7f}",
+        ] {
+            assert!(parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//
+// Lightweight span profiler.
+//
+// `profile_scope!("name")` times the rest of the enclosing block. Spans are
+// summed into per-frame buckets, the last `HISTORY_FRAMES` frames are kept,
+// and `dump` prints a summary of them. Without the `profiling` feature the
+// macro expands to nothing and `end_frame`/`dump` are empty.
+//
+
+#[cfg(feature = "profiling")]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_guard = $crate::profiler::SpanGuard::new($name);
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {};
+}
+
+#[cfg(feature = "profiling")]
+pub use self::enabled::*;
+
+#[cfg(not(feature = "profiling"))]
+pub fn end_frame() {}
+
+#[cfg(not(feature = "profiling"))]
+pub fn dump(_write_json: bool) {
+    log::warn!("Built without the `profiling` feature; nothing to dump.");
+}
+
+#[cfg(feature = "profiling")]
+mod enabled {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::fmt::Write;
+    use std::time::{Duration, Instant};
+
+    /// How many frames of history the summary covers.
+    pub const HISTORY_FRAMES: usize = 600;
+
+    /// Where `dump(true)` writes its JSON copy of the summary.
+    const JSON_PATH: &str = "tgol-profile.json";
+
+    thread_local! {
+        static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+    }
+
+    /// Total time and number of calls of one span within a frame.
+    #[derive(Clone, Copy, Debug)]
+    struct Bucket {
+        name: &'static str,
+        time: Duration,
+        calls: u32,
+    }
+
+    #[derive(Debug, Default)]
+    struct Frame {
+        buckets: Vec<Bucket>,
+        total: Duration,
+    }
+
+    /// Per-span statistics over the frames in the history window.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct SpanSummary {
+        pub name: &'static str,
+        pub mean: Duration,
+        pub p95: Duration,
+        pub calls: u64,
+        // Fraction of all frame time spent in this span (nested spans are
+        // also counted in their parents, so these don't sum to 1).
+        pub share: f64,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Profiler {
+        current: Frame,
+        frame_start: Option<Instant>,
+        history: VecDeque<Frame>,
+    }
+
+    impl Profiler {
+        pub fn record(&mut self, name: &'static str, elapsed: Duration) {
+            // A handful of distinct spans per frame; a linear scan beats hashing.
+            match self.current.buckets.iter_mut().find(|b| b.name == name) {
+                Some(bucket) => {
+                    bucket.time += elapsed;
+                    bucket.calls += 1;
+                }
+                None => self.current.buckets.push(Bucket {
+                    name,
+                    time: elapsed,
+                    calls: 1,
+                }),
+            }
+        }
+
+        /// Closes the current frame, which lasted `total`.
+        pub fn end_frame_with(&mut self, total: Duration) {
+            let mut frame = std::mem::take(&mut self.current);
+            frame.total = total;
+
+            if self.history.len() == HISTORY_FRAMES {
+                self.history.pop_front();
+            }
+            self.history.push_back(frame);
+        }
+
+        fn end_frame(&mut self, now: Instant) {
+            let total = self
+                .frame_start
+                .map(|start| now - start)
+                .unwrap_or_default();
+            self.frame_start = Some(now);
+            self.end_frame_with(total);
+        }
+
+        pub fn frames(&self) -> usize {
+            self.history.len()
+        }
+
+        /// Summaries of every span seen in the window, slowest mean first.
+        pub fn summary(&self) -> Vec<SpanSummary> {
+            let frame_time: Duration = self.history.iter().map(|f| f.total).sum();
+
+            let mut names: Vec<&'static str> = Vec::new();
+            for bucket in self.history.iter().flat_map(|f| &f.buckets) {
+                if !names.contains(&bucket.name) {
+                    names.push(bucket.name);
+                }
+            }
+
+            let mut summaries: Vec<SpanSummary> = names
+                .into_iter()
+                .map(|name| {
+                    // A frame where the span never ran counts as zero time.
+                    let mut per_frame: Vec<Duration> = Vec::with_capacity(self.history.len());
+                    let mut calls = 0_u64;
+                    for frame in &self.history {
+                        let bucket = frame.buckets.iter().find(|b| b.name == name);
+                        per_frame.push(bucket.map(|b| b.time).unwrap_or_default());
+                        calls += bucket.map(|b| b.calls as u64).unwrap_or_default();
+                    }
+
+                    let total: Duration = per_frame.iter().sum();
+                    per_frame.sort_unstable();
+
+                    SpanSummary {
+                        name,
+                        mean: total / per_frame.len() as u32,
+                        p95: percentile(&per_frame, 0.95),
+                        calls,
+                        share: if frame_time.is_zero() {
+                            0.0
+                        } else {
+                            total.as_secs_f64() / frame_time.as_secs_f64()
+                        },
+                    }
+                })
+                .collect();
+
+            summaries.sort_by(|a, b| b.mean.cmp(&a.mean).then(a.name.cmp(b.name)));
+            summaries
+        }
+    }
+
+    // Nearest-rank percentile of an ascending, non-empty slice.
+    fn percentile(sorted: &[Duration], p: f64) -> Duration {
+        let rank = (p * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    /// Records the time from its creation to its drop under `name`.
+    pub struct SpanGuard {
+        name: &'static str,
+        start: Instant,
+    }
+
+    impl SpanGuard {
+        pub fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                start: Instant::now(),
+            }
+        }
+    }
+
+    impl Drop for SpanGuard {
+        fn drop(&mut self) {
+            let elapsed = self.start.elapsed();
+            PROFILER.with(|p| p.borrow_mut().record(self.name, elapsed));
+        }
+    }
+
+    /// Marks the end of a frame; call once per rendered frame.
+    pub fn end_frame() {
+        let now = Instant::now();
+        PROFILER.with(|p| p.borrow_mut().end_frame(now));
+    }
+
+    /// Logs a summary of the history window, and writes it to
+    /// `tgol-profile.json` too when `write_json` is set.
+    pub fn dump(write_json: bool) {
+        let (frames, summary) = PROFILER.with(|p| {
+            let p = p.borrow();
+            (p.frames(), p.summary())
+        });
+
+        log::info!("Profile over the last {} frames:", frames);
+        log::info!(
+            "{:<16} {:>10} {:>10} {:>10} {:>7}",
+            "span",
+            "mean",
+            "p95",
+            "calls",
+            "share"
+        );
+        for s in &summary {
+            log::info!(
+                "{:<16} {:>10.3?} {:>10.3?} {:>10} {:>6.1}%",
+                s.name,
+                s.mean,
+                s.p95,
+                s.calls,
+                s.share * 100.0
+            );
+        }
+
+        if write_json {
+            match std::fs::write(JSON_PATH, to_json(frames, &summary)) {
+                Ok(()) => log::info!("Profile written to {}", JSON_PATH),
+                Err(e) => log::error!("Failed to write {}: {}", JSON_PATH, e),
+            }
+        }
+    }
+
+    pub fn to_json(frames: usize, summary: &[SpanSummary]) -> String {
+        let mut json = format!("{{\"frames\":{},\"spans\":[", frames);
+        for (i, s) in summary.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"name\":\"{}\",\"mean_us\":{:.3},\"p95_us\":{:.3},\"calls\":{},\"share\":{:.5}}}",
+                s.name,
+                s.mean.as_secs_f64() * 1e6,
+                s.p95.as_secs_f64() * 1e6,
+                s.calls,
+                s.share
+            );
+        }
+        json.push_str("]}\n");
+        json
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn ms(n: u64) -> Duration {
+            Duration::from_millis(n)
+        }
+
+        #[test]
+        fn spans_add_up_within_a_frame_and_average_across_frames() {
+            let mut profiler = Profiler::default();
+            profiler.record("update", ms(2));
+            profiler.record("draw", ms(1));
+            profiler.record("update", ms(3));
+            profiler.end_frame_with(ms(10));
+            // A frame without "draw" counts as no time in it.
+            profiler.record("update", ms(7));
+            profiler.end_frame_with(ms(10));
+
+            let summary = profiler.summary();
+            assert_eq!(profiler.frames(), 2);
+            assert_eq!(
+                summary.iter().map(|s| s.name).collect::<Vec<_>>(),
+                ["update", "draw"]
+            );
+
+            // 12ms over three calls, 5ms and 7ms a frame: the slower is the p95.
+            let update = &summary[0];
+            assert_eq!((update.calls, update.mean, update.p95), (3, ms(6), ms(7)));
+            assert!((update.share - 0.6).abs() < 1e-9);
+
+            let draw = &summary[1];
+            assert_eq!(
+                (draw.calls, draw.mean, draw.p95),
+                (1, Duration::from_micros(500), ms(1))
+            );
+            assert!((draw.share - 0.05).abs() < 1e-9);
+        }
+
+        #[test]
+        fn only_the_latest_frames_are_summarized() {
+            let mut profiler = Profiler::default();
+            profiler.record("old", ms(100));
+            profiler.end_frame_with(ms(100));
+            for _ in 0..HISTORY_FRAMES {
+                profiler.record("new", ms(1));
+                profiler.end_frame_with(ms(1));
+            }
+
+            let summary = profiler.summary();
+            assert_eq!(profiler.frames(), HISTORY_FRAMES);
+            assert_eq!(summary.len(), 1);
+            assert_eq!(summary[0].name, "new");
+            assert_eq!(summary[0].calls, HISTORY_FRAMES as u64);
+            assert_eq!((summary[0].mean, summary[0].p95), (ms(1), ms(1)));
+            assert!((summary[0].share - 1.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn frames_without_time_share_nothing() {
+            let mut profiler = Profiler::default();
+            profiler.record("span", ms(1));
+            profiler.end_frame_with(Duration::ZERO);
+            assert_eq!(profiler.summary()[0].share, 0.0);
+        }
+    }
+}
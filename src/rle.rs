@@ -0,0 +1,271 @@
+//
+// Run Length Encoded (RLE) patterns, the format LifeWiki and Golly use:
+//
+//   #C optional comment lines
+//   x = 3, y = 3, rule = B3/S23
+//   bob$2bo$3o!
+//
+// `b` is a dead cell, `o` a live one, `$` ends a row, `!` ends the pattern,
+// and any of them can be prefixed with a repeat count. Automata with more
+// states write them Golly's way instead: `.` for state 0, `A` to `X` for
+// 1 to 24, and `pA` on up (24 to a prefix letter, `p` to `y`) past that.
+//
+
+use std::fmt;
+
+use crate::pattern::{self, PatternBuffer};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RleError {
+    /// The `x = .., y = ..` line couldn't be read.
+    BadHeader(String),
+    /// A character that isn't part of the cell data.
+    UnexpectedChar(char),
+    /// Cells were placed outside the size given in the header.
+    TooBig { width: usize, height: usize },
+    /// The pattern is more than `pattern::MAX_SIDE` cells on a side.
+    TooLarge,
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadHeader(line) => write!(f, "bad RLE header: {:?}", line),
+            Self::UnexpectedChar(c) => write!(f, "unexpected character in RLE: {:?}", c),
+            Self::TooBig { width, height } => {
+                write!(f, "RLE cells don't fit its {} x {} header", width, height)
+            }
+            Self::TooLarge => write!(
+                f,
+                "RLE pattern is over {} cells on a side",
+                pattern::MAX_SIDE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RleError {}
+
+/// Parses an RLE pattern. Without a header, the pattern is sized to fit
+/// its live cells.
+pub fn parse(text: &str) -> Result<PatternBuffer, RleError> {
+    let mut header: Option<(usize, usize)> = None;
+    let mut live: Vec<(usize, usize, u8)> = Vec::new();
+    let (mut x, mut y) = (0_usize, 0_usize);
+    let (mut width, mut height) = (0_usize, 0_usize);
+
+    'lines: for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if header.is_none() && live.is_empty() && line.starts_with('x') {
+            header = Some(parse_header(line)?);
+            continue;
+        }
+
+        let mut count: Option<usize> = None;
+        // The 24s a `p` to `y` prefix adds to the state that follows.
+        let mut prefix: Option<u8> = None;
+        for c in line.chars() {
+            let run = count.unwrap_or(1);
+
+            let state = match (prefix.take(), c) {
+                (None, 'o') => Some(1),
+                (high, 'A'..='X') => {
+                    let state = high.unwrap_or(0) as u32 * 24 + (c as u32 - 'A' as u32 + 1);
+                    Some(u8::try_from(state).map_err(|_| RleError::UnexpectedChar(c))?)
+                }
+                (None, 'p'..='y') => {
+                    prefix = Some(c as u8 - b'p' + 1);
+                    continue;
+                }
+                (Some(_), c) => return Err(RleError::UnexpectedChar(c)),
+                (None, _) => None,
+            };
+            if let Some(state) = state {
+                if x.saturating_add(run) > pattern::MAX_SIDE || y >= pattern::MAX_SIDE {
+                    return Err(RleError::TooLarge);
+                }
+                live.extend((x..x + run).map(|cx| (cx, y, state)));
+                x += run;
+                width = width.max(x);
+                height = height.max(y + 1);
+                count = None;
+                continue;
+            }
+
+            match c {
+                '0'..='9' => {
+                    let digit = c as usize - '0' as usize;
+                    count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    continue;
+                }
+                'b' | '.' => x = x.saturating_add(run),
+                '$' => {
+                    y = y.saturating_add(run);
+                    x = 0;
+                }
+                '!' => break 'lines,
+                c if c.is_whitespace() => {}
+                c => return Err(RleError::UnexpectedChar(c)),
+            }
+
+            count = None;
+        }
+    }
+
+    if let Some((w, h)) = header {
+        if width > w || height > h {
+            return Err(RleError::TooBig {
+                width: w,
+                height: h,
+            });
+        }
+        width = w;
+        height = h;
+    }
+
+    let mut pattern = PatternBuffer::new(width, height);
+    for (x, y, state) in live {
+        pattern.set_state(x, y, state);
+    }
+
+    Ok(pattern)
+}
+
+/// The rule an RLE pattern's header names, if it has a header naming one.
+pub fn rule(text: &str) -> Option<&str> {
+    let header = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| line.starts_with('x'))?;
+    header
+        .split(',')
+        .filter_map(|field| field.split_once('='))
+        .find(|(key, _)| key.trim() == "rule")
+        .map(|(_, rule)| rule.trim())
+}
+
+// Longest line `write` makes, as Golly keeps to.
+const LINE_LEN: usize = 70;
+
+/// Writes a pattern as RLE, with `rule` in its header. Cells are `b` and
+/// `o` if every one is dead or alive, else numbered states.
+pub fn write(pattern: &PatternBuffer, rule: &str) -> String {
+    let multi_state = pattern.multi_state();
+    let mut lines = vec![format!(
+        "x = {}, y = {}, rule = {}",
+        pattern.width(),
+        pattern.height(),
+        rule
+    )];
+    let mut line = String::new();
+    let mut push = |run: usize, tag: &str| {
+        let item = match run {
+            1 => tag.to_string(),
+            _ => format!("{}{}", run, tag),
+        };
+        if line.len() + item.len() > LINE_LEN {
+            lines.push(std::mem::take(&mut line));
+        }
+        line.push_str(&item);
+    };
+
+    // Row ends are held back until there's more on a later row, so runs
+    // of them merge and none trail the last cells.
+    let mut row_ends = 0;
+    for y in 0..pattern.height() {
+        let mut x = 0;
+        while x < pattern.width() {
+            let state = pattern.state(x, y);
+            let run = (x..pattern.width())
+                .take_while(|&cx| pattern.state(cx, y) == state)
+                .count();
+            x += run;
+            if state == 0 && x == pattern.width() {
+                // Dead cells at the end of a row go without saying.
+                break;
+            }
+            if row_ends > 0 {
+                push(row_ends, "$");
+                row_ends = 0;
+            }
+            push(run, &state_tag(state, multi_state));
+        }
+        row_ends += 1;
+    }
+    push(1, "!");
+    lines.push(line);
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+// How `write` spells a cell in `state`.
+fn state_tag(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (state, true) => {
+            let (high, low) = ((state - 1) / 24, (state - 1) % 24);
+            let letter = (b'A' + low) as char;
+            match high {
+                0 => letter.to_string(),
+                high => format!("{}{}", (b'p' + high - 1) as char, letter),
+            }
+        }
+    }
+}
+
+// Reads the `x` and `y` out of `x = 3, y = 3, rule = B3/S23`.
+fn parse_header(line: &str) -> Result<(usize, usize), RleError> {
+    let bad = || RleError::BadHeader(line.to_string());
+    let (mut width, mut height) = (None, None);
+
+    for field in line.split(',') {
+        let (key, value) = field.split_once('=').ok_or_else(bad)?;
+        match key.trim() {
+            "x" => width = Some(value.trim().parse().map_err(|_| bad())?),
+            "y" => height = Some(value.trim().parse().map_err(|_| bad())?),
+            _ => {}
+        }
+    }
+
+    let (width, height) = (width.ok_or_else(bad)?, height.ok_or_else(bad)?);
+    if width > pattern::MAX_SIDE || height > pattern::MAX_SIDE {
+        return Err(RleError::TooLarge);
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_state_patterns_are_written_with_b_and_o() {
+        let glider = PatternBuffer::from_rows(&[".#.", "..#", "###"]);
+        let text = write(&glider, "B3/S23");
+        assert_eq!(text, "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+        assert_eq!(parse(&text).unwrap(), glider);
+    }
+
+    #[test]
+    fn multi_state_patterns_read_back_as_written() {
+        let mut pattern = PatternBuffer::new(40, 3);
+        for (x, state) in [1, 2, 3, 24, 25, 48, 49, 200].into_iter().enumerate() {
+            pattern.set_state(2 * x, 1, state);
+        }
+        // Long runs, and rows with nothing on them at either end.
+        for x in 20..40 {
+            pattern.set_state(x, 1, 3);
+        }
+        let text = write(&pattern, "WireWorld");
+        assert!(text.lines().all(|line| line.len() <= LINE_LEN), "{}", text);
+        assert_eq!(parse(&text).unwrap(), pattern);
+    }
+}
@@ -0,0 +1,29 @@
+//
+// Randomness. Everything random in a session (soups, [K], the rule
+// explorer) comes from one PCG32 built from a single seed, so the seed
+// alone is enough to replay a session.
+//
+
+use byteorder::{ByteOrder, NativeEndian};
+
+// PCG32 stream used for every session; only the seed varies.
+const RNG_STREAM: u64 = 1442695040888963407;
+
+/// The session RNG for `seed`.
+pub fn session_rng(seed: u64) -> randomize::PCG32 {
+    (seed, RNG_STREAM).into()
+}
+
+/// A seed for an RNG of its own, drawn from `rng`.
+pub fn next_seed(rng: &mut randomize::PCG32) -> u64 {
+    (rng.next_u32() as u64) << 32 | rng.next_u32() as u64
+}
+
+/// Generate a pseudorandom seed for the game's PRNG.
+pub fn generate_seed() -> u64 {
+    let mut seed = [0_u8; 8];
+
+    getrandom::getrandom(&mut seed).expect("failed to getrandom");
+
+    NativeEndian::read_u64(&seed)
+}
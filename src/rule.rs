@@ -0,0 +1,113 @@
+//
+// Life-like rules in B/S notation: `B3/S23` means a dead cell with 3 live
+// neighbors is born, and a live cell with 2 or 3 survives.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    // Bit n set: a cell with n live neighbors is born / survives.
+    birth: u16,
+    survival: u16,
+}
+
+impl Rule {
+    /// Conway's Game of Life.
+    pub const CONWAY: Self = Self {
+        birth: 1 << 3,
+        survival: (1 << 2) | (1 << 3),
+    };
+
+    pub fn births(&self, neighbors: usize) -> bool {
+        self.birth & (1 << neighbors) != 0
+    }
+
+    pub fn survives(&self, neighbors: usize) -> bool {
+        self.survival & (1 << neighbors) != 0
+    }
+
+    /// Flips one of the 18 B/S bits: 0-8 are B0-B8, 9-17 are S0-S8.
+    pub fn flip_bit(&mut self, bit: u32) {
+        debug_assert!(bit < 18);
+        if bit < 9 {
+            self.birth ^= 1 << bit;
+        } else {
+            self.survival ^= 1 << (bit - 9);
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        for n in (0..=8).filter(|&n| self.births(n)) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in (0..=8).filter(|&n| self.survives(n)) {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseRuleError(String);
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid rule {:?} (expected something like B3/S23)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    /// Accepts `B3/S23` (in either order, any case) and the older `23/3`
+    /// survival/birth form.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseRuleError(s.to_string());
+        let (a, b) = s.trim().split_once('/').ok_or_else(err)?;
+
+        let digits = |part: &str| -> Result<u16, ParseRuleError> {
+            part.chars()
+                .try_fold(0_u16, |mask, c| match c.to_digit(10) {
+                    Some(n) if n <= 8 => Ok(mask | 1 << n),
+                    _ => Err(err()),
+                })
+        };
+
+        let tagged = |part: &str| -> Option<(char, String)> {
+            let mut chars = part.chars();
+            let tag = chars.next()?.to_ascii_uppercase();
+            matches!(tag, 'B' | 'S').then(|| (tag, chars.collect()))
+        };
+
+        match (tagged(a), tagged(b)) {
+            (Some(('B', birth)), Some(('S', survival)))
+            | (Some(('S', survival)), Some(('B', birth))) => Ok(Self {
+                birth: digits(&birth)?,
+                survival: digits(&survival)?,
+            }),
+            (None, None) => Ok(Self {
+                survival: digits(a)?,
+                birth: digits(b)?,
+            }),
+            _ => Err(err()),
+        }
+    }
+}
@@ -0,0 +1,704 @@
+//
+// Golly's `.rule` files, the `@TABLE` kind: a list of transitions, each a
+// cell's state, its neighbors' and the state it goes to, like
+//
+//   @RULE Example
+//   @TABLE
+//   n_states:3
+//   neighborhood:Moore
+//   symmetries:rotate4
+//   var a={0,1,2}
+//   var b={a}
+//   0,1,a,0,0,0,0,0,b,2
+//
+// Neighbors are listed clockwise from north: N, NE, E, SE, S, SW, W, NW
+// for the Moore neighborhood, N, E, S, W for von Neumann's. A variable, or
+// a `{..}` set written in place, matches any of its states. A variable
+// named more than once in a transition stands for the same state each
+// time, so one can carry a neighbor's state through to the new one.
+// Symmetries add the transition's rotations (`rotate4`, and `rotate8` for
+// Moore) or every order of its neighbors (`permute`). The first
+// transition that matches wins, and a cell none match stays as it is.
+//
+// Colors come from `@COLORS` lines, `STATE R G B` or a `R G B R G B`
+// gradient over the live states, and default to Golly's red to yellow.
+// `@ICONS` and `@NAMES` only matter to Golly's screen and are skipped;
+// anything else, `@TREE` included, is refused rather than run wrong.
+//
+// Transitions are compiled the way Golly does: for each position and
+// state, a bitmask of the transitions that allow it, so looking a cell up
+// is an AND across positions and a search for the first bit set. Tables
+// small enough have every neighborhood looked up once, up front.
+//
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Most states a table can have.
+pub const MAX_STATES: usize = 256;
+
+// Tables with no more neighborhoods than this are looked up in full when
+// they're loaded.
+const MAX_PRECOMPUTED: usize = 1 << 20;
+
+// Most words of transition bitmasks a table can compile to (64 MiB).
+const MAX_MASK_WORDS: usize = 1 << 23;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The eight cells around.
+    Moore,
+    /// The four cells sharing an edge.
+    VonNeumann,
+}
+
+impl Neighborhood {
+    /// How many neighbors a cell has.
+    pub fn size(self) -> usize {
+        match self {
+            Self::Moore => 8,
+            Self::VonNeumann => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Symmetry {
+    None,
+    Rotate4,
+    Rotate8,
+    Permute,
+}
+
+// A set of states, one bit each.
+type States = [u64; MAX_STATES / 64];
+
+fn single(state: u8) -> States {
+    let mut set = [0; MAX_STATES / 64];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+fn contains(set: &States, state: usize) -> bool {
+    set[state / 64] & 1 << (state % 64) != 0
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleTable {
+    /// The name after `@RULE`, or the file's.
+    pub name: String,
+    /// Where it was loaded from, for sessions to name.
+    pub path: PathBuf,
+    pub states: usize,
+    pub neighborhood: Neighborhood,
+    /// A color for each state, in state order.
+    pub colors: Vec<[u8; 4]>,
+    // For each position (the cell, then its neighbors) and state, which
+    // transitions allow it, `words` u64s apiece.
+    masks: Vec<u64>,
+    words: usize,
+    // Each transition's new state.
+    outputs: Vec<u8>,
+    // Every neighborhood's new state, indexed by its states as digits in
+    // base `states`, the cell's lowest; empty when there are too many.
+    precomputed: Vec<u8>,
+}
+
+impl RuleTable {
+    /// Reads and parses a `.rule` file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        let mut table = Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if table.name.is_empty() {
+            table.name = path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        }
+        table.path = path.to_path_buf();
+        Ok(table)
+    }
+
+    /// Parses the text of a `.rule` file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::new();
+        let mut section = "";
+        let mut table_lines = Vec::new();
+        let mut color_lines = Vec::new();
+        let mut has_table = false;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let Some(header) = line.strip_prefix('@') {
+                let (key, rest) = header
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((header, ""));
+                section = match key {
+                    "RULE" => {
+                        name = rest.trim().to_string();
+                        "RULE"
+                    }
+                    "TABLE" => {
+                        has_table = true;
+                        "TABLE"
+                    }
+                    "COLORS" => "COLORS",
+                    "ICONS" | "NAMES" => "skipped",
+                    "TREE" => {
+                        return Err(format!(
+                            "line {}: @TREE rules aren't supported, only @TABLE",
+                            n + 1
+                        ))
+                    }
+                    other => return Err(format!("line {}: unknown section @{}", n + 1, other)),
+                };
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match section {
+                "TABLE" => table_lines.push((n + 1, line)),
+                "COLORS" => color_lines.push((n + 1, line)),
+                _ => {}
+            }
+        }
+        if !has_table {
+            return Err("no @TABLE section".to_string());
+        }
+
+        let mut table = compile(&table_lines)?;
+        table.name = name;
+        for (n, line) in color_lines {
+            let at = |e: String| format!("line {}: {}", n, e);
+            let numbers = line
+                .split_whitespace()
+                .map(|word| word.parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| at(format!("bad color {:?}", line)))?;
+            match numbers[..] {
+                [state, r, g, b] => {
+                    if let Some(color) = table.colors.get_mut(state as usize) {
+                        *color = [r, g, b, 0xff];
+                    }
+                }
+                [r1, g1, b1, r2, g2, b2] => {
+                    table.colors = gradient(table.states, [r1, g1, b1], [r2, g2, b2]);
+                }
+                _ => {
+                    return Err(at(format!(
+                        "expected STATE R G B or R G B R G B, not {:?}",
+                        line
+                    )))
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    /// The state a cell in `state` goes to, with `around` the states of
+    /// the eight cells around it, clockwise from north. Von Neumann tables
+    /// only look at every other one of those. States past the table's
+    /// count as 0.
+    pub fn next(&self, state: u8, around: [u8; 8]) -> u8 {
+        let mut cells = [0_u8; 9];
+        cells[0] = state;
+        match self.neighborhood {
+            Neighborhood::Moore => cells[1..].copy_from_slice(&around),
+            Neighborhood::VonNeumann => {
+                for (cell, &neighbor) in cells[1..5].iter_mut().zip(around.iter().step_by(2)) {
+                    *cell = neighbor;
+                }
+            }
+        }
+        let cells = &mut cells[..self.neighborhood.size() + 1];
+        for cell in cells.iter_mut() {
+            if *cell as usize >= self.states {
+                *cell = 0;
+            }
+        }
+
+        if self.precomputed.is_empty() {
+            self.look_up(cells)
+        } else {
+            let index = cells
+                .iter()
+                .rev()
+                .fold(0, |index, &cell| index * self.states + cell as usize);
+            self.precomputed[index]
+        }
+    }
+
+    // The first transition matching `cells` (the cell, then its
+    // neighbors), or the cell's own state if none do.
+    fn look_up(&self, cells: &[u8]) -> u8 {
+        for word in 0..self.words {
+            let mut matching = !0_u64;
+            for (position, &cell) in cells.iter().enumerate() {
+                let entry = position * self.states + cell as usize;
+                matching &= self.masks[entry * self.words + word];
+                if matching == 0 {
+                    break;
+                }
+            }
+            if matching != 0 {
+                return self.outputs[word * 64 + matching.trailing_zeros() as usize];
+            }
+        }
+        cells[0]
+    }
+}
+
+// One position of a transition as written.
+#[derive(Clone, Debug)]
+enum Term {
+    State(u8),
+    Variable(String),
+    Set(States),
+}
+
+// Builds the table from its `@TABLE` lines (with their line numbers).
+fn compile(lines: &[(usize, &str)]) -> Result<RuleTable, String> {
+    let mut states: Option<usize> = None;
+    let mut neighborhood = Neighborhood::Moore;
+    let mut symmetry = Symmetry::None;
+    let mut variables: HashMap<String, States> = HashMap::new();
+    // Every transition, as the states allowed at each position, and the
+    // state it goes to.
+    let mut transitions: Vec<(Vec<States>, u8)> = Vec::new();
+
+    for &(n, line) in lines {
+        let at = |e: String| format!("line {}: {}", n, e);
+        if let Some(definition) = line.strip_prefix("var ") {
+            let states = states.ok_or_else(|| at("a var before n_states".into()))?;
+            let (name, set) = definition
+                .split_once('=')
+                .ok_or_else(|| at(format!("expected var NAME={{..}}, not {:?}", line)))?;
+            let set = parse_set(set.trim(), states, &variables).map_err(at)?;
+            variables.insert(name.trim().to_string(), set);
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => {
+                    let n_states = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| (2..=MAX_STATES).contains(n))
+                        .ok_or_else(|| {
+                            at(format!(
+                                "n_states must be 2 to {}, not {:?}",
+                                MAX_STATES, value
+                            ))
+                        })?;
+                    states = Some(n_states);
+                }
+                "neighborhood" => {
+                    neighborhood = match value {
+                        "Moore" => Neighborhood::Moore,
+                        "vonNeumann" => Neighborhood::VonNeumann,
+                        _ => {
+                            return Err(at(format!(
+                                "neighborhood {} isn't supported (only Moore and vonNeumann)",
+                                value
+                            )))
+                        }
+                    }
+                }
+                "symmetries" => {
+                    symmetry = match value {
+                        "none" => Symmetry::None,
+                        "rotate4" => Symmetry::Rotate4,
+                        "rotate8" => Symmetry::Rotate8,
+                        "permute" => Symmetry::Permute,
+                        _ => {
+                            return Err(at(format!(
+                        "symmetries {} aren't supported (only none, rotate4, rotate8 and permute)",
+                        value
+                    )))
+                        }
+                    }
+                }
+                other => return Err(at(format!("unknown setting {:?}", other))),
+            }
+        } else {
+            let states = states.ok_or_else(|| at("a transition before n_states".into()))?;
+            if symmetry == Symmetry::Rotate8 && neighborhood == Neighborhood::VonNeumann {
+                return Err(at("rotate8 needs the Moore neighborhood".into()));
+            }
+            let terms = parse_transition(line, states, neighborhood).map_err(at)?;
+            expand(&terms, &variables, symmetry, &mut transitions).map_err(at)?;
+        }
+    }
+
+    let states = states.ok_or("no n_states")?;
+    let positions = neighborhood.size() + 1;
+    let words = transitions.len().div_ceil(64);
+    if positions * states * words > MAX_MASK_WORDS {
+        return Err(format!(
+            "the table expands to too many transitions ({})",
+            transitions.len()
+        ));
+    }
+    let mut masks = vec![0_u64; positions * states * words];
+    for (t, (inputs, _)) in transitions.iter().enumerate() {
+        for (position, set) in inputs.iter().enumerate() {
+            for state in (0..states).filter(|&state| contains(set, state)) {
+                let entry = position * states + state;
+                masks[entry * words + t / 64] |= 1 << (t % 64);
+            }
+        }
+    }
+
+    let mut table = RuleTable {
+        name: String::new(),
+        path: PathBuf::new(),
+        states,
+        neighborhood,
+        colors: gradient(states, [0xff, 0, 0], [0xff, 0xff, 0]),
+        masks,
+        words,
+        outputs: transitions.into_iter().map(|(_, output)| output).collect(),
+        precomputed: Vec::new(),
+    };
+
+    let neighborhoods = (0..positions).try_fold(1_usize, |total, _| total.checked_mul(states));
+    if let Some(total) = neighborhoods.filter(|&total| total <= MAX_PRECOMPUTED) {
+        let mut cells = vec![0_u8; positions];
+        table.precomputed = (0..total)
+            .map(|index| {
+                let mut rest = index;
+                for cell in cells.iter_mut() {
+                    *cell = (rest % states) as u8;
+                    rest /= states;
+                }
+                table.look_up(&cells)
+            })
+            .collect();
+    }
+    Ok(table)
+}
+
+// `{0,1,a}`: states, and the states of variables already defined.
+fn parse_set(
+    text: &str,
+    states: usize,
+    variables: &HashMap<String, States>,
+) -> Result<States, String> {
+    let inner = text
+        .strip_prefix('{')
+        .and_then(|text| text.strip_suffix('}'))
+        .ok_or_else(|| format!("expected {{..}}, not {:?}", text))?;
+    let mut set = [0; MAX_STATES / 64];
+    for element in inner.split(',').map(str::trim) {
+        let element = match parse_state(element, states)? {
+            Some(state) => single(state),
+            None => *variables
+                .get(element)
+                .ok_or_else(|| format!("unknown variable {:?}", element))?,
+        };
+        for (word, element) in set.iter_mut().zip(element) {
+            *word |= element;
+        }
+    }
+    Ok(set)
+}
+
+// A state number, or None if `text` isn't a number at all.
+fn parse_state(text: &str, states: usize) -> Result<Option<u8>, String> {
+    if !text.bytes().all(|c| c.is_ascii_digit()) || text.is_empty() {
+        return Ok(None);
+    }
+    match text.parse::<usize>() {
+        Ok(state) if state < states => Ok(Some(state as u8)),
+        _ => Err(format!("state {} is past n_states", text)),
+    }
+}
+
+// A transition line's terms: comma separated, or for tables of up to ten
+// states, one digit apiece with nothing between.
+fn parse_transition(
+    line: &str,
+    states: usize,
+    neighborhood: Neighborhood,
+) -> Result<Vec<Term>, String> {
+    let mut words = Vec::new();
+    if line.contains(',') {
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in line.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    words.push(line[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        words.push(line[start..].trim());
+    } else {
+        words.extend((0..line.len()).filter_map(|i| line.get(i..i + 1)));
+    }
+
+    let expected = neighborhood.size() + 2;
+    if words.len() != expected {
+        return Err(format!(
+            "expected {} states in a transition, not {} ({:?})",
+            expected,
+            words.len(),
+            line
+        ));
+    }
+    words
+        .into_iter()
+        .map(|word| {
+            Ok(if word.starts_with('{') {
+                Term::Set(parse_set(word, states, &HashMap::new())?)
+            } else if let Some(state) = parse_state(word, states)? {
+                Term::State(state)
+            } else {
+                Term::Variable(word.to_string())
+            })
+        })
+        .collect()
+}
+
+// Adds a transition's every binding of its repeated variables, and what
+// its symmetries make of those, to `transitions`.
+fn expand(
+    terms: &[Term],
+    variables: &HashMap<String, States>,
+    symmetry: Symmetry,
+    transitions: &mut Vec<(Vec<States>, u8)>,
+) -> Result<(), String> {
+    let (inputs, output) = terms.split_at(terms.len() - 1);
+    let uses = |name: &str| {
+        terms
+            .iter()
+            .filter(|term| matches!(term, Term::Variable(v) if v == name))
+            .count()
+    };
+
+    // Variables named more than once are bound: tried one state at a
+    // time. The others match their whole set.
+    let mut bound: Vec<(&str, Vec<u8>)> = Vec::new();
+    for term in inputs {
+        if let Term::Variable(name) = term {
+            let set = variables
+                .get(name)
+                .ok_or_else(|| format!("unknown variable {:?}", name))?;
+            if uses(name) > 1 && !bound.iter().any(|(b, _)| b == name) {
+                let states = (0..MAX_STATES)
+                    .filter(|&state| contains(set, state))
+                    .map(|state| state as u8)
+                    .collect();
+                bound.push((name, states));
+            }
+        }
+    }
+    match &output[0] {
+        Term::Variable(name) if !bound.iter().any(|(b, _)| b == name) => {
+            return Err(format!(
+                "the new state is variable {:?}, which the cells don't name",
+                name
+            ))
+        }
+        Term::Set(_) => return Err("the new state can't be a set".to_string()),
+        _ => {}
+    }
+    if bound.iter().any(|(_, states)| states.is_empty()) {
+        return Ok(());
+    }
+
+    let mut choice = vec![0_usize; bound.len()];
+    loop {
+        let state_of = |name: &str| {
+            bound
+                .iter()
+                .position(|(b, _)| *b == name)
+                .map(|b| bound[b].1[choice[b]])
+        };
+        let sets: Vec<States> = inputs
+            .iter()
+            .map(|term| match term {
+                Term::State(state) => single(*state),
+                Term::Set(set) => *set,
+                Term::Variable(name) => match state_of(name) {
+                    Some(state) => single(state),
+                    None => variables[name],
+                },
+            })
+            .collect();
+        let new_state = match &output[0] {
+            Term::State(state) => *state,
+            Term::Variable(name) => state_of(name).unwrap_or(0),
+            Term::Set(_) => unreachable!(),
+        };
+        add_symmetric(sets, new_state, symmetry, transitions);
+
+        // On to the next binding, odometer style.
+        let mut b = 0;
+        loop {
+            if b == bound.len() {
+                return Ok(());
+            }
+            choice[b] += 1;
+            if choice[b] < bound[b].1.len() {
+                break;
+            }
+            choice[b] = 0;
+            b += 1;
+        }
+    }
+}
+
+// Adds the transition from `sets` (the cell, then its neighbors) to
+// `new_state`, and every copy of it `symmetry` calls for.
+fn add_symmetric(
+    sets: Vec<States>,
+    new_state: u8,
+    symmetry: Symmetry,
+    transitions: &mut Vec<(Vec<States>, u8)>,
+) {
+    let neighbors = sets.len() - 1;
+    let rotations = |step: usize| {
+        (0..neighbors).step_by(step).map(|by| {
+            let mut rotated = sets.clone();
+            rotated[1..].rotate_left(by);
+            rotated
+        })
+    };
+    let copies: Vec<Vec<States>> = match symmetry {
+        Symmetry::None => vec![sets.clone()],
+        Symmetry::Rotate4 => rotations(neighbors / 4).collect(),
+        Symmetry::Rotate8 => rotations(1).collect(),
+        Symmetry::Permute => {
+            // Every distinct order of the neighbors, in lexicographic
+            // order from the sorted one.
+            let mut order = sets.clone();
+            order[1..].sort_unstable();
+            let mut copies = vec![order.clone()];
+            while next_permutation(&mut order[1..]) {
+                copies.push(order.clone());
+            }
+            copies
+        }
+    };
+    let mut seen = std::collections::HashSet::new();
+    for copy in copies {
+        if seen.insert(copy.clone()) {
+            transitions.push((copy, new_state));
+        }
+    }
+}
+
+// Rearranges `items` into the next permutation in lexicographic order,
+// returning false once they're back to the first.
+fn next_permutation<T: Ord>(items: &mut [T]) -> bool {
+    let Some(i) = (1..items.len()).rev().find(|&i| items[i - 1] < items[i]) else {
+        items.reverse();
+        return false;
+    };
+    let j = (i..items.len())
+        .rev()
+        .find(|&j| items[j] > items[i - 1])
+        .unwrap_or(i);
+    items.swap(i - 1, j);
+    items[i..].reverse();
+    true
+}
+
+// State 0 black, and the live states fading from `from` to `to`.
+fn gradient(states: usize, from: [u8; 3], to: [u8; 3]) -> Vec<[u8; 4]> {
+    let mut colors = vec![[0, 0, 0, 0xff]];
+    let live = states - 1;
+    for state in 0..live {
+        let t = if live > 1 {
+            state as f32 / (live - 1) as f32
+        } else {
+            0.0
+        };
+        let mix = |c: usize| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * t).round() as u8;
+        colors.push([mix(0), mix(1), mix(2), 0xff]);
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every neighborhood of `states` states, clockwise from north.
+    fn neighborhoods(states: u8) -> impl Iterator<Item = [u8; 8]> {
+        let count = (states as u32).pow(8);
+        (0..count).map(move |mut n| {
+            let mut around = [0; 8];
+            for neighbor in &mut around {
+                *neighbor = (n % states as u32) as u8;
+                n /= states as u32;
+            }
+            around
+        })
+    }
+
+    #[test]
+    fn life_as_a_permute_table_is_life() {
+        let table = RuleTable::parse(
+            "@RULE Life\n\
+             @TABLE\n\
+             n_states:2\n\
+             neighborhood:Moore\n\
+             symmetries:permute\n\
+             var a={0,1}\nvar b={a}\nvar c={a}\nvar d={a}\n\
+             var e={a}\nvar f={a}\nvar g={a}\nvar h={a}\n\
+             0,1,1,1,0,0,0,0,0,1\n\
+             1,1,1,0,0,0,0,0,0,1\n\
+             1,1,1,1,0,0,0,0,0,1\n\
+             1,a,b,c,d,e,f,g,h,0\n",
+        )
+        .unwrap();
+        assert_eq!(table.name, "Life");
+        for around in neighborhoods(2) {
+            let live = around.iter().filter(|&&state| state == 1).count();
+            assert_eq!(table.next(0, around), (live == 3) as u8, "{:?}", around);
+            let survives = live == 2 || live == 3;
+            assert_eq!(table.next(1, around), survives as u8, "{:?}", around);
+        }
+    }
+
+    #[test]
+    fn brians_brain_as_a_table_is_brians_brain() {
+        let table = RuleTable::parse(
+            "@TABLE\n\
+             n_states:3\n\
+             neighborhood:Moore\n\
+             symmetries:permute\n\
+             var a={0,2}\nvar b={a}\nvar c={a}\nvar d={a}\nvar e={a}\nvar f={a}\n\
+             0,1,1,a,b,c,d,e,f,1\n\
+             var g={0,1,2}\nvar h={g}\nvar i={g}\nvar j={g}\n\
+             var k={g}\nvar l={g}\nvar m={g}\nvar n={g}\n\
+             1,g,h,i,j,k,l,m,n,2\n\
+             2,g,h,i,j,k,l,m,n,0\n",
+        )
+        .unwrap();
+        for around in neighborhoods(3) {
+            let on = around.iter().filter(|&&state| state == 1).count();
+            assert_eq!(table.next(0, around), (on == 2) as u8, "{:?}", around);
+            assert_eq!(table.next(1, around), 2);
+            assert_eq!(table.next(2, around), 0);
+        }
+    }
+
+    #[test]
+    fn what_cant_be_run_right_is_refused() {
+        for text in [
+            "@RULE NoTable\n",
+            "@TABLE\nn_states:2\n@TREE\n",
+            "@TABLE\nn_states:2\n@SOUNDS\n",
+            "@TABLE\nn_states:2\nneighborhood:Moore\n0,1,1,1,1\n",
+        ] {
+            assert!(RuleTable::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
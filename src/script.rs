@@ -0,0 +1,351 @@
+//
+// `--script <path>` (`scripting` feature): a Rhai script that sets up the
+// starting grid and, if it defines `on_generation()`, reacts to it after
+// every generation:
+//
+//   // A glider every 20 cells down the diagonal.
+//   for i in 0..width() / 20 {
+//       paste("bo$2bo$3o!", i * 20, i * 20);
+//   }
+//
+//   fn on_generation() {
+//       if population() > 5000 {
+//           kill(0.3);
+//       }
+//   }
+//
+// The grid is there through a few functions:
+//
+//   width(), height(), generation(), population()
+//   get(x, y)              whether the cell is alive
+//   set(x, y, alive)
+//   paste(rle, x, y)       an RLE pattern with its top-left corner at x, y
+//   randomize(density)     a new soup
+//   kill(probability)      each live cell dies with that chance
+//   random()               a number from 0 up to 1
+//
+// Edits go through the session like any other, so they're recorded, and
+// they're undone the way the brush's are: a paste, soup or kill ends an
+// undo step, and so does the end of each run of the script. Randomness
+// comes from the session seed, so the same seed scripts the same grid.
+//
+// `on_generation` gets a few milliseconds a generation, and the top of the
+// script a few seconds. A script that runs over, or fails, is stopped with
+// its error in the log (with the line it was on), and the hook isn't called
+// again; the grid carries on without it.
+//
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use instant::Instant;
+use log::error;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use tgol::grid::Grid;
+use tgol::history::History;
+use tgol::rle;
+use tgol::rng::{next_seed, session_rng};
+use tgol::symmetry::SoupSymmetry;
+
+use crate::session::{self, perform, Recorder};
+
+// Time the top of the script has, and `on_generation` has each generation.
+const INIT_BUDGET: Duration = Duration::from_secs(5);
+const HOOK_BUDGET: Duration = Duration::from_millis(5);
+
+// Operations between looks at the clock.
+const PROGRESS_INTERVAL: u64 = 256;
+
+const HOOK: &str = "on_generation";
+
+pub struct Script {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    // What the script's functions work on while it runs.
+    lent: Arc<Mutex<Lent>>,
+    // Whether `on_generation` is still to be called.
+    hook: bool,
+}
+
+// The grid and what goes with editing it, lent to the script for as long
+// as it runs, and the script's own state.
+struct Lent {
+    life: Grid,
+    history: History,
+    recorder: Option<Recorder>,
+    rng: randomize::PCG32,
+    // Whether the script has changed the grid this run.
+    edited: bool,
+    deadline: Instant,
+}
+
+impl Lent {
+    fn perform(&mut self, event: session::Event) {
+        perform(&mut self.recorder, &mut self.life, &mut self.history, event);
+        self.edited = true;
+    }
+}
+
+impl Script {
+    /// Reads and compiles the script at `path`, drawing its randomness
+    /// from `seed`.
+    pub fn load(path: &Path, seed: u64) -> Result<Self, String> {
+        let lent = Arc::new(Mutex::new(Lent {
+            life: Grid::new_empty_grid(0, 0),
+            history: History::default(),
+            recorder: None,
+            // Its own stream, not a copy of the session RNG's.
+            rng: session_rng(next_seed(&mut session_rng(seed))),
+            edited: false,
+            deadline: Instant::now(),
+        }));
+        let engine = engine(&lent);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let hook = ast
+            .iter_functions()
+            .any(|f| f.name == HOOK && f.params.is_empty());
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            lent,
+            hook,
+        })
+    }
+
+    /// Runs the top of the script, once, on the starting grid. Returns
+    /// whether it changed the grid.
+    pub fn init(
+        &mut self,
+        life: &mut Grid,
+        history: &mut History,
+        recorder: &mut Option<Recorder>,
+    ) -> bool {
+        let (engine, ast) = (&self.engine, &self.ast);
+        let (result, edited) = lend(&self.lent, life, history, recorder, INIT_BUDGET, || {
+            engine.run_ast(ast)
+        });
+        if let Err(e) = result {
+            self.fail(&e, INIT_BUDGET);
+        }
+        edited
+    }
+
+    /// Calls `on_generation()`, if there is one and it hasn't failed.
+    /// Returns whether it changed the grid.
+    pub fn on_generation(
+        &mut self,
+        life: &mut Grid,
+        history: &mut History,
+        recorder: &mut Option<Recorder>,
+    ) -> bool {
+        if !self.hook {
+            return false;
+        }
+        let (engine, ast) = (&self.engine, &self.ast);
+        let (result, edited) = lend(&self.lent, life, history, recorder, HOOK_BUDGET, || {
+            // Only the function: the top of the script was run at the start.
+            let options = CallFnOptions::new().eval_ast(false);
+            engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, HOOK, ())
+        });
+        if let Err(e) = result {
+            self.fail(&e, HOOK_BUDGET);
+        }
+        edited
+    }
+
+    // Logs why the script stopped. It isn't called again after that.
+    fn fail(&mut self, e: &EvalAltResult, budget: Duration) {
+        let why = match e {
+            EvalAltResult::ErrorTerminated(..) => format!("ran for more than {:?}", budget),
+            _ => e.to_string(),
+        };
+        if self.hook {
+            error!(
+                "{}: {}; {} won't be called again",
+                self.path.display(),
+                why,
+                HOOK
+            );
+        } else {
+            error!("{}: {}", self.path.display(), why);
+        }
+        self.hook = false;
+    }
+}
+
+// Runs `script` with the grid lent to the script's functions, for no more
+// than `budget`. What it changes is committed as one edit; the second
+// value says whether there was any.
+fn lend<T>(
+    lent: &Mutex<Lent>,
+    life: &mut Grid,
+    history: &mut History,
+    recorder: &mut Option<Recorder>,
+    budget: Duration,
+    script: impl FnOnce() -> Result<T, Box<EvalAltResult>>,
+) -> (Result<T, Box<EvalAltResult>>, bool) {
+    {
+        let mut lent = lent.lock().unwrap();
+        std::mem::swap(&mut lent.life, life);
+        std::mem::swap(&mut lent.history, history);
+        std::mem::swap(&mut lent.recorder, recorder);
+        lent.edited = false;
+        lent.deadline = Instant::now() + budget;
+    }
+    let result = script();
+    let mut lent = lent.lock().unwrap();
+    let edited = lent.edited;
+    if edited {
+        lent.perform(session::Event::Commit);
+    }
+    std::mem::swap(&mut lent.life, life);
+    std::mem::swap(&mut lent.history, history);
+    std::mem::swap(&mut lent.recorder, recorder);
+    (result, edited)
+}
+
+// An engine with the grid's functions, working on `lent`.
+fn engine(lent: &Arc<Mutex<Lent>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_print(|text| log::info!("{}", text));
+    engine.on_debug(|text, _, at| log::debug!("{} {}", at, text));
+    let state = Arc::clone(lent);
+    engine.on_progress(move |operations| {
+        let late =
+            operations % PROGRESS_INTERVAL == 0 && Instant::now() > state.lock().unwrap().deadline;
+        late.then_some(Dynamic::UNIT)
+    });
+
+    // Registers a function that works on the lent grid.
+    macro_rules! register {
+        ($name:literal, |$lent:ident $(, $arg:ident: $ty:ty)*| $(-> $ret:ty)? $body:block) => {{
+            let state = Arc::clone(lent);
+            engine.register_fn($name, move |$($arg: $ty),*| $(-> $ret)? {
+                let $lent = &mut *state.lock().unwrap();
+                $body
+            });
+        }};
+    }
+
+    register!("width", |lent| { lent.life.width as i64 });
+    register!("height", |lent| { lent.life.height as i64 });
+    register!("generation", |lent| { lent.life.generation() as i64 });
+    register!("population", |lent| { lent.life.population() as i64 });
+    register!("get", |lent, x: i64, y: i64| {
+        let life = &lent.life;
+        life.edit_idx(x as isize, y as isize)
+            .is_some_and(|i| life.grid.get(i).alive)
+    });
+    register!("set", |lent, x: i64, y: i64, alive: bool| {
+        lent.perform(session::Event::Disc {
+            x: x as isize,
+            y: y as isize,
+            radius: 0,
+            alive,
+        })
+    });
+    register!("paste", |lent,
+                        pattern: &str,
+                        x: i64,
+                        y: i64|
+     -> Result<(), Box<EvalAltResult>> {
+        let pattern = rle::parse(pattern).map_err(|e| e.to_string())?;
+        lent.perform(session::Event::Paste {
+            x: x as isize,
+            y: y as isize,
+            pattern,
+        });
+        Ok(())
+    });
+    register!("randomize", |lent, density: f64| {
+        let seed = next_seed(&mut lent.rng);
+        lent.perform(session::Event::Randomize {
+            seed,
+            density: density.clamp(0.0, 1.0) as f32,
+            symmetry: SoupSymmetry::None,
+            pre_roll: 0,
+        })
+    });
+    register!("kill", |lent, probability: f64| {
+        let seed = next_seed(&mut lent.rng);
+        lent.perform(session::Event::Kill {
+            seed,
+            probability: probability.clamp(0.0, 1.0) as f32,
+        })
+    });
+    register!("random", |lent| {
+        lent.rng.next_u32() as f64 / (u32::MAX as f64 + 1.0)
+    });
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, text: &str, seed: u64) -> Result<Script, String> {
+        let path =
+            std::env::temp_dir().join(format!("tgol-script-{}-{}.rhai", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        let script = Script::load(&path, seed);
+        let _ = std::fs::remove_file(&path);
+        script
+    }
+
+    #[test]
+    fn edits_are_undone_a_step_at_a_time() {
+        let mut script =
+            load("top", "paste(\"bo$2bo$3o!\", 4, 4);\nset(0, 0, true);\n", 1).unwrap();
+        let mut life = Grid::new_empty_grid(16, 16);
+        let mut history = History::default();
+        assert!(script.init(&mut life, &mut history, &mut None));
+        assert_eq!(life.population(), 6);
+        // There's no hook to call.
+        assert!(!script.on_generation(&mut life, &mut history, &mut None));
+
+        // The paste ended one step, and the end of the run the set's.
+        assert!(history.undo(&mut life));
+        assert_eq!(life.population(), 5);
+        assert!(history.undo(&mut life));
+        assert_eq!(life.population(), 0);
+        assert!(!history.undo(&mut life));
+    }
+
+    #[test]
+    fn the_same_seed_scripts_the_same_grid() {
+        let run = |seed| {
+            let mut script = load("seed", "randomize(0.5);\nkill(random());\n", seed).unwrap();
+            let mut life = Grid::new_empty_grid(32, 32);
+            script.init(&mut life, &mut History::default(), &mut None);
+            life.grid.alive
+        };
+        assert_eq!(run(3), run(3));
+        assert_ne!(run(3), run(4));
+    }
+
+    #[test]
+    fn a_hook_that_runs_over_is_not_called_again() {
+        let mut script = load("hook", "fn on_generation() {\n    loop {}\n}\n", 1).unwrap();
+        let mut life = Grid::new_empty_grid(16, 16);
+        let mut history = History::default();
+        assert!(!script.init(&mut life, &mut history, &mut None));
+        assert!(script.hook);
+
+        let start = Instant::now();
+        script.on_generation(&mut life, &mut history, &mut None);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(!script.hook);
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_with_their_line() {
+        let error = load("syntax", "let a = 1;\nlet = 2;\n", 1).err().unwrap();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,800 @@
+//
+// `--record <PATH>` and `--replay <PATH>`: a session as a log of what was
+// done to the grid, each line stamped with the generation it happened at.
+// The header holds everything the starting grid is made from, and every
+// random event carries its own seed, so a replay drives the grid through
+// the same changes at the same generations and ends up exactly where the
+// recording did. The last line is a hash of the final grid to check that.
+//
+//   seed 1234
+//   size 384 240
+//   ...
+//   120 disc 10 20 0 alive
+//   121 line 10 20 14 22 0 alive
+//   125 commit
+//   310 randomize 998877 0.25 none 5
+//   end 400 12345678901234567
+//
+// What only changes how things look (colors, grid lines, selections) isn't
+// recorded. Editing while a replay is playing makes it diverge.
+//
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tgol::ant::DEFAULT_ANT_STEPS;
+use tgol::automaton::Automaton;
+use tgol::decay::Decay;
+use tgol::engine::Engine;
+use tgol::grid::Grid;
+use tgol::history::History;
+use tgol::noise::Noise;
+use tgol::pattern::PatternBuffer;
+use tgol::rng::session_rng;
+use tgol::rule::Rule;
+use tgol::rule_table::RuleTable;
+use tgol::stochastic::Stochastic;
+use tgol::symmetry::SoupSymmetry;
+use tgol::topology::Topology;
+use tgol::world::World;
+
+use crate::cli::{GridSetup, Options, Placement};
+
+/// One thing done to the grid, with everything needed to do it again.
+#[derive(Clone, Debug)]
+pub enum Event {
+    Randomize {
+        seed: u64,
+        density: f32,
+        symmetry: SoupSymmetry,
+        pre_roll: usize,
+    },
+    Kill {
+        seed: u64,
+        probability: f32,
+    },
+    Invert,
+    /// A click: the brush disc at (x, y) set to `alive`.
+    Disc {
+        x: isize,
+        y: isize,
+        radius: u32,
+        alive: bool,
+    },
+    /// Dragging the brush from one cell to the next.
+    Line {
+        from: (isize, isize),
+        to: (isize, isize),
+        radius: u32,
+        alive: bool,
+    },
+    Rect {
+        from: (isize, isize),
+        to: (isize, isize),
+        alive: bool,
+    },
+    /// One frame's worth of airbrush around (x, y).
+    Spray {
+        x: isize,
+        y: isize,
+        radius: u32,
+        probability: f32,
+        seed: u64,
+    },
+    Paste {
+        x: isize,
+        y: isize,
+        pattern: PatternBuffer,
+    },
+    /// Ends the undo step the brush events since the last one make up.
+    Commit,
+    Undo,
+    Redo,
+    Rule(Rule),
+    Trails(bool),
+    Decay(Decay),
+    Noise(Noise),
+    Engine(Engine),
+    Topology(Topology),
+    /// The team that cells brought to life by edits join.
+    Team(u8),
+    /// The Wireworld or rule table state edits paint.
+    Material(u8),
+    /// A new Langton's Ant at (x, y).
+    Ant(isize, isize),
+    Advance(u64),
+    View(i64, i64),
+}
+
+impl Event {
+    /// Does it to `life`, through `history` for anything undoable. Returns
+    /// false for an undo or redo with nothing to do, true otherwise.
+    pub fn apply(&self, life: &mut Grid, history: &mut History) -> bool {
+        match *self {
+            Event::Randomize {
+                seed,
+                density,
+                symmetry,
+                pre_roll,
+            } => history.record_one(life, |grid| {
+                grid.new_soup(seed, density, symmetry, pre_roll);
+            }),
+            Event::Kill { seed, probability } => history.record_one(life, |grid| {
+                grid.randomly_kill(probability, &mut session_rng(seed));
+            }),
+            Event::Invert => history.record_one(life, Grid::invert),
+            Event::Disc {
+                x,
+                y,
+                radius,
+                alive,
+            } => history.record(life, |grid| grid.set_disc(x, y, radius, alive)),
+            Event::Line {
+                from,
+                to,
+                radius,
+                alive,
+            } => history.record(life, |grid| {
+                grid.set_line(from.0, from.1, to.0, to.1, radius, alive)
+            }),
+            Event::Rect { from, to, alive } => history.record_one(life, |grid| {
+                grid.set_rect(from.0, from.1, to.0, to.1, alive)
+            }),
+            Event::Spray {
+                x,
+                y,
+                radius,
+                probability,
+                seed,
+            } => history.record(life, |grid| {
+                grid.spray(x, y, radius, probability, &mut session_rng(seed))
+            }),
+            Event::Paste { x, y, ref pattern } => {
+                history.record_one(life, |grid| grid.insert_pattern(pattern, x, y))
+            }
+            Event::Commit => history.commit(),
+            Event::Undo => return history.undo(life),
+            Event::Redo => return history.redo(life),
+            Event::Rule(rule) => life.rule = rule,
+            Event::Trails(trails) => life.trails = trails,
+            Event::Decay(decay) => life.decay = decay,
+            Event::Noise(noise) => life.noise = noise,
+            Event::Engine(engine) => life.engine = engine,
+            Event::Topology(topology) => life.topology = topology,
+            Event::Team(team) => life.team = team,
+            Event::Material(material) => life.material = material,
+            Event::Ant(x, y) => life.add_ant(x, y),
+            Event::Advance(generations) => life.advance(generations),
+            Event::View(x, y) => {
+                life.set_view(x, y);
+                history.clear();
+            }
+        }
+        true
+    }
+}
+
+// A pattern as rows of `#` and `.` (and digits for other states up to 9,
+// which is more than any automaton has), separated by slashes.
+pub fn rows_text(pattern: &PatternBuffer) -> String {
+    let rows: Vec<String> = (0..pattern.height())
+        .map(|y| {
+            (0..pattern.width())
+                .map(|x| match pattern.state(x, y) {
+                    0 => '.',
+                    state @ 2..=9 => (b'0' + state) as char,
+                    _ => '#',
+                })
+                .collect()
+        })
+        .collect();
+    rows.join("/")
+}
+
+fn from_rows_text(text: &str) -> PatternBuffer {
+    let rows: Vec<&str> = text.split('/').collect();
+    PatternBuffer::from_rows(&rows)
+}
+
+fn alive_word(alive: bool) -> &'static str {
+    if alive {
+        "alive"
+    } else {
+        "dead"
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Randomize {
+                seed,
+                density,
+                symmetry,
+                pre_roll,
+            } => write!(
+                f,
+                "randomize {} {} {} {}",
+                seed, density, symmetry, pre_roll
+            ),
+            Event::Kill { seed, probability } => write!(f, "kill {} {}", seed, probability),
+            Event::Invert => write!(f, "invert"),
+            Event::Disc {
+                x,
+                y,
+                radius,
+                alive,
+            } => write!(f, "disc {} {} {} {}", x, y, radius, alive_word(*alive)),
+            Event::Line {
+                from,
+                to,
+                radius,
+                alive,
+            } => write!(
+                f,
+                "line {} {} {} {} {} {}",
+                from.0,
+                from.1,
+                to.0,
+                to.1,
+                radius,
+                alive_word(*alive)
+            ),
+            Event::Rect { from, to, alive } => write!(
+                f,
+                "rect {} {} {} {} {}",
+                from.0,
+                from.1,
+                to.0,
+                to.1,
+                alive_word(*alive)
+            ),
+            Event::Spray {
+                x,
+                y,
+                radius,
+                probability,
+                seed,
+            } => write!(f, "spray {} {} {} {} {}", x, y, radius, probability, seed),
+            Event::Paste { x, y, pattern } => {
+                write!(f, "paste {} {} {}", x, y, rows_text(pattern))
+            }
+            Event::Commit => write!(f, "commit"),
+            Event::Undo => write!(f, "undo"),
+            Event::Redo => write!(f, "redo"),
+            Event::Rule(rule) => write!(f, "rule {}", rule),
+            Event::Trails(trails) => write!(f, "trails {}", trails),
+            Event::Decay(decay) => write!(f, "decay {}", decay),
+            Event::Noise(noise) => write!(f, "noise {}", noise),
+            Event::Engine(engine) => write!(f, "engine {}", engine),
+            Event::Topology(topology) => write!(f, "topology {}", topology),
+            Event::Team(team) => write!(f, "team {}", team),
+            Event::Material(material) => write!(f, "material {}", material),
+            Event::Ant(x, y) => write!(f, "ant {} {}", x, y),
+            Event::Advance(generations) => write!(f, "advance {}", generations),
+            Event::View(x, y) => write!(f, "view {} {}", x, y),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let mut arg = || {
+            words
+                .next()
+                .ok_or_else(|| format!("{:?} is missing arguments", s))
+        };
+
+        fn parsed<T: FromStr>(word: &str) -> Result<T, String> {
+            word.parse().map_err(|_| format!("bad value {:?}", word))
+        }
+        fn alive(word: &str) -> Result<bool, String> {
+            match word {
+                "alive" => Ok(true),
+                "dead" => Ok(false),
+                _ => Err(format!("expected alive or dead, got {:?}", word)),
+            }
+        }
+
+        Ok(match name {
+            "randomize" => Event::Randomize {
+                seed: parsed(arg()?)?,
+                density: parsed(arg()?)?,
+                symmetry: parsed(arg()?)?,
+                pre_roll: parsed(arg()?)?,
+            },
+            "kill" => Event::Kill {
+                seed: parsed(arg()?)?,
+                probability: parsed(arg()?)?,
+            },
+            "invert" => Event::Invert,
+            "disc" => Event::Disc {
+                x: parsed(arg()?)?,
+                y: parsed(arg()?)?,
+                radius: parsed(arg()?)?,
+                alive: alive(arg()?)?,
+            },
+            "line" => Event::Line {
+                from: (parsed(arg()?)?, parsed(arg()?)?),
+                to: (parsed(arg()?)?, parsed(arg()?)?),
+                radius: parsed(arg()?)?,
+                alive: alive(arg()?)?,
+            },
+            "rect" => Event::Rect {
+                from: (parsed(arg()?)?, parsed(arg()?)?),
+                to: (parsed(arg()?)?, parsed(arg()?)?),
+                alive: alive(arg()?)?,
+            },
+            "spray" => Event::Spray {
+                x: parsed(arg()?)?,
+                y: parsed(arg()?)?,
+                radius: parsed(arg()?)?,
+                probability: parsed(arg()?)?,
+                seed: parsed(arg()?)?,
+            },
+            "paste" => {
+                let x = parsed(arg()?)?;
+                let y = parsed(arg()?)?;
+                Event::Paste {
+                    x,
+                    y,
+                    pattern: from_rows_text(arg()?),
+                }
+            }
+            "commit" => Event::Commit,
+            "undo" => Event::Undo,
+            "redo" => Event::Redo,
+            "rule" => Event::Rule(arg()?.parse::<Rule>().map_err(|e| e.to_string())?),
+            "trails" => Event::Trails(parsed(arg()?)?),
+            "decay" => Event::Decay(arg()?.parse()?),
+            "noise" => Event::Noise(arg()?.parse()?),
+            "engine" => Event::Engine(arg()?.parse()?),
+            "topology" => Event::Topology(arg()?.parse()?),
+            "team" => Event::Team(parsed(arg()?)?),
+            "material" => Event::Material(parsed(arg()?)?),
+            "ant" => Event::Ant(parsed(arg()?)?, parsed(arg()?)?),
+            "advance" => Event::Advance(parsed(arg()?)?),
+            "view" => Event::View(parsed(arg()?)?, parsed(arg()?)?),
+            _ => return Err(format!("unknown event {:?}", name)),
+        })
+    }
+}
+
+/// FNV-1a over every cell (heat, age and any team included) and the
+/// generation, to tell whether a replay ended where its recording did.
+pub fn grid_hash(life: &Grid) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut eat = |byte: u8| hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+    let states = life.automaton != Automaton::Life;
+    for cell in life.grid.iter() {
+        eat(cell.alive as u8);
+        eat(cell.heat);
+        cell.age.to_le_bytes().into_iter().for_each(&mut eat);
+        if states {
+            eat(cell.state);
+        }
+    }
+    for ant in &life.ants {
+        (ant.x as u64).to_le_bytes().into_iter().for_each(&mut eat);
+        (ant.y as u64).to_le_bytes().into_iter().for_each(&mut eat);
+        eat(ant.heading as u8);
+    }
+    life.generation().to_le_bytes().into_iter().for_each(eat);
+    hash
+}
+
+/// Writes a session out as it happens, a line at a time so a crash loses
+/// nothing.
+pub struct Recorder {
+    out: LineWriter<File>,
+}
+
+impl Recorder {
+    /// Starts a recording of a session from `seed`, made the way `options`
+    /// ask, now that `life` holds its starting grid.
+    pub fn create(path: &Path, options: &Options, seed: u64, life: &Grid) -> io::Result<Self> {
+        let mut out = LineWriter::new(File::create(path)?);
+        writeln!(out, "seed {}", seed)?;
+        writeln!(out, "size {} {}", life.width, life.height)?;
+        writeln!(out, "rule {}", life.rule)?;
+        writeln!(out, "automaton {}", life.automaton)?;
+        if let Some(table) = &life.rule_table {
+            writeln!(out, "rulefile {}", table.path.display())?;
+        }
+        writeln!(out, "ant-steps {}", life.ant_steps)?;
+        if let Some(stochastic) = life.stochastic {
+            writeln!(out, "stochastic {}", stochastic)?;
+        }
+        writeln!(out, "density {}", options.density)?;
+        writeln!(out, "symmetry {}", options.symmetry)?;
+        writeln!(out, "pre-roll {}", options.pre_roll)?;
+        writeln!(out, "decay {}", life.decay)?;
+        writeln!(out, "noise {}", life.noise)?;
+        writeln!(out, "trails {}", life.trails)?;
+        writeln!(out, "engine {}", life.engine)?;
+        writeln!(out, "world {}", life.world())?;
+        writeln!(out, "topology {}", life.topology)?;
+        writeln!(out, "empty {}", options.empty)?;
+        for (pattern, x, y) in crate::placements(options, (life.width, life.height)) {
+            writeln!(out, "pattern {} {} {}", x, y, rows_text(pattern))?;
+        }
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, generation: u64, event: &Event) -> io::Result<()> {
+        writeln!(self.out, "{} {}", generation, event)
+    }
+
+    /// Ends the recording with the final grid's hash.
+    pub fn finish(&mut self, life: &Grid) -> io::Result<()> {
+        writeln!(self.out, "end {} {}", life.generation(), grid_hash(life))?;
+        self.out.flush()
+    }
+}
+
+/// Records `event` (if there's a recording going) and applies it. Returns
+/// what `Event::apply` does.
+pub fn perform(
+    recorder: &mut Option<Recorder>,
+    life: &mut Grid,
+    history: &mut History,
+    event: Event,
+) -> bool {
+    if let Some(out) = recorder {
+        if let Err(e) = out.record(life.generation(), &event) {
+            log::error!("Couldn't write to the recording, so it's stopped: {}", e);
+            *recorder = None;
+        }
+    }
+    event.apply(life, history)
+}
+
+/// A recorded session being played back.
+pub struct Replay {
+    // Header values, for `configure`.
+    seed: u64,
+    size: (u32, u32),
+    rule: Rule,
+    automaton: Automaton,
+    rule_table: Option<Arc<RuleTable>>,
+    ant_steps: u32,
+    stochastic: Option<Stochastic>,
+    density: f32,
+    symmetry: SoupSymmetry,
+    pre_roll: usize,
+    decay: Decay,
+    noise: Noise,
+    pub trails: bool,
+    engine: Engine,
+    world: World,
+    topology: Topology,
+    empty: bool,
+    patterns: Vec<Placement>,
+    events: VecDeque<(u64, Event)>,
+    // Where the recording ended, and the hash it ended with.
+    end: Option<(u64, u64)>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+
+        let mut replay = Self {
+            seed: 0,
+            size: (0, 0),
+            rule: Rule::default(),
+            automaton: Automaton::default(),
+            rule_table: None,
+            ant_steps: DEFAULT_ANT_STEPS,
+            stochastic: None,
+            density: 0.25,
+            symmetry: SoupSymmetry::default(),
+            pre_roll: 0,
+            decay: Decay::default(),
+            noise: Noise::Off,
+            trails: true,
+            engine: Engine::default(),
+            world: World::default(),
+            topology: Topology::default(),
+            empty: false,
+            patterns: Vec::new(),
+            events: VecDeque::new(),
+            end: None,
+        };
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let at = |e: String| format!("{}:{}: {}", path.display(), n + 1, e);
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            fn parsed<T: FromStr>(word: &str) -> Result<T, String> {
+                word.parse().map_err(|_| format!("bad value {:?}", word))
+            }
+
+            match key {
+                "" => {}
+                "seed" => replay.seed = parsed(rest).map_err(at)?,
+                "size" => {
+                    let (w, h) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| at("expected a width and height".into()))?;
+                    replay.size = (parsed(w).map_err(at)?, parsed(h.trim()).map_err(at)?);
+                }
+                "rule" => replay.rule = rest.parse::<Rule>().map_err(|e| at(e.to_string()))?,
+                "automaton" => replay.automaton = rest.parse().map_err(at)?,
+                "rulefile" => {
+                    let table = RuleTable::load(Path::new(rest)).map_err(at)?;
+                    replay.rule_table = Some(Arc::new(table));
+                }
+                "ant-steps" => replay.ant_steps = parsed(rest).map_err(at)?,
+                "stochastic" => replay.stochastic = Some(rest.parse().map_err(at)?),
+                "density" => replay.density = parsed(rest).map_err(at)?,
+                "symmetry" => replay.symmetry = rest.parse().map_err(at)?,
+                "pre-roll" => replay.pre_roll = parsed(rest).map_err(at)?,
+                "decay" => replay.decay = rest.parse().map_err(at)?,
+                "noise" => replay.noise = rest.parse().map_err(at)?,
+                "trails" => replay.trails = parsed(rest).map_err(at)?,
+                "engine" => replay.engine = rest.parse().map_err(at)?,
+                "world" => replay.world = rest.parse().map_err(at)?,
+                "topology" => replay.topology = rest.parse().map_err(at)?,
+                "empty" => replay.empty = parsed(rest).map_err(at)?,
+                "pattern" => {
+                    let mut words = rest.split_whitespace();
+                    let mut word = || words.next().ok_or_else(|| at("expected X Y ROWS".into()));
+                    let x = parsed(word()?).map_err(at)?;
+                    let y = parsed(word()?).map_err(at)?;
+                    let pattern = from_rows_text(word()?);
+                    replay.patterns.push(Placement {
+                        pattern,
+                        at: Some((x, y)),
+                    });
+                }
+                "end" => {
+                    let (generation, hash) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| at("expected a generation and hash".into()))?;
+                    replay.end = Some((
+                        parsed(generation).map_err(at)?,
+                        parsed(hash.trim()).map_err(at)?,
+                    ));
+                }
+                generation => {
+                    let generation = parsed(generation).map_err(at)?;
+                    replay
+                        .events
+                        .push_back((generation, rest.parse().map_err(at)?));
+                }
+            }
+        }
+        // Checked as the command line is, as it's started the same way.
+        let setup = GridSetup {
+            size: replay.size,
+            automaton: replay.automaton,
+            rule_table: replay.rule_table.is_some(),
+            ant_steps: replay.ant_steps,
+            world: replay.world,
+            topology: replay.topology,
+            stochastic: replay.stochastic.is_some(),
+        };
+        setup
+            .check()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if !(0.0..=1.0).contains(&replay.density) {
+            return Err(format!(
+                "{}: the density must be between 0 and 1",
+                path.display()
+            ));
+        }
+        Ok(replay)
+    }
+
+    /// Makes `options` start the grid the way the recording did.
+    pub fn configure(&self, options: &mut Options) {
+        options.seed = Some(self.seed);
+        options.width = Some(self.size.0);
+        options.height = Some(self.size.1);
+        options.rule = self.rule;
+        options.automaton = self.automaton;
+        options.rule_table = self.rule_table.clone();
+        options.ant_steps = self.ant_steps;
+        options.stochastic = self.stochastic;
+        options.density = self.density;
+        options.symmetry = self.symmetry;
+        options.pre_roll = self.pre_roll;
+        options.decay = Some(self.decay);
+        options.noise = self.noise;
+        options.engine = Some(self.engine);
+        options.world = self.world;
+        options.topology = Some(self.topology);
+        options.empty = self.empty;
+        options.patterns = self
+            .patterns
+            .iter()
+            .map(|placement| Placement {
+                pattern: placement.pattern.clone(),
+                at: placement.at,
+            })
+            .collect();
+        options.no_splash = true;
+    }
+
+    /// Applies every event due by `life`'s generation.
+    pub fn apply_due(&mut self, life: &mut Grid, history: &mut History) {
+        while let Some((_, event)) = self
+            .events
+            .front()
+            .filter(|(generation, _)| *generation <= life.generation())
+        {
+            event.apply(life, history);
+            self.events.pop_front();
+        }
+    }
+
+    /// Whether the replay has caught up with the end of the recording.
+    pub fn finished(&self, life: &Grid) -> bool {
+        self.events.is_empty()
+            && self
+                .end
+                .is_none_or(|(generation, _)| life.generation() >= generation)
+    }
+
+    /// Checks the grid against the recording's final hash, if it has one.
+    pub fn matches(&self, life: &Grid) -> Option<bool> {
+        self.end.map(|(_, hash)| hash == grid_hash(life))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{session_seed, starting_grid};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("tgol-session-{}-{}", std::process::id(), name))
+    }
+
+    // Starts the grid `options` ask for, as the window does.
+    fn start(options: &Options) -> (u64, Grid) {
+        let seed = session_seed(options);
+        let life = starting_grid(options, options.grid_size(), seed);
+        (seed, life)
+    }
+
+    #[test]
+    fn a_replay_ends_where_its_recording_did() {
+        let path = temp_path("replay");
+        let options = Options::from_args(&[
+            "--seed", "42", "--width", "48", "--height", "32", "--noise", "3",
+        ])
+        .unwrap();
+        let (seed, mut life) = start(&options);
+        let mut recorder = Some(Recorder::create(&path, &options, seed, &life).unwrap());
+        let mut history = History::default();
+
+        // A scripted session: a bit of everything, at various generations.
+        let script: Vec<(u64, Vec<Event>)> = vec![
+            (
+                3,
+                vec![
+                    "disc 5 5 2 alive".parse().unwrap(),
+                    "line 5 5 40 30 1 alive".parse().unwrap(),
+                    Event::Commit,
+                ],
+            ),
+            (10, vec!["kill 77 0.5".parse().unwrap(), Event::Invert]),
+            (12, vec![Event::Undo]),
+            (20, vec!["randomize 1234 0.3 mirror-x 5".parse().unwrap()]),
+            (
+                25,
+                vec![
+                    "rule B36/S23".parse().unwrap(),
+                    "paste 46 30 .#./..#/###".parse().unwrap(),
+                    "spray 20 20 4 0.5 99".parse().unwrap(),
+                    Event::Commit,
+                ],
+            ),
+            (40, vec!["rect -3 -3 4 4 dead".parse().unwrap()]),
+            (50, vec!["engine scalar".parse().unwrap()]),
+        ];
+        for generation in 0..80 {
+            for (at, events) in &script {
+                if *at == generation {
+                    for event in events {
+                        perform(&mut recorder, &mut life, &mut history, event.clone());
+                    }
+                }
+            }
+            life.update();
+        }
+        recorder.unwrap().finish(&life).unwrap();
+
+        // Played back from nothing but the file, as --replay does.
+        let mut replay = Replay::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut options = Options::from_args(&[]).unwrap();
+        replay.configure(&mut options);
+        let (_, mut played) = start(&options);
+        let mut history = History::default();
+        loop {
+            replay.apply_due(&mut played, &mut history);
+            if replay.finished(&played) {
+                break;
+            }
+            played.update();
+        }
+        assert_eq!(played.generation(), life.generation());
+        assert_eq!(replay.matches(&played), Some(true));
+        assert_eq!(played.to_string(), life.to_string());
+    }
+
+    #[test]
+    fn starting_patterns_are_replayed_where_they_were_placed() {
+        let pattern_path = temp_path("glider.cells");
+        std::fs::write(&pattern_path, ".O\n..O\nOOO\n").unwrap();
+        let path = temp_path("patterns");
+        let pattern_arg = pattern_path.to_str().unwrap();
+        let args = [
+            "--width",
+            "16",
+            "--height",
+            "12",
+            "--empty",
+            "--pattern",
+            pattern_arg,
+            "--at",
+            "3,4",
+            "--pattern",
+            pattern_arg,
+        ];
+        let options = Options::from_args(&args).unwrap();
+        let (seed, life) = start(&options);
+        let _ = std::fs::remove_file(&pattern_path);
+        // One glider where it was put and one in the middle, and nothing else.
+        assert_eq!(life.population(), 10);
+        assert!(life.grid.alive[life.grid_idx(4, 4).unwrap()]);
+        Recorder::create(&path, &options, seed, &life)
+            .unwrap()
+            .finish(&life)
+            .unwrap();
+
+        let replay = Replay::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut options = Options::from_args(&[]).unwrap();
+        replay.configure(&mut options);
+        let (_, played) = start(&options);
+        assert_eq!(played.to_string(), life.to_string());
+        assert_eq!(replay.matches(&played), Some(true));
+    }
+
+    #[test]
+    fn replays_of_grids_the_command_line_refuses_are_refused() {
+        let path = temp_path("refused");
+        for size in ["0 0", "100000 100000", "0 10", "10 8193"] {
+            std::fs::write(&path, format!("seed 1\nsize {}\nend 0 0\n", size)).unwrap();
+            assert!(Replay::load(&path).is_err(), "size {}", size);
+        }
+        std::fs::write(&path, "seed 1\nsize 10 10\ndensity 2\n").unwrap();
+        assert!(Replay::load(&path).is_err());
+        std::fs::write(
+            &path,
+            "seed 1\nsize 10 10\nautomaton quadlife\nworld infinite\n",
+        )
+        .unwrap();
+        assert!(Replay::load(&path).is_err());
+        std::fs::write(&path, "seed 1\nsize 10 10\nant-steps 0\n").unwrap();
+        assert!(Replay::load(&path).is_err());
+        std::fs::write(&path, "seed 1\nsize 10 10\n").unwrap();
+        assert!(Replay::load(&path).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,341 @@
+//
+// Spotting gliders and the small spaceships ([Shift+G]): every phase of
+// each, in all eight orientations, matched cell for cell against the grid.
+// A match has to stand alone: every cell touching it has to be dead, so a
+// glider caught up in junk isn't claimed.
+//
+// The shapes aren't written out phase by phase. Each ship is run under the
+// grid's rule from one phase, and is looked for only if it comes back
+// round moved (under a rule where it isn't a ship, it's never counted).
+//
+// Each shape is matched from its first live cell, row by row. The four
+// cells touching that one from below and to the right are each either the
+// shape's or its halo's, so they pick out the few shapes worth checking;
+// the four above and to the left have to be dead. Most live cells get no
+// further. Ships that straddle the grid's edges aren't counted.
+//
+
+use std::collections::HashMap;
+
+use crate::grid::Grid;
+use crate::pattern::PatternBuffer;
+use crate::rule::Rule;
+
+/// Color matched cells are tinted.
+pub const SHIP_COLOR: [u8; 4] = [0x30, 0xff, 0x70, 0xff];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ship {
+    Glider,
+    Lwss,
+    Mwss,
+    Hwss,
+}
+
+impl Ship {
+    pub const ALL: [Ship; 4] = [Ship::Glider, Ship::Lwss, Ship::Mwss, Ship::Hwss];
+
+    /// Its name, in the plural.
+    pub fn plural(self) -> &'static str {
+        match self {
+            Ship::Glider => "gliders",
+            Ship::Lwss => "LWSS",
+            Ship::Mwss => "MWSS",
+            Ship::Hwss => "HWSS",
+        }
+    }
+
+    // One phase of it.
+    fn rows(self) -> &'static [&'static str] {
+        match self {
+            Ship::Glider => &[".#.", "..#", "###"],
+            Ship::Lwss => &[".#..#", "#....", "#...#", "####."],
+            Ship::Mwss => &["...#..", ".#...#", "#.....", "#....#", "#####."],
+            Ship::Hwss => &["...##..", ".#....#", "#......", "#.....#", "######."],
+        }
+    }
+}
+
+/// What a scan found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sightings {
+    /// How many of each, in `Ship::ALL` order.
+    pub counts: [u32; Ship::ALL.len()],
+    /// Indices of their cells.
+    pub cells: Vec<usize>,
+}
+
+// One phase in one orientation, as offsets from its first live cell.
+struct Shape {
+    ship: Ship,
+    cells: Vec<(isize, isize)>,
+    // Every cell touching the shape that isn't part of it.
+    halo: Vec<(isize, isize)>,
+    // How far it reaches, halo and all, from its first cell.
+    left: isize,
+    right: isize,
+    down: isize,
+}
+
+pub struct ShipFinder {
+    // The rule the shapes were made under.
+    rule: Rule,
+    shapes: Vec<Shape>,
+    // Shapes by the four cells after their first one (see `corner`).
+    by_corner: [Vec<usize>; 16],
+}
+
+impl ShipFinder {
+    pub fn new(rule: Rule) -> Self {
+        let mut finder = Self {
+            rule,
+            shapes: Vec::new(),
+            by_corner: Default::default(),
+        };
+        finder.make_shapes();
+        finder
+    }
+
+    /// Finds the ships on `life`.
+    pub fn scan(&mut self, life: &Grid) -> Sightings {
+        profile_scope!("ships");
+
+        let mut sightings = Sightings::default();
+        if !life.automaton.life_like() {
+            return sightings;
+        }
+        if life.rule != self.rule {
+            self.rule = life.rule;
+            self.make_shapes();
+        }
+
+        let (w, h) = (life.width as isize, life.height as isize);
+        let alive = &life.grid.alive;
+        let at = |x: isize, y: isize| alive[(x + y * w) as usize];
+        for (i, _) in alive.iter().enumerate().filter(|(_, &live)| live) {
+            let (x, y) = ((i as isize) % w, (i as isize) / w);
+            // The shape's halo runs a cell past it on every side but the
+            // bottom and right, where `fits` checks it.
+            if x == 0 || y == 0 || x + 1 == w {
+                continue;
+            }
+            if at(x - 1, y) || at(x - 1, y - 1) || at(x, y - 1) || at(x + 1, y - 1) {
+                continue;
+            }
+            let corner = corner(|dx, dy| y + dy < h && at(x + dx, y + dy));
+            for &s in &self.by_corner[corner] {
+                let shape = &self.shapes[s];
+                let fits = x - shape.left >= 0 && x + shape.right < w && y + shape.down < h;
+                if fits
+                    && shape.cells.iter().all(|&(dx, dy)| at(x + dx, y + dy))
+                    && !shape.halo.iter().any(|&(dx, dy)| at(x + dx, y + dy))
+                {
+                    sightings.counts[shape.ship as usize] += 1;
+                    let cells = shape.cells.iter().map(|&(dx, dy)| x + dx + (y + dy) * w);
+                    sightings.cells.extend(cells.map(|i| i as usize));
+                    break;
+                }
+            }
+        }
+        sightings
+    }
+
+    // Every phase of every ship that's a ship under the rule, every way
+    // round.
+    fn make_shapes(&mut self) {
+        self.shapes.clear();
+        for ship in Ship::ALL {
+            let mut seen: Vec<Vec<(isize, isize)>> = Vec::new();
+            for phase in phases(ship, self.rule) {
+                for turn in 0..8 {
+                    let cells = normalize(phase.iter().map(|&p| orient(p, turn)).collect());
+                    if !seen.contains(&cells) {
+                        seen.push(cells.clone());
+                        self.shapes.push(Shape::new(ship, &cells));
+                    }
+                }
+            }
+        }
+        self.by_corner = Default::default();
+        for (s, shape) in self.shapes.iter().enumerate() {
+            let live = |dx, dy| shape.cells.contains(&(dx, dy));
+            self.by_corner[corner(live)].push(s);
+        }
+    }
+}
+
+impl Shape {
+    fn new(ship: Ship, cells: &[(isize, isize)]) -> Self {
+        // Offsets from the first live cell, row by row.
+        let (x0, y0) = cells[0];
+        let cells: Vec<_> = cells.iter().map(|&(x, y)| (x - x0, y - y0)).collect();
+        let mut halo = Vec::new();
+        for &(x, y) in &cells {
+            for (dx, dy) in NEIGHBORS {
+                let p = (x + dx, y + dy);
+                if !cells.contains(&p) && !halo.contains(&p) {
+                    halo.push(p);
+                }
+            }
+        }
+        Self {
+            ship,
+            left: -halo.iter().map(|p| p.0).min().unwrap_or(0),
+            right: halo.iter().map(|p| p.0).max().unwrap_or(0),
+            down: halo.iter().map(|p| p.1).max().unwrap_or(0),
+            cells,
+            halo,
+        }
+    }
+}
+
+const NEIGHBORS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Which of the cells right of, below left, below and below right of a
+// shape's first cell are alive, as four bits.
+fn corner(live: impl Fn(isize, isize) -> bool) -> usize {
+    [(1, 0), (-1, 1), (0, 1), (1, 1)]
+        .iter()
+        .enumerate()
+        .filter(|(_, &(dx, dy))| live(dx, dy))
+        .map(|(bit, _)| 1 << bit)
+        .sum()
+}
+
+// Its phases under `rule`, if it comes back to the first one moved within
+// four generations, as spaceships up to the HWSS do under Life; none if
+// it doesn't.
+fn phases(ship: Ship, rule: Rule) -> Vec<Vec<(isize, isize)>> {
+    let pattern = PatternBuffer::from_rows(ship.rows());
+    let cells = pattern.live_cells().map(|(x, y)| (x as isize, y as isize));
+    let start = normalize(cells.collect());
+    let mut phases = vec![start.clone()];
+    let mut cells = start.clone();
+    for _ in 0..4 {
+        cells = step(&cells, rule);
+        let phase = normalize(cells.clone());
+        if phase == start {
+            // Back where it started is an oscillator, not a ship.
+            return if cells == start { Vec::new() } else { phases };
+        }
+        if cells.is_empty() || cells.len() > 4 * start.len() {
+            break;
+        }
+        phases.push(phase);
+    }
+    Vec::new()
+}
+
+// One generation of a few cells on an open plane.
+fn step(cells: &[(isize, isize)], rule: Rule) -> Vec<(isize, isize)> {
+    let mut counts: HashMap<(isize, isize), usize> = HashMap::new();
+    for &(x, y) in cells {
+        for (dx, dy) in NEIGHBORS {
+            *counts.entry((x + dx, y + dy)).or_default() += 1;
+        }
+    }
+    let mut next: Vec<_> = counts
+        .into_iter()
+        .filter(|&(p, n)| {
+            if cells.contains(&p) {
+                rule.survives(n)
+            } else {
+                rule.births(n)
+            }
+        })
+        .map(|(p, _)| p)
+        .collect();
+    next.sort_by_key(|&(x, y)| (y, x));
+    next
+}
+
+// One of the eight ways of turning and flipping a shape.
+fn orient((x, y): (isize, isize), turn: u8) -> (isize, isize) {
+    let (x, y) = if turn & 4 != 0 { (y, x) } else { (x, y) };
+    let x = if turn & 1 != 0 { -x } else { x };
+    let y = if turn & 2 != 0 { -y } else { y };
+    (x, y)
+}
+
+// Moves it to the top-left corner, its cells in row order.
+fn normalize(mut cells: Vec<(isize, isize)>) -> Vec<(isize, isize)> {
+    let x0 = cells.iter().map(|p| p.0).min().unwrap_or(0);
+    let y0 = cells.iter().map(|p| p.1).min().unwrap_or(0);
+    for p in &mut cells {
+        *p = (p.0 - x0, p.1 - y0);
+    }
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The ship every way round, on a grid of its own.
+    fn orientations(ship: Ship) -> Vec<PatternBuffer> {
+        let mut pattern = PatternBuffer::from_rows(ship.rows());
+        let mut all = Vec::new();
+        for _ in 0..2 {
+            for _ in 0..4 {
+                pattern.rotate_cw();
+                all.push(pattern.clone());
+            }
+            pattern.flip_horizontal();
+        }
+        all
+    }
+
+    #[test]
+    fn ships_are_found_in_every_phase_and_orientation() {
+        let mut finder = ShipFinder::new(Rule::default());
+        for ship in Ship::ALL {
+            for pattern in orientations(ship) {
+                let mut life = Grid::new_empty_grid(40, 40);
+                life.insert_pattern(&pattern, 17, 17);
+                for generation in 0..12 {
+                    let sightings = finder.scan(&life);
+                    let mut counts = [0; Ship::ALL.len()];
+                    counts[ship as usize] = 1;
+                    assert_eq!(sightings.counts, counts, "{:?} at {}", ship, generation);
+                    assert_eq!(sightings.cells.len(), life.population());
+                    life.update();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_glider_touching_another_cell_is_not_counted() {
+        let mut finder = ShipFinder::new(Rule::default());
+        let glider = PatternBuffer::from_rows(Ship::Glider.rows());
+        // Beside it, below it, and only corner to corner.
+        for (x, y) in [(13, 11), (11, 13), (10, 9)] {
+            let mut life = Grid::new_empty_grid(24, 24);
+            life.insert_pattern(&glider, 10, 10);
+            life.set_disc(x, y, 0, true);
+            assert_eq!(finder.scan(&life), Sightings::default(), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn nothing_is_a_ship_under_a_rule_it_doesnt_fly_in() {
+        let mut finder = ShipFinder::new("B2/S".parse().unwrap());
+        let mut life = Grid::new_empty_grid(40, 40);
+        life.rule = "B2/S".parse().unwrap();
+        for (n, ship) in Ship::ALL.into_iter().enumerate() {
+            let pattern = PatternBuffer::from_rows(ship.rows());
+            life.insert_pattern(&pattern, 2 + 9 * n as isize, 17);
+        }
+        assert_eq!(finder.scan(&life), Sightings::default());
+    }
+}
@@ -0,0 +1,1019 @@
+//
+// The simulation thread. The window's event loop only turns input into
+// `Command`s and shows the latest `Frame`; a worker thread owns the grid,
+// steps it on a clock of its own, and applies commands between
+// generations in the order they came, so an edit made while the grid is
+// running never races an update. Whatever follows the grid generation by
+// generation (the recording, --stats-out, --pause-on-stable, a replay, the
+// intro) lives there too.
+//
+// Frames are the grid rendered to RGBA, the way the window shows it, plus
+// a `Status` with what the window needs to know about the grid. Only the
+// newest frame matters; buffers come back to the worker to be reused.
+//
+// The browser has no threads, so there the same `Worker` is stepped from
+// the event loop instead, which sleeps until the next tick is due.
+//
+// Nothing runs once there's nothing to do: the worker blocks while the
+// grid is paused, and the window only redraws for a new frame or input.
+//
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use instant::Instant;
+
+use log::{debug, error};
+use winit::event_loop::ControlFlow;
+
+use tgol::automaton::MAX_TEAMS;
+use tgol::cycle::CycleDetector;
+use tgol::decay::Decay;
+use tgol::density::DensityMap;
+use tgol::engine::Engine;
+use tgol::gradient::Gradient;
+use tgol::grid::{Grid, Probe};
+use tgol::heat::HeatColors;
+use tgol::history::History;
+use tgol::minimap::MiniMap;
+use tgol::noise::Noise;
+use tgol::pattern::PatternBuffer;
+use tgol::png_export::PngOptions;
+use tgol::rule::Rule;
+use tgol::ships::{Ship, ShipFinder, Sightings, SHIP_COLOR};
+use tgol::splash::Splash;
+use tgol::svg::SvgOptions;
+use tgol::timings::{self, Phase, Timings};
+use tgol::topology::Topology;
+use tgol::world::WorldStats;
+use tgol::{profile_scope, profiler};
+
+#[cfg(feature = "audio")]
+use crate::audio::Feed;
+use crate::autosave::Autosaver;
+use crate::capture::ApngCapture;
+use crate::config::DEFAULT_TPS;
+use crate::frame_dump::FrameDump;
+use crate::frame_pipe::FramePipe;
+use crate::last_session::LastSession;
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+use crate::session::{self, perform, Recorder, Replay};
+use crate::stats_csv::{write_stats, StatsCsv};
+
+// Time a tick may spend on fast-forward generations before it's drawn
+// anyway, so frames keep coming at any speed.
+const FAST_FORWARD_BUDGET_MS: f64 = 10.0;
+
+// Generations between the birth/death summaries logged at debug level.
+const STATS_LOG_INTERVAL: u64 = 100;
+
+// Generations between refreshes of an infinite world's stats (or the
+// teams') in the title.
+const WORLD_STATS_INTERVAL: u64 = 30;
+
+/// What the window asks of the simulation.
+pub enum Command {
+    /// Something done to the grid, recorded and applied in turn.
+    Event(session::Event),
+    /// The mouse (`finger` None) or a finger went down at (x, y): the
+    /// brush disc there toggles to the opposite of the clicked cell, and
+    /// the rest of the stroke (`BrushTo`) paints the same. Each finger's
+    /// stroke is its own; they end with `BrushUp`, and the edit with a
+    /// `session::Event::Commit`.
+    BrushDown {
+        finger: Option<u64>,
+        x: isize,
+        y: isize,
+        radius: u32,
+    },
+    BrushTo {
+        finger: Option<u64>,
+        from: (isize, isize),
+        to: (isize, isize),
+        radius: u32,
+    },
+    BrushUp(Option<u64>),
+    Pause,
+    Resume,
+    TogglePause,
+    /// Generations per tick while running (more than 1 to fast-forward).
+    Speed(u32),
+    /// Ticks a second.
+    Tps(u32),
+    AgeColors(Option<Gradient>),
+    /// The heat colors, and the palette of an automaton drawn from one
+    /// (see `Grid::palette`).
+    Colors {
+        heat_colors: Box<HeatColors>,
+        palette: Vec<[u8; 4]>,
+    },
+    /// Whether frames come with a mini-map.
+    MiniMap(bool),
+    /// Blocks this many cells a side to wash frames over by how crowded
+    /// they are, or None for no wash.
+    DensityWash(Option<usize>),
+    /// Whether gliders and small spaceships are looked for, tinted in
+    /// frames and counted in the status.
+    Ships(bool),
+    /// The cell frames report on (`Frame::probe`), or None for none.
+    Probe(Option<(isize, isize)>),
+    /// Copies the cells between two corners, or the live cells' bounding
+    /// box, answered by `Reply::Copied`.
+    Copy(Option<((isize, isize), (isize, isize))>),
+    /// Asks for the grid as it is, answered by `Reply::Status`.
+    Status,
+    /// Exports to `tgol-<generation>.svg`.
+    ExportSvg(SvgOptions),
+    /// Exports to `tgol-<generation>.png`.
+    ExportPng(PngOptions),
+    /// Exports the cells between two corners, or the whole grid, to
+    /// `tgol-<generation>.rle`.
+    ExportRle(Option<((isize, isize), (isize, isize))>),
+    /// Captures the next `frames` generations shown to
+    /// `tgol-<generation>-anim.png`, each shown for `delay_ms`.
+    CaptureApng {
+        frames: u32,
+        delay_ms: u16,
+    },
+    SkipIntro,
+    /// Hands the grid to `Worker::autosaver`, unless it's just as it was
+    /// last time. The rest is what the window has to say about it.
+    Autosave {
+        fast_forward: Option<u32>,
+        age_colors: bool,
+    },
+    /// Logs the simulation thread's profile (and writes it as JSON).
+    ProfilerDump(bool),
+    Quit,
+}
+
+/// What the simulation answers a command with, if anything.
+pub enum Reply {
+    /// The cells copied, and the rule to write them out with.
+    Copied {
+        cells: PatternBuffer,
+        rule: String,
+    },
+    Status(Status),
+}
+
+/// The grid as of a frame.
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "http-view"), allow(dead_code))]
+pub struct Status {
+    /// How many commands had been handled.
+    pub seen: u64,
+    pub intro: bool,
+    pub paused: bool,
+    pub generation: u64,
+    pub population: usize,
+    pub rule: Rule,
+    pub engine: Engine,
+    pub topology: Topology,
+    pub decay: Decay,
+    pub trails: bool,
+    pub noise: Noise,
+    pub view: Option<(i64, i64)>,
+    pub world_stats: Option<WorldStats>,
+    /// Live cells on each team, if there are teams.
+    pub teams: [u32; MAX_TEAMS],
+    /// Ships of each kind, in `Ship::ALL` order, while they're looked for.
+    pub ships: Option<[u32; Ship::ALL.len()]>,
+    pub history_truncated: bool,
+}
+
+pub struct Frame {
+    pub pixels: Vec<u8>,
+    pub status: Status,
+    /// While asked for with `Command::MiniMap`.
+    pub minimap: Option<MiniMap>,
+    /// The cell asked for with `Command::Probe`, as of this frame.
+    pub probe: Option<Probe>,
+}
+
+/// The grid and everything that goes along with it. Set the public fields
+/// up before handing it to `Simulation::start`.
+pub struct Worker {
+    pub life: Grid,
+    pub history: History,
+    pub recorder: Option<Recorder>,
+    pub record_path: Option<PathBuf>,
+    pub stats_csv: Option<StatsCsv>,
+    // --pause-on-stable: watches for the grid repeating itself.
+    pub cycles: Option<CycleDetector>,
+    pub replay: Option<Replay>,
+    pub splash: Option<Splash>,
+    // --pipe-frames / --ffmpeg, and the generation it was last sent.
+    pub frame_pipe: Option<FramePipe>,
+    piped: Option<u64>,
+    // --dump-frames, and the generation it was last given.
+    pub frame_dump: Option<FrameDump>,
+    dumped: Option<u64>,
+    // --autosave, and the generation and edit count it last got.
+    pub autosaver: Option<Autosaver>,
+    autosaved: Option<(u64, u64)>,
+    // --timings: where stepping and drawing the grid are timed.
+    pub timings: Option<Arc<Timings>>,
+    // --script, once its top has run (see `start_script`).
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+    // --audio's ear on each generation.
+    #[cfg(feature = "audio")]
+    pub sound: Option<Feed>,
+    paused: bool,
+    speed: u32,
+    // Time between ticks.
+    tick: Duration,
+    minimap: bool,
+    // [D]: the density wash's block size, while it's on.
+    density_block: Option<usize>,
+    // [Shift+G]: what looks for ships, and what it found on the last frame.
+    ships: Option<ShipFinder>,
+    sightings: Option<Sightings>,
+    // The cursor readout's cell, while it's on.
+    probe: Option<(isize, isize)>,
+    // What the brush strokes in progress set cells to, by finger (None for
+    // the mouse).
+    strokes: HashMap<Option<u64>, bool>,
+    // Births, deaths and noise flips since the last summary in the debug
+    // log.
+    stats_totals: (u64, u64, u64),
+    world_stats: Option<WorldStats>,
+    teams: [u32; MAX_TEAMS],
+    world_stats_generation: Option<u64>,
+    // The grid as last drawn; only changed cells are redrawn into it,
+    // unless `redraw_all` is set.
+    screen: Vec<u8>,
+    redraw_all: bool,
+    // Whether there's anything new to send since the last frame.
+    changed: bool,
+    seen: u64,
+    // [Shift+A]'s animation in progress, and the generation it last got.
+    capture: Option<ApngCapture>,
+    captured: Option<u64>,
+}
+
+impl Worker {
+    pub fn new(life: Grid, history: History) -> Self {
+        Self {
+            screen: vec![0; 4 * life.grid.len()],
+            life,
+            history,
+            recorder: None,
+            record_path: None,
+            stats_csv: None,
+            cycles: None,
+            replay: None,
+            splash: None,
+            frame_pipe: None,
+            piped: None,
+            frame_dump: None,
+            dumped: None,
+            autosaver: None,
+            timings: None,
+            autosaved: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "audio")]
+            sound: None,
+            paused: false,
+            speed: 1,
+            tick: tick_length(DEFAULT_TPS),
+            minimap: false,
+            density_block: None,
+            ships: None,
+            sightings: None,
+            probe: None,
+            strokes: HashMap::new(),
+            stats_totals: (0, 0, 0),
+            world_stats: None,
+            teams: [0; MAX_TEAMS],
+            world_stats_generation: None,
+            redraw_all: true,
+            changed: true,
+            seen: 0,
+            capture: None,
+            captured: None,
+        }
+    }
+
+    fn handle(&mut self, command: Command) -> Option<Reply> {
+        self.seen += 1;
+        self.changed = true;
+        match command {
+            Command::Event(event) => self.perform(event),
+            Command::BrushDown {
+                finger,
+                x,
+                y,
+                radius,
+            } => {
+                let life = &self.life;
+                let alive = life.edit_idx(x, y).is_some_and(|i| !life.painted(i, true));
+                self.strokes.insert(finger, alive);
+                self.perform(session::Event::Disc {
+                    x,
+                    y,
+                    radius,
+                    alive,
+                });
+            }
+            Command::BrushTo {
+                finger,
+                from,
+                to,
+                radius,
+            } => self.perform(session::Event::Line {
+                from,
+                to,
+                radius,
+                alive: self.strokes.get(&finger).copied().unwrap_or(true),
+            }),
+            Command::BrushUp(finger) => {
+                self.strokes.remove(&finger);
+            }
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Speed(generations) => self.speed = generations.max(1),
+            Command::Tps(tps) => self.tick = tick_length(tps),
+            Command::AgeColors(gradient) => {
+                self.life.age_colors = gradient;
+                self.redraw_all = true;
+            }
+            Command::Colors {
+                heat_colors,
+                palette,
+            } => {
+                self.life.heat_colors = *heat_colors;
+                self.life.palette = palette;
+                self.redraw_all = true;
+            }
+            Command::MiniMap(shown) => self.minimap = shown,
+            Command::DensityWash(block) => self.density_block = block,
+            Command::Ships(on) => {
+                self.ships = on.then(|| ShipFinder::new(self.life.rule));
+                self.sightings = None;
+            }
+            Command::Probe(cell) => self.probe = cell,
+            Command::Copy(corners) => {
+                let cells = match corners {
+                    Some((a, b)) => self.life.copy_rect(a, b),
+                    None => self.life.copy_live_bounds(),
+                };
+                let rule = self.life.rle_rule();
+                return Some(Reply::Copied { cells, rule });
+            }
+            Command::Status => return Some(Reply::Status(self.status())),
+            Command::ExportSvg(options) => {
+                let path = format!("tgol-{}.svg", self.life.generation());
+                match self.life.export_svg(Path::new(&path), &options) {
+                    Ok(()) => log::info!("Exported {}", path),
+                    Err(e) => error!("Failed to export {}: {}", path, e),
+                }
+            }
+            Command::ExportPng(options) => {
+                let path = format!("tgol-{}.png", self.life.generation());
+                match self.life.export_png(Path::new(&path), &options) {
+                    Ok(()) => log::info!("Exported {}", path),
+                    Err(e) => error!("Failed to export {}: {}", path, e),
+                }
+            }
+            Command::ExportRle(corners) => {
+                let path = format!("tgol-{}.rle", self.life.generation());
+                match self.life.export_rle(Path::new(&path), corners) {
+                    Ok(()) => log::info!("Exported {}", path),
+                    Err(e) => error!("Failed to export {}: {}", path, e),
+                }
+            }
+            Command::CaptureApng { .. }
+                if self.capture.as_ref().is_some_and(ApngCapture::capturing) =>
+            {
+                log::info!("Already capturing an APNG");
+            }
+            Command::CaptureApng { frames, delay_ms } => {
+                let path = format!("tgol-{}-anim.png", self.life.generation());
+                let size = (self.life.width as u32, self.life.height as u32);
+                match ApngCapture::start(path.clone().into(), size, frames, delay_ms) {
+                    Ok(capture) => {
+                        log::info!("Capturing {} generations to {}", frames, path);
+                        if let Some(done) = self.capture.replace(capture) {
+                            done.finish();
+                        }
+                        self.captured = None;
+                    }
+                    Err(e) => error!("Couldn't capture to {}: {}", path, e),
+                }
+            }
+            Command::SkipIntro => {
+                if let Some(mut intro) = self.splash.take() {
+                    intro.finish(&mut self.life);
+                    self.redraw_all = true;
+                }
+            }
+            Command::Autosave {
+                fast_forward,
+                age_colors,
+            } => {
+                let at = (self.life.generation(), self.life.edit_count());
+                if let Some(saver) = &self.autosaver {
+                    if self.splash.is_none() && self.autosaved != Some(at) {
+                        let paused = self.paused;
+                        saver.save(LastSession::new(
+                            &self.life,
+                            paused,
+                            fast_forward,
+                            age_colors,
+                        ));
+                        self.autosaved = Some(at);
+                    }
+                }
+            }
+            Command::ProfilerDump(write_json) => profiler::dump(write_json),
+            Command::Quit => {}
+        }
+        None
+    }
+
+    // Records and applies `event`, saying how it went where that's not
+    // plain to see.
+    fn perform(&mut self, event: session::Event) {
+        let before = self.life.population();
+        let done = match &event {
+            session::Event::Undo => "undo",
+            session::Event::Redo => "redo",
+            _ => "",
+        };
+        let kill = matches!(event, session::Event::Kill { .. });
+        let jump = matches!(event, session::Event::Advance(_));
+        if matches!(
+            event,
+            session::Event::Trails(_)
+                | session::Event::View(..)
+                | session::Event::Randomize { .. }
+                | session::Event::Topology(_)
+        ) {
+            self.redraw_all = true;
+            self.world_stats_generation = None;
+        }
+        if let (session::Event::Randomize { .. }, Some(csv)) = (&event, &mut self.stats_csv) {
+            csv.new_run();
+        }
+
+        let applied = perform(&mut self.recorder, &mut self.life, &mut self.history, event);
+        if !applied && !done.is_empty() {
+            log::info!("Nothing to {}", done);
+        } else if kill {
+            log::info!(
+                "Randomly killed {:?} cells..",
+                before - self.life.population()
+            );
+        } else if jump {
+            log::info!("Jumped to generation {}", self.life.generation());
+        }
+    }
+
+    /// Runs the top of `script` on the grid, and its `on_generation` after
+    /// every generation from then on.
+    #[cfg(feature = "scripting")]
+    pub fn start_script(&mut self, mut script: Script) {
+        if script.init(&mut self.life, &mut self.history, &mut self.recorder) {
+            self.redraw_all = true;
+        }
+        self.script = Some(script);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // Whether there's nothing to do until the next command: paused, and
+    // everything drawn and sent.
+    fn idle(&self) -> bool {
+        self.paused && self.splash.is_none() && !self.changed
+    }
+
+    // Plays the intro, or steps the grid as fast as asked (unless paused).
+    fn tick(&mut self) {
+        profile_scope!("tick");
+
+        if let Some(intro) = &mut self.splash {
+            if intro.step(&mut self.life, &mut self.screen) {
+                self.changed = true;
+                return;
+            }
+            self.splash = None;
+            self.redraw_all = true;
+        }
+
+        if !self.paused {
+            // Only the last of a fast-forward's generations is drawn, but
+            // each one still cools the trails.
+            let start = crate::now_ms();
+            for _ in 0..self.speed {
+                if self.step_replay() {
+                    break;
+                }
+                self.step();
+                if self.paused || crate::now_ms() - start > FAST_FORWARD_BUDGET_MS {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Draws what's changed since the last frame (the intro draws itself).
+    fn draw(&mut self) {
+        if self.splash.is_some() {
+            return;
+        }
+        timings::time(self.timings.as_deref(), Phase::Draw, || {
+            match self.life.dirty_cells() {
+                Some(cells) if !self.redraw_all => {
+                    self.changed |= !cells.is_empty();
+                    self.life.draw_dirty(&mut self.screen, cells);
+                }
+                _ => {
+                    self.changed = true;
+                    self.life.draw(&mut self.screen);
+                }
+            }
+        });
+        self.life.clear_dirty();
+        self.redraw_all = false;
+    }
+
+    // Applies what's due of a replay. Returns true (and pauses) once
+    // it's over.
+    fn step_replay(&mut self) -> bool {
+        let Some(playing) = &mut self.replay else {
+            return false;
+        };
+        playing.apply_due(&mut self.life, &mut self.history);
+        if !playing.finished(&self.life) {
+            return false;
+        }
+        match playing.matches(&self.life) {
+            Some(true) => log::info!("Replay finished, matching the recording"),
+            Some(false) => error!("Replay finished, but doesn't match the recording"),
+            None => log::info!("Replay finished (the recording has no end to check)"),
+        }
+        self.replay = None;
+        self.paused = true;
+        self.redraw_all = true;
+        true
+    }
+
+    // One generation, and what's kept up with it.
+    fn step(&mut self) {
+        let stats = timings::time(self.timings.as_deref(), Phase::Update, || {
+            self.life.update()
+        });
+        write_stats(&mut self.stats_csv, &stats);
+        self.stats_totals.0 += stats.births as u64;
+        self.stats_totals.1 += stats.deaths as u64;
+        self.stats_totals.2 += stats.noise_flips as u64;
+        if stats.generation.is_multiple_of(STATS_LOG_INTERVAL) {
+            debug!(
+                "Generation {}: {} births, {} deaths, {} noise flips in the last {}, population {}",
+                stats.generation,
+                self.stats_totals.0,
+                self.stats_totals.1,
+                self.stats_totals.2,
+                STATS_LOG_INTERVAL,
+                stats.population
+            );
+            self.stats_totals = (0, 0, 0);
+        }
+        #[cfg(feature = "audio")]
+        if let Some(sound) = &self.sound {
+            sound.generation(&stats, self.life.grid.len());
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = &mut self.script {
+            if script.on_generation(&mut self.life, &mut self.history, &mut self.recorder) {
+                self.redraw_all = true;
+                self.world_stats_generation = None;
+            }
+        }
+        // Stopped on the repeat, which looks just like the cycle's first
+        // generation.
+        if let Some(cycle) = self.cycles.as_mut().and_then(|c| c.observe(&self.life)) {
+            log::info!(
+                "Repeating with period {} since generation {}; pausing",
+                cycle.period,
+                cycle.start
+            );
+            self.paused = true;
+        }
+    }
+
+    /// The grid as it is now.
+    pub fn status(&mut self) -> Status {
+        let generation = self.life.generation();
+        let stats_due = self
+            .world_stats_generation
+            .is_none_or(|at| !(at..at + WORLD_STATS_INTERVAL).contains(&generation));
+        if stats_due {
+            self.world_stats_generation = Some(generation);
+            self.world_stats = self.life.world_stats();
+            self.teams = self.life.team_populations();
+        }
+
+        Status {
+            seen: self.seen,
+            intro: self.splash.is_some(),
+            paused: self.paused,
+            generation,
+            population: self.life.population(),
+            rule: self.life.rule,
+            engine: self.life.engine,
+            topology: self.life.topology,
+            decay: self.life.decay,
+            trails: self.life.trails,
+            noise: self.life.noise,
+            view: self.life.view(),
+            world_stats: self.world_stats,
+            teams: self.teams,
+            ships: self.sightings.as_ref().map(|found| found.counts),
+            history_truncated: self.history.truncated(),
+        }
+    }
+
+    // A frame in `pixels` (a spare buffer, or an empty one), if there's
+    // been anything new since the last.
+    fn frame(&mut self, mut pixels: Vec<u8>) -> Option<Frame> {
+        self.draw();
+        self.capture_frame();
+        self.pipe_frame();
+        self.dump_frame();
+        if !std::mem::take(&mut self.changed) {
+            return None;
+        }
+        pixels.clear();
+        pixels.extend_from_slice(&self.screen);
+        // Washed and tinted in the window's copy only, so the screen stays as the grid
+        // is for everything else that's given it.
+        if let Some(block) = self.density_block.filter(|_| self.splash.is_none()) {
+            DensityMap::new(&self.life, block).wash(&mut pixels, self.life.width);
+        }
+        if self.splash.is_none() {
+            self.sightings = self.ships.as_mut().map(|finder| finder.scan(&self.life));
+        }
+        for &i in self.sightings.iter().flat_map(|found| &found.cells) {
+            pixels[4 * i..4 * i + 4].copy_from_slice(&SHIP_COLOR);
+        }
+        // A quarter of the grid across and down.
+        let max = (self.life.width / 4, self.life.height / 4);
+        Some(Frame {
+            pixels,
+            status: self.status(),
+            minimap: self.minimap.then(|| self.life.minimap(max)),
+            probe: self.probe(),
+        })
+    }
+
+    // The cursor readout's cell, if it's on the grid.
+    fn probe(&mut self) -> Option<Probe> {
+        let (x, y) = self.probe.filter(|_| self.splash.is_none())?;
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        (x < self.life.width && y < self.life.height).then(|| self.life.probe(x, y))
+    }
+
+    // Hands the screen to the APNG being captured, if it's a generation
+    // it hasn't had yet.
+    fn capture_frame(&mut self) {
+        let generation = self.life.generation();
+        if self.splash.is_some() || self.captured == Some(generation) {
+            return;
+        }
+        if let Some(capture) = self.capture.as_mut().filter(|c| c.capturing()) {
+            self.captured = Some(generation);
+            capture.push(&self.screen);
+        }
+    }
+
+    // Writes the screen down --pipe-frames, if it's a new generation. This
+    // blocks for as long as the other end takes to read it.
+    fn pipe_frame(&mut self) {
+        let generation = self.life.generation();
+        if self.splash.is_some() || self.piped == Some(generation) {
+            return;
+        }
+        if let Some(pipe) = &mut self.frame_pipe {
+            self.piped = Some(generation);
+            if let Err(e) = pipe.write(&self.screen) {
+                error!("Couldn't pipe frames any further: {}", e);
+                self.frame_pipe = None;
+            }
+        }
+    }
+
+    // Queues the screen for --dump-frames, if it's a new generation. This
+    // blocks while the encoders are behind.
+    fn dump_frame(&mut self) {
+        let generation = self.life.generation();
+        if self.splash.is_some() || self.dumped == Some(generation) {
+            return;
+        }
+        if let Some(dump) = &mut self.frame_dump {
+            self.dumped = Some(generation);
+            if !dump.push(&self.screen) {
+                error!("Couldn't dump frames any further");
+                self.frame_dump = None;
+            }
+        }
+    }
+
+    // Flushes --stats-out, ends the recording, any APNG capture, the frame
+    // pipe and the frame dump.
+    fn finish(&mut self) {
+        if let Some(pipe) = self.frame_pipe.take() {
+            pipe.finish();
+        }
+        if let Some(dump) = self.frame_dump.take() {
+            dump.finish();
+        }
+        if let Some(capture) = self.capture.take() {
+            capture.finish();
+        }
+        if let Some(Err(e)) = self.stats_csv.as_mut().map(StatsCsv::flush) {
+            error!("Couldn't finish writing --stats-out: {}", e);
+        }
+        if let Some(out) = &mut self.recorder {
+            match out.finish(&self.life) {
+                Ok(()) => {
+                    if let Some(path) = &self.record_path {
+                        log::info!("Recorded to {}", path.display());
+                    }
+                }
+                Err(e) => error!("Couldn't finish the recording: {}", e),
+            }
+        }
+    }
+}
+
+/// The window's end of the simulation thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Simulation {
+    commands: Sender<Command>,
+    frames: Receiver<Frame>,
+    replies: Receiver<Reply>,
+    spare: Sender<Vec<u8>>,
+    thread: Option<std::thread::JoinHandle<Worker>>,
+    sent: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Simulation {
+    /// Starts stepping `worker` at `tps` ticks a second. `wake` is called
+    /// whenever there's a new frame or a reply, to wake the event loop.
+    pub fn start(mut worker: Worker, tps: u32, wake: impl Fn() + Send + 'static) -> Self {
+        worker.tick = tick_length(tps);
+        let (commands, command_rx) = mpsc::channel();
+        // Room for one frame: the window only ever wants the newest.
+        let (frame_tx, frames) = mpsc::sync_channel(1);
+        let (reply_tx, replies) = mpsc::channel();
+        let (spare, spare_rx) = mpsc::channel();
+        let channels = Channels {
+            commands: command_rx,
+            frames: frame_tx,
+            replies: reply_tx,
+            spare: spare_rx,
+        };
+        let thread = std::thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || run(worker, channels, wake))
+            .expect("couldn't start the simulation thread");
+        Self {
+            commands,
+            frames,
+            replies,
+            spare,
+            thread: Some(thread),
+            sent: 0,
+        }
+    }
+
+    pub fn send(&mut self, command: Command) {
+        self.sent += 1;
+        // Only fails once the thread is gone, and then there's no one to
+        // tell.
+        let _ = self.commands.send(command);
+    }
+
+    /// Whether `status` is from after every command sent so far.
+    pub fn caught_up(&self, status: &Status) -> bool {
+        status.seen == self.sent
+    }
+
+    /// The newest frame since the last call, if there is one.
+    pub fn latest_frame(&mut self) -> Option<Frame> {
+        let mut latest: Option<Frame> = None;
+        while let Ok(frame) = self.frames.try_recv() {
+            if let Some(old) = latest.replace(frame) {
+                self.recycle(old.pixels);
+            }
+        }
+        latest
+    }
+
+    /// Hands a frame's buffer back to be drawn into again.
+    pub fn recycle(&mut self, pixels: Vec<u8>) {
+        let _ = self.spare.send(pixels);
+    }
+
+    pub fn replies(&mut self) -> impl Iterator<Item = Reply> + '_ {
+        self.replies.try_iter()
+    }
+
+    /// How long the event loop can sleep: until woken for the next frame.
+    pub fn control_flow(&self) -> ControlFlow {
+        ControlFlow::Wait
+    }
+
+    /// Stops the thread, once it's finished the recording and the stats,
+    /// and hands back the worker with the grid as it was left.
+    pub fn shutdown(&mut self) -> Option<Worker> {
+        let _ = self.commands.send(Command::Quit);
+        let worker = self.thread.take()?.join();
+        if worker.is_err() {
+            error!("The simulation thread panicked");
+        }
+        worker.ok()
+    }
+}
+
+// The simulation thread's ends of the channels.
+#[cfg(not(target_arch = "wasm32"))]
+struct Channels {
+    commands: Receiver<Command>,
+    frames: SyncSender<Frame>,
+    replies: Sender<Reply>,
+    spare: Receiver<Vec<u8>>,
+}
+
+// The simulation thread: commands as they come in, a tick whenever one's
+// due (or none at all while it's idle), until told to quit (or the
+// window's gone).
+#[cfg(not(target_arch = "wasm32"))]
+fn run(mut worker: Worker, channels: Channels, wake: impl Fn()) -> Worker {
+    let mut next_tick = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_tick {
+            worker.tick();
+            profiler::end_frame();
+            next_tick = (next_tick + worker.tick).max(now);
+
+            let pixels = channels.spare.try_recv().unwrap_or_default();
+            if let Some(frame) = worker.frame(pixels) {
+                match channels.frames.try_send(frame) {
+                    Ok(()) => wake(),
+                    // The last one hasn't been taken yet; send this one
+                    // next tick instead.
+                    Err(TrySendError::Full(_)) => worker.changed = true,
+                    Err(TrySendError::Disconnected(_)) => break,
+                }
+            }
+        }
+
+        let command = if worker.idle() {
+            channels
+                .commands
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            channels.commands.recv_timeout(timeout)
+        };
+        match command {
+            Ok(Command::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(command) => {
+                if let Some(reply) = worker.handle(command) {
+                    let _ = channels.replies.send(reply);
+                    wake();
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+    worker.finish();
+    worker
+}
+
+/// The simulation, stepped from the event loop.
+#[cfg(target_arch = "wasm32")]
+pub struct Simulation {
+    worker: Worker,
+    replies: Vec<Reply>,
+    spare: Vec<Vec<u8>>,
+    sent: u64,
+    next_tick: Instant,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Simulation {
+    /// The event loop's woken for each tick anyway, so there's no `wake`.
+    pub fn start(mut worker: Worker, tps: u32, _wake: impl Fn()) -> Self {
+        worker.tick = tick_length(tps);
+        Self {
+            worker,
+            replies: Vec::new(),
+            spare: Vec::new(),
+            sent: 0,
+            next_tick: Instant::now(),
+        }
+    }
+
+    pub fn send(&mut self, command: Command) {
+        self.sent += 1;
+        if let Some(reply) = self.worker.handle(command) {
+            self.replies.push(reply);
+        }
+    }
+
+    pub fn caught_up(&self, status: &Status) -> bool {
+        status.seen == self.sent
+    }
+
+    /// Ticks if one's due, and returns the frame if anything changed.
+    pub fn latest_frame(&mut self) -> Option<Frame> {
+        let now = Instant::now();
+        if now >= self.next_tick {
+            self.worker.tick();
+            self.next_tick = (self.next_tick + self.worker.tick).max(now);
+        }
+        let pixels = self.spare.pop().unwrap_or_default();
+        self.worker.frame(pixels)
+    }
+
+    pub fn recycle(&mut self, pixels: Vec<u8>) {
+        self.spare.push(pixels);
+    }
+
+    pub fn replies(&mut self) -> impl Iterator<Item = Reply> + '_ {
+        self.replies.drain(..)
+    }
+
+    /// How long the event loop can sleep: until the next tick, unless
+    /// there's nothing to do until there's input.
+    pub fn control_flow(&self) -> ControlFlow {
+        if self.worker.idle() {
+            ControlFlow::Wait
+        } else {
+            ControlFlow::WaitUntil(self.next_tick)
+        }
+    }
+
+    /// Finishes up. There's nowhere to save the grid in the browser, so
+    /// there's no worker to hand back.
+    pub fn shutdown(&mut self) -> Option<Worker> {
+        self.worker.finish();
+        None
+    }
+}
+
+// Time between ticks at `tps` a second.
+fn tick_length(tps: u32) -> Duration {
+    Duration::from_secs_f64(1.0 / tps.max(1) as f64)
+}
+
+// The browser's simulation runs inline and never calls `wake`.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn commands_are_applied_in_the_order_sent() {
+        let worker = Worker::new(Grid::new_empty_grid(8, 8), History::default());
+        let (woken, wakes) = mpsc::channel();
+        let mut sim = Simulation::start(worker, 60, move || {
+            let _ = woken.send(());
+        });
+        sim.send(Command::Pause);
+        sim.send(Command::Event("rect 1 1 3 2 alive".parse().unwrap()));
+        sim.send(Command::Event(session::Event::Invert));
+        sim.send(Command::Copy(Some(((0, 0), (4, 3)))));
+
+        // The reply wakes the window, which finds it waiting.
+        let copied = loop {
+            wakes
+                .recv_timeout(Duration::from_secs(5))
+                .expect("never woken");
+            if let Some(Reply::Copied { cells, .. }) = sim.replies().next() {
+                break cells;
+            }
+        };
+        sim.shutdown();
+        let expected = PatternBuffer::from_rows(&["#####", "#...#", "#...#", "#####"]);
+        assert_eq!(copied, expected);
+    }
+}
@@ -0,0 +1,141 @@
+//
+// Startup intro: "TGOL" spelled in live cells, held for a moment, then
+// released into the rules and cross-faded into the starting soup.
+//
+// Everything is counted in frames rather than wall time, so the intro plays
+// the same way every run for a given grid size (about two seconds at 60 Hz).
+// Any key or click ends it right away (`--no-splash` skips it entirely).
+//
+
+use crate::grid::{self, Grid};
+use crate::pattern::PatternBuffer;
+
+// Frames the letters are shown before the rules kick in.
+const HOLD_FRAMES: u32 = 30;
+// Frames the intro lasts in total; the last `FADE_FRAMES` fade to the soup.
+const TOTAL_FRAMES: u32 = 120;
+const FADE_FRAMES: u32 = 45;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+// 5x7 glyphs for the letters the intro needs.
+fn glyph(c: char) -> Option<[&'static str; GLYPH_HEIGHT]> {
+    Some(match c {
+        'T' => [
+            "#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#..",
+        ],
+        'G' => [
+            ".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".###.",
+        ],
+        'O' => [
+            ".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###.",
+        ],
+        'L' => [
+            "#....", "#....", "#....", "#....", "#....", "#....", "#####",
+        ],
+        _ => return None,
+    })
+}
+
+/// Rasterizes `text` at `scale` cells per glyph pixel, one blank column
+/// between letters. Characters without a glyph are left blank.
+pub fn text_pattern(text: &str, scale: usize) -> PatternBuffer {
+    let letters = text.chars().count();
+    let width = (letters * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale;
+    let mut pattern = PatternBuffer::new(width, GLYPH_HEIGHT * scale);
+
+    for (n, rows) in text
+        .chars()
+        .enumerate()
+        .filter_map(|(n, c)| Some((n, glyph(c)?)))
+    {
+        let left = n * (GLYPH_WIDTH + 1) * scale;
+        for (gy, row) in rows.iter().enumerate() {
+            for (gx, _) in row.chars().enumerate().filter(|&(_, c)| c == '#') {
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        pattern.set(left + gx * scale + dx, gy * scale + dy, true);
+                    }
+                }
+            }
+        }
+    }
+
+    pattern
+}
+
+pub struct Splash {
+    frame: u32,
+    // The grid the game starts with once the intro is over.
+    soup: Grid,
+    soup_pixels: Vec<u8>,
+}
+
+impl Splash {
+    /// Moves `life`'s contents aside as the soup to fade into, and writes
+    /// the title into `life` in their place.
+    pub fn start(life: &mut Grid) -> Self {
+        let mut soup = Grid::new_empty_grid(life.width, life.height);
+        soup.rule = life.rule;
+        soup.decay = life.decay;
+        soup.trails = life.trails;
+        soup.age_colors = life.age_colors.clone();
+        soup.heat_colors = life.heat_colors.clone();
+        std::mem::swap(&mut soup.grid, &mut life.grid);
+        // The title plays out on a torus, leaving an infinite world as it was.
+        soup.swap_world(life);
+        life.touch_all();
+
+        // As big as fits in half the grid's width.
+        let text = "TGOL";
+        let glyphs_width = text.len() * (GLYPH_WIDTH + 1) - 1;
+        let scale = (life.width / 2 / glyphs_width).max(1);
+        let title = text_pattern(text, scale);
+        let (x, y) = title.origin_centered_on((life.width as isize / 2, life.height as isize / 2));
+        life.insert_pattern(&title, x, y);
+
+        Self {
+            frame: 0,
+            soup_pixels: vec![0; 4 * soup.grid.len()],
+            soup,
+        }
+    }
+
+    /// Advances the intro by a frame and draws it. Returns false once the
+    /// intro is over, after handing the soup back to `life`.
+    pub fn step(&mut self, life: &mut Grid, screen: &mut [u8]) -> bool {
+        if self.frame >= TOTAL_FRAMES {
+            self.finish(life);
+            return false;
+        }
+
+        if self.frame >= HOLD_FRAMES {
+            life.update();
+        }
+        life.draw(screen);
+
+        let fade_start = TOTAL_FRAMES - FADE_FRAMES;
+        if self.frame >= fade_start {
+            let alpha = (self.frame - fade_start + 1) as f32 / FADE_FRAMES as f32;
+            self.soup.draw(&mut self.soup_pixels);
+            for (pix, soup) in screen
+                .chunks_exact_mut(4)
+                .zip(self.soup_pixels.chunks_exact(4))
+            {
+                grid::blend(pix, [soup[0], soup[1], soup[2], soup[3]], alpha);
+            }
+        }
+
+        self.frame += 1;
+        true
+    }
+
+    /// Ends the intro now: `life` gets the soup, at generation 0.
+    pub fn finish(&mut self, life: &mut Grid) {
+        std::mem::swap(&mut life.grid, &mut self.soup.grid);
+        life.swap_world(&mut self.soup);
+        life.touch_all();
+        life.generation = 0;
+    }
+}
@@ -0,0 +1,152 @@
+//
+// `--stats-out <PATH>`: a CSV line per generation with how many cells were
+// born, died and are alive, for looking at runs afterwards. Noise flips
+// are counted on their own, not as births or deaths. Each new soup starts
+// a new run, numbered in the first column. Generations count from the
+// starting grid, after its pre-roll, so a run's first line is generation 1:
+//
+//   run,generation,population,births,deaths,noise
+//   0,1,2311,402,377,0
+//   0,2,2298,365,378,0
+//
+// With teams (--automaton immigration or quadlife), each team's live cells
+// follow in columns of their own, named after them.
+//
+// Lines are flushed every so often, so a crash only loses the last few.
+//
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use tgol::automaton::TEAM_NAMES;
+use tgol::grid::GenerationStats;
+
+// Lines written between flushes.
+const FLUSH_LINES: u32 = 64;
+
+pub struct StatsCsv {
+    out: BufWriter<File>,
+    run: u32,
+    unflushed: u32,
+    // Team columns at the end of each line; none for plain Life.
+    teams: usize,
+}
+
+impl StatsCsv {
+    /// Starts a CSV at `path`, with a column for every one of `teams` if
+    /// there's more than one.
+    pub fn create(path: &Path, teams: usize) -> io::Result<Self> {
+        let teams = if teams > 1 { teams } else { 0 };
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "run,generation,population,births,deaths,noise")?;
+        for name in &TEAM_NAMES[..teams] {
+            write!(out, ",{}", name)?;
+        }
+        writeln!(out)?;
+        Ok(Self {
+            out,
+            run: 0,
+            unflushed: 0,
+            teams,
+        })
+    }
+
+    /// Lines from here on are for a new soup.
+    pub fn new_run(&mut self) {
+        self.run += 1;
+    }
+
+    pub fn write(&mut self, stats: &GenerationStats) -> io::Result<()> {
+        write!(
+            self.out,
+            "{},{},{},{},{},{}",
+            self.run,
+            stats.generation,
+            stats.population,
+            stats.births,
+            stats.deaths,
+            stats.noise_flips
+        )?;
+        for count in &stats.teams[..self.teams] {
+            write!(self.out, ",{}", count)?;
+        }
+        writeln!(self.out)?;
+        self.unflushed += 1;
+        if self.unflushed >= FLUSH_LINES {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.unflushed = 0;
+        self.out.flush()
+    }
+}
+
+/// Writes `stats` to `csv` if there is one, giving up on the file (and
+/// saying so) if that fails.
+pub fn write_stats(csv: &mut Option<StatsCsv>, stats: &GenerationStats) {
+    if let Some(out) = csv {
+        if let Err(e) = out.write(stats) {
+            log::error!("Couldn't write to --stats-out, so it's stopped: {}", e);
+            *csv = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use tgol::grid::Grid;
+
+    #[test]
+    fn each_line_is_one_update_in_its_run() {
+        let mut blinker: Grid = ".....\n..#..\n..#..\n..#..\n.....\n".parse().unwrap();
+        let path = std::env::temp_dir().join(format!("tgol-runs-{}.csv", std::process::id()));
+        let mut csv = StatsCsv::create(&path, 1).unwrap();
+        for _ in 0..2 {
+            csv.write(&blinker.update()).unwrap();
+        }
+        csv.new_run();
+        csv.write(&blinker.update()).unwrap();
+        csv.flush().unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "run,generation,population,births,deaths,noise",
+                "0,1,3,2,2,0",
+                "0,2,3,2,2,0",
+                "1,3,3,2,2,0",
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_count_generations_from_the_starting_grid() {
+        let options = Options::from_args(&["--pre-roll", "5"]).unwrap();
+        let mut life = crate::starting_grid(&options, (32, 24), 6);
+        let path = std::env::temp_dir().join(format!("tgol-stats-{}.csv", std::process::id()));
+        let mut csv = StatsCsv::create(&path, 1).unwrap();
+        for _ in 0..3 {
+            csv.write(&life.update()).unwrap();
+        }
+        csv.flush().unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "run,generation,population,births,deaths,noise");
+        let generations: Vec<&str> = lines[1..]
+            .iter()
+            .map(|line| line.split(',').nth(1).unwrap())
+            .collect();
+        assert_eq!(generations, ["1", "2", "3"]);
+    }
+}
@@ -0,0 +1,171 @@
+//
+// `--stochastic`: the rule only gets its way most of the time. A dead cell
+// the rule would bring to life is born with chance `births`, and a live
+// cell it would keep survives with chance `survivals`; otherwise each does
+// the opposite. Deaths and cells staying dead always happen as the rule
+// says. Written `pb=0.98,ps=0.99`, either one left out meaning 1.
+//
+// The chances are drawn from `Grid::noise_seed`, the generation and the
+// cell alone, so a run is the same every time under one --seed, whichever
+// order cells are stepped in. At 1 nothing is drawn at all, and a run is
+// the plain rule's. Stochastic runs are stepped a whole grid at a time.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stochastic {
+    /// Chance a birth the rule calls for happens.
+    pub births: f32,
+    /// Chance a survival the rule calls for happens.
+    pub survivals: f32,
+}
+
+impl Default for Stochastic {
+    fn default() -> Self {
+        Self {
+            births: 1.0,
+            survivals: 1.0,
+        }
+    }
+}
+
+impl Stochastic {
+    /// Whether a cell that's `was` alive, and that the rule has `alive`
+    /// next generation, really is. `seed`, `generation` and `i` (the
+    /// cell's index) pick the draw.
+    pub fn apply(self, was: bool, alive: bool, seed: u64, generation: u64, i: usize) -> bool {
+        let chance = match (was, alive) {
+            (false, true) => self.births,
+            (true, true) => self.survivals,
+            _ => return alive,
+        };
+        if chance >= 1.0 {
+            return true;
+        }
+        randomize::f32_half_open_right(draw(seed, generation, i)) < chance
+    }
+}
+
+// A hash of the three, good enough to pass for random: splitmix64's
+// finalizer over them all mixed together.
+fn draw(seed: u64, generation: u64, i: usize) -> u32 {
+    let mut z = seed
+        ^ generation.wrapping_mul(0x9e3779b97f4a7c15)
+        ^ (i as u64).wrapping_mul(0xd1b54a32d192ed03);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    (z ^ (z >> 31)) as u32
+}
+
+impl fmt::Display for Stochastic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pb={},ps={}", self.births, self.survivals)
+    }
+}
+
+impl FromStr for Stochastic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || {
+            format!(
+                "bad stochastic rule {:?} (expected pb=P,ps=P with chances from 0 to 1)",
+                s
+            )
+        };
+        let mut stochastic = Self::default();
+        for part in s.trim().split(',') {
+            let (key, value) = part.split_once('=').ok_or_else(bad)?;
+            let value: f32 = value.trim().parse().map_err(|_| bad())?;
+            if !(0.0..=1.0).contains(&value) {
+                return Err(bad());
+            }
+            match key.trim() {
+                "pb" => stochastic.births = value,
+                "ps" => stochastic.survivals = value,
+                _ => return Err(bad()),
+            }
+        }
+        Ok(stochastic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::grid::Grid;
+    use crate::rng::session_rng;
+
+    fn soup(seed: u64, engine: Engine, stochastic: Option<Stochastic>) -> Grid {
+        let mut g = Grid::new_empty_grid(61, 37);
+        g.randomize(&mut session_rng(seed), 0.35);
+        g.engine = engine;
+        g.noise_seed = seed;
+        g.stochastic = stochastic;
+        g
+    }
+
+    #[test]
+    fn certain_chances_step_exactly_like_the_rule() {
+        let certain = "pb=1,ps=1".parse().unwrap();
+        assert_eq!(certain, Stochastic::default());
+        for engine in [Engine::Scalar, Engine::Bitset, Engine::Active] {
+            let mut plain = soup(8, engine, None);
+            let mut stochastic = soup(8, engine, Some(certain));
+            for generation in 0..200 {
+                let (a, b) = (plain.update(), stochastic.update());
+                assert_eq!((a.births, a.deaths), (b.births, b.deaths));
+                assert!(
+                    plain.grid == stochastic.grid,
+                    "{:?}, generation {}",
+                    engine,
+                    generation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_births_and_survivals_are_left_to_chance() {
+        let never = Stochastic {
+            births: 0.0,
+            survivals: 0.0,
+        };
+        for i in 0..100 {
+            assert!(!never.apply(false, true, 1, 2, i));
+            assert!(!never.apply(true, true, 1, 2, i));
+            assert!(!never.apply(true, false, 1, 2, i));
+            assert!(!never.apply(false, false, 1, 2, i));
+            assert!(Stochastic::default().apply(false, true, 1, 2, i));
+            assert!(Stochastic::default().apply(true, true, 1, 2, i));
+        }
+    }
+
+    #[test]
+    fn a_seed_gives_the_same_run_every_time() {
+        let chancy = Some("pb=0.9,ps=0.95".parse().unwrap());
+        let mut a = soup(3, Engine::Bitset, chancy);
+        let mut b = soup(3, Engine::Bitset, chancy);
+        let mut plain = soup(3, Engine::Bitset, None);
+        for _ in 0..50 {
+            a.update();
+            b.update();
+            plain.update();
+        }
+        assert!(a.grid == b.grid);
+        assert!(a.grid != plain.grid);
+    }
+
+    #[test]
+    fn chances_parse_from_zero_to_one() {
+        let parsed: Stochastic = "ps=0.5".parse().unwrap();
+        assert_eq!((parsed.births, parsed.survivals), (1.0, 0.5));
+        assert_eq!(parsed.to_string().parse(), Ok(parsed));
+        for bad in ["pb=1.5", "ps=-0.1", "pb", "px=0.5", "pb=x"] {
+            assert!(bad.parse::<Stochastic>().is_err(), "{}", bad);
+        }
+    }
+}
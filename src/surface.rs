@@ -0,0 +1,166 @@
+//
+// Bookkeeping for the window surface, shared by every path that changes
+// its size (resize, fullscreen, scale factor, minimize/restore), and what
+// to do about a render that fails: put the last size back on the next
+// frame, and if that fails too, rebuild `Pixels` from scratch. Deciding
+// is kept apart from the window and the GPU, which `main` does the doing
+// with.
+//
+
+use winit::dpi::PhysicalSize;
+
+/// What to do to the surface before the next render.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceStep {
+    /// It's the right size already.
+    Keep,
+    /// The window's minimized, and there's nothing to render into.
+    Wait,
+    /// Resize it.
+    Resize(PhysicalSize<u32>),
+    /// Build a new `Pixels` this size, after the resizes didn't help.
+    Rebuild(PhysicalSize<u32>),
+}
+
+#[derive(Default)]
+pub struct SurfaceState {
+    // Size to push to the surface before the next render.
+    pending: Option<PhysicalSize<u32>>,
+    // Size last pushed to the surface.
+    current: Option<PhysicalSize<u32>>,
+    // Consecutive failed renders since the last good one.
+    failures: u8,
+    // Set when `Pixels` was rebuilt, so anything made on its device has to
+    // be made again.
+    pub rebuilt: bool,
+}
+
+impl SurfaceState {
+    pub fn request(&mut self, size: PhysicalSize<u32>) {
+        self.pending = Some(size);
+    }
+
+    /// The step to take before the next render, with the surface no
+    /// smaller than `min`. Until it's taken and `applied`, this stays the
+    /// same; while the window's minimized the resize waits for it.
+    pub fn next_step(&self, min: PhysicalSize<u32>) -> SurfaceStep {
+        let Some(size) = self.pending else {
+            return SurfaceStep::Keep;
+        };
+        if size.width == 0 || size.height == 0 {
+            return SurfaceStep::Wait;
+        }
+
+        let size = PhysicalSize::new(size.width.max(min.width), size.height.max(min.height));
+        if self.failures >= 2 {
+            SurfaceStep::Rebuild(size)
+        } else {
+            SurfaceStep::Resize(size)
+        }
+    }
+
+    /// Records that `step` was taken.
+    pub fn applied(&mut self, step: SurfaceStep) {
+        if let SurfaceStep::Resize(size) | SurfaceStep::Rebuild(size) = step {
+            self.pending = None;
+            self.current = Some(size);
+            self.rebuilt |= matches!(step, SurfaceStep::Rebuild(_));
+        }
+    }
+
+    /// Records a failed render. The first failure re-applies the last size
+    /// (or `window_size`, before there was one) on the next frame, the
+    /// second rebuilds `Pixels`. Returns false once even that didn't help.
+    pub fn render_failed(&mut self, window_size: PhysicalSize<u32>) -> bool {
+        self.failures += 1;
+        if self.failures > 2 {
+            return false;
+        }
+
+        self.pending = Some(self.current.unwrap_or(window_size));
+        true
+    }
+
+    pub fn render_ok(&mut self) {
+        self.failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: PhysicalSize<u32> = PhysicalSize::new(320, 240);
+
+    fn size(width: u32, height: u32) -> PhysicalSize<u32> {
+        PhysicalSize::new(width, height)
+    }
+
+    // A surface sized to 800x600 and rendering fine.
+    fn sized() -> SurfaceState {
+        let mut surface = SurfaceState::default();
+        surface.request(size(800, 600));
+        surface.applied(surface.next_step(MIN));
+        surface.render_ok();
+        surface
+    }
+
+    #[test]
+    fn requests_are_resized_to_at_least_the_minimum() {
+        let mut surface = SurfaceState::default();
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Keep);
+        surface.request(size(100, 900));
+        let step = surface.next_step(MIN);
+        assert_eq!(step, SurfaceStep::Resize(size(320, 900)));
+        surface.applied(step);
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Keep);
+        assert!(!surface.rebuilt);
+    }
+
+    #[test]
+    fn minimized_windows_wait_with_the_resize_pending() {
+        let mut surface = sized();
+        surface.request(size(0, 0));
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Wait);
+        surface.applied(SurfaceStep::Wait);
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Wait);
+        surface.request(size(1024, 768));
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Resize(size(1024, 768)));
+    }
+
+    #[test]
+    fn a_failed_render_retries_the_last_size_once() {
+        let mut surface = sized();
+        assert!(surface.render_failed(size(640, 480)));
+        let step = surface.next_step(MIN);
+        assert_eq!(step, SurfaceStep::Resize(size(800, 600)));
+        surface.applied(step);
+
+        // That worked, so the next failure is a first one again.
+        surface.render_ok();
+        assert!(surface.render_failed(size(640, 480)));
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Resize(size(800, 600)));
+        assert!(!surface.rebuilt);
+    }
+
+    #[test]
+    fn failing_again_rebuilds_and_then_gives_up() {
+        let mut surface = sized();
+        assert!(surface.render_failed(size(640, 480)));
+        surface.applied(surface.next_step(MIN));
+        assert!(surface.render_failed(size(640, 480)));
+        let step = surface.next_step(MIN);
+        assert_eq!(step, SurfaceStep::Rebuild(size(800, 600)));
+        surface.applied(step);
+        assert!(surface.rebuilt);
+
+        assert!(!surface.render_failed(size(640, 480)));
+    }
+
+    #[test]
+    fn failing_before_any_resize_retries_the_window_size() {
+        let mut surface = SurfaceState::default();
+        assert!(surface.render_failed(size(640, 480)));
+        assert_eq!(surface.next_step(MIN), SurfaceStep::Resize(size(640, 480)));
+    }
+}
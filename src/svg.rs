@@ -0,0 +1,234 @@
+//
+// Vector (SVG) export of a block of cells.
+//
+// Each horizontal run of live cells becomes a single <rect>, which keeps
+// files for soups a fraction of the size of one rect per cell.
+//
+
+use std::fmt::Write;
+
+pub struct SvgOptions {
+    pub region: SvgRegion,
+    /// Side of one cell, in SVG user units.
+    pub cell_size: u32,
+    /// Stroke a thin line between every cell.
+    pub grid_lines: bool,
+    pub alive: [u8; 4],
+    pub background: [u8; 4],
+    pub line: [u8; 4],
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            region: SvgRegion::Whole,
+            cell_size: 8,
+            grid_lines: false,
+            alive: crate::ALIVE_COLOR,
+            background: crate::BACKGROUND_COLOR,
+            line: [0x40, 0x40, 0x40, 0xff],
+        }
+    }
+}
+
+/// Part of the grid to export, in grid cells.
+pub enum SvgRegion {
+    Whole,
+    /// Two opposite corners, in any order; clamped to the grid.
+    Rect((isize, isize), (isize, isize)),
+    /// The smallest rectangle holding every live cell.
+    LiveBounds,
+}
+
+/// Renders a `width` x `height` block of cells to an SVG document.
+/// `generation` and `rule` are stored in the document's metadata.
+pub fn render(
+    width: usize,
+    height: usize,
+    is_alive: impl Fn(usize, usize) -> bool,
+    options: &SvgOptions,
+    generation: u64,
+    rule: &str,
+) -> String {
+    let cs = options.cell_size as usize;
+    let (w, h) = (width * cs, height * cs);
+    let mut svg = String::new();
+
+    // Writing into a String can't fail.
+    let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<metadata><tgol:state xmlns:tgol="https://github.com/twhiting/tgol.rust" generation="{}" rule="{}"/></metadata>"#,
+        generation,
+        // Read from a pattern file, it could be anything.
+        rule.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('"', "&quot;")
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{w}" height="{h}" fill="{}"/>"#,
+        hex(options.background)
+    );
+
+    let _ = writeln!(svg, r#"<g fill="{}">"#, hex(options.alive));
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            if !is_alive(x, y) {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x < width && is_alive(x, y) {
+                x += 1;
+            }
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+                start * cs,
+                y * cs,
+                (x - start) * cs,
+                cs
+            );
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if options.grid_lines && width > 0 && height > 0 {
+        let mut d = String::new();
+        for x in 0..=width {
+            let _ = write!(d, "M{} 0V{}", x * cs, h);
+        }
+        for y in 0..=height {
+            let _ = write!(d, "M0 {}H{}", y * cs, w);
+        }
+        let _ = writeln!(
+            svg,
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+            d,
+            hex(options.line),
+            (cs as f32 / 16.0).max(0.25)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// `#rrggbb`, or `#rrggbbaa` when not fully opaque.
+fn hex([r, g, b, a]: [u8; 4]) -> String {
+    if a == 0xff {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks `svg` is well-formed XML, as far as this writes it: a
+    // declaration, then elements whose tags nest and whose attributes are
+    // quoted, with nothing but whitespace between them. Returns the name
+    // of each element, in document order.
+    fn elements(svg: &str) -> Vec<String> {
+        let body = svg
+            .strip_prefix(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+            .expect("no XML declaration");
+        let mut names = Vec::new();
+        let mut open: Vec<String> = Vec::new();
+        let mut rest = body.trim_start();
+        while !rest.is_empty() {
+            assert!(rest.starts_with('<'), "text outside a tag: {:?}", rest);
+            let end = rest.find('>').expect("unterminated tag");
+            let tag = &rest[1..end];
+            rest = rest[end + 1..].trim_start();
+
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open.pop().as_deref(), Some(name), "mismatched </{}>", name);
+                assert!(!open.is_empty() || rest.is_empty(), "more than one root");
+                continue;
+            }
+            let (tag, closed) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let (name, mut attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+            assert!(
+                !name.is_empty() && !name.contains(['<', '"', '=']),
+                "bad name {:?}",
+                name
+            );
+            while !attributes.trim().is_empty() {
+                let (key, value) = attributes
+                    .trim_start()
+                    .split_once("=\"")
+                    .expect("bad attribute");
+                let (value, after) = value.split_once('"').expect("unquoted attribute");
+                assert!(!key.contains([' ', '<']) && !value.contains(['<', '&']));
+                attributes = after;
+            }
+
+            names.push(name.to_string());
+            if !closed {
+                open.push(name.to_string());
+            }
+        }
+        assert!(open.is_empty(), "unclosed {:?}", open);
+        assert_eq!(
+            names.first().map(String::as_str),
+            Some("svg"),
+            "more than one root"
+        );
+        names
+    }
+
+    fn rects(svg: &str) -> usize {
+        elements(svg).iter().filter(|name| *name == "rect").count()
+    }
+
+    #[test]
+    fn output_is_well_formed() {
+        let options = SvgOptions {
+            grid_lines: true,
+            alive: [1, 2, 3, 0x80],
+            ..SvgOptions::default()
+        };
+        let svg = render(5, 3, |x, y| (x + y) % 2 == 0, &options, 42, "B3/S23");
+        let names = elements(&svg);
+        assert_eq!(names[..3], ["svg", "metadata", "tgol:state"]);
+        assert_eq!(names.last().map(String::as_str), Some("path"));
+        assert!(svg.contains(r#"generation="42" rule="B3/S23""#));
+    }
+
+    #[test]
+    fn runs_of_live_cells_merge_into_one_rect() {
+        // The background, then "##.##", ".....", "#####" and "#.#.#".
+        let rows = ["##.##", ".....", "#####", "#.#.#"];
+        let alive = |x: usize, y: usize| rows[y].as_bytes()[x] == b'#';
+        let svg = render(5, 4, alive, &SvgOptions::default(), 0, "B3/S23");
+        assert_eq!(rects(&svg), 1 + 2 + 1 + 3);
+        assert!(svg.contains(r#"<rect x="0" y="16" width="40" height="8"/>"#));
+    }
+
+    #[test]
+    fn empty_grids_are_empty_documents() {
+        let options = SvgOptions {
+            grid_lines: true,
+            ..SvgOptions::default()
+        };
+        let svg = render(0, 0, |_, _| true, &options, 0, "B3/S23");
+        assert_eq!(
+            elements(&svg),
+            ["svg", "metadata", "tgol:state", "rect", "g"]
+        );
+        assert!(svg.contains(r#"width="0" height="0" viewBox="0 0 0 0""#));
+    }
+}
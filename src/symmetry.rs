@@ -0,0 +1,69 @@
+//
+// Symmetries a random soup can be made to have. Life's rules treat every
+// direction alike, so a symmetric soup stays symmetric as it evolves.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoupSymmetry {
+    #[default]
+    None,
+    /// Left half mirrored onto the right half.
+    MirrorX,
+    /// Top half mirrored onto the bottom half.
+    MirrorY,
+    /// Both mirrors: the top-left quadrant is reflected into the other three.
+    Both,
+    /// The first half of the grid rotated half a turn onto the second.
+    Rot180,
+}
+
+impl SoupSymmetry {
+    const ALL: [Self; 5] = [
+        Self::None,
+        Self::MirrorX,
+        Self::MirrorY,
+        Self::Both,
+        Self::Rot180,
+    ];
+
+    /// The next mode, wrapping around after the last one.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::MirrorX => "mirror-x",
+            Self::MirrorY => "mirror-y",
+            Self::Both => "both",
+            Self::Rot180 => "rot180",
+        }
+    }
+}
+
+impl fmt::Display for SoupSymmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SoupSymmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown symmetry {:?} (expected none, mirror-x, mirror-y, both or rot180)",
+                    s
+                )
+            })
+    }
+}
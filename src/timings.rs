@@ -0,0 +1,208 @@
+//
+// `--timings`: how long each phase of a frame takes, for knowing where the
+// time goes before optimizing. Stepping the grid (`Grid::update`, once a
+// generation), drawing it (`Grid::draw` or `draw_dirty`, once a frame) and
+// presenting it (`Pixels::render`) are timed as they happen, on whichever
+// thread they happen on, and the latest `WINDOW` of each are kept for the
+// averages and 95th percentiles reported.
+//
+// Unlike the `profiling` feature's spans, this is in every build, and
+// costs a check of an `Option` per phase when it's off: the callers hold
+// None, and `time` just runs the phase.
+//
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use instant::Instant;
+
+/// Samples kept of each phase, unless told otherwise.
+pub const WINDOW: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Update,
+    Draw,
+    Render,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Update, Phase::Draw, Phase::Render];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Update => "update",
+            Phase::Draw => "draw",
+            Phase::Render => "render",
+        }
+    }
+}
+
+pub struct Timings {
+    window: usize,
+    // The latest samples of each phase, in `Phase::ALL`'s order.
+    samples: Mutex<[VecDeque<Duration>; 3]>,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::with_window(WINDOW)
+    }
+}
+
+impl Timings {
+    /// Keeping the latest `window` samples of each phase.
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            samples: Mutex::default(),
+        }
+    }
+
+    pub fn record(&self, phase: Phase, elapsed: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let samples = &mut samples[phase as usize];
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+    }
+
+    /// The phases timed so far, over the latest samples of each.
+    pub fn report(&self) -> Report {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let phases = Phase::ALL
+            .into_iter()
+            .filter_map(|phase| {
+                let mut sorted: Vec<Duration> = samples[phase as usize].iter().copied().collect();
+                if sorted.is_empty() {
+                    return None;
+                }
+                sorted.sort_unstable();
+                let total: Duration = sorted.iter().sum();
+                Some(PhaseReport {
+                    phase,
+                    samples: sorted.len(),
+                    mean: total / sorted.len() as u32,
+                    p95: percentile(&sorted, 0.95),
+                })
+            })
+            .collect();
+        Report { phases }
+    }
+}
+
+/// Runs `f`, timed as `phase` if there are `timings` to record it in.
+pub fn time<R>(timings: Option<&Timings>, phase: Phase, f: impl FnOnce() -> R) -> R {
+    let Some(timings) = timings else {
+        return f();
+    };
+    let start = Instant::now();
+    let result = f();
+    timings.record(phase, start.elapsed());
+    result
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseReport {
+    pub phase: Phase,
+    pub samples: usize,
+    pub mean: Duration,
+    pub p95: Duration,
+}
+
+/// Means and 95th percentiles of the phases that have been timed.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub phases: Vec<PhaseReport>,
+}
+
+impl Report {
+    /// A table of the phases, a line each, for the log or --bench.
+    pub fn table(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:>8} {:>10} {:>10} {:>8}",
+            "phase", "mean", "p95", "samples"
+        )];
+        for p in &self.phases {
+            lines.push(format!(
+                "{:>8} {:>10.3?} {:>10.3?} {:>8}",
+                p.phase.name(),
+                p.mean,
+                p.p95,
+                p.samples
+            ));
+        }
+        lines
+    }
+}
+
+impl fmt::Display for Report {
+    /// All on one line, in milliseconds: "update 1.20/2.41  draw …"
+    /// (mean/p95).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, p) in self.phases.iter().enumerate() {
+            if n > 0 {
+                write!(f, "  ")?;
+            }
+            let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+            write!(f, "{} {:.2}/{:.2}", p.phase.name(), ms(p.mean), ms(p.p95))?;
+        }
+        Ok(())
+    }
+}
+
+// Nearest-rank percentile of an ascending, non-empty slice.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn reports_cover_the_latest_samples_of_each_phase() {
+        let timings = Timings::with_window(20);
+        // 1..=30 ms: only the last 20, 11..=30, are kept.
+        for n in 1..=30 {
+            timings.record(Phase::Update, ms(n));
+        }
+        timings.record(Phase::Render, ms(7));
+
+        let report = timings.report();
+        assert_eq!(report.phases.len(), 2);
+        let update = report.phases[0];
+        assert_eq!(update.phase, Phase::Update);
+        assert_eq!(update.samples, 20);
+        assert_eq!(update.mean, Duration::from_micros(20_500));
+        assert_eq!(update.p95, ms(29));
+        let render = report.phases[1];
+        assert_eq!(render.phase, Phase::Render);
+        assert_eq!((render.samples, render.mean, render.p95), (1, ms(7), ms(7)));
+
+        assert_eq!(report.to_string(), "update 20.50/29.00  render 7.00/7.00");
+        // A header, then a line a phase.
+        assert_eq!(report.table().len(), 3);
+    }
+
+    #[test]
+    fn nothing_is_timed_without_timings() {
+        assert_eq!(time(None, Phase::Draw, || 5), 5);
+
+        let timings = Timings::default();
+        assert_eq!(time(Some(&timings), Phase::Draw, || 5), 5);
+        let report = timings.report();
+        assert_eq!(report.phases.len(), 1);
+        assert_eq!(report.phases[0].phase, Phase::Draw);
+        assert_eq!(report.phases[0].samples, 1);
+        assert!(Timings::default().report().phases.is_empty());
+    }
+}
@@ -0,0 +1,405 @@
+//
+// How the edges of a bounded grid are glued together, as Golly has them.
+// A torus joins left to right and top to bottom straight across. A Klein
+// bottle glues one pair with a flip: with `KleinX`, whatever leaves past
+// the right edge comes back in on the left mirrored top to bottom, and
+// `KleinY` does the same for the top and bottom edges, mirrored left to
+// right. A cross-surface flips both pairs, and a plane glues nothing: past
+// its edges every cell is dead. A wall is a plane with every cell past its
+// edges alive instead, so the border is forever feeding whatever grows in
+// from it (rule tables see state 1 there).
+//
+// A shifted torus (`torus+X,Y`, or --wrap-shift x=X,y=Y) is a torus with a
+// twist: whatever leaves past the right edge comes back in on the left Y
+// cells further down, or past the bottom edge comes back in at the top X
+// cells further right (and the other way round going the other way). A
+// spaceship that circles it comes back on a different track each time.
+// Only one pair of edges can be shifted, as in Golly: with both, the
+// edges don't meet up into a surface, and cells would have neighbors
+// that don't have them back. Unrolled, a shifted torus is copies of the
+// grid tiling the plane, each column of them Y cells below the last, or
+// each row X cells right of the last. Shifting both ways would need
+// copies at (width, Y) and at (X, height) from each other, and for any
+// X and Y but 0 those overlap some copies and leave gaps between others.
+//
+// To place a cell that's off the grid, it's brought back across the side
+// edges first, then across the top and bottom. Near a corner of a
+// cross-surface that makes for surprising neighbors: a corner cell is its
+// own neighbor across the corner, and has the far corner for a neighbor
+// twice over, once across each edge. On a shifted torus, the cell across
+// a corner is shifted too, whichever of the edges is.
+//
+// Anything but a torus is stepped cell by cell, whichever engine is
+// picked, and never as an infinite world.
+//
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The border drawn round a walled grid, over its outermost cells.
+pub const WALL_COLOR: [u8; 4] = [0xff, 0xf0, 0xc0, 0xff];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    #[default]
+    Torus,
+    /// Left and right edges glued with a flip.
+    KleinX,
+    /// Top and bottom edges glued with a flip.
+    KleinY,
+    /// Both pairs glued with a flip.
+    CrossSurface,
+    /// No edges glued.
+    Plane,
+    /// No edges glued, and live cells all round.
+    Wall,
+    /// A torus that moves what crosses the left and right edges `y` cells
+    /// down, or what crosses the top and bottom `x` cells right; the other
+    /// is 0 (see `shifted`).
+    Shifted { x: i32, y: i32 },
+}
+
+impl Topology {
+    pub const ALL: [Self; 6] = [
+        Self::Torus,
+        Self::KleinX,
+        Self::KleinY,
+        Self::CrossSurface,
+        Self::Plane,
+        Self::Wall,
+    ];
+
+    /// A torus shifted by `x` or `y` (see `Shifted`); a plain torus for
+    /// no shift at all.
+    pub fn shifted(x: i32, y: i32) -> Result<Self, String> {
+        match (x, y) {
+            (0, 0) => Ok(Self::Torus),
+            (0, _) | (_, 0) => Ok(Self::Shifted { x, y }),
+            _ => Err(format!(
+                "a torus can be shifted across one pair of edges, not both (x={}, y={})",
+                x, y
+            )),
+        }
+    }
+
+    /// The one after this in `ALL`, back round to the torus after the last.
+    /// A shifted torus goes on to the Klein bottles, as a torus does.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Whether cells past the edges count as alive.
+    pub fn walled(self) -> bool {
+        self == Self::Wall
+    }
+
+    /// Where (x, y) lands on a `width` x `height` grid, or None if that's
+    /// off a plane (or a wall).
+    pub fn wrap(self, x: isize, y: isize, size: (usize, usize)) -> Option<(usize, usize)> {
+        self.wrap_mirrored(x, y, size).map(|(x, y, _, _)| (x, y))
+    }
+
+    /// Like `wrap`, but also says whether the trip there mirrored things
+    /// left to right, and top to bottom, so what's moving can be turned
+    /// around to match.
+    pub fn wrap_mirrored(
+        self,
+        x: isize,
+        y: isize,
+        (width, height): (usize, usize),
+    ) -> Option<(usize, usize, bool, bool)> {
+        let (w, h) = (width as isize, height as isize);
+        if let Self::Shifted {
+            x: shift_x,
+            y: shift_y,
+        } = self
+        {
+            // Each time across the side edges shifts y, and each time
+            // across the top and bottom shifts x (one of them by nothing).
+            let y = y + x.div_euclid(w) * shift_y as isize;
+            let x = x.rem_euclid(w) + y.div_euclid(h) * shift_x as isize;
+            return Some((
+                x.rem_euclid(w) as usize,
+                y.rem_euclid(h) as usize,
+                false,
+                false,
+            ));
+        }
+        let glued = !matches!(self, Self::Plane | Self::Wall);
+        if !(glued || (0..w).contains(&x) && (0..h).contains(&y)) {
+            return None;
+        }
+        let flip_x_edges = matches!(self, Self::KleinX | Self::CrossSurface);
+        let flip_y_edges = matches!(self, Self::KleinY | Self::CrossSurface);
+
+        // Each time across a flipped edge mirrors the other coordinate.
+        let mirror_y = flip_x_edges && x.div_euclid(w) % 2 != 0;
+        let y = if mirror_y { h - 1 - y } else { y };
+        let x = x.rem_euclid(w);
+        let mirror_x = flip_y_edges && y.div_euclid(h) % 2 != 0;
+        let x = if mirror_x { w - 1 - x } else { x };
+        let y = y.rem_euclid(h);
+        Some((x as usize, y as usize, mirror_x, mirror_y))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Torus => "torus",
+            Self::KleinX => "klein-x",
+            Self::KleinY => "klein-y",
+            Self::CrossSurface => "cross-surface",
+            Self::Plane => "plane",
+            Self::Wall => "wall",
+            Self::Shifted { .. } => "torus",
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shifted { x, y } => f.pad(&format!("torus+{},{}", x, y)),
+            _ => f.pad(self.name()),
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(shift) = s.trim().strip_prefix("torus+") {
+            let bad = || format!("expected torus+X,Y with whole-number shifts, not {:?}", s);
+            let (x, y) = shift.split_once(',').ok_or_else(bad)?;
+            let x = x.trim().parse().map_err(|_| bad())?;
+            let y = y.trim().parse().map_err(|_| bad())?;
+            return Self::shifted(x, y);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown topology {:?} (expected torus, klein-x, klein-y, cross-surface, plane, wall or torus+X,Y)",
+                    s
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    const SIZE: (usize, usize) = (4, 3);
+
+    // The neighbors of (x, y), in reading order, None for those off a plane.
+    fn neighbors(topology: Topology, x: usize, y: usize) -> Vec<Option<(usize, usize)>> {
+        let mut cells = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) {
+                    cells.push(topology.wrap(x as isize + dx, y as isize + dy, SIZE));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn torus_corners_wrap_both_ways() {
+        let expected = [
+            (3, 2),
+            (0, 2),
+            (1, 2),
+            (3, 0),
+            (1, 0),
+            (3, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        let expected: Vec<_> = expected.into_iter().map(Some).collect();
+        assert_eq!(neighbors(Topology::Torus, 0, 0), expected);
+    }
+
+    #[test]
+    fn shifted_corners_are_shifted_across_either_edge() {
+        // Across the sides a cell down; the corner goes across a side too.
+        let down = [
+            (3, 1),
+            (0, 2),
+            (1, 2),
+            (3, 2),
+            (1, 0),
+            (3, 0),
+            (0, 1),
+            (1, 1),
+        ];
+        let down: Vec<_> = down.into_iter().map(Some).collect();
+        assert_eq!(neighbors(Topology::shifted(0, 1).unwrap(), 0, 0), down);
+
+        // Across the top two cells left; the corner goes across it too.
+        let left = [
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (3, 0),
+            (1, 0),
+            (3, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        let left: Vec<_> = left.into_iter().map(Some).collect();
+        assert_eq!(neighbors(Topology::shifted(2, 0).unwrap(), 0, 0), left);
+    }
+
+    // Like `neighbors`, with which way each trip there mirrored things.
+    fn mirrored(topology: Topology, x: usize, y: usize) -> Vec<(usize, usize, bool, bool)> {
+        let mut cells = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) {
+                    let (x, y) = (x as isize + dx, y as isize + dy);
+                    cells.push(topology.wrap_mirrored(x, y, SIZE).unwrap());
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn klein_corners_flip_across_one_pair_of_edges() {
+        // Across the sides, rows come back upside down.
+        let (f, t) = (false, true);
+        assert_eq!(
+            mirrored(Topology::KleinX, 0, 0),
+            [
+                (3, 0, f, t),
+                (0, 2, f, f),
+                (1, 2, f, f),
+                (3, 2, f, t),
+                (1, 0, f, f),
+                (3, 1, f, t),
+                (0, 1, f, f),
+                (1, 1, f, f),
+            ]
+        );
+        // Across the top, columns come back the other way round.
+        assert_eq!(
+            mirrored(Topology::KleinY, 0, 0),
+            [
+                (0, 2, t, f),
+                (3, 2, t, f),
+                (2, 2, t, f),
+                (3, 0, f, f),
+                (1, 0, f, f),
+                (3, 1, f, f),
+                (0, 1, f, f),
+                (1, 1, f, f),
+            ]
+        );
+    }
+
+    #[test]
+    fn cross_surface_corners_are_their_own_neighbors() {
+        // Each corner is its own neighbor across the corner, and has the
+        // far corner for a neighbor across either edge.
+        let (f, t) = (false, true);
+        assert_eq!(
+            mirrored(Topology::CrossSurface, 0, 0),
+            [
+                (0, 0, t, t),
+                (3, 2, t, f),
+                (2, 2, t, f),
+                (3, 2, f, t),
+                (1, 0, f, f),
+                (3, 1, f, t),
+                (0, 1, f, f),
+                (1, 1, f, f),
+            ]
+        );
+        assert_eq!(
+            mirrored(Topology::CrossSurface, 3, 2),
+            [
+                (2, 1, f, f),
+                (3, 1, f, f),
+                (0, 1, f, t),
+                (2, 2, f, f),
+                (0, 0, f, t),
+                (1, 0, t, f),
+                (0, 0, t, f),
+                (3, 2, t, t),
+            ]
+        );
+    }
+
+    #[test]
+    fn plane_corners_have_three_neighbors() {
+        let on_grid: Vec<_> = neighbors(Topology::Plane, 3, 2)
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(on_grid, [(2, 1), (3, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn walls_count_as_live_neighbors_past_the_edges() {
+        assert_eq!(Topology::Wall.wrap(-1, 0, SIZE), None);
+
+        // Corners, an edge cell and a cell inside, with what they count.
+        let count = |grid: &Grid, expected: [usize; 6]| {
+            let cells = [(0, 0), (4, 0), (0, 3), (4, 3), (2, 0), (2, 2)];
+            let counts = cells.map(|(x, y)| grid.count_neighbors(x, y));
+            assert_eq!(counts, expected, "at {:?}", cells);
+        };
+        let mut grid = Grid::new_empty_grid(5, 4);
+        grid.topology = Topology::Wall;
+        count(&grid, [5, 5, 5, 5, 3, 0]);
+
+        grid.toggle(1, 1);
+        grid.toggle(3, 2);
+        count(&grid, [6, 5, 5, 6, 4, 2]);
+    }
+
+    #[test]
+    fn neighbors_have_them_back() {
+        let mut topologies = Topology::ALL.to_vec();
+        topologies.extend([Topology::shifted(0, 1), Topology::shifted(-5, 0)].map(Result::unwrap));
+        for topology in topologies {
+            for y in 0..SIZE.1 {
+                for x in 0..SIZE.0 {
+                    for (nx, ny) in neighbors(topology, x, y).into_iter().flatten() {
+                        let here = Some((x, y));
+                        let there = neighbors(topology, nx, ny);
+                        let back = there.iter().filter(|&&cell| cell == here).count();
+                        let forth = neighbors(topology, x, y)
+                            .into_iter()
+                            .filter(|&cell| cell == Some((nx, ny)))
+                            .count();
+                        assert_eq!(
+                            back,
+                            forth,
+                            "{} from {:?} to {:?}",
+                            topology,
+                            (x, y),
+                            (nx, ny)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shifting_both_pairs_of_edges_is_refused() {
+        assert_eq!(Topology::shifted(0, 0), Ok(Topology::Torus));
+        assert_eq!(
+            Topology::shifted(3, 0),
+            Ok(Topology::Shifted { x: 3, y: 0 })
+        );
+        assert!(Topology::shifted(1, 1).is_err());
+        assert!("torus+1,-2".parse::<Topology>().is_err());
+    }
+}
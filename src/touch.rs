@@ -0,0 +1,277 @@
+//
+// Touchscreens: each finger draws as the mouse's left button does, a tap
+// toggling the cell under it and a drag painting the same all the way.
+// Fingers down together draw strokes of their own, each from where it was
+// last.
+//
+// Two fingers landing at once are a gesture instead: dragged, they pan an
+// infinite world; anywhere else, the gesture toggles pause. So that the
+// first of them doesn't leave a dot behind, a finger waits until it moves,
+// lifts or has been down for `GESTURE_WINDOW` before it starts drawing.
+//
+
+use std::time::Duration;
+
+use instant::Instant;
+
+// How soon after the first a second finger has to land to make a gesture.
+const GESTURE_WINDOW: Duration = Duration::from_millis(120);
+
+/// What the fingers did since the last `update`, in cells.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Touch {
+    /// A finger started a stroke at (x, y).
+    Down { finger: u64, x: isize, y: isize },
+    /// A finger's stroke went on from one cell to the next.
+    Line {
+        finger: u64,
+        from: (isize, isize),
+        to: (isize, isize),
+    },
+    /// A finger's stroke is over.
+    Up { finger: u64 },
+    /// The last stroke is over: the edit they made is done.
+    Commit,
+    /// Two fingers landed together.
+    Gesture,
+    /// The two fingers of the gesture moved by (dx, dy) between them.
+    Pan(isize, isize),
+}
+
+struct Finger {
+    id: u64,
+    down: Instant,
+    start: (isize, isize),
+    cell: (isize, isize),
+    lifted: bool,
+    role: Role,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    // Down, but not drawing yet.
+    Waiting,
+    // Drawing; the cell its stroke got to.
+    Drawing((isize, isize)),
+    // One of the gesture's two.
+    Gesture,
+}
+
+#[derive(Default)]
+pub struct Touches {
+    fingers: Vec<Finger>,
+    // A gesture that's yet to be reported.
+    gesture_started: bool,
+    // Where between its two fingers the gesture was on the last update.
+    gesture_at: Option<(isize, isize)>,
+    // Whether any stroke was drawn since the last commit.
+    drawn: bool,
+}
+
+impl Touches {
+    /// A finger landed on the `cell`.
+    pub fn started(&mut self, id: u64, cell: (isize, isize)) {
+        let now = Instant::now();
+        let mut role = Role::Waiting;
+        if let [first] = &mut self.fingers[..] {
+            if first.role == Role::Waiting && now.duration_since(first.down) < GESTURE_WINDOW {
+                first.role = Role::Gesture;
+                role = Role::Gesture;
+                self.gesture_started = true;
+            }
+        }
+        self.fingers.push(Finger {
+            id,
+            down: now,
+            start: cell,
+            cell,
+            lifted: false,
+            role,
+        });
+    }
+
+    /// A finger moved over the `cell`.
+    pub fn moved(&mut self, id: u64, cell: (isize, isize)) {
+        if let Some(finger) = self.finger(id) {
+            finger.cell = cell;
+        }
+    }
+
+    /// A finger lifted (or the system took it away) over the `cell`.
+    pub fn ended(&mut self, id: u64, cell: (isize, isize)) {
+        if let Some(finger) = self.finger(id) {
+            finger.cell = cell;
+            finger.lifted = true;
+        }
+    }
+
+    fn finger(&mut self, id: u64) -> Option<&mut Finger> {
+        self.fingers
+            .iter_mut()
+            .find(|finger| finger.id == id && !finger.lifted)
+    }
+
+    /// What the fingers did since the last update. Once a frame.
+    pub fn update(&mut self) -> Vec<Touch> {
+        let now = Instant::now();
+        let mut touches = Vec::new();
+        if std::mem::take(&mut self.gesture_started) {
+            touches.push(Touch::Gesture);
+        }
+
+        for finger in &mut self.fingers {
+            let due = now.duration_since(finger.down) >= GESTURE_WINDOW;
+            if finger.role == Role::Waiting && (due || finger.lifted || finger.cell != finger.start)
+            {
+                let (x, y) = finger.start;
+                touches.push(Touch::Down {
+                    finger: finger.id,
+                    x,
+                    y,
+                });
+                finger.role = Role::Drawing(finger.start);
+                self.drawn = true;
+            }
+            if let Role::Drawing(from) = finger.role {
+                if from != finger.cell {
+                    touches.push(Touch::Line {
+                        finger: finger.id,
+                        from,
+                        to: finger.cell,
+                    });
+                    finger.role = Role::Drawing(finger.cell);
+                }
+                if finger.lifted {
+                    touches.push(Touch::Up { finger: finger.id });
+                }
+            }
+        }
+
+        // The gesture pans with the point between its two fingers, while
+        // both are down.
+        let gesture: Vec<_> = self
+            .fingers
+            .iter()
+            .filter(|finger| finger.role == Role::Gesture && !finger.lifted)
+            .map(|finger| finger.cell)
+            .collect();
+        let at = match gesture[..] {
+            [(ax, ay), (bx, by)] => Some(((ax + bx) / 2, (ay + by) / 2)),
+            _ => None,
+        };
+        if let (Some((x0, y0)), Some((x1, y1))) = (self.gesture_at, at) {
+            if (x0, y0) != (x1, y1) {
+                touches.push(Touch::Pan(x1 - x0, y1 - y0));
+            }
+        }
+        self.gesture_at = at;
+
+        self.fingers.retain(|finger| !finger.lifted);
+        let drawing = self
+            .fingers
+            .iter()
+            .any(|finger| matches!(finger.role, Role::Drawing(_)));
+        if self.drawn && !drawing {
+            touches.push(Touch::Commit);
+            self.drawn = false;
+        }
+        touches
+    }
+
+    /// Whether any finger's down.
+    pub fn active(&self) -> bool {
+        !self.fingers.is_empty()
+    }
+
+    /// When a finger that's waiting will start drawing, if one is, for
+    /// the event loop to wake then.
+    pub fn next_check(&self) -> Option<Instant> {
+        self.fingers
+            .iter()
+            .filter(|finger| finger.role == Role::Waiting)
+            .map(|finger| finger.down + GESTURE_WINDOW)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tap_is_a_stroke_of_one_cell() {
+        let mut touches = Touches::default();
+        touches.started(7, (3, 4));
+        touches.ended(7, (3, 4));
+        assert_eq!(
+            touches.update(),
+            [
+                Touch::Down {
+                    finger: 7,
+                    x: 3,
+                    y: 4
+                },
+                Touch::Up { finger: 7 },
+                Touch::Commit,
+            ]
+        );
+        assert!(!touches.active());
+        assert!(touches.update().is_empty());
+    }
+
+    #[test]
+    fn fingers_draw_strokes_of_their_own() {
+        let mut touches = Touches::default();
+        touches.started(1, (0, 0));
+        touches.moved(1, (2, 0));
+        assert_eq!(touches.update().len(), 2);
+
+        // The first is drawing already, so the second isn't a gesture.
+        touches.started(2, (9, 9));
+        touches.moved(2, (9, 7));
+        touches.moved(1, (3, 1));
+        assert_eq!(
+            touches.update(),
+            [
+                Touch::Line {
+                    finger: 1,
+                    from: (2, 0),
+                    to: (3, 1)
+                },
+                Touch::Down {
+                    finger: 2,
+                    x: 9,
+                    y: 9
+                },
+                Touch::Line {
+                    finger: 2,
+                    from: (9, 9),
+                    to: (9, 7)
+                },
+            ]
+        );
+
+        // The edit's done once the last of them lifts.
+        touches.ended(1, (3, 1));
+        assert_eq!(touches.update(), [Touch::Up { finger: 1 }]);
+        touches.ended(2, (9, 7));
+        assert_eq!(touches.update(), [Touch::Up { finger: 2 }, Touch::Commit]);
+    }
+
+    #[test]
+    fn two_fingers_together_pan_and_draw_nothing() {
+        let mut touches = Touches::default();
+        touches.started(1, (10, 10));
+        touches.started(2, (20, 10));
+        assert_eq!(touches.update(), [Touch::Gesture]);
+        assert_eq!(touches.next_check(), None);
+
+        touches.moved(1, (14, 12));
+        touches.moved(2, (24, 12));
+        assert_eq!(touches.update(), [Touch::Pan(4, 2)]);
+        touches.ended(1, (14, 12));
+        touches.ended(2, (24, 12));
+        assert!(touches.update().is_empty());
+        assert!(!touches.active());
+    }
+}
@@ -0,0 +1,232 @@
+//
+// `--tui`: an interactive terminal frontend. Each character shows two grid
+// rows using half blocks.
+//
+//   SPACE  pause, or step one generation while paused
+//   P      toggle pause
+//   R      new soup
+//   arrows move the cursor
+//   ENTER  toggle the cell under the cursor
+//   Q      quit (also Esc, Ctrl+C)
+//
+// The grid keeps the size it started with. When the terminal is bigger it
+// is letterboxed in the middle; when smaller, the view scrolls with the
+// cursor.
+//
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+
+use tgol::grid::Grid;
+use tgol::rng::{next_seed, session_rng};
+
+use crate::cli::Options;
+use crate::stats_csv::{write_stats, StatsCsv};
+use crate::{session_seed, starting_grid};
+
+// Puts the terminal back on drop, so it's restored even on a panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(options: &Options) -> io::Result<()> {
+    // One row is kept for the status line; each other row holds two cells.
+    let (cols, rows) = terminal::size()?;
+    let width = options.width.unwrap_or(cols as u32).max(1);
+    let height = options
+        .height
+        .unwrap_or(rows.saturating_sub(1) as u32 * 2)
+        .max(1);
+
+    let seed = session_seed(options);
+    let mut rng = session_rng(seed);
+    let mut life = starting_grid(options, (width, height), seed);
+    let mut stats_csv = options
+        .stats_out
+        .as_deref()
+        .map(|path| StatsCsv::create(path, options.automaton.teams()))
+        .transpose()?;
+
+    let _guard = TerminalGuard::enter()?;
+    let mut stdout = io::stdout().lock();
+
+    let tick = Duration::from_millis(options.tick_ms);
+    let mut paused = false;
+    let mut cursor = (life.width / 2, life.height / 2);
+    let mut next_tick = Instant::now() + tick;
+
+    loop {
+        render(&mut stdout, &life, cursor, paused)?;
+
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    let keep_going = handle_key(
+                        key,
+                        &mut life,
+                        &mut stats_csv,
+                        &mut cursor,
+                        &mut paused,
+                        || starting_grid(options, (width, height), next_seed(&mut rng)),
+                    );
+                    if !keep_going {
+                        if let Some(csv) = &mut stats_csv {
+                            csv.flush()?;
+                        }
+                        return Ok(());
+                    }
+                }
+                Event::Resize(..) => queue!(stdout, terminal::Clear(terminal::ClearType::All))?,
+                _ => {}
+            }
+        }
+
+        if Instant::now() >= next_tick {
+            if !paused {
+                write_stats(&mut stats_csv, &life.update());
+            }
+            next_tick = Instant::now() + tick;
+        }
+    }
+}
+
+// Applies one key press. Returns false when it's time to quit.
+fn handle_key(
+    key: KeyEvent,
+    life: &mut Grid,
+    stats_csv: &mut Option<StatsCsv>,
+    cursor: &mut (usize, usize),
+    paused: &mut bool,
+    new_soup: impl FnOnce() -> Grid,
+) -> bool {
+    let (w, h) = (life.width, life.height);
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+        KeyCode::Char(' ') if *paused => write_stats(stats_csv, &life.update()),
+        KeyCode::Char(' ') => *paused = true,
+        KeyCode::Char('p') => *paused = !*paused,
+        KeyCode::Char('r') => {
+            *life = new_soup();
+            if let Some(csv) = stats_csv {
+                csv.new_run();
+            }
+        }
+        KeyCode::Enter => {
+            life.toggle(cursor.0 as isize, cursor.1 as isize);
+        }
+        KeyCode::Left => cursor.0 = (cursor.0 + w - 1) % w,
+        KeyCode::Right => cursor.0 = (cursor.0 + 1) % w,
+        KeyCode::Up => cursor.1 = (cursor.1 + h - 1) % h,
+        KeyCode::Down => cursor.1 = (cursor.1 + 1) % h,
+        _ => {}
+    }
+    true
+}
+
+fn render(
+    out: &mut impl Write,
+    life: &Grid,
+    cursor: (usize, usize),
+    paused: bool,
+) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let (cols, view_rows) = (cols as usize, rows.saturating_sub(1) as usize);
+
+    // Grid span shown, in cells, and where it starts on screen and in the
+    // grid. Rows are counted in cells here, two per terminal row.
+    let (span_x, pad_x, origin_x) = fit(life.width, cols, cursor.0);
+    let (span_y, pad_y, origin_y) = fit(life.height, view_rows * 2, cursor.1);
+    // Letterboxing has to start on a terminal row boundary.
+    let pad_y = pad_y & !1;
+
+    let alive = |x: usize, y: usize| y < life.height && life.grid.alive[x + y * life.width];
+
+    for row in 0..view_rows {
+        queue!(out, cursor::MoveTo(0, row as u16))?;
+
+        let top = row * 2;
+        if top < pad_y || top >= pad_y + span_y {
+            queue!(out, terminal::Clear(terminal::ClearType::CurrentLine))?;
+            continue;
+        }
+
+        let y = origin_y + top - pad_y;
+        let mut line = " ".repeat(pad_x);
+        for x in origin_x..origin_x + span_x {
+            let here = cursor.0 == x && (cursor.1 == y || cursor.1 == y + 1);
+            if here {
+                queue!(out, Print(&line), SetAttribute(Attribute::Reverse))?;
+                line.clear();
+            }
+            line.push(match (alive(x, y), alive(x, y + 1)) {
+                (false, false) => ' ',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (true, true) => '█',
+            });
+            if here {
+                queue!(out, Print(&line), SetAttribute(Attribute::Reset))?;
+                line.clear();
+            }
+        }
+        queue!(
+            out,
+            Print(line),
+            terminal::Clear(terminal::ClearType::UntilNewLine)
+        )?;
+    }
+
+    let population = life.population();
+    let status = format!(
+        "gen {}  pop {}  {}{}  ({}, {}){}  [space/p/r/enter/arrows, q quits]",
+        life.generation(),
+        population,
+        life.rule,
+        match life.stochastic {
+            Some(stochastic) => format!(" stochastic {}", stochastic),
+            None => String::new(),
+        },
+        cursor.0,
+        cursor.1,
+        if paused { "  PAUSED" } else { "" }
+    );
+    queue!(
+        out,
+        cursor::MoveTo(0, view_rows as u16),
+        Print(status.chars().take(cols).collect::<String>()),
+        terminal::Clear(terminal::ClearType::UntilNewLine)
+    )?;
+
+    out.flush()
+}
+
+// Fits `len` grid cells into `room` screen cells along one axis. Returns
+// how many cells are shown, the padding before them, and the first one
+// shown, which follows `cursor` when the grid doesn't fit.
+fn fit(len: usize, room: usize, cursor: usize) -> (usize, usize, usize) {
+    if len <= room {
+        (len, (room - len) / 2, 0)
+    } else {
+        let origin = cursor.saturating_sub(room / 2).min(len - room);
+        (room, 0, origin)
+    }
+}
@@ -0,0 +1,121 @@
+//
+// Where the grid lands in the window, for mapping the cursor (and
+// fingers) to cells: scaled up by the largest whole factor that fits (at
+// least 1, cropped if even that doesn't), and centered, as `pixels` draws
+// it. Positions are physical pixels from the window's top-left corner,
+// which is how winit reports them at any scale factor.
+//
+// `Pixels::window_pos_to_pixel` does the same job, but only knows the
+// surface size as of the last resize it was given, and puts the grid's
+// left and top edges at the window's when the window is the smaller of
+// the two, where they're really cropped off evenly on both sides.
+//
+
+/// A `grid`-sized frame drawn on a `surface`-sized window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub grid: (u32, u32),
+    pub surface: (u32, u32),
+}
+
+impl Viewport {
+    pub fn new(grid: (u32, u32), surface: (u32, u32)) -> Self {
+        Self { grid, surface }
+    }
+
+    /// Physical pixels to a cell, across and down.
+    pub fn scale(&self) -> u32 {
+        let (gw, gh) = self.grid;
+        let (sw, sh) = self.surface;
+        (sw / gw.max(1)).min(sh / gh.max(1)).max(1)
+    }
+
+    /// Where the grid's top-left corner is in the window; negative where
+    /// the grid's cropped.
+    pub fn origin(&self) -> (i64, i64) {
+        let scale = self.scale() as i64;
+        // Half the room to spare, to the pixel.
+        let offset =
+            |surface: u32, grid: u32| (surface as i64 - grid as i64 * scale + 1).div_euclid(2);
+        (
+            offset(self.surface.0, self.grid.0),
+            offset(self.surface.1, self.grid.1),
+        )
+    }
+
+    /// The cell under the window position (x, y), whether it's on the grid
+    /// or not.
+    pub fn cell_at(&self, (x, y): (f64, f64)) -> (isize, isize) {
+        let scale = self.scale() as f64;
+        let (ox, oy) = self.origin();
+        let cell = |pos: f64, origin: i64| ((pos - origin as f64) / scale).floor() as isize;
+        (cell(x, ox), cell(y, oy))
+    }
+
+    /// Whether the cell (x, y) is on the grid.
+    pub fn on_grid(&self, (x, y): (isize, isize)) -> bool {
+        (0..self.grid.0 as isize).contains(&x) && (0..self.grid.1 as isize).contains(&y)
+    }
+
+    /// The nearest cell on the grid to (x, y).
+    pub fn clamp(&self, (x, y): (isize, isize)) -> (isize, isize) {
+        let max = |cells: u32| (cells as isize - 1).max(0);
+        (x.clamp(0, max(self.grid.0)), y.clamp(0, max(self.grid.1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: (u32, u32) = (100, 70);
+
+    // A 330x250 window (in logical pixels) at a scale factor.
+    fn at(factor: f64) -> Viewport {
+        let physical = |logical: u32| (logical as f64 * factor) as u32;
+        Viewport::new(GRID, (physical(330), physical(250)))
+    }
+
+    #[test]
+    fn cells_are_found_at_any_scale_factor() {
+        // Scale factor, then the whole scale that fits and where the grid
+        // starts once the margins are split.
+        let cases = [(1.0, 3, (15, 20)), (1.5, 4, (48, 48)), (2.0, 6, (30, 40))];
+        for (factor, scale, (ox, oy)) in cases {
+            let view = at(factor);
+            assert_eq!(view.scale(), scale, "at {}", factor);
+            assert_eq!(view.origin(), (ox, oy), "at {}", factor);
+
+            let (ox, oy, s) = (ox as f64, oy as f64, scale as f64);
+            assert_eq!(view.cell_at((ox, oy)), (0, 0));
+            assert_eq!(view.cell_at((ox + s - 0.5, oy + s - 0.5)), (0, 0));
+            assert_eq!(view.cell_at((ox + s, oy + s)), (1, 1));
+
+            // The last column and row, and the margin just past them.
+            let (right, bottom) = (ox + 100.0 * s, oy + 70.0 * s);
+            let last = view.cell_at((right - 0.5, bottom - 0.5));
+            assert_eq!(last, (99, 69), "at {}", factor);
+            assert!(view.on_grid(last));
+            let past = view.cell_at((right, bottom));
+            assert_eq!(past, (100, 70), "at {}", factor);
+            assert!(!view.on_grid(past));
+            assert_eq!(view.clamp(past), (99, 69));
+
+            // The margin before the first column and row.
+            let before = view.cell_at((ox - 0.5, oy - 0.5));
+            assert_eq!(before, (-1, -1), "at {}", factor);
+            assert!(!view.on_grid(before));
+            assert_eq!(view.clamp(before), (0, 0));
+            assert_eq!(view.clamp(view.cell_at((0.0, 0.0))), (0, 0));
+        }
+    }
+
+    #[test]
+    fn grids_bigger_than_the_window_are_cropped_evenly() {
+        let view = Viewport::new(GRID, (90, 60));
+        assert_eq!(view.scale(), 1);
+        assert_eq!(view.origin(), (-5, -5));
+        assert_eq!(view.cell_at((0.0, 0.0)), (5, 5));
+        assert_eq!(view.cell_at((89.5, 59.5)), (94, 64));
+    }
+}
@@ -0,0 +1,190 @@
+//
+// `--world infinite`: an unbounded plane instead of the torus, so nothing
+// comes back round to crash into its own debris. Only live cells are
+// stored, keyed by their (x, y), and each generation only looks at their
+// neighborhoods. The `Grid` becomes a window onto the plane (the view),
+// and is what gets drawn and edited as usual; panning moves the window.
+//
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::rule::Rule;
+
+/// The shape of the world the grid lives in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum World {
+    /// The grid wraps around its edges.
+    #[default]
+    Torus,
+    /// The grid is a view onto an unbounded plane.
+    Infinite,
+}
+
+impl World {
+    pub const ALL: [Self; 2] = [Self::Torus, Self::Infinite];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Torus => "torus",
+            Self::Infinite => "infinite",
+        }
+    }
+}
+
+impl fmt::Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for World {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|world| world.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown world {:?} (expected torus or infinite)", s))
+    }
+}
+
+/// What's going on in an infinite world, for keeping an eye on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldStats {
+    pub population: usize,
+    /// Inclusive (xmin, ymin, xmax, ymax) bounds of every live cell.
+    pub bounds: Option<(i64, i64, i64, i64)>,
+    /// World coordinates of the grid's top-left cell.
+    pub view: (i64, i64),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SparseWorld {
+    live: HashSet<(i64, i64)>,
+    /// World coordinates of the grid's top-left cell.
+    pub view: (i64, i64),
+    // Scratch space for `step`: live neighbor counts.
+    counts: HashMap<(i64, i64), u8>,
+}
+
+impl SparseWorld {
+    pub fn get(&self, x: i64, y: i64) -> bool {
+        self.live.contains(&(x, y))
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, alive: bool) {
+        if alive {
+            self.live.insert((x, y));
+        } else {
+            self.live.remove(&(x, y));
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    /// Inclusive (xmin, ymin, xmax, ymax) bounds of every live cell, or
+    /// None if there aren't any.
+    pub fn bounds(&self) -> Option<(i64, i64, i64, i64)> {
+        let mut cells = self.live.iter();
+        let &(x, y) = cells.next()?;
+        Some(cells.fold((x, y, x, y), |(x0, y0, x1, y1), &(x, y)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        }))
+    }
+
+    /// Every live cell, in no particular order.
+    pub fn live_cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.live.iter().copied()
+    }
+
+    /// Live cells inside the `width` x `height` rectangle at `(x0, y0)`,
+    /// relative to its corner.
+    pub fn live_in(
+        &self,
+        (x0, y0): (i64, i64),
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.live.iter().filter_map(move |&(x, y)| {
+            let (x, y) = (x - x0, y - y0);
+            ((0..width as i64).contains(&x) && (0..height as i64).contains(&y))
+                .then_some((x as usize, y as usize))
+        })
+    }
+
+    /// Kills every cell in the `width` x `height` rectangle at `(x0, y0)`.
+    pub fn clear_rect(&mut self, (x0, y0): (i64, i64), width: usize, height: usize) {
+        self.live.retain(|&(x, y)| {
+            !((x0..x0 + width as i64).contains(&x) && (y0..y0 + height as i64).contains(&y))
+        });
+    }
+
+    /// Runs the world a generation on.
+    pub fn step(&mut self, rule: Rule) {
+        self.counts.clear();
+        for &(x, y) in &self.live {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx, dy) != (0, 0) {
+                        *self.counts.entry((x + dx, y + dy)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        // Live cells with no live neighbors never got a count.
+        let live = &self.live;
+        let mut next: HashSet<(i64, i64)> = self
+            .counts
+            .iter()
+            .filter(|&(cell, &count)| {
+                if live.contains(cell) {
+                    rule.survives(count as usize)
+                } else {
+                    rule.births(count as usize)
+                }
+            })
+            .map(|(&cell, _)| cell)
+            .collect();
+        if rule.survives(0) {
+            next.extend(live.iter().filter(|cell| !self.counts.contains_key(cell)));
+        }
+        self.live = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::{Heading, PatternBuffer};
+
+    #[test]
+    fn gliders_keep_going_past_where_they_started() {
+        let mut world = SparseWorld::default();
+        let glider = PatternBuffer::glider(Heading::NorthWest);
+        for (x, y) in glider.live_cells() {
+            world.set(x as i64, y as i64, true);
+        }
+        let (x0, y0, x1, y1) = world.bounds().unwrap();
+        for period in 1..=50 {
+            for _ in 0..4 {
+                world.step(Rule::default());
+            }
+            let p = period as i64;
+            assert_eq!(world.population(), 5);
+            assert_eq!(world.bounds(), Some((x0 - p, y0 - p, x1 - p, y1 - p)));
+        }
+
+        // Seen from where it now is, it's the glider it started as.
+        let mut seen: Vec<_> = world.live_in((-50, -50), 3, 3).collect();
+        let mut start: Vec<_> = glider.live_cells().collect();
+        seen.sort_unstable();
+        start.sort_unstable();
+        assert_eq!(seen, start);
+        assert_eq!(world.live_in((0, 0), 3, 3).count(), 0);
+    }
+}